@@ -4,11 +4,20 @@ use gdk::Display;
 use gio::prelude::{ApplicationExt as _, ApplicationExtManual as _};
 use gtk::prelude::{ButtonExt as _, GtkWindowExt as _, WidgetExt as _};
 use gtk::{
-    Align, Application, ApplicationWindow, Button, ConstraintGuide, CssProvider, Label,
-    StyleContext,
+    Align, Application, ApplicationWindow, Button, CallbackAction, ConstraintGuide, CssProvider,
+    Label, Orientation, Overflow, Separator, Shortcut, ShortcutController, ShortcutTrigger,
+    StyleContext, TextDirection,
 };
-use springsteel::{add_constraint, glib_run_future, ConstraintView, ImpulseStream};
+use springsteel::constraint_view::{ConstraintId, Easing, SplitterOptions};
+use springsteel::controller_streams::{pointer_streams, shortcut_impulses};
+use springsteel::debug::{report_on_shutdown, ShutdownReport};
+use springsteel::frame_sync::glib_run_future_frame_synced;
+use springsteel::glib_future::bind_to_application;
+use springsteel::time::interval;
+use springsteel::widget_streams::direction_changes;
+use springsteel::{add_constraints, constraint, glib_run_stream, ConstraintView, ImpulseStream};
 use std::future::ready;
+use std::time::Duration;
 
 const APP_ID: &str = "com.dridus.springsteel-workbench";
 
@@ -16,6 +25,8 @@ fn main() {
     let app = Application::builder().application_id(APP_ID).build();
     app.connect_startup(|_| load_css());
     app.connect_activate(build_ui);
+    bind_to_application(&app);
+    report_on_shutdown(&app, ShutdownReport::Print);
     app.run();
 }
 
@@ -23,15 +34,26 @@ fn load_css() {
     let provider = CssProvider::new();
     provider.load_from_data(
         b"
-        label#display {
+        constraintview > label#display {
             font-weight: bold;
             font-size: 7em;
         }
 
-        button {
+        constraintview > button {
             font-weight: bold;
             font-size: 2em;
         }
+
+        constraintview > button.hovered {
+            color: orange;
+        }
+
+        constraintview > label#badge {
+            background: crimson;
+            color: white;
+            border-radius: 999px;
+            padding: 0 6px;
+        }
     ",
     );
 
@@ -51,79 +73,247 @@ fn build_ui(app: &Application) {
 
     let increments = ImpulseStream::new();
     let increment = Button::with_label("+");
+    increment.set_widget_name("increment");
     increment.connect_clicked(increments.triggerer());
 
     let decrements = ImpulseStream::new();
     let decrement = Button::with_label("-");
+    decrement.set_widget_name("decrement");
     decrement.connect_clicked(decrements.triggerer());
 
-    let deltas = stream_select!(increments.map(|()| 1), decrements.map(|()| -1));
+    let panel_toggles = ImpulseStream::new();
+    let panel_toggle = Button::with_label("panel");
+    panel_toggle.set_widget_name("panel_toggle");
+    panel_toggle.connect_clicked(panel_toggles.triggerer());
+
+    let panel = Label::builder().label(&"panel").name("panel").build();
+
+    let ticks = interval(Duration::from_secs(1));
+
+    let deltas = stream_select!(
+        increments.map(|()| 1),
+        decrements.map(|()| -1),
+        ticks.map(|()| 1)
+    );
     let count = deltas.scan(0, |s, d| {
         *s += d;
         ready(Some(*s))
     });
 
-    let display_for_future = display.clone();
-    let display_count = count.for_each(move |c| {
-        display_for_future.set_text(&*format!("{}", c));
-        ready(())
+    let display_for_count = display.clone();
+    glib_run_stream(count, move |c| {
+        display_for_count.set_text(&*format!("{}", c))
+    })
+    .detach();
+
+    hover_highlight(&increment);
+    hover_highlight(&decrement);
+
+    let badge = Label::builder().label(&"new").name("badge").build();
+
+    let split_handle = Separator::new(Orientation::Vertical);
+    split_handle.set_widget_name("split_handle");
+    split_handle.set_cursor_from_name(Some("col-resize"));
+
+    let content_body = ConstraintGuide::builder().name("content").build();
+    let controls_display_spacer = ConstraintGuide::builder()
+        .name("controls_display_spacer")
+        .build();
+    let content = ConstraintView::builder()
+        .widget_name("content")
+        .padding(20.0)
+        .content_guide(&content_body)
+        .spacer(
+            &controls_display_spacer,
+            Orientation::Horizontal,
+            10.0,
+            10.0,
+            10.0,
+        )
+        .child(&display)
+        .child(&badge)
+        .child(&increment)
+        .child(&decrement)
+        .child(&panel_toggle)
+        .child(&panel)
+        .child(&split_handle)
+        .build();
+    add_constraints!(content, {
+        increment.top == content_body.top;
+        increment.left == content_body.left;
+        increment.right == controls_display_spacer.left;
+        decrement.top == increment.bottom + 10.0;
+        decrement.bottom == content_body.bottom;
+        decrement.left == content_body.left;
+        decrement.right == controls_display_spacer.left;
+        increment.height == decrement.height;
+        badge.center_x == increment.right;
+        badge.center_y == increment.top;
+        split_handle.center_x == controls_display_spacer.center_x;
+        split_handle.top == content_body.top;
+        split_handle.bottom == content_body.bottom;
+        display.top == content_body.top;
+        display.left == controls_display_spacer.right;
+        display.right == content_body.end;
+        display.bottom == panel_toggle.top - 10.0;
+        panel_toggle.right == content_body.end;
+        panel_toggle.bottom == content_body.bottom;
+        panel.top == top;
+        panel.bottom == bottom;
+        panel.width == 200.0;
     });
+    content.set_overflow(Overflow::Hidden);
 
-    glib_run_future(display_count);
+    // The badge was added before the button it's pinned to, so would be drawn under it.
+    content.raise_child(&badge);
+    assert!(badge.next_sibling().is_none(), "badge is drawn last");
 
-    let content = ConstraintView::new();
-    content.set_widget_name("content");
-    let content_layout = content.layout();
+    let window = ApplicationWindow::builder()
+        .application(app)
+        .title("hi")
+        .child(&content)
+        .build();
 
-    display.set_parent(&content);
-    increment.set_parent(&content);
-    decrement.set_parent(&content);
+    window.add_controller(&design_mode_shortcut(&content));
+    window.add_controller(&debug_overlay_shortcut(&content));
+    #[cfg(feature = "tracing")]
+    window.add_controller(&stuck_tasks_shortcut());
+    direction_toggle(&window, &content);
+    sliding_panel(&content, &panel, panel_toggles);
+    let split = content.add_constraint(&constraint!(
+        controls_display_spacer.left == content_body.left + 120.0
+    ));
+    splitter(&content, &split_handle, split);
 
-    let content_body = ConstraintGuide::new();
-    let controls_display_spacer = ConstraintGuide::new();
-    content_layout.add_guide(&content_body);
-    content_layout.add_guide(&controls_display_spacer);
+    window.present();
+}
 
-    add_constraint!(content_layout, content_body.top == top + 20.0);
-    add_constraint!(content_layout, content_body.left == left + 20.0);
-    add_constraint!(content_layout, right == content_body.right + 20.0);
-    add_constraint!(content_layout, bottom == content_body.bottom + 20.0);
+/// Flip the text direction of the given view between LTR and RTL on Ctrl+R, showing the current
+/// direction in the window title.
+fn direction_toggle(window: &ApplicationWindow, content: &ConstraintView) {
+    let toggles = shortcut_impulses(window, "<Control>r").expect("direction toggle shortcut");
+    toggles.guard.keep();
 
-    add_constraint!(content_layout, increment.top == content_body.top);
-    add_constraint!(content_layout, increment.left == content_body.left);
-    add_constraint!(
-        content_layout,
-        increment.right == controls_display_spacer.left
-    );
+    let content_for_toggle = content.clone();
+    glib_run_stream(toggles.impulses, move |()| {
+        let flipped = match content_for_toggle.direction() {
+            TextDirection::Rtl => TextDirection::Ltr,
+            _ => TextDirection::Rtl,
+        };
+        content_for_toggle.set_direction(flipped);
+    })
+    .detach();
 
-    add_constraint!(content_layout, increment.width == increment.height);
+    let window = window.clone();
+    glib_run_stream(direction_changes(content), move |direction| {
+        window.set_title(Some(&format!("hi ({:?})", direction)));
+    })
+    .detach();
+}
 
-    add_constraint!(content_layout, decrement.top == increment.bottom + 10.0);
+/// Slide the given panel in from the right edge of the view and back out again each time the given
+/// stream triggers, waiting for each slide to finish before starting the next.
+fn sliding_panel(content: &ConstraintView, panel: &Label, mut toggles: ImpulseStream) {
+    let slide = content.add_constraint(&constraint!(panel.left == right));
+    let content_for_slide = content.clone();
+    glib_run_future_frame_synced(content, async move {
+        let mut shown = false;
+        while let Some(()) = toggles.next().await {
+            shown = !shown;
+            let to = if shown { -200.0 } else { 0.0 };
+            let duration = Duration::from_millis(300);
+            content_for_slide
+                .animate_constant(slide, to, duration, Easing::EaseInOutCubic)
+                .await;
+        }
+    })
+    .detach();
+}
 
-    add_constraint!(content_layout, decrement.bottom == content_body.bottom);
-    add_constraint!(content_layout, decrement.left == content_body.left);
-    add_constraint!(
-        content_layout,
-        decrement.right == controls_display_spacer.left
-    );
+/// Let the given handle be dragged to move the split between the controls and the display,
+/// logging where it was left at the end of each drag.
+fn splitter(content: &ConstraintView, handle: &Separator, split: ConstraintId) {
+    let options = SplitterOptions {
+        min_opt: Some(60.0),
+        max_opt: Some(400.0),
+    };
+    let splitter = content.add_splitter(handle, Orientation::Horizontal, split, options);
+    splitter.guard.keep();
+    glib_run_stream(splitter.positions, |position| {
+        log::info!("split at {}", position)
+    })
+    .detach();
+}
 
-    add_constraint!(content_layout, increment.height == decrement.height);
+/// Add the `hovered` CSS class to the given button while the pointer is over it.
+fn hover_highlight(button: &Button) {
+    let pointer = pointer_streams(button);
+    pointer.guard.keep();
+    let hovers = stream_select!(pointer.enter.map(|()| true), pointer.leave.map(|()| false));
 
-    add_constraint!(content_layout, controls_display_spacer.width == 10.0);
+    let button = button.clone();
+    glib_run_stream(hovers, move |hovered| {
+        if hovered {
+            button.add_css_class("hovered");
+        } else {
+            button.remove_css_class("hovered");
+        }
+    })
+    .detach();
+}
 
-    add_constraint!(content_layout, display.top == content_body.top);
-    add_constraint!(
-        content_layout,
-        display.left == controls_display_spacer.right
-    );
-    add_constraint!(content_layout, display.right == content_body.end);
-    add_constraint!(content_layout, display.bottom == content_body.bottom);
+/// Make a shortcut controller which toggles the debug overlay of the given view on Ctrl+O.
+fn debug_overlay_shortcut(content: &ConstraintView) -> ShortcutController {
+    let content = content.clone();
+    let action = CallbackAction::new(move |_, _| {
+        content.set_debug_overlay(!content.is_debug_overlay());
+        true
+    });
 
-    let window = ApplicationWindow::builder()
-        .application(app)
-        .title("hi")
-        .child(&content)
-        .build();
+    let controller = ShortcutController::new();
+    controller.add_shortcut(&Shortcut::new(
+        ShortcutTrigger::parse_string("<Control>o"),
+        Some(action),
+    ));
+    controller
+}
 
-    window.present();
+/// Make a shortcut controller which toggles design mode of the given view on Ctrl+D, printing the
+/// (possibly tweaked) constraints when design mode is turned off.
+fn design_mode_shortcut(content: &ConstraintView) -> ShortcutController {
+    let content = content.clone();
+    let action = CallbackAction::new(move |_, _| {
+        let enable = !content.is_design_mode();
+        content.enable_design_mode(enable);
+        if !enable {
+            print!("{}", content.design_export());
+        }
+        true
+    });
+
+    let controller = ShortcutController::new();
+    controller.add_shortcut(&Shortcut::new(
+        ShortcutTrigger::parse_string("<Control>d"),
+        Some(action),
+    ));
+    controller
+}
+
+/// Make a shortcut controller which logs the tasks that haven't been polled for a minute on
+/// Ctrl+Shift+T, to help find futures leaked waiting on streams which will never produce.
+#[cfg(feature = "tracing")]
+fn stuck_tasks_shortcut() -> ShortcutController {
+    let action = CallbackAction::new(|_, _| {
+        // Each stuck task is logged as a warning as it's found.
+        let stuck = springsteel::glib_future::dump_stuck_tasks(Duration::from_secs(60));
+        log::info!("{} stuck tasks", stuck.len());
+        true
+    });
+
+    let controller = ShortcutController::new();
+    controller.add_shortcut(&Shortcut::new(
+        ShortcutTrigger::parse_string("<Control><Shift>t"),
+        Some(action),
+    ));
+    controller
 }
@@ -0,0 +1,70 @@
+//! Streams and futures over [`gdk::Clipboard`]: [`clipboard_changes`] to know when the content
+//! changed and [`read_text`] to read it, shaped to run on
+//! [`glib_run_future`](crate::glib_run_future).
+
+use crate::impulse_stream::ImpulseStream;
+use crate::signal_future::SignalGuard;
+use futures::stream::StreamExt as _;
+use glib::object::IsA;
+use glib::{Cast as _, ObjectExt as _};
+use gtk::prelude::WidgetExt as _;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Make an [`ImpulseStream`] which is triggered whenever the content of the given clipboard
+/// changes. The handler is disconnected when the stream is dropped.
+pub fn clipboard_changes(clipboard: &gdk::Clipboard) -> ImpulseStream {
+    let changes = ImpulseStream::new();
+    let changes_for_changed = changes.downgrade();
+    let changed = clipboard.connect_changed(move |_| changes_for_changed.trigger());
+    changes.hold(SignalGuard::new(clipboard, vec![changed]));
+    changes
+}
+
+/// Read the content of the given clipboard as text, resolving to `None` if the clipboard is empty,
+/// doesn't hold text, or can't be read.
+///
+/// The future holds its own reference to the clipboard, so it can safely outlive whatever widget
+/// the clipboard was obtained from; code which uses the result to update a widget should hold that
+/// widget weakly, as [`bind_sensitive_to_clipboard_text`] does.
+pub fn read_text(clipboard: &gdk::Clipboard) -> impl Future<Output = Option<glib::GString>> {
+    let read = clipboard.read_text_future();
+    async move { read.await.ok().flatten() }
+}
+
+/// Make a widget (typically a "Paste" button) sensitive only while the given clipboard holds text,
+/// re-reading the clipboard every time it changes.
+///
+/// The returned future should be run with [`glib_run_future`](crate::glib_run_future). It holds
+/// the widget only weakly, so a read still in flight when the widget goes away is harmless: the
+/// result is discarded and the future completes.
+///
+/// ```
+///    # use springsteel::clipboard::bind_sensitive_to_clipboard_text;
+///    # use springsteel::glib_run_future;
+///    # use gtk::prelude::WidgetExt as _;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let paste = gtk::Button::with_label("Paste");
+///    glib_run_future(bind_sensitive_to_clipboard_text(&paste, &paste.clipboard()));
+/// ```
+pub fn bind_sensitive_to_clipboard_text(
+    widget: &impl IsA<gtk::Widget>,
+    clipboard: &gdk::Clipboard,
+) -> impl Future<Output = ()> + Unpin {
+    let widget_weak = widget.upcast_ref::<gtk::Widget>().downgrade();
+    let clipboard = clipboard.clone();
+    let mut changes = clipboard_changes(&clipboard);
+
+    Box::pin(async move {
+        loop {
+            let has_text = read_text(&clipboard).await.is_some();
+            match widget_weak.upgrade() {
+                Some(widget) => widget.set_sensitive(has_text),
+                None => break,
+            }
+
+            changes.next().await;
+        }
+    }) as Pin<Box<dyn Future<Output = ()>>>
+}
@@ -1,7 +1,10 @@
 #![doc(hidden)]
 //! Module containing the [`constraint!`](crate::constraint) and
 //! [`add_constraint!`](crate::add_constraint) macros which define a shorthand grammar for building
-//! constraints and a quick way to add those to a [`gtk::ConstraintLayout`], respectively.
+//! constraints and a quick way to add those to a [`gtk::ConstraintLayout`], respectively,
+//! [`add_constraints!`](crate::add_constraints) to add several at once,
+//! [`constraints!`](crate::constraints) to make several without adding them, and
+//! [`constraint_set!`](crate::constraint_set) to collect constraints into a set.
 //!
 //! Similar in concept to the VFL supported by
 //! [`gtk::ConstraintLayout::add_constraints_from_description`] but instead of using a run-time
@@ -10,7 +13,7 @@
 //! See [`constraint!`](crate::constraint) for a description of the grammar.
 
 /// Translate a constraint attribute by keyword (left, right, etc.) into the corresponding
-/// [`gtk::ConstraintAttribute`] value.
+/// [`gtk::ConstraintAttribute`] value, or fail to compile naming the unknown keyword.
 #[doc(hidden)]
 #[macro_export]
 macro_rules! constraint_attribute {
@@ -24,10 +27,15 @@ macro_rules! constraint_attribute {
     (height) => { gtk::ConstraintAttribute::Height };
     (center_x) => { gtk::ConstraintAttribute::CenterX };
     (center_y) => { gtk::ConstraintAttribute::CenterY };
+    (baseline) => { gtk::ConstraintAttribute::Baseline };
+    ($other:tt) => {
+        compile_error!(concat!("unknown constraint attribute `", stringify!($other), "`"))
+    };
 }
 
-/// Translate a strength keyword or literal value into the corresponding `i32` or if no keyword
-/// provided then expand to required strength.
+/// Translate a strength keyword, literal value, identifier, or parenthesized expression into the
+/// corresponding `i32` or if no keyword provided then expand to required strength. The keywords
+/// are matched first, so an identifier is only taken as a constant or variable if it isn't one.
 #[doc(hidden)]
 #[macro_export]
 macro_rules! constraint_strength {
@@ -37,6 +45,8 @@ macro_rules! constraint_strength {
     (medium) => (gtk::ffi::GTK_CONSTRAINT_STRENGTH_MEDIUM);
     (weak) => (gtk::ffi::GTK_CONSTRAINT_STRENGTH_WEAK);
     ($s:literal) => ($s);
+    ($s:ident) => ($s);
+    (($s:expr)) => ($s);
 }
 
 /// Translate a relation operator (`==`, `<=`, or `>=`) into the equivalent
@@ -49,26 +59,13 @@ macro_rules! constraint_relation {
     (>=) => { gtk::ConstraintRelation::Ge };
 }
 
-/// Translate some combination of positive and negative constants, both of which are optional, into
-/// a final constant value for a constraint.
-#[doc(hidden)]
-#[macro_export]
-macro_rules! constraint_constant {
-    (;) => (0.0);
-    ($c_p:literal; ) => ($c_p);
-    (; $c_n:literal) => (0.0 - $c_n);
-    ($c_p:literal; $c_n:literal) => ($c_p - $c_n);
-}
-
-/// Translate some combination of multiplier and divisor, both of which are optional, into a final
-/// coefficient value for a constraint.
+/// Translate a numeric term of a constraint, either a literal or a parenthesized expression, into
+/// an expression for its value as an `f64`, so that integers are never divided as integers.
 #[doc(hidden)]
 #[macro_export]
-macro_rules! constraint_multiplier {
-    (;) => (1.0);
-    ($f:literal; ) => ($f);
-    (; $d:literal) => (1/$d);
-    ($f:literal; $d:literal) => ($f * (1/$d));
+macro_rules! constraint_number {
+    ($n:literal) => ($n as f64);
+    (($e:expr)) => ($e as f64);
 }
 
 /// Generate a [`gtk::Constraint`] from a small grammar, for brevity.
@@ -125,147 +122,733 @@ macro_rules! constraint_multiplier {
 ///
 /// Two forms are supported, a constant form:
 ///
-/// `TARGET OP LITERAL [@STRENGTH]`
+/// `TARGET OP NUMBER [@STRENGTH]`
 ///
 /// And the general form:
 ///
-/// `TARGET OP SOURCE [* FACTOR] [/ DIVISOR] [+ CONSTANT] [- CONSTANT] [@STRENGTH]`
+/// `TARGET OP SOURCE [* FACTOR] [/ DIVISOR] [+ CONSTANT]... [- CONSTANT]... [@STRENGTH]`
+///
+/// where the source can also be negated, as `-SOURCE`, or preceded by a factor or a constant, as
+/// `FACTOR * SOURCE`, `CONSTANT + SOURCE`, or `CONSTANT - SOURCE`. The terms after the source can
+/// come in any order, but the strength always comes last.
 ///
 /// In either form:
 ///
-///  - `TARGET` or `SOURCE`: either `IDENT.ATTR` or just `ATTR`. `IDENT.ATTR` means the given
-///    attribute of some guide or widget within the layout, whereas `ATTR` by itself means the
-///    given attribute of the widget which is being laid out, i.e. the container. Attributes are as
-///    given in [`gtk::ConstraintAttribute`] but in `lower_kebab_case`: `left`, `right`, `top`,
-///    `bottom`, `start`, `end`, `width`, `height`, `center_x`, and `center_y`.
+///  - `TARGET` or `SOURCE`: either `IDENT.ATTR`, `(EXPR).ATTR`, or just `ATTR`. `IDENT.ATTR` means
+///    the given attribute of some guide or widget within the layout, and `(EXPR).ATTR` the same
+///    for a guide or widget given by any expression, e.g. `(self.display).left`, whereas `ATTR` by
+///    itself means the given attribute of the widget which is being laid out, i.e. the container.
+///    Attributes are as given in [`gtk::ConstraintAttribute`] but in `lower_kebab_case`: `left`,
+///    `right`, `top`, `bottom`, `start`, `end`, `width`, `height`, `center_x`, `center_y`, and
+///    `baseline`, the last being where the text of e.g. a label or entry sits, so that text can be
+///    lined up across widgets.
 ///
 ///  - `OP`: the constraint relation, usually `==` but `<=` and `>=` can also be used.
 ///
-///  - `LITERAL`: a literal value which is used as the constant.
+///  - `NUMBER`: a literal value, or any expression in parentheses, which is used as the constant.
 ///
 ///  - `[* FACTOR]`, `[/ DIVISOR]`, `[+ CONSTANT]`, `[- CONSTANT]`: factor and constant value
 ///    applied to the right hand side then related to the left hand side. E.g.
 ///    `width == height * 2 + 10` makes the width of the laid out widget be twice the height
 ///    plus 10. `/ DIVISOR` is equivalent to `* (1/DIVISOR)`, while `- CONSTANT` is equivalent to
-///    `+ (-CONSTANT)`.
+///    `+ (-CONSTANT)`. A factor or divisor always applies to the source, never to a constant, so
+///    `width == height + 10 * 2` is the same as `width == height * 2 + 10`. There can be at most
+///    one factor and one divisor, but any number of constants, which are summed. Each of
+///    `FACTOR`, `DIVISOR`, and `CONSTANT` is a literal value, or any expression in parentheses,
+///    e.g. `+ (margin)` or `* (scale_factor())`, and can be negative, e.g. `* -1.0` or
+///    `* -(scale)`.
+///
+///    Every number is converted to `f64` before any arithmetic, so integers work as well as
+///    floats, and `/ 2` halves. Take care with integer arithmetic inside parenthesized
+///    expressions though, which happens before the conversion: `/ (3 / 2)` divides by one. (Older
+///    versions of this macro divided integer literal divisors as integers too, so `/ 2` made the
+///    multiplier zero and silently dropped the source from the constraint.)
 ///
 ///  - `[@STRENGTH]`: optional constraint strength. If not given, defaults to required.
-///    Strength can be one of the enumerated strength values given as a keyword, or a literal i32
-///    strength value. Keywords supported: `required`, `strong`, `medium`, `weak`.
+///    Strength can be one of the enumerated strength values given as a keyword, a literal i32
+///    strength value, the name of an i32 constant or variable, or any i32 expression in
+///    parentheses. Keywords supported: `required`, `strong`, `medium`, `weak`. Keywords take
+///    precedence over names, so a variable called e.g. `weak` has to be given as `@(weak)`.
+///
+/// Factors and divisors are combined in floating point, whether given as integers or not:
+///
+/// ```
+///    # use springsteel::constraint;
+///    # gtk::init().expect("gtk::init");
+///    # let (a, b) = (gtk::Label::new(None), gtk::Label::new(None));
+///    #
+///    assert_eq!(constraint!(a.width == b.width / 2).multiplier(), 0.5);
+///    assert_eq!(constraint!(a.width == b.width * 3 / 2).multiplier(), 1.5);
+///    assert_eq!(constraint!(a.width == b.width * 3 / 2 + 10).constant(), 10.0);
+///    assert_eq!(constraint!(a.width == 100).constant(), 100.0);
+/// ```
+///
+/// The terms after the source can come in any order, with any number of constants:
+///
+/// ```
+///    # use springsteel::constraint;
+///    # gtk::init().expect("gtk::init");
+///    # let (a, b) = (gtk::Label::new(None), gtk::Label::new(None));
+///    #
+///    let margin = 4.0;
+///    let c = constraint!(a.width == b.height + 10 * 2);
+///    assert_eq!((c.multiplier(), c.constant()), (2.0, 10.0));
+///    let c = constraint!(a.width == b.width + 5 + 5);
+///    assert_eq!((c.multiplier(), c.constant()), (1.0, 10.0));
+///    let c = constraint!(a.width == b.width / 2 * 3);
+///    assert_eq!(c.multiplier(), 1.5);
+///    let c = constraint!(a.width == b.width - 2 / 4 + (margin) * 2 - 1 @weak);
+///    assert_eq!((c.multiplier(), c.constant()), (0.5, 1.0));
+///    assert_eq!(c.strength(), gtk::ffi::GTK_CONSTRAINT_STRENGTH_WEAK);
+///    let c = constraint!(a.width == 2 * b.width - 6 / 4 - (margin));
+///    assert_eq!((c.multiplier(), c.constant()), (0.5, -10.0));
+/// ```
+///
+/// Whereas repeating a factor or divisor, or putting the strength anywhere but last, fails to
+/// compile with an error saying so:
+///
+/// ```compile_fail
+///    # use springsteel::constraint;
+///    # let (a, b) = (gtk::Label::new(None), gtk::Label::new(None));
+///    constraint!(a.width == b.width * 2 * 3);
+/// ```
+///
+/// ```compile_fail
+///    # use springsteel::constraint;
+///    # let (a, b) = (gtk::Label::new(None), gtk::Label::new(None));
+///    constraint!(a.width == 2 * b.width * 3);
+/// ```
+///
+/// ```compile_fail
+///    # use springsteel::constraint;
+///    # let (a, b) = (gtk::Label::new(None), gtk::Label::new(None));
+///    constraint!(a.width == b.width / 2 + 1 / 3);
+/// ```
+///
+/// ```compile_fail
+///    # use springsteel::constraint;
+///    # let (a, b) = (gtk::Label::new(None), gtk::Label::new(None));
+///    constraint!(a.width == b.width @weak + 10);
+/// ```
+///
+/// ```compile_fail
+///    # use springsteel::constraint;
+///    # let (a, b, c) = (gtk::Label::new(None), gtk::Label::new(None), gtk::Label::new(None));
+///    constraint!(a.width == b.width + c.width);
+/// ```
+///
+/// Negative factors and a negated or leading source mirror a layout:
+///
+/// ```
+///    # use springsteel::constraint;
+///    # gtk::init().expect("gtk::init");
+///    # let (a, b) = (gtk::Label::new(None), gtk::Label::new(None));
+///    #
+///    let offset = 40.0;
+///    let c = constraint!(a.center_x == b.center_x * -1.0 + (offset));
+///    assert_eq!((c.multiplier(), c.constant()), (-1.0, 40.0));
+///    let c = constraint!(a.center_x == b.center_x * -(offset) / 2);
+///    assert_eq!(c.multiplier(), -20.0);
+///    let c = constraint!(a.center_x == -b.center_x);
+///    assert_eq!(c.multiplier(), -1.0);
+///    let c = constraint!(a.center_x == -2.0 * b.center_x + 8.0);
+///    assert_eq!((c.multiplier(), c.constant()), (-2.0, 8.0));
+///    let c = constraint!(a.left == -20.0 + b.left);
+///    assert_eq!((c.multiplier(), c.constant()), (1.0, -20.0));
+///    let c = constraint!(a.left == (offset) - b.width - 4.0);
+///    assert_eq!((c.multiplier(), c.constant()), (-1.0, 36.0));
+///    let c = constraint!(a.left == -(offset) @weak);
+///    assert_eq!(c.constant(), -40.0);
+/// ```
+///
+/// Values only known at run time are given as parenthesized expressions wherever a literal
+/// number is accepted:
+///
+/// ```
+///    # use springsteel::constraint;
+///    # gtk::init().expect("gtk::init");
+///    # let (body, header) = (gtk::Label::new(None), gtk::Label::new(None));
+///    #
+///    const GUTTER: f64 = 6.0;
+///    fn scale_factor() -> f64 {
+///        2.0
+///    }
+///    let margin = 20.0;
+///
+///    let c = constraint!(body.top == header.bottom + (margin));
+///    assert_eq!(c.constant(), 20.0);
+///    let c = constraint!(body.top == header.bottom + (margin + GUTTER) - (GUTTER / 2.0));
+///    assert_eq!(c.constant(), 23.0);
+///    let c = constraint!(body.top == header.bottom + (-margin));
+///    assert_eq!(c.constant(), -20.0);
+///    let c = constraint!(body.width == width * (scale_factor()) / (4.0 * GUTTER) - 1.0);
+///    assert_eq!((c.multiplier(), c.constant()), (2.0 / 24.0, -1.0));
+///    let c = constraint!(body.height >= (margin * 2.0) @weak);
+///    assert_eq!(c.constant(), 40.0);
+///    let c = constraint!(height == (GUTTER));
+///    assert_eq!(c.constant(), 6.0);
+/// ```
+///
+/// Guides and widgets held in fields, or anywhere else, are given as parenthesized expressions,
+/// in either position and alongside the other forms:
+///
+/// ```
+///    # use springsteel::constraint;
+///    # use glib::Cast as _;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    struct Row {
+///        display: gtk::Label,
+///        spacer: gtk::ConstraintGuide,
+///    }
+///    let row = Row { display: gtk::Label::new(None), spacer: gtk::ConstraintGuide::new() };
+///    let icon = gtk::Image::new();
+///
+///    let c = constraint!((row.display).left == (row.spacer).right + 4.0);
+///    assert_eq!(c.target(), Some(row.display.clone().upcast()));
+///    assert_eq!(c.source(), Some(row.spacer.clone().upcast()));
+///    assert_eq!(c.constant(), 4.0);
+///
+///    let c = constraint!((row.display).height == icon.height * 2.0 @strong);
+///    assert_eq!(c.source(), Some(icon.clone().upcast()));
+///    assert_eq!(c.multiplier(), 2.0);
+///
+///    let c = constraint!(icon.right <= (row.spacer).left);
+///    assert_eq!(c.target(), Some(icon.upcast()));
+///    let c = constraint!((row.display).right == right);
+///    assert_eq!(c.source(), None);
+///    let c = constraint!(width >= (row.display).width);
+///    assert_eq!(c.target(), None);
+///    let c = constraint!((row.display).width == (120.0));
+///    assert_eq!(c.target_attribute(), gtk::ConstraintAttribute::Width);
+/// ```
+///
+/// Baselines line up text in widgets of different heights:
+///
+/// ```
+///    # use springsteel::constraint;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let display = gtk::Label::new(Some("Name"));
+///    let entry = gtk::Entry::new();
+///    let c = constraint!(display.baseline == entry.baseline);
+///    assert_eq!(c.target_attribute(), gtk::ConstraintAttribute::Baseline);
+///    assert_eq!(c.source_attribute(), gtk::ConstraintAttribute::Baseline);
+/// ```
+///
+/// A misspelled attribute fails to compile, naming it:
+///
+/// ```compile_fail
+///    # use springsteel::constraint;
+///    # let (a, b) = (gtk::Label::new(None), gtk::Label::new(None));
+///    constraint!(a.cetner_x == b.center_x);
+/// ```
+///
+/// Strengths other than the keywords can be named or computed:
+///
+/// ```
+///    # use springsteel::{add_constraint, constraint};
+///    # gtk::init().expect("gtk::init");
+///    # let layout = gtk::ConstraintLayout::new();
+///    # let a = gtk::Label::new(None);
+///    #
+///    const EMPHASIS: i32 = 750;
+///    fn weak_plus_one() -> i32 {
+///        gtk::ffi::GTK_CONSTRAINT_STRENGTH_WEAK + 1
+///    }
+///
+///    let c = constraint!(a.width == 100.0 @EMPHASIS);
+///    assert_eq!(c.strength(), 750);
+///    let c = constraint!(a.width == width * 0.5 @(weak_plus_one()));
+///    assert_eq!(c.strength(), gtk::ffi::GTK_CONSTRAINT_STRENGTH_WEAK + 1);
+///    add_constraint!(layout, a.width == 100.0 @(weak_plus_one()));
+///
+///    // The keyword wins over a variable of the same name, unless it's parenthesized.
+///    let weak = 500;
+///    let c = constraint!(a.height == 10.0 @weak);
+///    assert_eq!(c.strength(), gtk::ffi::GTK_CONSTRAINT_STRENGTH_WEAK);
+///    let c = constraint!(a.height == 10.0 @(weak));
+///    assert_eq!(c.strength(), 500);
+/// ```
 ///
 /// See also [`add_constraint!`](crate::add_constraint) which makes it even more brief to add a
 /// constraint to a [`gtk::ConstraintLayout`].
 #[macro_export]
 macro_rules! constraint {
+    ($target:ident.$target_attr:ident $relation:tt $($rest:tt)*) => (
+        $crate::constraint_terms!(@rhs [[Some(&$target)] $target_attr $relation]; $($rest)*)
+    );
+
+    (($target:expr).$target_attr:ident $relation:tt $($rest:tt)*) => (
+        $crate::constraint_terms!(@rhs [[Some(&$target)] $target_attr $relation]; $($rest)*)
+    );
+
+    ($target_attr:ident $relation:tt $($rest:tt)*) => (
+        $crate::constraint_terms!(
+            @rhs [[None::<&gtk::ConstraintGuide>] $target_attr $relation]; $($rest)*
+        )
+    );
+}
+
+/// Parse the right hand side of a constraint for [`constraint!`](crate::constraint), given its
+/// target, as an `Option`, target attribute, and relation, and build the [`gtk::Constraint`].
+///
+/// Past the constant form, the right hand side is munched in stages: `@leading` for a sign,
+/// factor, or constant before the source, `@source`, then `@tail` which takes factors, divisors,
+/// and constants one at a time in whatever order they come, and finally `@build` with the
+/// strength, if any. Between stages the state is the header, then bracketed lists of the signs,
+/// factors, divisors, and constants so far, then the source once parsed. Misplaced or repeated
+/// terms in the tail become a `compile_error!` saying what's wrong, rather than leaving the
+/// caller with no rule matching.
+///
+/// Wherever a term can start with `-`, the arms taking the `-` explicitly come before those taking
+/// a `literal`, as a `literal` fragment accepts a leading `-`, and if what follows isn't a literal
+/// fails the whole macro rather than moving on to the next arm.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! constraint_terms {
     (
-        $lhs:ident.$lhs_attr:ident $relation:tt $lit:literal
-        $(@$strength:tt)?
+        @rhs [[$target:expr] $target_attr:ident $relation:tt];
+        - $constant:literal $(@$strength:tt)?
     ) => (
-        gtk::Constraint::new_constant(
-            Some(&$lhs), // target
-            $crate::constraint_attribute!($lhs_attr), // target_attribute
-            $crate::constraint_relation!($relation),
-            $lit, // constant
-            $crate::constraint_strength!($($strength)?),
+        $crate::constraint_terms!(
+            @constant [[$target] $target_attr $relation]
+            [-$crate::constraint_number!($constant)] $($strength)?
         )
     );
-
     (
-        $lhs_attr:ident $relation:tt $lit:literal
-        $(@$strength:tt)?
+        @rhs [[$target:expr] $target_attr:ident $relation:tt];
+        - ($constant:expr) $(@$strength:tt)?
+    ) => (
+        $crate::constraint_terms!(
+            @constant [[$target] $target_attr $relation]
+            [-$crate::constraint_number!(($constant))] $($strength)?
+        )
+    );
+    (@rhs $header:tt; - $($rest:tt)+) => (
+        $crate::constraint_terms!(@leading $header [] [] [] [] []; - $($rest)+)
+    );
+    (
+        @rhs [[$target:expr] $target_attr:ident $relation:tt];
+        $constant:literal $(@$strength:tt)?
+    ) => (
+        $crate::constraint_terms!(
+            @constant [[$target] $target_attr $relation]
+            [$crate::constraint_number!($constant)] $($strength)?
+        )
+    );
+    (
+        @rhs [[$target:expr] $target_attr:ident $relation:tt];
+        ($constant:expr) $(@$strength:tt)?
+    ) => (
+        $crate::constraint_terms!(
+            @constant [[$target] $target_attr $relation]
+            [$crate::constraint_number!(($constant))] $($strength)?
+        )
+    );
+    (@rhs $header:tt; $($rest:tt)+) => (
+        $crate::constraint_terms!(@leading $header [] [] [] [] []; $($rest)+)
+    );
+    (
+        @constant [[$target:expr] $target_attr:ident $relation:tt] [$constant:expr] $($strength:tt)?
     ) => (
         gtk::Constraint::new_constant(
-            None::<&gtk::ConstraintGuide>,
-            $crate::constraint_attribute!($lhs_attr),
+            $target,
+            $crate::constraint_attribute!($target_attr),
             $crate::constraint_relation!($relation),
-            $lit,
+            $constant,
             $crate::constraint_strength!($($strength)?),
         )
     );
 
+    // A factor or constant before the source, or a negated source.
     (
-        $lhs:ident.$lhs_attr:ident
-        $relation:tt
-        $rhs:ident.$rhs_attr:ident
-            $(* $f:literal)? $(/ $d:literal)?
-            $(+ $c_p:literal)? $(- $c_n:literal)?
-        $(@$strength:tt)?
+        @leading $header:tt [] [] [] [] [];
+        - ($n:expr) * $($rest:tt)+
     ) => (
-        gtk::Constraint::new(
-            Some(&$lhs),
-            $crate::constraint_attribute!($lhs_attr),
-            $crate::constraint_relation!($relation),
-            Some(&$rhs),
-            $crate::constraint_attribute!($rhs_attr),
-            $crate::constraint_multiplier!($($f)?; $($d)?),
-            $crate::constraint_constant!($($c_p)?; $($c_n)?),
-            $crate::constraint_strength!($($strength)?),
+        $crate::constraint_terms!(
+            @source $header [] [(-$crate::constraint_number!(($n)))] [] [] [];
+            $($rest)+
+        )
+    );
+    (
+        @leading $header:tt [] [] [] [] [];
+        - ($n:expr) + $($rest:tt)+
+    ) => (
+        $crate::constraint_terms!(
+            @source $header [] [] [] [(-$crate::constraint_number!(($n)))] [];
+            $($rest)+
+        )
+    );
+    (
+        @leading $header:tt [] [] [] [] [];
+        - ($n:expr) - $($rest:tt)+
+    ) => (
+        $crate::constraint_terms!(
+            @source $header [-] [] [] [(-$crate::constraint_number!(($n)))] [];
+            $($rest)+
+        )
+    );
+    (@leading $header:tt [] [] [] [] []; - $source:ident $($rest:tt)*) => (
+        $crate::constraint_terms!(@source $header [-] [] [] [] []; $source $($rest)*)
+    );
+    (@leading $header:tt [] [] [] [] []; - ($($source:tt)*) $($rest:tt)*) => (
+        $crate::constraint_terms!(@source $header [-] [] [] [] []; ($($source)*) $($rest)*)
+    );
+    (
+        @leading $header:tt [] [] [] [] [];
+        $n:literal * $($rest:tt)+
+    ) => (
+        $crate::constraint_terms!(
+            @source $header [] [($crate::constraint_number!($n))] [] [] [];
+            $($rest)+
+        )
+    );
+    (
+        @leading $header:tt [] [] [] [] [];
+        $n:literal + $($rest:tt)+
+    ) => (
+        $crate::constraint_terms!(
+            @source $header [] [] [] [($crate::constraint_number!($n))] [];
+            $($rest)+
+        )
+    );
+    (
+        @leading $header:tt [] [] [] [] [];
+        $n:literal - $($rest:tt)+
+    ) => (
+        $crate::constraint_terms!(
+            @source $header [-] [] [] [($crate::constraint_number!($n))] [];
+            $($rest)+
+        )
+    );
+    (
+        @leading $header:tt [] [] [] [] [];
+        ($n:expr) * $($rest:tt)+
+    ) => (
+        $crate::constraint_terms!(
+            @source $header [] [($crate::constraint_number!(($n)))] [] [] [];
+            $($rest)+
         )
     );
+    (
+        @leading $header:tt [] [] [] [] [];
+        ($n:expr) + $($rest:tt)+
+    ) => (
+        $crate::constraint_terms!(
+            @source $header [] [] [] [($crate::constraint_number!(($n)))] [];
+            $($rest)+
+        )
+    );
+    (
+        @leading $header:tt [] [] [] [] [];
+        ($n:expr) - $($rest:tt)+
+    ) => (
+        $crate::constraint_terms!(
+            @source $header [-] [] [] [($crate::constraint_number!(($n)))] [];
+            $($rest)+
+        )
+    );
+    (@leading $($state:tt)*) => (
+        $crate::constraint_terms!(@source $($state)*)
+    );
 
+    // The source, required past the constant form.
     (
-        $lhs:ident.$lhs_attr:ident
-        $relation:tt
-        $rhs_attr:ident
-            $(* $f:literal)? $(/ $d:literal)?
-            $(+ $c_p:literal)? $(- $c_n:literal)?
-        $(@$strength:tt)?
+        @source $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt [];
+        $source:ident.$source_attr:ident $($rest:tt)*
     ) => (
-        gtk::Constraint::new(
-            Some(&$lhs),
-            $crate::constraint_attribute!($lhs_attr),
-            $crate::constraint_relation!($relation),
-            None::<&gtk::ConstraintGuide>,
-            $crate::constraint_attribute!($rhs_attr),
-            $crate::constraint_multiplier!($($f)?; $($d)?),
-            $crate::constraint_constant!($($c_p)?; $($c_n)?),
-            $crate::constraint_strength!($($strength)?),
+        $crate::constraint_terms!(
+            @tail $header $sign $factor $divisor $constant
+            [[Some(&$source)] $source_attr];
+            $($rest)*
+        )
+    );
+    (
+        @source $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt [];
+        ($source:expr).$source_attr:ident $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign $factor $divisor $constant
+            [[Some(&$source)] $source_attr];
+            $($rest)*
+        )
+    );
+    (
+        @source $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt [];
+        $source_attr:ident $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign $factor $divisor $constant
+            [[None::<&gtk::ConstraintGuide>] $source_attr];
+            $($rest)*
         )
     );
 
+    // Factors, divisors, and constants in any order, then the strength, if any, last.
+    (@tail $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt $source:tt; @$strength:tt) => (
+        $crate::constraint_terms!(
+            @build $header $sign $factor $divisor $constant $source; $strength
+        )
+    );
+    (@tail $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt $source:tt;) => (
+        $crate::constraint_terms!(@build $header $sign $factor $divisor $constant $source;)
+    );
     (
-        $lhs_attr:ident
-        $relation:tt
-        $rhs:ident.$rhs_attr:ident
-            $(* $f:literal)? $(/ $d:literal)?
-            $(+ $c_p:literal)? $(- $c_n:literal)?
-        $(@$strength:tt)?
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt $source:tt;
+        @$strength:tt $($rest:tt)+
     ) => (
-        gtk::Constraint::new(
-            None::<&gtk::ConstraintGuide>,
-            $crate::constraint_attribute!($lhs_attr),
-            $crate::constraint_relation!($relation),
-            Some(&$rhs),
-            $crate::constraint_attribute!($rhs_attr),
-            $crate::constraint_multiplier!($($f)?; $($d)?),
-            $crate::constraint_constant!($($c_p)?; $($c_n)?),
-            $crate::constraint_strength!($($strength)?),
+        compile_error!("the strength of a constraint has to come last")
+    );
+    (
+        @tail $header:tt $sign:tt [$($f:tt)+] $divisor:tt $constant:tt $source:tt;
+        * $($rest:tt)*
+    ) => (
+        compile_error!("a constraint can only have one `*` factor, so multiply them together")
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt [$($d:tt)+] $constant:tt $source:tt;
+        / $($rest:tt)*
+    ) => (
+        compile_error!("a constraint can only have one `/` divisor, so multiply them together")
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt $source:tt;
+        $op:tt $other:ident.$attr:ident $($rest:tt)*
+    ) => (
+        compile_error!(concat!(
+            "a constraint relates its target to only one source, so `",
+            stringify!($op $other.$attr),
+            "` can't be added",
+        ))
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt $source:tt;
+        $op:tt ($other:expr).$attr:ident $($rest:tt)*
+    ) => (
+        compile_error!(concat!(
+            "a constraint relates its target to only one source, so `",
+            stringify!($op ($other).$attr),
+            "` can't be added",
+        ))
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt $source:tt;
+        + $attr:ident $($rest:tt)*
+    ) => (
+        compile_error!(concat!(
+            "a constraint relates its target to only one source, so `",
+            stringify!(+ $attr),
+            "` can't be added",
+        ))
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt $source:tt;
+        - $attr:ident $($rest:tt)*
+    ) => (
+        compile_error!(concat!(
+            "a constraint relates its target to only one source, so `",
+            stringify!(- $attr),
+            "` can't be added",
+        ))
+    );
+    (
+        @tail $header:tt $sign:tt [$($f:tt)*] $divisor:tt $constant:tt $source:tt;
+        * - ($n:expr) $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign
+            [$($f)* (-$crate::constraint_number!(($n)))] $divisor $constant $source;
+            $($rest)*
+        )
+    );
+    (
+        @tail $header:tt $sign:tt [$($f:tt)*] $divisor:tt $constant:tt $source:tt;
+        * $n:literal $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign
+            [$($f)* ($crate::constraint_number!($n))] $divisor $constant $source;
+            $($rest)*
+        )
+    );
+    (
+        @tail $header:tt $sign:tt [$($f:tt)*] $divisor:tt $constant:tt $source:tt;
+        * ($n:expr) $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign
+            [$($f)* ($crate::constraint_number!(($n)))] $divisor $constant $source;
+            $($rest)*
+        )
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt [$($d:tt)*] $constant:tt $source:tt;
+        / - ($n:expr) $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign
+            $factor [$($d)* (-$crate::constraint_number!(($n)))] $constant $source;
+            $($rest)*
         )
     );
+    (
+        @tail $header:tt $sign:tt $factor:tt [$($d:tt)*] $constant:tt $source:tt;
+        / $n:literal $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign
+            $factor [$($d)* ($crate::constraint_number!($n))] $constant $source;
+            $($rest)*
+        )
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt [$($d:tt)*] $constant:tt $source:tt;
+        / ($n:expr) $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign
+            $factor [$($d)* ($crate::constraint_number!(($n)))] $constant $source;
+            $($rest)*
+        )
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt [$($c:tt)*] $source:tt;
+        + - ($n:expr) $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign
+            $factor $divisor [$($c)* (-$crate::constraint_number!(($n)))] $source;
+            $($rest)*
+        )
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt [$($c:tt)*] $source:tt;
+        + $n:literal $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign
+            $factor $divisor [$($c)* ($crate::constraint_number!($n))] $source;
+            $($rest)*
+        )
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt [$($c:tt)*] $source:tt;
+        + ($n:expr) $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign
+            $factor $divisor [$($c)* ($crate::constraint_number!(($n)))] $source;
+            $($rest)*
+        )
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt [$($c:tt)*] $source:tt;
+        - - ($n:expr) $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign
+            $factor $divisor [$($c)* ($crate::constraint_number!(($n)))] $source;
+            $($rest)*
+        )
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt [$($c:tt)*] $source:tt;
+        - $n:literal $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign
+            $factor $divisor [$($c)* (-$crate::constraint_number!($n))] $source;
+            $($rest)*
+        )
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt [$($c:tt)*] $source:tt;
+        - ($n:expr) $($rest:tt)*
+    ) => (
+        $crate::constraint_terms!(
+            @tail $header $sign
+            $factor $divisor [$($c)* (-$crate::constraint_number!(($n)))] $source;
+            $($rest)*
+        )
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt $source:tt;
+        * $unexpected:tt $($rest:tt)*
+    ) => (
+        compile_error!(concat!(
+            "expected a literal number or an expression in parentheses after `*` in a ",
+            "constraint, but found `",
+            stringify!($unexpected),
+            "`",
+        ))
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt $source:tt;
+        / $unexpected:tt $($rest:tt)*
+    ) => (
+        compile_error!(concat!(
+            "expected a literal number or an expression in parentheses after `/` in a ",
+            "constraint, but found `",
+            stringify!($unexpected),
+            "`",
+        ))
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt $source:tt;
+        + $unexpected:tt $($rest:tt)*
+    ) => (
+        compile_error!(concat!(
+            "expected a literal number or an expression in parentheses after `+` in a ",
+            "constraint, but found `",
+            stringify!($unexpected),
+            "`",
+        ))
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt $source:tt;
+        - $unexpected:tt $($rest:tt)*
+    ) => (
+        compile_error!(concat!(
+            "expected a literal number or an expression in parentheses after `-` in a ",
+            "constraint, but found `",
+            stringify!($unexpected),
+            "`",
+        ))
+    );
+    (
+        @tail $header:tt $sign:tt $factor:tt $divisor:tt $constant:tt $source:tt;
+        $unexpected:tt $($rest:tt)*
+    ) => (
+        compile_error!(concat!(
+            "expected `*`, `/`, `+`, or `-` and a number, or `@` and a strength, in a ",
+            "constraint, but found `",
+            stringify!($unexpected),
+            "`",
+        ))
+    );
 
     (
-        $lhs_attr:ident
-        $relation:tt
-        $rhs_attr:ident
-            $(* $f:literal)? $(/ $d:literal)?
-            $(+ $c_p:literal)? $(- $c_n:literal)?
-        $(@$strength:tt)?
+        @build [[$target:expr] $target_attr:ident $relation:tt]
+        [$($sign:tt)*] [$($f:tt)*] [$($d:tt)*] [$($c:tt)*] [[$source:expr] $source_attr:ident];
+        $($strength:tt)?
     ) => (
         gtk::Constraint::new(
-            None::<&gtk::ConstraintGuide>,
-            $crate::constraint_attribute!($lhs_attr),
+            $target,
+            $crate::constraint_attribute!($target_attr),
             $crate::constraint_relation!($relation),
-            None::<&gtk::ConstraintGuide>,
-            $crate::constraint_attribute!($rhs_attr),
-            $crate::constraint_multiplier!($($f)?; $($d)?),
-            $crate::constraint_constant!($($c_p)?; $($c_n)?),
+            $source,
+            $crate::constraint_attribute!($source_attr),
+            $($sign)* 1.0 $(* $f)* $(/ $d)*,
+            0.0 $(+ $c)*,
             $crate::constraint_strength!($($strength)?),
         )
     );
 }
 
 /// Create a [`gtk::Constraint`] using the grammar of [`constraint!`] and then add it to a given
-/// [`gtk::ConstraintLayout`].
+/// [`gtk::ConstraintLayout`], or to a [`ConstraintView`](crate::ConstraintView) which keeps track
+/// of it, evaluating to its [`ConstraintId`](crate::constraint_view::ConstraintId).
 ///
 /// E.g.
 /// ```
@@ -280,6 +863,207 @@ macro_rules! constraint {
 #[macro_export]
 macro_rules! add_constraint {
     ($layout:expr, $($constraint:tt)*) => {
-        $layout.add_constraint(&$crate::constraint!($($constraint)*));
+        $layout.add_constraint(&$crate::constraint!($($constraint)*))
+    };
+}
+
+/// Add a constraint to a given [`gtk::ConstraintLayout`], or
+/// [`ConstraintView`](crate::ConstraintView), for each of a block of clauses separated by
+/// semicolons, each in the grammar of [`constraint!`], evaluating the layout only once.
+///
+/// ```
+///    # use springsteel::{add_constraints, ConstraintView};
+///    # use gtk::prelude::ListModelExt as _;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let view = ConstraintView::new();
+///    let (a, b) = (gtk::Label::new(None), gtk::Label::new(None));
+///    view.add_child(&a);
+///    view.add_child(&b);
+///
+///    add_constraints!(view, {
+///        a.top == top + 20.0;
+///        a.left == left + 20.0;
+///        right == a.right + 20.0 @strong;
+///        b.top == a.bottom + 8.0;;
+///        b.width == a.width * 0.5 @weak;
+///    });
+///    assert_eq!(view.tracked_constraints().len(), 5);
+///
+///    add_constraints!(view.layout(), { b.left == a.left });
+///    assert_eq!(view.layout().observe_constraints().n_items(), 6);
+/// ```
+#[macro_export]
+macro_rules! add_constraints {
+    ($layout:expr, { $($clauses:tt)* }) => {{
+        let layout = &$layout;
+        $crate::constraint_clauses!(constraint_add_each [layout] [] [] $($clauses)*)
+    }};
+}
+
+/// Make a `Vec` of [`gtk::Constraint`]s from clauses separated by semicolons, each in the grammar
+/// of [`constraint!`], without adding them to any layout, e.g. to add later or to put in a
+/// [`ConstraintSet`](crate::constraint_view::ConstraintSet). The clauses are split in the same way
+/// as for [`add_constraints!`](crate::add_constraints).
+///
+/// ```
+///    # use springsteel::constraints;
+///    # use gtk::{ConstraintAttribute, ConstraintRelation};
+///    # gtk::init().expect("gtk::init");
+///    # let (title, entry) = (gtk::Label::new(None), gtk::Entry::new());
+///    #
+///    let editing = constraints![
+///        entry.top == title.bottom + 8.0;
+///        entry.left == title.left;
+///        entry.width >= 200.0 @strong;
+///    ];
+///    assert_eq!(editing.len(), 3);
+///    assert_eq!(editing[0].target_attribute(), ConstraintAttribute::Top);
+///    assert_eq!(editing[0].source_attribute(), ConstraintAttribute::Bottom);
+///    assert_eq!(editing[0].constant(), 8.0);
+///    assert_eq!(editing[2].relation(), ConstraintRelation::Ge);
+///    assert_eq!(editing[2].strength(), gtk::ffi::GTK_CONSTRAINT_STRENGTH_STRONG);
+///
+///    let none: Vec<gtk::Constraint> = constraints![];
+///    assert!(none.is_empty());
+/// ```
+#[macro_export]
+macro_rules! constraints {
+    ($($clauses:tt)*) => {
+        $crate::constraint_clauses!(constraint_vec [] [] [] $($clauses)*)
+    };
+}
+
+/// Make a `Vec` of the constraints for each clause split out by [`constraint_clauses!`], for
+/// [`constraints!`](crate::constraints).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! constraint_vec {
+    ([] $([$($clause:tt)*])*) => {
+        vec![$($crate::constraint!($($clause)*)),*]
+    };
+}
+
+/// Add the constraint for each clause split out by [`constraint_clauses!`] to the given layout,
+/// for [`add_constraints!`](crate::add_constraints).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! constraint_add_each {
+    ([$layout:ident] $([$($clause:tt)*])*) => {{
+        $($layout.add_constraint(&$crate::constraint!($($clause)*));)*
+    }};
+}
+
+/// Split a list of clauses separated by semicolons into bracketed groups of tokens, then pass them
+/// after the given arguments to the given callback macro, which has to be exported from this crate.
+/// Empty clauses, e.g. from a trailing semicolon, are dropped.
+///
+/// A semicolon can't be told apart from any other token by a repetition of `tt`, so the clauses are
+/// munched, up to eight tokens at a time, to keep the recursion shallow for long lists: each of the
+/// arms looking for a semicolon at one of the first eight tokens has to fail before a run of eight
+/// can be taken as part of the clause.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! constraint_clauses {
+    ($callback:ident [$($args:tt)*] [$($clauses:tt)*] []) => (
+        $crate::$callback!([$($args)*] $($clauses)*)
+    );
+    ($callback:ident [$($args:tt)*] [$($clauses:tt)*] [$($clause:tt)+]) => (
+        $crate::$callback!([$($args)*] $($clauses)* [$($clause)+])
+    );
+    ($callback:ident [$($args:tt)*] [$($clauses:tt)*] [] ; $($rest:tt)*) => (
+        $crate::constraint_clauses!($callback [$($args)*] [$($clauses)*] [] $($rest)*)
+    );
+    ($callback:ident [$($args:tt)*] [$($clauses:tt)*] [$($clause:tt)+] ; $($rest:tt)*) => (
+        $crate::constraint_clauses!($callback [$($args)*] [$($clauses)* [$($clause)+]] [] $($rest)*)
+    );
+    (
+        $callback:ident [$($args:tt)*] [$($clauses:tt)*] [$($clause:tt)*]
+        $a:tt ; $($rest:tt)*
+    ) => (
+        $crate::constraint_clauses!(
+            $callback [$($args)*] [$($clauses)* [$($clause)* $a]] [] $($rest)*
+        )
+    );
+    (
+        $callback:ident [$($args:tt)*] [$($clauses:tt)*] [$($clause:tt)*]
+        $a:tt $b:tt ; $($rest:tt)*
+    ) => (
+        $crate::constraint_clauses!(
+            $callback [$($args)*] [$($clauses)* [$($clause)* $a $b]] [] $($rest)*
+        )
+    );
+    (
+        $callback:ident [$($args:tt)*] [$($clauses:tt)*] [$($clause:tt)*]
+        $a:tt $b:tt $c:tt ; $($rest:tt)*
+    ) => (
+        $crate::constraint_clauses!(
+            $callback [$($args)*] [$($clauses)* [$($clause)* $a $b $c]] [] $($rest)*
+        )
+    );
+    (
+        $callback:ident [$($args:tt)*] [$($clauses:tt)*] [$($clause:tt)*]
+        $a:tt $b:tt $c:tt $d:tt ; $($rest:tt)*
+    ) => (
+        $crate::constraint_clauses!(
+            $callback [$($args)*] [$($clauses)* [$($clause)* $a $b $c $d]] [] $($rest)*
+        )
+    );
+    (
+        $callback:ident [$($args:tt)*] [$($clauses:tt)*] [$($clause:tt)*]
+        $a:tt $b:tt $c:tt $d:tt $e:tt ; $($rest:tt)*
+    ) => (
+        $crate::constraint_clauses!(
+            $callback [$($args)*] [$($clauses)* [$($clause)* $a $b $c $d $e]] [] $($rest)*
+        )
+    );
+    (
+        $callback:ident [$($args:tt)*] [$($clauses:tt)*] [$($clause:tt)*]
+        $a:tt $b:tt $c:tt $d:tt $e:tt $f:tt ; $($rest:tt)*
+    ) => (
+        $crate::constraint_clauses!(
+            $callback [$($args)*] [$($clauses)* [$($clause)* $a $b $c $d $e $f]] [] $($rest)*
+        )
+    );
+    (
+        $callback:ident [$($args:tt)*] [$($clauses:tt)*] [$($clause:tt)*]
+        $a:tt $b:tt $c:tt $d:tt $e:tt $f:tt $g:tt ; $($rest:tt)*
+    ) => (
+        $crate::constraint_clauses!(
+            $callback [$($args)*] [$($clauses)* [$($clause)* $a $b $c $d $e $f $g]] [] $($rest)*
+        )
+    );
+    (
+        $callback:ident [$($args:tt)*] [$($clauses:tt)*] [$($clause:tt)*]
+        $a:tt $b:tt $c:tt $d:tt $e:tt $f:tt $g:tt $h:tt $($rest:tt)*
+    ) => (
+        $crate::constraint_clauses!(
+            $callback [$($args)*] [$($clauses)*] [$($clause)* $a $b $c $d $e $f $g $h] $($rest)*
+        )
+    );
+    ($callback:ident [$($args:tt)*] [$($clauses:tt)*] [$($clause:tt)*] $($last:tt)+) => (
+        $crate::$callback!([$($args)*] $($clauses)* [$($clause)* $($last)+])
+    );
+}
+
+/// Make a [`ConstraintSet`](crate::constraint_view::ConstraintSet) with the given name and
+/// constraints, e.g. made with [`constraint!`]:
+///
+/// ```
+///    # use springsteel::{constraint, constraint_set};
+///    # gtk::init().expect("gtk::init");
+///    # let (sidebar, body) = (gtk::Label::new(None), gtk::Label::new(None));
+///    #
+///    let phone = constraint_set!["phone";
+///        constraint!(body.top == sidebar.bottom),
+///        constraint!(body.left == left),
+///    ];
+///    assert_eq!(phone.name(), "phone");
+///    assert_eq!(phone.constraints().len(), 2);
+/// ```
+#[macro_export]
+macro_rules! constraint_set {
+    ($name:expr $(; $($constraint:expr),* $(,)?)?) => {
+        $crate::constraint_view::ConstraintSet::new($name)$($(.constraint($constraint))*)?
     };
 }
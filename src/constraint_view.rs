@@ -1,61 +1,766 @@
 //! Provides [`ConstraintView`], a [`gtk::Widget`] which contains other widgets and lays them out
 //! using [`gtk::ConstraintLayout`].
 
+mod accessible;
+mod animate;
+mod breakpoints;
+mod buildable;
+mod builder;
+mod collapse;
+mod content;
+mod design;
+mod diagnose;
+mod equalize;
+mod insets;
+mod margin;
+mod order;
+mod overlay;
+mod pages;
+mod parts;
+mod probe;
+mod set;
+mod size;
+mod spacer;
+mod splitter;
+mod stack;
+mod subclass;
+mod tracked;
+
+pub use animate::{ConstantAnimation, Easing};
+pub use builder::ConstraintViewBuilder;
+pub use collapse::Collapse;
+pub use diagnose::ConstraintIssue;
+pub use equalize::{EqualizeGroup, NotASizeAttribute};
+pub use insets::Insets;
+pub use set::{AsConstraintLayout, ConstraintSet};
+pub use splitter::{Splitter, SplitterOptions};
+pub use stack::{StackAlign, StackOptions};
+pub use subclass::ConstraintViewImpl;
+pub use tracked::{ConstraintId, TrackedConstraint};
+
 mod imp {
+    use glib::once_cell::sync::Lazy;
     use glib::subclass::prelude::{ObjectImpl, ObjectSubclass, ObjectSubclassExt as _};
+    use glib::subclass::InitializingObject;
+    use glib::{
+        Cast as _, ParamSpec, ParamSpecBoolean, ParamSpecFloat, ParamSpecString, ParamSpecUInt,
+        ToValue as _, Value,
+    };
     use gtk::prelude::WidgetExt as _;
-    use gtk::subclass::prelude::{WidgetClassSubclassExt, WidgetImpl};
+    use gtk::subclass::prelude::{
+        BuildableImpl, WidgetClassSubclassExt, WidgetImpl, WidgetImplExt as _,
+    };
+    use std::cell::{Cell, RefCell};
 
     #[derive(Default)]
-    pub struct ConstraintView;
+    pub struct ConstraintView {
+        /// Design mode state, if [enabled](super::ConstraintView::enable_design_mode).
+        pub(super) design: RefCell<Option<super::design::DesignMode>>,
+
+        /// Debug overlay state, if [enabled](super::ConstraintView::set_debug_overlay).
+        pub(super) debug_overlay: RefCell<Option<super::overlay::DebugOverlay>>,
+
+        /// Padding between the edges of the view and its
+        /// [content guide](super::ConstraintView::content_guide), in the order of
+        /// [`PADDING_PROPERTIES`](super::content::PADDING_PROPERTIES).
+        pub(super) padding: Cell<[f32; 4]>,
+
+        /// The content guide and the constraints holding it in from the edges, once requested.
+        pub(super) content: RefCell<Option<super::content::ContentGuide>>,
+
+        /// The margin guide and the constraints holding it in from the edges, once requested.
+        pub(super) margin: RefCell<Option<super::margin::MarginGuide>>,
+
+        /// Whether visible-child mode is on, once a
+        /// [visible child](super::ConstraintView::set_visible_child) has been set.
+        pub(super) paged: Cell<bool>,
+
+        /// The child shown in visible-child mode, if any.
+        pub(super) visible_child: RefCell<Option<gtk::Widget>>,
+
+        /// Milliseconds to crossfade between visible children for, per the `transition-duration`
+        /// property.
+        pub(super) transition_duration: Cell<u32>,
+
+        /// The crossfade between visible children in progress, if any.
+        pub(super) crossfade: RefCell<Option<super::pages::Crossfade>>,
+
+        /// Animations of the constants of tracked constraints, per
+        /// [`animate_constant`](super::ConstraintView::animate_constant).
+        pub(super) animations: RefCell<super::animate::Animations>,
+
+        /// Constraint sets switched between by the width of the view, per
+        /// [`add_breakpoint`](super::ConstraintView::add_breakpoint).
+        pub(super) breakpoints: RefCell<Vec<super::breakpoints::Breakpoint>>,
+
+        /// Whether constraint issues are logged on map, per the `debug-constraints` property.
+        pub(super) debug_constraints: Cell<bool>,
+
+        /// Minimum and maximum sizes of the view and the constraints enforcing them, in the order
+        /// of [`SIZE_PROPERTIES`](super::size::SIZE_PROPERTIES).
+        pub(super) size_limits: RefCell<[super::size::SizeLimit; 4]>,
+
+        /// Whether [`measure`](WidgetImpl::measure) reports what the layout measured unchanged,
+        /// per the `raw-measure` property.
+        pub(super) raw_measure: Cell<bool>,
+
+        /// Children whose constraints are parked while they're hidden, per
+        /// [`set_collapse_when_hidden`](super::ConstraintView::set_collapse_when_hidden).
+        pub(super) collapsing: RefCell<Vec<super::collapse::CollapseWatch>>,
+
+        /// Constraints added with [`add_constraint`](super::ConstraintView::add_constraint).
+        pub(super) tracked: RefCell<Vec<super::tracked::TrackedEntry>>,
+
+        /// The id to give the next constraint added with
+        /// [`add_constraint`](super::ConstraintView::add_constraint).
+        pub(super) next_constraint_id: Cell<u64>,
+
+        /// Streams returned by [`allocations`](super::ConstraintView::allocations), pushed to
+        /// after every allocation until they're dropped.
+        pub(super) allocation_streams: RefCell<Vec<crate::value_stream::WeakValueStream<()>>>,
+
+        /// Spawner of futures tied to the view, once [requested](super::ConstraintView::spawner).
+        pub(super) spawner: RefCell<Option<crate::widget_spawner::WidgetSpawner>>,
+    }
 
     #[glib::object_subclass]
     impl ObjectSubclass for ConstraintView {
         const NAME: &'static str = "SpringsteelWorkbenchConstraintView";
         type Type = super::ConstraintView;
         type ParentType = gtk::Widget;
+        type Interfaces = (gtk::Buildable,);
 
         fn class_init(klass: &mut Self::Class) {
             klass.set_layout_manager_type::<gtk::ConstraintLayout>();
+            klass.set_css_name("constraintview");
+            klass.set_accessible_role(gtk::AccessibleRole::Group);
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            // Runs before the instance initialization of subclasses, which is where composite
+            // templates are parsed.
+            super::buildable::install_custom_tags(unsafe { obj.as_ref() });
         }
     }
 
     impl ObjectImpl for ConstraintView {
+        fn properties() -> &'static [ParamSpec] {
+            static PROPERTIES: Lazy<Vec<ParamSpec>> = Lazy::new(|| {
+                let padding = super::content::PADDING_PROPERTIES.iter().map(|name| {
+                    ParamSpecFloat::builder(name)
+                        .minimum(0.0)
+                        .explicit_notify()
+                        .build()
+                });
+                let sizes = super::size::SIZE_PROPERTIES.iter().map(|name| {
+                    ParamSpecFloat::builder(name)
+                        .minimum(-1.0)
+                        .default_value(-1.0)
+                        .explicit_notify()
+                        .build()
+                });
+                let raw_measure = ParamSpecBoolean::builder("raw-measure")
+                    .explicit_notify()
+                    .build();
+                let debug_constraints = ParamSpecBoolean::builder("debug-constraints")
+                    .explicit_notify()
+                    .build();
+                let visible_child_name = ParamSpecString::builder("visible-child-name")
+                    .explicit_notify()
+                    .build();
+                let transition_duration = ParamSpecUInt::builder("transition-duration")
+                    .explicit_notify()
+                    .build();
+                padding
+                    .chain(sizes)
+                    .chain([
+                        raw_measure,
+                        debug_constraints,
+                        visible_child_name,
+                        transition_duration,
+                    ])
+                    .collect()
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(&self, _id: usize, value: &Value, pspec: &ParamSpec) {
+            let obj = self.obj();
+            match pspec.name() {
+                "raw-measure" => {
+                    obj.set_raw_measure(value.get().expect("ConstraintView raw-measure type"));
+                }
+                "debug-constraints" => {
+                    let debug = value.get().expect("ConstraintView debug-constraints type");
+                    obj.set_debug_constraints(debug);
+                }
+                "visible-child-name" => {
+                    let name_opt: Option<String> =
+                        value.get().expect("ConstraintView visible-child-name type");
+                    obj.set_visible_child_name(name_opt.as_deref());
+                }
+                "transition-duration" => {
+                    let millis = value
+                        .get()
+                        .expect("ConstraintView transition-duration type");
+                    obj.set_transition_duration(millis);
+                }
+                name if super::size::SIZE_PROPERTIES.contains(&name) => {
+                    let size = value.get().expect("ConstraintView size property type");
+                    obj.set_size_limit(name, size);
+                }
+                name => {
+                    let padding = value.get().expect("ConstraintView padding property type");
+                    obj.set_padding_edge(name, padding);
+                }
+            }
+        }
+
+        fn property(&self, _id: usize, pspec: &ParamSpec) -> Value {
+            match pspec.name() {
+                "raw-measure" => self.raw_measure.get().to_value(),
+                "debug-constraints" => self.debug_constraints.get().to_value(),
+                "visible-child-name" => self.obj().visible_child_name().to_value(),
+                "transition-duration" => self.transition_duration.get().to_value(),
+                name if super::size::SIZE_PROPERTIES.contains(&name) => {
+                    self.obj().size_limit(name).to_value()
+                }
+                name => self.obj().padding_edge(name).to_value(),
+            }
+        }
+
         fn dispose(&self) {
             let obj = self.obj();
 
+            // Futures tied to the view might hold it or its children, so drop them first.
+            if let Some(spawner) = self.spawner.borrow_mut().take() {
+                spawner.abort_all();
+            }
+
+            if let Some(design) = self.design.borrow_mut().take() {
+                design.disable(&obj);
+            }
+            if let Some(overlay) = self.debug_overlay.borrow_mut().take() {
+                overlay.disable(&obj);
+            }
+
+            obj.clear_collapse_watches();
+            self.crossfade.borrow_mut().take();
+            self.visible_child.borrow_mut().take();
+            obj.finish_constant_animations();
             while let Some(child) = obj.first_child() {
                 child.unparent();
             }
+
+            // The layout outlives the view if anything else holds it, so empty it of constraints
+            // and guides too, as those would keep the children and guides alive.
+            self.content.borrow_mut().take();
+            self.margin.borrow_mut().take();
+            self.tracked.borrow_mut().clear();
+            self.breakpoints.borrow_mut().clear();
+            let layout_opt = obj
+                .layout_manager()
+                .and_then(|manager| manager.downcast::<gtk::ConstraintLayout>().ok());
+            if let Some(layout) = layout_opt {
+                obj.clear_size_limits();
+                layout.remove_all_constraints();
+                for guide in obj.guides() {
+                    layout.remove_guide(&guide);
+                }
+            }
         }
     }
 
-    impl WidgetImpl for ConstraintView {}
+    impl BuildableImpl for ConstraintView {}
+
+    impl WidgetImpl for ConstraintView {
+        fn size_allocate(&self, width: i32, height: i32, baseline: i32) {
+            // Switch constraint sets first, so that the children are allocated by the new one.
+            self.obj().update_breakpoints(f64::from(width));
+            self.parent_size_allocate(width, height, baseline);
+            let streams: Vec<_> = {
+                let mut streams = self.allocation_streams.borrow_mut();
+                streams.retain(|s| s.upgrade().is_some());
+                streams.iter().filter_map(|s| s.upgrade()).collect()
+            };
+            for stream in streams {
+                stream.push(());
+            }
+        }
+
+        fn map(&self) {
+            self.parent_map();
+            self.obj().log_constraint_issues();
+        }
+
+        fn unmap(&self) {
+            self.obj().finish_constant_animations();
+            self.parent_unmap();
+        }
+
+        fn measure(&self, orientation: gtk::Orientation, for_size: i32) -> (i32, i32, i32, i32) {
+            let measured = self.parent_measure(orientation, for_size);
+            if self.raw_measure.get() {
+                measured
+            } else {
+                self.obj().adjust_measure(orientation, measured)
+            }
+        }
+
+        fn snapshot(&self, snapshot: &gtk::Snapshot) {
+            self.parent_snapshot(snapshot);
+
+            if let Some(design) = self.design.borrow().as_ref() {
+                design.snapshot(&self.obj(), snapshot);
+            }
+            if let Some(overlay) = self.debug_overlay.borrow().as_ref() {
+                overlay.snapshot(&self.obj(), snapshot);
+            }
+        }
+    }
 }
 
-use glib::{Cast, Object};
-use gtk::prelude::WidgetExt as _;
+use crate::widget_spawner::WidgetSpawner;
+use crate::ValueStream;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::{Cast, IsA, Object, ObjectExt as _, StaticType as _};
+use gtk::prelude::{ListModelExt as _, WidgetExt as _};
 
 glib::wrapper! {
     /// [`gtk::Widget`] container (like [`gtk::Box`] or [`gtk::Grid`]) which lays out its children
     /// using a [`gtk::ConstraintLayout`].
+    ///
+    /// Children are drawn in the order of their sibling list, which is the order they were added
+    /// in unless moved with [`raise_child`](ConstraintView::raise_child),
+    /// [`lower_child`](ConstraintView::lower_child), or
+    /// [`insert_child_above`](ConstraintView::insert_child_above), so where they overlap the later
+    /// ones are on top.
     pub struct ConstraintView(ObjectSubclass<imp::ConstraintView>)
         @extends gtk::Widget,
         @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
 }
 
 impl ConstraintView {
-    /// Create a new empty [`ConstraintView`].
+    /// Create a new empty [`ConstraintView`], whose CSS node is named `constraintview`.
+    ///
+    /// When the view is destroyed, its children, guides, and constraints are all released, even if
+    /// something else holds on to its layout:
+    ///
+    /// ```
+    ///    # use springsteel::{add_constraint, ConstraintView};
+    ///    # use glib::{Cast as _, ObjectExt as _};
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let mut layouts = Vec::new();
+    ///    let mut weak_refs: Vec<glib::WeakRef<glib::Object>> = Vec::new();
+    ///    for _ in 0..10 {
+    ///        let view = ConstraintView::new();
+    ///        let label = gtk::Label::new(Some("leak?"));
+    ///        view.add_child(&label);
+    ///        let guide = view.add_guide_named("spacer");
+    ///        let constraint = springsteel::constraint!(label.left == guide.right);
+    ///        view.add_constraint(&constraint);
+    ///        add_constraint!(view.layout(), guide.width == 10.0);
+    ///        view.content_guide();
+    ///
+    ///        layouts.push(view.layout());
+    ///        weak_refs.push(view.upcast_ref::<glib::Object>().downgrade());
+    ///        weak_refs.push(label.upcast_ref::<glib::Object>().downgrade());
+    ///        weak_refs.push(guide.upcast_ref::<glib::Object>().downgrade());
+    ///        weak_refs.push(constraint.upcast_ref::<glib::Object>().downgrade());
+    ///    }
+    ///
+    ///    assert!(weak_refs.iter().all(|weak| weak.upgrade().is_none()));
+    /// ```
     pub fn new() -> Self {
         Object::new(&[])
     }
 
+    /// Create a new empty [`ConstraintView`] whose CSS node has the given name rather than
+    /// `constraintview`, so that different kinds of container can be styled without resorting to
+    /// `#name` selectors.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    assert_eq!(ConstraintView::new().css_name(), "constraintview");
+    ///    assert_eq!(ConstraintView::new_with_css_name("toolbar").css_name(), "toolbar");
+    /// ```
+    pub fn new_with_css_name(css_name: &str) -> Self {
+        Object::new(&[("css-name", &css_name)])
+    }
+
+    /// Create a new empty [`ConstraintView`] laid out by the given [`gtk::ConstraintLayout`]
+    /// rather than one of its own, e.g. one already holding guides and constraints made from
+    /// visual format descriptions. The layout can't belong to another widget already.
+    ///
+    /// ```
+    ///    # use springsteel::testing::LayoutHarness;
+    ///    # use springsteel::{add_constraint, ConstraintView};
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let layout = gtk::ConstraintLayout::new();
+    ///    let gutter = gtk::ConstraintGuide::builder().name("gutter").build();
+    ///    layout.add_guide(&gutter);
+    ///    add_constraint!(layout, gutter.left == left + 30.0);
+    ///    add_constraint!(layout, gutter.width == 12.0);
+    ///
+    ///    let view = ConstraintView::with_layout(&layout);
+    ///    assert_eq!(view.layout(), layout);
+    ///    assert_eq!(view.guide("gutter"), Some(gutter.clone()));
+    ///
+    ///    let label = gtk::Label::new(Some("after the gutter"));
+    ///    view.add_child(&label);
+    ///    add_constraint!(view, label.left == gutter.right);
+    ///    add_constraint!(view, label.top == top);
+    ///
+    ///    let harness = LayoutHarness::new(&view, 300, 100);
+    ///    assert_eq!(harness.allocation_of(&label).x(), 42);
+    /// ```
+    pub fn with_layout(layout: &gtk::ConstraintLayout) -> Self {
+        Object::new(&[("layout-manager", layout)])
+    }
+
+    /// Add a style class to this view, as
+    /// [`add_css_class`](gtk::prelude::WidgetExt::add_css_class).
+    pub fn add_style_class(&self, class: &str) {
+        self.add_css_class(class);
+    }
+
+    /// Remove a style class from this view, as
+    /// [`remove_css_class`](gtk::prelude::WidgetExt::remove_css_class).
+    pub fn remove_style_class(&self, class: &str) {
+        self.remove_css_class(class);
+    }
+
+    /// Register the [`ConstraintView`] type with GObject, so that GtkBuilder XML, including
+    /// templates, can refer to it as `SpringsteelWorkbenchConstraintView` before any view has been
+    /// made in code. Within the view, a `<constraints>` element, and a `<guides>` element, are
+    /// handled as for a [`gtk::ConstraintLayout`], referring to children by id:
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use gtk::prelude::ListModelExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    ConstraintView::ensure_type();
+    ///    let builder = gtk::Builder::from_string(
+    ///        r#"
+    ///        <interface>
+    ///          <object class="SpringsteelWorkbenchConstraintView" id="view">
+    ///            <child>
+    ///              <object class="GtkLabel" id="label"/>
+    ///            </child>
+    ///            <constraints>
+    ///              <constraint target="label" target-attribute="start" relation="eq"
+    ///                          source="super" source-attribute="start" constant="12"/>
+    ///              <constraint target="label" target-attribute="width" relation="ge"
+    ///                          constant="100" strength="strong"/>
+    ///            </constraints>
+    ///          </object>
+    ///        </interface>
+    ///        "#,
+    ///    );
+    ///
+    ///    let view: ConstraintView = builder.object("view").expect("view");
+    ///    assert_eq!(view.layout().observe_constraints().n_items(), 2);
+    /// ```
+    pub fn ensure_type() {
+        Self::static_type();
+    }
+
     /// Return the [`gtk::ConstraintLayout`] for this view.
+    ///
+    /// Panics if the layout manager of the view has been removed, or replaced by one which isn't
+    /// a [`gtk::ConstraintLayout`], with
+    /// [`set_layout_manager`](gtk::prelude::WidgetExt::set_layout_manager).
     pub fn layout(&self) -> gtk::ConstraintLayout {
-        unsafe {
-            self.layout_manager()
-                .expect("ConstraintView::layout_manager")
-                .unsafe_cast()
+        match self.layout_manager().map(|manager| manager.downcast()) {
+            Some(Ok(layout)) => layout,
+            Some(Err(manager)) => panic!(
+                "ConstraintView::layout: {} has a {} as its layout manager",
+                self.widget_name(),
+                manager.type_()
+            ),
+            None => panic!(
+                "ConstraintView::layout: {} has no layout manager",
+                self.widget_name()
+            ),
+        }
+    }
+
+    /// Add a widget as the last child of this view. If the widget already has a parent, this
+    /// view or another, a warning is logged and nothing is done, as reparenting a widget by
+    /// accident is easy and leaves it in the wrong layout.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use glib::Cast as _;
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let other = ConstraintView::new();
+    ///    let label = gtk::Label::new(Some("hello"));
+    ///    view.add_child(&label);
+    ///    other.add_child(&label);
+    ///    assert_eq!(label.parent(), Some(view.clone().upcast()));
+    ///
+    ///    view.remove_child(&label);
+    ///    other.add_child(&label);
+    ///    assert_eq!(label.parent(), Some(other.clone().upcast()));
+    ///
+    ///    other.clear_children();
+    ///    assert_eq!(label.parent(), None);
+    ///    assert_eq!(other.first_child(), None);
+    ///
+    ///    other.add_child(&label);
+    ///    drop(other);
+    ///    assert_eq!(label.parent(), None);
+    /// ```
+    pub fn add_child(&self, child: &impl IsA<gtk::Widget>) {
+        if let Some(parent) = child.parent() {
+            log::warn!(
+                "ConstraintView::add_child: widget {} already has a parent {}",
+                child.widget_name(),
+                parent.widget_name()
+            );
+            return;
+        }
+        child.set_parent(self);
+        if self.imp().paged.get() {
+            child.set_visible(false);
+        }
+    }
+
+    /// Remove a child from this view, along with every constraint of the layout which refers to
+    /// it. If the widget isn't a child of this view, a warning is logged and nothing is done.
+    ///
+    /// ```
+    ///    # use springsteel::{add_constraint, ConstraintView};
+    ///    # use gtk::prelude::ListModelExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let (label, button) = (gtk::Label::new(None), gtk::Button::new());
+    ///    view.add_child(&label);
+    ///    view.add_child(&button);
+    ///    let layout = view.layout();
+    ///    add_constraint!(layout, label.left == left);
+    ///    add_constraint!(layout, button.left == label.right);
+    ///    add_constraint!(layout, button.right == right);
+    ///
+    ///    view.remove_child(&label);
+    ///    assert_eq!(layout.observe_constraints().n_items(), 1);
+    /// ```
+    pub fn remove_child(&self, child: &impl IsA<gtk::Widget>) {
+        if child.parent().as_ref() != Some(self.upcast_ref::<gtk::Widget>()) {
+            log::warn!(
+                "ConstraintView::remove_child: widget {} isn't a child of {}",
+                child.widget_name(),
+                self.widget_name()
+            );
+            return;
+        }
+
+        self.forget_collapse(child.upcast_ref());
+        self.forget_visible_child(child.upcast_ref());
+        self.forget_breakpoint_constraints_for(child.upcast_ref());
+        // Also drops constraints parked while another child is collapsed.
+        self.remove_constraints_for(child);
+
+        let layout = self.layout();
+        let target = child.upcast_ref::<gtk::ConstraintTarget>();
+        let constraints = layout.observe_constraints();
+        let referring: Vec<gtk::Constraint> = (0..constraints.n_items())
+            .filter_map(|i| constraints.item(i)?.downcast::<gtk::Constraint>().ok())
+            .filter(|c| c.target().as_ref() == Some(target) || c.source().as_ref() == Some(target))
+            .collect();
+        for constraint in referring {
+            layout.remove_constraint(&constraint);
+        }
+
+        child.unparent();
+    }
+
+    /// Remove every child from this view, along with the constraints which refer to them. The
+    /// widgets which design mode and the debug overlay add to show guides are left in place.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    view.add_child(&gtk::Label::new(Some("a")));
+    ///    view.add_guide_named("gutter");
+    ///    view.enable_design_mode(true);
+    ///
+    ///    view.clear_children();
+    ///    assert_eq!(view.children().count(), 0);
+    ///    view.enable_design_mode(false);
+    /// ```
+    pub fn clear_children(&self) {
+        for child in self.children() {
+            self.remove_child(&child);
+        }
+    }
+
+    /// Return an iterator over the children of this view in sibling order, first to last. The
+    /// children are collected up front, so the view can be changed while iterating, e.g. to
+    /// [remove](Self::remove_child) some of them.
+    ///
+    /// The widgets which [design mode](Self::enable_design_mode) and the
+    /// [debug overlay](Self::set_debug_overlay) add to show guides aren't included.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    for name in ["a", "b", "c"] {
+    ///        let label = gtk::Label::new(Some(name));
+    ///        label.set_widget_name(name);
+    ///        view.add_child(&label);
+    ///    }
+    ///
+    ///    let names: Vec<_> = view.children().map(|c| c.widget_name()).collect();
+    ///    assert_eq!(names, ["a", "b", "c"]);
+    ///
+    ///    view.add_guide_named("gutter");
+    ///    view.enable_design_mode(true);
+    ///    assert_eq!(view.children().count(), 3);
+    ///    view.enable_design_mode(false);
+    ///
+    ///    for child in view.children() {
+    ///        view.remove_child(&child);
+    ///    }
+    ///    assert_eq!(view.children().count(), 0);
+    /// ```
+    pub fn children(&self) -> impl Iterator<Item = gtk::Widget> {
+        let mut children = Vec::new();
+        let mut child_opt = self.first_child();
+        while let Some(child) = child_opt {
+            child_opt = child.next_sibling();
+            if !self.is_probe_widget(&child) {
+                children.push(child);
+            }
+        }
+        children.into_iter()
+    }
+
+    /// Return the first child of this view whose
+    /// [widget name](gtk::prelude::WidgetExt::widget_name) is `name`, e.g. to find the widget made
+    /// for some data item and update the constraints targeting it.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use glib::Cast as _;
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let label = gtk::Label::new(Some("item 7"));
+    ///    label.set_widget_name("item-7");
+    ///    view.add_child(&label);
+    ///
+    ///    assert_eq!(view.child_by_name("item-7"), Some(label.upcast()));
+    ///    assert_eq!(view.child_by_name("item-8"), None);
+    /// ```
+    pub fn child_by_name(&self, name: &str) -> Option<gtk::Widget> {
+        self.children().find(|child| child.widget_name() == name)
+    }
+
+    /// Create a guide with the given name and add it to the layout of this view, so that it can
+    /// be looked up with [`guide`](Self::guide) wherever else the layout is described.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use gtk::prelude::ListModelExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let sidebar = view.add_guide_named("sidebar");
+    ///    assert_eq!(view.guide("sidebar"), Some(sidebar));
+    ///    assert_eq!(view.guide("footer"), None);
+    ///
+    ///    let layout = view.layout();
+    ///    drop(view);
+    ///    assert_eq!(layout.observe_guides().n_items(), 0);
+    /// ```
+    ///
+    /// Panics if the view already has a guide with that name, including the
+    /// [content guide](Self::content_guide), named `content`.
+    pub fn add_guide_named(&self, name: &str) -> gtk::ConstraintGuide {
+        if self.guide(name).is_some() {
+            panic!(
+                "ConstraintView::add_guide_named: view {} already has a guide named {:?}",
+                self.widget_name(),
+                name
+            );
         }
+
+        let guide = gtk::ConstraintGuide::builder().name(name).build();
+        self.layout().add_guide(&guide);
+        guide
+    }
+
+    /// Return the guide of this view's layout with the given name, if any.
+    pub fn guide(&self, name: &str) -> Option<gtk::ConstraintGuide> {
+        self.guides()
+            .into_iter()
+            .find(|guide| guide.name().as_deref() == Some(name))
+    }
+
+    /// Return every guide of this view's layout, in order of addition.
+    fn guides(&self) -> Vec<gtk::ConstraintGuide> {
+        let guides = self.layout().observe_guides();
+        (0..guides.n_items())
+            .filter_map(|i| guides.item(i)?.downcast::<gtk::ConstraintGuide>().ok())
+            .collect()
+    }
+
+    /// Return a stream which outputs after every allocation of this view, once its constraints have
+    /// been resolved and its children allocated, e.g. to reposition a popover or redraw something
+    /// which depends on where the children ended up. The stream is
+    /// [coalescing](crate::ValueStream::coalescing), so the many allocations during a window
+    /// resize queue at most one output. The view only holds the stream weakly, so it stops being
+    /// pushed to once every clone of it is dropped.
+    ///
+    /// ```
+    ///    # use springsteel::{assert_pending, assert_yields, ConstraintView};
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let mut allocations = view.allocations();
+    ///    assert_pending!(allocations);
+    ///
+    ///    view.measure(gtk::Orientation::Horizontal, -1);
+    ///    view.measure(gtk::Orientation::Vertical, -1);
+    ///    for width in [100, 110, 120] {
+    ///        view.size_allocate(&gtk::Allocation::new(0, 0, width, 50), -1);
+    ///    }
+    ///    assert_yields!(allocations, 1);
+    ///    assert_pending!(allocations);
+    /// ```
+    pub fn allocations(&self) -> ValueStream<()> {
+        let stream = ValueStream::coalescing();
+        let mut streams = self.imp().allocation_streams.borrow_mut();
+        streams.retain(|s| s.upgrade().is_some());
+        streams.push(stream.downgrade());
+        stream
+    }
+
+    /// Return the [`WidgetSpawner`] for this view, so futures consuming streams for the view can
+    /// be tied to its lifetime and aborted when it's destroyed.
+    pub fn spawner(&self) -> WidgetSpawner {
+        self.imp()
+            .spawner
+            .borrow_mut()
+            .get_or_insert_with(|| WidgetSpawner::for_widget(self))
+            .clone()
     }
 }
@@ -0,0 +1,44 @@
+//! Accessibility of [`ConstraintView`], so that a view acting as a composite control, e.g. a
+//! toolbar or a region of a form, can describe itself to assistive technologies.
+
+use super::ConstraintView;
+use glib::Object;
+use gtk::prelude::AccessibleExtManual as _;
+
+impl ConstraintView {
+    /// Create a new empty [`ConstraintView`] with the given accessible role rather than
+    /// [`Group`](gtk::AccessibleRole::Group). The role can only be set on construction.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use gtk::prelude::AccessibleExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    assert_eq!(ConstraintView::new().accessible_role(), gtk::AccessibleRole::Group);
+    ///
+    ///    let toolbar = ConstraintView::new_with_accessible_role(gtk::AccessibleRole::Toolbar);
+    ///    toolbar.set_accessible_label("Formatting");
+    ///    toolbar.set_accessible_description("Bold, italic, and underline");
+    ///    assert_eq!(toolbar.accessible_role(), gtk::AccessibleRole::Toolbar);
+    ///    assert!(gtk::test_accessible_has_property(&toolbar, gtk::AccessibleProperty::Label));
+    ///    assert!(gtk::test_accessible_has_property(
+    ///        &toolbar,
+    ///        gtk::AccessibleProperty::Description,
+    ///    ));
+    /// ```
+    pub fn new_with_accessible_role(role: gtk::AccessibleRole) -> Self {
+        Object::new(&[("accessible-role", &role)])
+    }
+
+    /// Set the label assistive technologies announce for this view, as the
+    /// [`Label`](gtk::accessible::Property::Label) accessible property.
+    pub fn set_accessible_label(&self, label: &str) {
+        self.update_property(&[gtk::accessible::Property::Label(label)]);
+    }
+
+    /// Set the description assistive technologies give for this view, as the
+    /// [`Description`](gtk::accessible::Property::Description) accessible property.
+    pub fn set_accessible_description(&self, description: &str) {
+        self.update_property(&[gtk::accessible::Property::Description(description)]);
+    }
+}
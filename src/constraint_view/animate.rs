@@ -0,0 +1,255 @@
+//! Animating the constants of tracked constraints of a [`ConstraintView`] over its frame clock.
+//! [`gtk::Constraint`] is immutable, so each frame replaces every animating constraint with one
+//! differing only in its constant, all from a single tick callback so that the layout is solved
+//! once per frame however many are running.
+
+use super::tracked::ConstraintId;
+use super::ConstraintView;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use gtk::prelude::{WidgetExt as _, WidgetExtManual as _};
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// How an animation made by [`ConstraintView::animate_constant`] progresses over its duration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Easing {
+    /// At a constant rate.
+    Linear,
+
+    /// Accelerating from rest to halfway, then decelerating to rest, along a cubic curve.
+    EaseInOutCubic,
+}
+
+impl Easing {
+    /// Map a fraction of the duration elapsed, from `0.0` to `1.0`, to the fraction of the
+    /// distance covered.
+    ///
+    /// ```
+    ///    # use springsteel::constraint_view::Easing;
+    ///    #
+    ///    assert_eq!(Easing::Linear.apply(0.25), 0.25);
+    ///    assert_eq!(Easing::EaseInOutCubic.apply(0.25), 0.0625);
+    ///    assert_eq!(Easing::EaseInOutCubic.apply(0.5), 0.5);
+    ///    assert_eq!(Easing::EaseInOutCubic.apply(1.0), 1.0);
+    /// ```
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseInOutCubic if t < 0.5 => 4.0 * t * t * t,
+            Self::EaseInOutCubic => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+        }
+    }
+}
+
+/// State of a [`ConstantAnimation`] shared with the view running it.
+#[derive(Default)]
+struct AnimationInner {
+    done: bool,
+    waker_opt: Option<Waker>,
+}
+
+/// A [`Future`] which resolves when an animation started by
+/// [`ConstraintView::animate_constant`] ends, whether by reaching its target or by being
+/// interrupted.
+#[derive(Default)]
+pub struct ConstantAnimation {
+    inner: Rc<RefCell<AnimationInner>>,
+}
+
+/// A [`ConstantAnimation`] can be unpinned as its state is a reference counted pointer.
+impl Unpin for ConstantAnimation {}
+
+impl Future for ConstantAnimation {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let mut inner = self.inner.borrow_mut();
+        if inner.done {
+            Poll::Ready(())
+        } else {
+            inner.waker_opt = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// Resolve the [`ConstantAnimation`] sharing the given state.
+fn finish(inner: &RefCell<AnimationInner>) {
+    let waker_opt = {
+        let mut inner = inner.borrow_mut();
+        inner.done = true;
+        inner.waker_opt.take()
+    };
+    if let Some(waker) = waker_opt {
+        waker.wake();
+    }
+}
+
+/// An animation of the constant of one tracked constraint.
+struct Running {
+    id: ConstraintId,
+    from: f64,
+    to: f64,
+    duration_us: f64,
+    easing: Easing,
+
+    /// The frame time of the first tick, once it has happened.
+    start_opt: Option<i64>,
+
+    inner: Rc<RefCell<AnimationInner>>,
+}
+
+/// The animations running in a view and the tick callback driving them, if any are.
+#[derive(Default)]
+pub(super) struct Animations {
+    tick_opt: Option<gtk::TickCallbackId>,
+    running: Vec<Running>,
+}
+
+impl ConstraintView {
+    /// Animate the constant of the constraint added with [`add_constraint`](Self::add_constraint)
+    /// under the given id from its present value to `to` over the given duration, replacing it
+    /// each frame with one whose constant is further along, under the same id. Returns a
+    /// [`ConstantAnimation`] which resolves when the animation ends, so that it can be awaited
+    /// before whatever comes next.
+    ///
+    /// Starting another animation of the same constraint interrupts this one where it got to.
+    /// While the view isn't mapped, or if the duration is zero, the constant is set to `to`
+    /// immediately, and unmapping the view jumps running animations to their ends. If the
+    /// constraint is removed, the animation ends there.
+    ///
+    /// ```
+    ///    # use springsteel::constraint_view::Easing;
+    ///    # use springsteel::glib_future::glib_spawn;
+    ///    # use springsteel::{constraint, ConstraintView};
+    ///    # use std::time::Duration;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let panel = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    ///    view.add_child(&panel);
+    ///    let slide = view.add_constraint(&constraint!(panel.left == right));
+    ///
+    ///    let shown = glib_spawn(view.animate_constant(
+    ///        slide,
+    ///        -200.0,
+    ///        Duration::from_millis(250),
+    ///        Easing::EaseInOutCubic,
+    ///    ));
+    ///    while glib::MainContext::default().iteration(false) {}
+    ///
+    ///    // Unmapped, the view has no frames to animate over.
+    ///    assert!(shown.is_finished());
+    ///    assert_eq!(view.tracked_constraints()[0].id, slide);
+    ///    assert_eq!(view.tracked_constraints()[0].constant, -200.0);
+    /// ```
+    ///
+    /// If the constraint isn't tracked, a warning is logged and the animation ends immediately.
+    pub fn animate_constant(
+        &self,
+        id: ConstraintId,
+        to: f64,
+        duration: Duration,
+        easing: Easing,
+    ) -> ConstantAnimation {
+        let animation = ConstantAnimation::default();
+        let from = match self.tracked_constant(id) {
+            Some(from) => from,
+            None => {
+                log::warn!(
+                    "ConstraintView::animate_constant: {} has no tracked constraint {:?}",
+                    self.widget_name(),
+                    id
+                );
+                finish(&animation.inner);
+                return animation;
+            }
+        };
+
+        self.interrupt_animation(id);
+        if duration.is_zero() || !self.is_mapped() {
+            self.update_tracked(id, |parts| parts.constant = to);
+            finish(&animation.inner);
+            return animation;
+        }
+
+        let mut animations = self.imp().animations.borrow_mut();
+        animations.running.push(Running {
+            id,
+            from,
+            to,
+            duration_us: duration.as_micros() as f64,
+            easing,
+            start_opt: None,
+            inner: animation.inner.clone(),
+        });
+        if animations.tick_opt.is_none() {
+            let tick = self.add_tick_callback(|view, frame_clock| {
+                glib::Continue(view.tick_animations(frame_clock.frame_time()))
+            });
+            animations.tick_opt = Some(tick);
+        }
+        animation
+    }
+
+    /// Jump every running animation to its end.
+    pub(super) fn finish_constant_animations(&self) {
+        let animations = self.imp().animations.take();
+        if let Some(tick) = animations.tick_opt {
+            tick.remove();
+        }
+        for animation in animations.running {
+            self.update_tracked(animation.id, |parts| parts.constant = animation.to);
+            finish(&animation.inner);
+        }
+    }
+
+    /// End the running animation of the given constraint, if any, leaving it where it got to.
+    pub(super) fn interrupt_animation(&self, id: ConstraintId) {
+        let interrupted: Vec<Running> = {
+            let mut animations = self.imp().animations.borrow_mut();
+            let (interrupted, running) = std::mem::take(&mut animations.running)
+                .into_iter()
+                .partition(|animation| animation.id == id);
+            animations.running = running;
+            interrupted
+        };
+        for animation in interrupted {
+            finish(&animation.inner);
+        }
+    }
+
+    /// Step every running animation to the given frame time, returning whether any are still
+    /// running and so whether the tick callback should continue.
+    fn tick_animations(&self, frame_time: i64) -> bool {
+        let running = std::mem::take(&mut self.imp().animations.borrow_mut().running);
+        let mut still_running = Vec::new();
+        for mut animation in running {
+            let start = *animation.start_opt.get_or_insert(frame_time);
+            let progress = ((frame_time - start) as f64 / animation.duration_us).min(1.0);
+            let eased = animation.easing.apply(progress);
+            let constant = animation.from + (animation.to - animation.from) * eased;
+            let updated = self.update_tracked(animation.id, |parts| parts.constant = constant);
+            if updated && progress < 1.0 {
+                still_running.push(animation);
+            } else {
+                finish(&animation.inner);
+            }
+        }
+
+        let mut animations = self.imp().animations.borrow_mut();
+        still_running.append(&mut animations.running);
+        animations.running = still_running;
+        let more = !animations.running.is_empty();
+        if !more {
+            // The callback is removed by returning, so don't remove it again.
+            animations.tick_opt = None;
+        }
+        more
+    }
+}
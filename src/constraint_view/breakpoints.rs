@@ -0,0 +1,109 @@
+//! Responsive layouts for [`ConstraintView`], which switch between [`ConstraintSet`]s according to
+//! the width the view is allocated, e.g. to stack a sidebar above the content on a phone and beside
+//! it on a desktop.
+
+use super::set::ConstraintSet;
+use super::ConstraintView;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::Cast as _;
+use gtk::prelude::WidgetExt as _;
+
+/// A constraint set added with [`ConstraintView::add_breakpoint`].
+pub(super) struct Breakpoint {
+    min_width: f64,
+    set: ConstraintSet,
+}
+
+impl ConstraintView {
+    /// Add a set of constraints which is in the layout of this view only while it's the set of
+    /// the breakpoint with the greatest `min_width` not above the width of the view, or, while the
+    /// view is narrower than every breakpoint, the one with the least. Exactly one set is active at
+    /// a time, and which is re-evaluated whenever the view is allocated. Constraints which hold at
+    /// every width belong in the layout as usual rather than in a set.
+    ///
+    /// ```
+    ///    # use springsteel::constraint_view::ConstraintSet;
+    ///    # use springsteel::{add_constraint, constraint, ConstraintView};
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let (sidebar, body) = (gtk::Label::new(Some("sidebar")), gtk::Label::new(Some("body")));
+    ///    view.add_child(&sidebar);
+    ///    view.add_child(&body);
+    ///    add_constraint!(view, sidebar.top == top);
+    ///    add_constraint!(view, sidebar.left == left);
+    ///    add_constraint!(view, body.right == right);
+    ///    add_constraint!(view, body.bottom == bottom);
+    ///
+    ///    let below = constraint!(body.top == sidebar.bottom);
+    ///    let beside = constraint!(body.left == sidebar.right + 12.0);
+    ///    view.add_breakpoint(0.0, ConstraintSet::new("phone").constraint(below.clone()));
+    ///    view.add_breakpoint(600.0, ConstraintSet::new("desktop").constraint(beside.clone()));
+    ///
+    ///    let allocate = |width: i32| {
+    ///        view.measure(gtk::Orientation::Horizontal, -1);
+    ///        view.measure(gtk::Orientation::Vertical, -1);
+    ///        view.size_allocate(&gtk::Allocation::new(0, 0, width, 400), -1);
+    ///    };
+    ///    allocate(360);
+    ///    assert_eq!(view.active_constraint_set().as_deref(), Some("phone"));
+    ///    assert!(below.is_attached() && !beside.is_attached());
+    ///
+    ///    allocate(1024);
+    ///    assert_eq!(view.active_constraint_set().as_deref(), Some("desktop"));
+    ///    assert!(beside.is_attached() && !below.is_attached());
+    /// ```
+    ///
+    /// Constraints in sets which refer to a child are dropped when it's
+    /// [removed](Self::remove_child).
+    pub fn add_breakpoint(&self, min_width: f64, set: ConstraintSet) {
+        self.imp()
+            .breakpoints
+            .borrow_mut()
+            .push(Breakpoint { min_width, set });
+        self.update_breakpoints(f64::from(self.width()));
+    }
+
+    /// Return the name of the [`ConstraintSet`] whose constraints are in the layout, if any
+    /// [breakpoints](Self::add_breakpoint) have been added.
+    pub fn active_constraint_set(&self) -> Option<String> {
+        self.imp()
+            .breakpoints
+            .borrow()
+            .iter()
+            .find(|breakpoint| breakpoint.set.is_active())
+            .map(|breakpoint| breakpoint.set.name().to_owned())
+    }
+
+    /// Activate the constraint set of the breakpoint for the given width, deactivating the others.
+    pub(super) fn update_breakpoints(&self, width: f64) {
+        let breakpoints = self.imp().breakpoints.borrow();
+        let by_width = |(_, a): &(usize, &Breakpoint), (_, b): &(usize, &Breakpoint)| {
+            a.min_width.total_cmp(&b.min_width)
+        };
+        let chosen_opt = breakpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, breakpoint)| breakpoint.min_width <= width)
+            .max_by(by_width)
+            .or_else(|| breakpoints.iter().enumerate().min_by(by_width));
+        let chosen = match chosen_opt {
+            Some((chosen, breakpoint)) if !breakpoint.set.is_active() => chosen,
+            _ => return,
+        };
+
+        for breakpoint in breakpoints.iter() {
+            breakpoint.set.deactivate();
+        }
+        breakpoints[chosen].set.activate_on(self);
+    }
+
+    /// Drop the constraints of every set which refer to the given child, removing those of the
+    /// active set from the layout.
+    pub(super) fn forget_breakpoint_constraints_for(&self, child: &gtk::Widget) {
+        for breakpoint in self.imp().breakpoints.borrow_mut().iter_mut() {
+            breakpoint.set.forget_constraints_for(child.upcast_ref());
+        }
+    }
+}
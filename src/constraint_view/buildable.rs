@@ -0,0 +1,122 @@
+//! [`gtk::Buildable`] support for `<constraints>` and `<guides>` elements inside a
+//! [`ConstraintView`] in GtkBuilder XML, which are forwarded to its [`gtk::ConstraintLayout`] as
+//! that already knows how to parse them.
+//!
+//! The gtk bindings don't expose the custom tag functions of [`gtk::Buildable`] to subclasses, so
+//! they're installed into the interface of the view's class directly.
+
+use super::ConstraintView;
+use glib::ffi::{gboolean, gpointer, GFALSE};
+use glib::gobject_ffi;
+use glib::translate::{from_glib_borrow, Borrowed};
+use glib::{ObjectExt as _, ObjectType as _};
+use gtk::ffi::{GtkBuildable, GtkBuildableIface, GtkBuildableParser, GtkBuilder};
+use gtk::prelude::WidgetExt as _;
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// Elements which are handled by the layout rather than the view.
+const LAYOUT_TAGS: &[&str] = &["constraints", "guides"];
+
+thread_local! {
+    /// Classes, the view's own and those of its subclasses, whose [`gtk::Buildable`] interface
+    /// already has the custom tag functions installed.
+    static INSTALLED: RefCell<Vec<glib::Type>> = RefCell::new(Vec::new());
+}
+
+/// Install the custom tag functions into the [`gtk::Buildable`] interface of the class of the given
+/// view, once per class. This has to happen after the class and its interfaces are initialized,
+/// and before a subclass initializes its template, so is done as each view is initialized.
+pub(super) fn install_custom_tags(view: &ConstraintView) {
+    let type_ = view.type_();
+    let installed = INSTALLED.with(|installed| {
+        let mut installed = installed.borrow_mut();
+        let was_installed = installed.contains(&type_);
+        if !was_installed {
+            installed.push(type_);
+        }
+        was_installed
+    });
+    if installed {
+        return;
+    }
+
+    unsafe {
+        let iface = buildable_iface(view.as_ptr() as *mut gobject_ffi::GObject);
+        (*iface).custom_tag_start = Some(custom_tag_start);
+        (*iface).custom_tag_end = Some(custom_tag_end);
+        (*iface).custom_finished = Some(custom_finished);
+    }
+}
+
+/// Return the [`gtk::Buildable`] interface of the class of the given object.
+unsafe fn buildable_iface(obj: *mut gobject_ffi::GObject) -> *mut GtkBuildableIface {
+    let class = (*(obj as *mut gobject_ffi::GTypeInstance)).g_class;
+    gobject_ffi::g_type_interface_peek(class as gpointer, gtk::ffi::gtk_buildable_get_type())
+        as *mut GtkBuildableIface
+}
+
+/// Return which buildable handles the given element of the view, and its interface: the layout
+/// for the elements it knows, or the view as a plain [`gtk::Widget`] for anything else.
+unsafe fn tag_handler(
+    buildable: *mut GtkBuildable,
+    child: *mut gobject_ffi::GObject,
+    tagname: *const c_char,
+) -> (*mut GtkBuildable, *const GtkBuildableIface) {
+    let view: Borrowed<gtk::Widget> = from_glib_borrow(buildable as *mut gtk::ffi::GtkWidget);
+    let tag = CStr::from_ptr(tagname).to_str().unwrap_or_default();
+    if child.is_null() && LAYOUT_TAGS.contains(&tag) {
+        if let Some(layout) = view.layout_manager() {
+            // The view keeps the layout alive, so its pointer outlives this borrow.
+            let layout_ptr = layout.as_ptr() as *mut gobject_ffi::GObject;
+            return (layout_ptr as *mut GtkBuildable, buildable_iface(layout_ptr));
+        }
+    }
+
+    let widget_class = gobject_ffi::g_type_class_peek(gtk::ffi::gtk_widget_get_type());
+    let widget_iface =
+        gobject_ffi::g_type_interface_peek(widget_class, gtk::ffi::gtk_buildable_get_type());
+    (buildable, widget_iface as *const GtkBuildableIface)
+}
+
+unsafe extern "C" fn custom_tag_start(
+    buildable: *mut GtkBuildable,
+    builder: *mut GtkBuilder,
+    child: *mut gobject_ffi::GObject,
+    tagname: *const c_char,
+    parser: *mut GtkBuildableParser,
+    data: *mut gpointer,
+) -> gboolean {
+    let (handler, iface) = tag_handler(buildable, child, tagname);
+    match (*iface).custom_tag_start {
+        Some(f) => f(handler, builder, child, tagname, parser, data),
+        None => GFALSE,
+    }
+}
+
+unsafe extern "C" fn custom_tag_end(
+    buildable: *mut GtkBuildable,
+    builder: *mut GtkBuilder,
+    child: *mut gobject_ffi::GObject,
+    tagname: *const c_char,
+    data: gpointer,
+) {
+    let (handler, iface) = tag_handler(buildable, child, tagname);
+    if let Some(f) = (*iface).custom_tag_end {
+        f(handler, builder, child, tagname, data);
+    }
+}
+
+unsafe extern "C" fn custom_finished(
+    buildable: *mut GtkBuildable,
+    builder: *mut GtkBuilder,
+    child: *mut gobject_ffi::GObject,
+    tagname: *const c_char,
+    data: gpointer,
+) {
+    let (handler, iface) = tag_handler(buildable, child, tagname);
+    if let Some(f) = (*iface).custom_finished {
+        f(handler, builder, child, tagname, data);
+    }
+}
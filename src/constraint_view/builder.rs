@@ -0,0 +1,243 @@
+//! [`ConstraintViewBuilder`], which assembles a [`ConstraintView`] with its children, guides, and
+//! constraints in one expression.
+
+use super::parts::ConstraintParts;
+use super::spacer::size_spacer;
+use super::ConstraintView;
+use glib::{Cast as _, Object, ObjectExt as _, ToValue};
+use gtk::prelude::{ListModelExt as _, WidgetExt as _};
+
+/// One step of building a [`ConstraintView`], applied in the order given to the builder.
+enum BuildStep {
+    Child(gtk::Widget),
+    Guide(gtk::ConstraintGuide),
+    Constraint(gtk::Constraint),
+}
+
+/// Builder for a [`ConstraintView`], made by [`ConstraintView::builder`].
+///
+/// Children, guides, and constraints are added to the view in the order they're given, so a
+/// constraint has to come after the children and guides it refers to.
+#[derive(Default)]
+pub struct ConstraintViewBuilder {
+    css_name_opt: Option<String>,
+    accessible_role_opt: Option<gtk::AccessibleRole>,
+    widget_name_opt: Option<String>,
+    padding_opt: Option<f32>,
+
+    /// The content guide to install in the view, if one was given.
+    content_guide_opt: Option<gtk::ConstraintGuide>,
+
+    steps: Vec<BuildStep>,
+}
+
+impl ConstraintView {
+    /// Make a [`ConstraintViewBuilder`] to assemble a view with its children, guides, and
+    /// constraints:
+    ///
+    /// ```
+    ///    # use springsteel::{constraint, ConstraintView};
+    ///    # use glib::Cast as _;
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let label = gtk::Label::new(Some("hello"));
+    ///    let body = gtk::ConstraintGuide::builder().name("body").build();
+    ///    let view = ConstraintView::builder()
+    ///        .widget_name("content")
+    ///        .child(&label)
+    ///        .guide(&body)
+    ///        .constraint(constraint!(body.left == left + 20.0))
+    ///        .constraint(constraint!(label.left == body.left))
+    ///        .build();
+    ///
+    ///    assert_eq!(label.parent(), Some(view.clone().upcast()));
+    ///    assert_eq!(view.widget_name(), "content");
+    /// ```
+    pub fn builder() -> ConstraintViewBuilder {
+        ConstraintViewBuilder::default()
+    }
+}
+
+impl ConstraintViewBuilder {
+    /// Set the CSS node name of the view, as [`ConstraintView::new_with_css_name`].
+    pub fn css_name(mut self, css_name: &str) -> Self {
+        self.css_name_opt = Some(css_name.to_owned());
+        self
+    }
+
+    /// Set the accessible role of the view, as [`ConstraintView::new_with_accessible_role`].
+    pub fn accessible_role(mut self, role: gtk::AccessibleRole) -> Self {
+        self.accessible_role_opt = Some(role);
+        self
+    }
+
+    /// Set the [widget name](gtk::prelude::WidgetExt::set_widget_name) of the view.
+    pub fn widget_name(mut self, widget_name: &str) -> Self {
+        self.widget_name_opt = Some(widget_name.to_owned());
+        self
+    }
+
+    /// Set the padding between every edge of the view and its
+    /// [content guide](ConstraintView::content_guide).
+    pub fn padding(mut self, padding: f32) -> Self {
+        self.padding_opt = Some(padding);
+        self
+    }
+
+    /// Make the given guide the [content guide](ConstraintView::content_guide) of the view, held
+    /// in from its edges by the padding, so that constraints given to the builder can refer to it:
+    ///
+    /// ```
+    ///    # use springsteel::{constraint, ConstraintView};
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let label = gtk::Label::new(Some("padded"));
+    ///    let body = gtk::ConstraintGuide::builder().name("content").build();
+    ///    let view = ConstraintView::builder()
+    ///        .padding(20.0)
+    ///        .content_guide(&body)
+    ///        .child(&label)
+    ///        .constraint(constraint!(label.top == body.top))
+    ///        .build();
+    ///    assert_eq!(view.content_guide(), body);
+    /// ```
+    pub fn content_guide(mut self, guide: &gtk::ConstraintGuide) -> Self {
+        self.content_guide_opt = Some(guide.clone());
+        self
+    }
+
+    /// Size the given guide along the given axis as [`ConstraintView::add_spacer`] does, and add
+    /// it to the layout of the view. Naming the guide lets it be found with
+    /// [`ConstraintView::guide`] and identifies it in the design export:
+    ///
+    /// ```
+    ///    # use springsteel::{constraint, ConstraintView};
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let (a, b) = (gtk::Button::new(), gtk::Button::new());
+    ///    let gap = gtk::ConstraintGuide::builder().name("gap").build();
+    ///    let view = ConstraintView::builder()
+    ///        .child(&a)
+    ///        .child(&b)
+    ///        .spacer(&gap, gtk::Orientation::Horizontal, 10.0, 10.0, 10.0)
+    ///        .constraint(constraint!(gap.left == a.right))
+    ///        .constraint(constraint!(b.left == gap.right))
+    ///        .build();
+    ///    assert_eq!(view.guide("gap"), Some(gap.clone()));
+    ///    assert_eq!((gap.min_width(), gap.min_height()), (10, 0));
+    /// ```
+    pub fn spacer(
+        mut self,
+        guide: &gtk::ConstraintGuide,
+        axis: gtk::Orientation,
+        min: f64,
+        nat: f64,
+        max: f64,
+    ) -> Self {
+        size_spacer(guide, axis, min, nat, max);
+        self.steps.push(BuildStep::Guide(guide.clone()));
+        self
+    }
+
+    /// Add a child to the view, first removing it from whatever parent it has.
+    pub fn child(mut self, child: &impl glib::IsA<gtk::Widget>) -> Self {
+        self.steps
+            .push(BuildStep::Child(child.clone().upcast::<gtk::Widget>()));
+        self
+    }
+
+    /// Add a guide to the layout of the view.
+    pub fn guide(mut self, guide: &gtk::ConstraintGuide) -> Self {
+        self.steps.push(BuildStep::Guide(guide.clone()));
+        self
+    }
+
+    /// Add a constraint, e.g. made with [`constraint!`](crate::constraint), to the layout of the
+    /// view.
+    pub fn constraint(mut self, constraint: gtk::Constraint) -> Self {
+        self.steps.push(BuildStep::Constraint(constraint));
+        self
+    }
+
+    /// Build the view, applying everything given in order.
+    ///
+    /// Panics, naming the widget or guide, if a constraint refers to anything which wasn't added
+    /// to the view before it.
+    pub fn build(self) -> ConstraintView {
+        let mut properties: Vec<(&str, &dyn ToValue)> = Vec::new();
+        if let Some(css_name) = &self.css_name_opt {
+            properties.push(("css-name", css_name));
+        }
+        if let Some(role) = &self.accessible_role_opt {
+            properties.push(("accessible-role", role));
+        }
+        let view: ConstraintView = Object::new(&properties);
+        if let Some(widget_name) = &self.widget_name_opt {
+            view.set_widget_name(widget_name);
+        }
+        if let Some(padding) = self.padding_opt {
+            view.set_padding(padding);
+        }
+        if let Some(guide) = &self.content_guide_opt {
+            view.install_content_guide(guide);
+        }
+
+        let layout = view.layout();
+        for step in self.steps {
+            match step {
+                BuildStep::Child(child) => {
+                    if child.parent().is_some() {
+                        child.unparent();
+                    }
+                    child.set_parent(&view);
+                }
+                BuildStep::Guide(guide) => layout.add_guide(&guide),
+                BuildStep::Constraint(constraint) => {
+                    let parts = ConstraintParts::of(&constraint);
+                    for target in parts.target.iter().chain(parts.source.iter()) {
+                        if !is_in_view(&view, target) {
+                            panic!(
+                                "ConstraintView::builder: constraint `{}` refers to {}, which \
+                                 isn't a child or guide added to the view before it",
+                                parts.format(&describe_target),
+                                describe_target(target),
+                            );
+                        }
+                    }
+                    layout.add_constraint(&constraint);
+                }
+            }
+        }
+        view
+    }
+}
+
+/// Whether a constraint target is a child of the view or a guide of its layout.
+fn is_in_view(view: &ConstraintView, target: &gtk::ConstraintTarget) -> bool {
+    if let Some(widget) = target.downcast_ref::<gtk::Widget>() {
+        return widget.parent().as_ref() == Some(view.upcast_ref::<gtk::Widget>());
+    }
+
+    let guides = view.layout().observe_guides();
+    (0..guides.n_items()).any(|i| guides.item(i).as_ref() == Some(target.upcast_ref::<Object>()))
+}
+
+/// Describe a constraint target for a panic message: the widget name and type of widgets, and
+/// the name of guides.
+fn describe_target(target: &gtk::ConstraintTarget) -> String {
+    if let Some(widget) = target.downcast_ref::<gtk::Widget>() {
+        return format!(
+            "widget `{}` ({})",
+            widget.widget_name(),
+            widget.type_().name()
+        );
+    }
+    match target.downcast_ref::<gtk::ConstraintGuide>() {
+        Some(guide) => match guide.name() {
+            Some(name) => format!("guide `{}`", name),
+            None => "an unnamed guide".to_owned(),
+        },
+        None => "an unknown target".to_owned(),
+    }
+}
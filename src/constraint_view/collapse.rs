@@ -0,0 +1,263 @@
+//! Collapsing hidden children of a [`ConstraintView`], by parking their tracked constraints while
+//! they're hidden so that they stop holding the layout open.
+
+use super::parts::ConstraintParts;
+use super::ConstraintView;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::{Cast as _, IsA, ObjectExt as _, SignalHandlerId, WeakRef};
+use gtk::prelude::WidgetExt as _;
+use gtk::ConstraintAttribute;
+
+/// What happens to the constraints of a child of a [`ConstraintView`] while it's hidden, set with
+/// [`ConstraintView::set_collapse_when_hidden`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Collapse {
+    /// Nothing; the child's constraints keep participating in the layout. This is the default.
+    Never,
+
+    /// Every tracked constraint referring to the child is removed, and the child is left out of
+    /// the layout altogether.
+    Remove,
+
+    /// Tracked constraints on the width and height of the child are removed in favor of holding it
+    /// to zero size, so that siblings positioned relative to it close the gap it leaves.
+    ZeroSize,
+}
+
+impl From<bool> for Collapse {
+    /// [`Remove`](Collapse::Remove) for `true`, [`Never`](Collapse::Never) for `false`.
+    fn from(collapse: bool) -> Self {
+        if collapse {
+            Self::Remove
+        } else {
+            Self::Never
+        }
+    }
+}
+
+/// A child which collapses when hidden, and the handler watching its visibility.
+pub(super) struct CollapseWatch {
+    child: WeakRef<gtk::Widget>,
+    collapse: Collapse,
+    handler: SignalHandlerId,
+
+    /// Whether the child's constraints are presently parked.
+    collapsed: bool,
+
+    /// Constraints holding the child to zero size while collapsed with
+    /// [`ZeroSize`](Collapse::ZeroSize).
+    zero_size: Vec<gtk::Constraint>,
+}
+
+impl ConstraintView {
+    /// Set what happens to the [tracked](Self::add_constraint) constraints of the given child of
+    /// this view while it's hidden, either a [`Collapse`] or `true` for
+    /// [`Collapse::Remove`]. The constraints are put back, in the order they were added and with
+    /// the same ids, when the child is shown again.
+    ///
+    /// ```
+    ///    # use springsteel::constraint_view::Collapse;
+    ///    # use springsteel::{add_constraint, ConstraintView};
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let [a, b, c] = [(), (), ()].map(|()| {
+    ///        let cell = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    ///        cell.set_size_request(20, 20);
+    ///        view.add_child(&cell);
+    ///        add_constraint!(view, cell.top == top);
+    ///        add_constraint!(view, cell.width == 20.0);
+    ///        cell
+    ///    });
+    ///    add_constraint!(view, a.left == left);
+    ///    add_constraint!(view, b.left == a.right);
+    ///    add_constraint!(view, c.left == b.right);
+    ///    view.set_collapse_when_hidden(&b, Collapse::ZeroSize);
+    ///
+    ///    let c_x = |view: &ConstraintView| {
+    ///        view.measure(gtk::Orientation::Horizontal, -1);
+    ///        view.measure(gtk::Orientation::Vertical, -1);
+    ///        view.size_allocate(&gtk::Allocation::new(0, 0, 200, 50), -1);
+    ///        c.compute_bounds(view).expect("compute_bounds").x()
+    ///    };
+    ///    assert_eq!(c_x(&view), 40.0);
+    ///
+    ///    b.set_visible(false);
+    ///    assert_eq!(c_x(&view), 20.0);
+    ///
+    ///    b.set_visible(true);
+    ///    assert_eq!(c_x(&view), 40.0);
+    /// ```
+    ///
+    /// If the widget isn't a child of this view, a warning is logged and nothing is done.
+    pub fn set_collapse_when_hidden(
+        &self,
+        child: &impl IsA<gtk::Widget>,
+        collapse: impl Into<Collapse>,
+    ) {
+        let child = child.upcast_ref::<gtk::Widget>();
+        if child.parent().as_ref() != Some(self.upcast_ref::<gtk::Widget>()) {
+            log::warn!(
+                "ConstraintView::set_collapse_when_hidden: widget {} isn't a child of {}",
+                child.widget_name(),
+                self.widget_name()
+            );
+            return;
+        }
+
+        self.forget_collapse(child);
+        let collapse = collapse.into();
+        if collapse == Collapse::Never {
+            return;
+        }
+
+        let view_weak = self.downgrade();
+        let handler = child.connect_visible_notify(move |child| {
+            if let Some(view) = view_weak.upgrade() {
+                view.update_collapse(child);
+            }
+        });
+        self.imp().collapsing.borrow_mut().push(CollapseWatch {
+            child: child.downgrade(),
+            collapse,
+            handler,
+            collapsed: false,
+            zero_size: Vec::new(),
+        });
+        self.update_collapse(child);
+    }
+
+    /// Stop collapsing the given child when hidden, putting back its constraints if it's
+    /// collapsed.
+    pub(super) fn forget_collapse(&self, child: &gtk::Widget) {
+        let position = self
+            .imp()
+            .collapsing
+            .borrow()
+            .iter()
+            .position(|watch| watch.child.upgrade().as_ref() == Some(child));
+        if let Some(position) = position {
+            self.expand(child);
+            let watch = self.imp().collapsing.borrow_mut().remove(position);
+            child.disconnect(watch.handler);
+        }
+    }
+
+    /// Stop watching every child, leaving their constraints as they are.
+    pub(super) fn clear_collapse_watches(&self) {
+        for watch in self.imp().collapsing.take() {
+            if let Some(child) = watch.child.upgrade() {
+                child.disconnect(watch.handler);
+            }
+        }
+    }
+
+    /// Collapse or expand the given child to match its visibility.
+    fn update_collapse(&self, child: &gtk::Widget) {
+        if child.is_visible() {
+            self.expand(child);
+        } else {
+            self.collapse(child);
+        }
+    }
+
+    /// Park the constraints of the given child, if it collapses and isn't collapsed already.
+    fn collapse(&self, child: &gtk::Widget) {
+        let collapse = match self.collapse_watch(child) {
+            Some((collapse, false)) => collapse,
+            _ => return,
+        };
+
+        let target = child.upcast_ref::<gtk::ConstraintTarget>();
+        let mut zero_size = Vec::new();
+        match collapse {
+            Collapse::Remove => self.park_constraints(target, |_| true),
+            Collapse::ZeroSize => {
+                self.park_constraints(target, |parts| is_size_of(parts, target));
+                let layout = self.layout();
+                for attribute in [ConstraintAttribute::Width, ConstraintAttribute::Height] {
+                    let constraint = gtk::Constraint::new_constant(
+                        Some(child),
+                        attribute,
+                        gtk::ConstraintRelation::Eq,
+                        0.0,
+                        gtk::ffi::GTK_CONSTRAINT_STRENGTH_REQUIRED,
+                    );
+                    layout.add_constraint(&constraint);
+                    zero_size.push(constraint);
+                }
+            }
+            Collapse::Never => {}
+        }
+
+        self.with_collapse_watch(child, |watch| {
+            watch.collapsed = true;
+            watch.zero_size = zero_size;
+        });
+    }
+
+    /// Put back the constraints of the given child, if it's collapsed, except those also referring
+    /// to another child which is still collapsed.
+    fn expand(&self, child: &gtk::Widget) {
+        if !matches!(self.collapse_watch(child), Some((_, true))) {
+            return;
+        }
+
+        let mut zero_size = Vec::new();
+        self.with_collapse_watch(child, |watch| {
+            watch.collapsed = false;
+            zero_size = std::mem::take(&mut watch.zero_size);
+        });
+        let layout = self.layout();
+        for constraint in zero_size {
+            layout.remove_constraint(&constraint);
+        }
+
+        let still_collapsed: Vec<gtk::ConstraintTarget> = self
+            .imp()
+            .collapsing
+            .borrow()
+            .iter()
+            .filter(|watch| watch.collapsed)
+            .filter_map(|watch| watch.child.upgrade())
+            .map(|child| child.upcast())
+            .collect();
+        self.unpark_constraints(child.upcast_ref(), |parts| {
+            still_collapsed.iter().any(|other| parts.refers_to(other))
+        });
+    }
+
+    /// Return how the given child collapses and whether it's collapsed, if it's watched.
+    fn collapse_watch(&self, child: &gtk::Widget) -> Option<(Collapse, bool)> {
+        self.imp()
+            .collapsing
+            .borrow()
+            .iter()
+            .find(|watch| watch.child.upgrade().as_ref() == Some(child))
+            .map(|watch| (watch.collapse, watch.collapsed))
+    }
+
+    /// Change the watch of the given child, if it's watched.
+    fn with_collapse_watch(&self, child: &gtk::Widget, f: impl FnOnce(&mut CollapseWatch)) {
+        let mut collapsing = self.imp().collapsing.borrow_mut();
+        if let Some(watch) = collapsing
+            .iter_mut()
+            .find(|watch| watch.child.upgrade().as_ref() == Some(child))
+        {
+            f(watch);
+        }
+    }
+}
+
+/// Whether the given constraint involves the width or height of `target`.
+fn is_size_of(parts: &ConstraintParts, target: &gtk::ConstraintTarget) -> bool {
+    let is_size = |attribute: ConstraintAttribute| {
+        matches!(
+            attribute,
+            ConstraintAttribute::Width | ConstraintAttribute::Height
+        )
+    };
+    (parts.target.as_ref() == Some(target) && is_size(parts.target_attribute))
+        || (parts.source.as_ref() == Some(target) && is_size(parts.source_attribute))
+}
@@ -0,0 +1,166 @@
+//! The content guide of [`ConstraintView`], held in from the edges of the view by its padding
+//! properties, so that children can be anchored inside a margin without constraining a guide to
+//! every edge by hand.
+
+use super::ConstraintView;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::ObjectExt as _;
+use gtk::prelude::WidgetExt as _;
+use gtk::{ConstraintAttribute, ConstraintGuide};
+
+/// Names of the padding properties, in the order the padding is stored.
+pub(super) const PADDING_PROPERTIES: [&str; 4] = [
+    "padding-top",
+    "padding-right",
+    "padding-bottom",
+    "padding-left",
+];
+
+/// The content guide of a view, and the constraints presently holding it in from the edges.
+pub(super) struct ContentGuide {
+    guide: ConstraintGuide,
+    constraints: Vec<gtk::Constraint>,
+}
+
+impl ConstraintView {
+    /// Return the content guide of this view, named `content`, which is held in from each edge of
+    /// the view by the `padding-top`, `padding-right`, `padding-bottom`, and `padding-left`
+    /// properties, for constraints to anchor children to. It's added to the layout the first time
+    /// it's requested, and its edge constraints are rebuilt whenever the padding changes.
+    ///
+    /// ```
+    ///    # use springsteel::{add_constraint, ConstraintView};
+    ///    # use glib::ObjectExt as _;
+    ///    # use gtk::prelude::ListModelExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    view.set_padding(20.0);
+    ///    let body = view.content_guide();
+    ///    let label = gtk::Label::new(Some("padded"));
+    ///    view.add_child(&label);
+    ///    add_constraint!(view.layout(), label.top == body.top);
+    ///
+    ///    view.set_property("padding-top", 8.0f32);
+    ///    assert_eq!(view.property::<f32>("padding-top"), 8.0);
+    ///    assert_eq!(view.property::<f32>("padding-left"), 20.0);
+    ///    assert_eq!(view.layout().observe_constraints().n_items(), 5);
+    /// ```
+    ///
+    /// Panics if the view already has another guide with the content guide's name, e.g. one added
+    /// by [`add_guide_named`](Self::add_guide_named), as [`guide`](Self::guide) could then return
+    /// either:
+    ///
+    /// ```should_panic
+    ///    # use springsteel::ConstraintView;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    view.add_guide_named("content");
+    ///    view.content_guide();
+    /// ```
+    pub fn content_guide(&self) -> ConstraintGuide {
+        if let Some(content) = self.imp().content.borrow().as_ref() {
+            return content.guide.clone();
+        }
+
+        let guide = ConstraintGuide::builder().name("content").build();
+        self.install_content_guide(&guide);
+        guide
+    }
+
+    /// Set the padding between every edge of this view and its
+    /// [content guide](Self::content_guide).
+    pub fn set_padding(&self, padding: f32) {
+        let _notify_guard = self.freeze_notify();
+        for name in PADDING_PROPERTIES {
+            self.set_padding_edge(name, padding);
+        }
+    }
+
+    /// Make the given guide the content guide of this view, adding it to the layout.
+    pub(super) fn install_content_guide(&self, guide: &ConstraintGuide) {
+        if let Some(name) = guide.name() {
+            if self.guide(&name).is_some() {
+                panic!(
+                    "ConstraintView::content_guide: view {} already has a guide named {:?}",
+                    self.widget_name(),
+                    name
+                );
+            }
+        }
+        self.layout().add_guide(guide);
+        *self.imp().content.borrow_mut() = Some(ContentGuide {
+            guide: guide.clone(),
+            constraints: Vec::new(),
+        });
+        self.update_content_constraints();
+    }
+
+    /// Return the padding of the edge with the given property name.
+    pub(super) fn padding_edge(&self, name: &str) -> f32 {
+        self.imp().padding.get()[padding_index(name)]
+    }
+
+    /// Set the padding of the edge with the given property name, rebuilding the edge constraints
+    /// of the content guide if it changed.
+    pub(super) fn set_padding_edge(&self, name: &str, padding: f32) {
+        let mut all = self.imp().padding.get();
+        let index = padding_index(name);
+        if all[index] == padding {
+            return;
+        }
+        all[index] = padding;
+        self.imp().padding.set(all);
+        self.update_content_constraints();
+        self.notify(name);
+    }
+
+    /// Replace the edge constraints of the content guide, if there is one, with ones for the
+    /// present padding, as [`gtk::Constraint`]s can't be changed once made.
+    fn update_content_constraints(&self) {
+        let layout = self.layout();
+        let padding = self.imp().padding.get();
+        let mut content_ref = self.imp().content.borrow_mut();
+        if let Some(content) = content_ref.as_mut() {
+            for constraint in content.constraints.drain(..) {
+                layout.remove_constraint(&constraint);
+            }
+            content.constraints = edge_constraints(&content.guide, padding);
+            for constraint in &content.constraints {
+                layout.add_constraint(constraint);
+            }
+        }
+    }
+}
+
+/// Return the index into the stored padding of the property with the given name.
+fn padding_index(name: &str) -> usize {
+    PADDING_PROPERTIES
+        .iter()
+        .position(|p| *p == name)
+        .unwrap_or_else(|| panic!("ConstraintView has no padding property {}", name))
+}
+
+/// Make the constraints holding a guide in from each edge of the view by the given padding.
+fn edge_constraints(guide: &ConstraintGuide, padding: [f32; 4]) -> Vec<gtk::Constraint> {
+    let [top, right, bottom, left] = padding.map(f64::from);
+    let inset = |attribute, constant| {
+        gtk::Constraint::new(
+            Some(guide),
+            attribute,
+            gtk::ConstraintRelation::Eq,
+            None::<&gtk::ConstraintTarget>,
+            attribute,
+            1.0,
+            constant,
+            gtk::ffi::GTK_CONSTRAINT_STRENGTH_REQUIRED,
+        )
+    };
+    vec![
+        inset(ConstraintAttribute::Top, top),
+        inset(ConstraintAttribute::Right, -right),
+        inset(ConstraintAttribute::Bottom, -bottom),
+        inset(ConstraintAttribute::Left, left),
+    ]
+}
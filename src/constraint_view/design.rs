@@ -0,0 +1,410 @@
+//! Design mode for [`ConstraintView`], which overlays draggable handles on guides and constraints
+//! so that constraint constants can be tweaked live in a running application and then exported
+//! back as [`constraint!`](crate::constraint) source.
+
+use super::parts::{identifier_for, ConstraintParts};
+use super::probe::GuideProbes;
+use super::ConstraintView;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::{Cast as _, ObjectExt as _};
+use gtk::prelude::{EventControllerExt as _, GestureExt as _, ListModelExt as _, WidgetExt as _};
+use gtk::{graphene, ConstraintAttribute, Orientation};
+
+/// Half the side length of a drawn handle, and the distance within which a press grabs it.
+const HANDLE_RADIUS: f32 = 5.0;
+
+/// Design mode state of a [`ConstraintView`], present only while design mode is enabled.
+pub(super) struct DesignMode {
+    /// Drag gesture installed on the view to move handles.
+    gesture: gtk::GestureDrag,
+
+    /// Probes reading back the geometry of the guides in the layout.
+    probes: GuideProbes,
+
+    /// The handle being dragged, if any.
+    drag_opt: Option<Drag>,
+}
+
+/// A handle for adjusting the constant of one constraint.
+struct Handle {
+    constraint: gtk::Constraint,
+    x: f32,
+    y: f32,
+    axis: Orientation,
+
+    /// How much the constant changes per pixel the handle is dragged along its axis.
+    sign: f64,
+}
+
+/// An in-progress drag of a [`Handle`].
+struct Drag {
+    constraint: gtk::Constraint,
+    axis: Orientation,
+    sign: f64,
+    start_constant: f64,
+}
+
+impl DesignMode {
+    /// Enter design mode on the given view, installing probes for its guides and the drag gesture.
+    pub(super) fn enable(view: &ConstraintView) -> Self {
+        let probes = GuideProbes::install(view);
+
+        let gesture = gtk::GestureDrag::new();
+        gesture.set_propagation_phase(gtk::PropagationPhase::Capture);
+
+        let view_weak = view.downgrade();
+        gesture.connect_drag_begin(move |gesture, x, y| {
+            if let Some(view) = view_weak.upgrade() {
+                view.design_drag_begin(gesture, x, y);
+            }
+        });
+
+        let view_weak = view.downgrade();
+        gesture.connect_drag_update(move |_, offset_x, offset_y| {
+            if let Some(view) = view_weak.upgrade() {
+                view.design_drag_update(offset_x, offset_y);
+            }
+        });
+
+        let view_weak = view.downgrade();
+        gesture.connect_drag_end(move |_, _, _| {
+            if let Some(view) = view_weak.upgrade() {
+                if let Some(design) = view.imp().design.borrow_mut().as_mut() {
+                    design.drag_opt = None;
+                }
+            }
+        });
+
+        view.add_controller(&gesture);
+        view.queue_draw();
+
+        Self {
+            gesture,
+            probes,
+            drag_opt: None,
+        }
+    }
+
+    /// Leave design mode, removing the probes and gesture from the given view.
+    pub(super) fn disable(self, view: &ConstraintView) {
+        self.probes.remove(view);
+        view.remove_controller(&self.gesture);
+        view.queue_draw();
+    }
+
+    /// Whether the given constraint was installed by design mode itself.
+    pub(super) fn is_probe_constraint(&self, constraint: &gtk::Constraint) -> bool {
+        self.probes.is_probe_constraint(constraint)
+    }
+
+    /// Whether the given widget was installed by design mode itself.
+    pub(super) fn is_probe_widget(&self, widget: &gtk::Widget) -> bool {
+        self.probes.is_probe_widget(widget)
+    }
+
+    /// The bounds of a constraint target relative to the view, `None` meaning the view itself.
+    fn bounds_of(
+        &self,
+        view: &ConstraintView,
+        target: Option<&gtk::ConstraintTarget>,
+    ) -> Option<graphene::Rect> {
+        match target {
+            None => Some(graphene::Rect::new(
+                0.0,
+                0.0,
+                view.width() as f32,
+                view.height() as f32,
+            )),
+            Some(t) => {
+                if let Some(widget) = t.downcast_ref::<gtk::Widget>() {
+                    widget.compute_bounds(view)
+                } else {
+                    self.probes.guide_bounds(view, t)
+                }
+            }
+        }
+    }
+
+    /// Compute the handles for every user constraint in the view's layout.
+    fn handles(&self, view: &ConstraintView) -> Vec<Handle> {
+        let constraints = view.layout().observe_constraints();
+        let mut handles = Vec::new();
+
+        for i in 0..constraints.n_items() {
+            let constraint = match constraints
+                .item(i)
+                .and_then(|o| o.downcast::<gtk::Constraint>().ok())
+            {
+                Some(c) => c,
+                None => continue,
+            };
+            let internal = self.is_probe_constraint(&constraint)
+                || view.is_debug_overlay_constraint(&constraint);
+            if internal {
+                continue;
+            }
+
+            let parts = ConstraintParts::of(&constraint);
+
+            // Constraints targeting the container itself would put their handle on the
+            // container's edge, which doesn't move, so those get their handle on the source and
+            // move in the opposite direction.
+            let (anchor, attribute, sign) = if parts.target.is_none() && parts.source.is_some() {
+                (
+                    parts.source.as_ref(),
+                    parts.source_attribute,
+                    -parts.multiplier,
+                )
+            } else {
+                (parts.target.as_ref(), parts.target_attribute, 1.0)
+            };
+
+            let bounds = match self.bounds_of(view, anchor) {
+                Some(b) => b,
+                None => continue,
+            };
+            let (left, top) = (bounds.x(), bounds.y());
+            let (right, bottom) = (left + bounds.width(), top + bounds.height());
+            let (center_x, center_y) = ((left + right) / 2.0, (top + bottom) / 2.0);
+
+            let (x, y, axis) = match attribute {
+                ConstraintAttribute::Left | ConstraintAttribute::Start => {
+                    (left, center_y, Orientation::Horizontal)
+                }
+                ConstraintAttribute::Right
+                | ConstraintAttribute::End
+                | ConstraintAttribute::Width => (right, center_y, Orientation::Horizontal),
+                ConstraintAttribute::CenterX => (center_x, center_y, Orientation::Horizontal),
+                ConstraintAttribute::Top => (center_x, top, Orientation::Vertical),
+                ConstraintAttribute::Bottom | ConstraintAttribute::Height => {
+                    (center_x, bottom, Orientation::Vertical)
+                }
+                ConstraintAttribute::CenterY => (center_x, center_y, Orientation::Vertical),
+                _ => continue,
+            };
+
+            handles.push(Handle {
+                constraint,
+                x,
+                y,
+                axis,
+                sign,
+            });
+        }
+
+        handles
+    }
+
+    /// Draw guide rectangles and constraint handles on top of the view's children.
+    pub(super) fn snapshot(&self, view: &ConstraintView, snapshot: &gtk::Snapshot) {
+        let guide_color = gdk::RGBA::new(0.2, 0.5, 1.0, 0.15);
+        for bounds in self.probes.bounds(view) {
+            snapshot.append_color(&guide_color, &bounds);
+        }
+
+        let handle_color = gdk::RGBA::new(1.0, 0.3, 0.1, 0.8);
+        let active_color = gdk::RGBA::new(1.0, 0.8, 0.0, 0.9);
+        for handle in self.handles(view) {
+            let active = matches!(&self.drag_opt, Some(d) if d.constraint == handle.constraint);
+            snapshot.append_color(
+                if active { &active_color } else { &handle_color },
+                &graphene::Rect::new(
+                    handle.x - HANDLE_RADIUS,
+                    handle.y - HANDLE_RADIUS,
+                    HANDLE_RADIUS * 2.0,
+                    HANDLE_RADIUS * 2.0,
+                ),
+            );
+        }
+    }
+}
+
+/// Name a constraint target for export: the widget name for widgets, the guide name or a
+/// synthesized `guideN` for guides.
+pub(super) fn target_name(view: &ConstraintView, target: &gtk::ConstraintTarget) -> String {
+    if let Some(widget) = target.downcast_ref::<gtk::Widget>() {
+        return identifier_for(&widget.widget_name());
+    }
+
+    let guides = view.layout().observe_guides();
+    for i in 0..guides.n_items() {
+        if let Some(guide) = guides
+            .item(i)
+            .and_then(|o| o.downcast::<gtk::ConstraintGuide>().ok())
+        {
+            if guide.upcast_ref::<gtk::ConstraintTarget>() == target {
+                return match guide.name() {
+                    Some(name) => identifier_for(&name),
+                    None => format!("guide{}", i),
+                };
+            }
+        }
+    }
+
+    "unknown".to_owned()
+}
+
+impl ConstraintView {
+    /// Enable or disable design mode, a debugging aid which overlays the layout's guides as
+    /// translucent rectangles and puts a draggable handle on every constraint. Dragging a handle
+    /// live-updates that constraint's constant, snapped to whole pixels, and
+    /// [`design_export`](Self::design_export) prints the constraints as they currently are so the
+    /// tweaked layout can be pasted back into code.
+    ///
+    /// Presses which don't land on a handle pass through to the children as usual, and when design
+    /// mode is disabled nothing is installed at all. Guides added while design mode is enabled are
+    /// picked up by enabling it again.
+    pub fn enable_design_mode(&self, enabled: bool) {
+        let previous_opt = self.imp().design.borrow_mut().take();
+        if let Some(previous) = previous_opt {
+            previous.disable(self);
+        }
+
+        if enabled {
+            let design = DesignMode::enable(self);
+            *self.imp().design.borrow_mut() = Some(design);
+        }
+    }
+
+    /// Whether design mode is presently enabled.
+    pub fn is_design_mode(&self) -> bool {
+        self.imp().design.borrow().is_some()
+    }
+
+    /// Export every constraint of the layout, one per line, in the grammar of
+    /// [`add_constraint!`](crate::add_constraint). Widgets are named by their
+    /// [widget name](gtk::prelude::WidgetExt::widget_name) and guides by their name or, if
+    /// unnamed, `guideN` in order of addition.
+    pub fn design_export(&self) -> String {
+        let design_ref = self.imp().design.borrow();
+        let probe_constraint = |c: &gtk::Constraint| match design_ref.as_ref() {
+            Some(design) => design.is_probe_constraint(c),
+            None => false,
+        };
+        let name = |t: &gtk::ConstraintTarget| target_name(self, t);
+
+        let constraints = self.layout().observe_constraints();
+        let mut out = String::new();
+        for i in 0..constraints.n_items() {
+            if let Some(c) = constraints
+                .item(i)
+                .and_then(|o| o.downcast::<gtk::Constraint>().ok())
+            {
+                if probe_constraint(&c) || self.is_debug_overlay_constraint(&c) {
+                    continue;
+                }
+                out.push_str(&ConstraintParts::of(&c).format(&name));
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Start dragging the handle under the given point, claiming the gesture, or deny the gesture
+    /// so that the press goes to the children if there's no handle there.
+    fn design_drag_begin(&self, gesture: &gtk::GestureDrag, x: f64, y: f64) {
+        let claimed = match self.imp().design.borrow_mut().as_mut() {
+            Some(design) => {
+                let hit = design.handles(self).into_iter().find(|h| {
+                    (h.x as f64 - x).abs() <= HANDLE_RADIUS as f64
+                        && (h.y as f64 - y).abs() <= HANDLE_RADIUS as f64
+                });
+                design.drag_opt = hit.map(|handle| Drag {
+                    start_constant: handle.constraint.constant(),
+                    constraint: handle.constraint,
+                    axis: handle.axis,
+                    sign: handle.sign,
+                });
+                design.drag_opt.is_some()
+            }
+            None => false,
+        };
+
+        // Changing the gesture state can synchronously end the drag, so the design state must not
+        // be borrowed at this point.
+        if claimed {
+            gesture.set_state(gtk::EventSequenceState::Claimed);
+            self.queue_draw();
+        } else {
+            gesture.set_state(gtk::EventSequenceState::Denied);
+        }
+    }
+
+    /// Update the constant of the dragged constraint to follow the pointer.
+    fn design_drag_update(&self, offset_x: f64, offset_y: f64) {
+        let mut design_ref = self.imp().design.borrow_mut();
+        let drag = match design_ref.as_mut().and_then(|d| d.drag_opt.as_mut()) {
+            Some(drag) => drag,
+            None => return,
+        };
+
+        let offset = match drag.axis {
+            Orientation::Horizontal => offset_x,
+            _ => offset_y,
+        };
+        let constant = (drag.start_constant + offset * drag.sign).round();
+        if constant == drag.constraint.constant() {
+            return;
+        }
+
+        // A tracked constraint is swapped through the registry, so that its id carries on
+        // referring to it; only untracked ones are swapped in the layout directly.
+        let replacement = match self.tracked_id(&drag.constraint) {
+            Some(id) => {
+                self.interrupt_animation(id);
+                self.update_tracked(id, |parts| parts.constant = constant);
+                match self.tracked_attached(id) {
+                    Some(replacement) => replacement,
+                    None => return,
+                }
+            }
+            None => {
+                let mut parts = ConstraintParts::of(&drag.constraint);
+                parts.constant = constant;
+                let replacement = parts.build();
+                let layout = self.layout();
+                layout.remove_constraint(&drag.constraint);
+                layout.add_constraint(&replacement);
+                replacement
+            }
+        };
+        drag.constraint = replacement;
+
+        self.queue_allocate();
+        self.queue_draw();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dragging_a_tracked_constraint_keeps_its_id() {
+        gtk::init().expect("gtk::init");
+        let view = ConstraintView::new();
+        let label = gtk::Label::new(None);
+        view.add_child(&label);
+        let constraint = crate::constraint!(label.left == left + 10.0);
+        let id = view.add_constraint(&constraint);
+
+        view.enable_design_mode(true);
+        if let Some(design) = view.imp().design.borrow_mut().as_mut() {
+            design.drag_opt = Some(Drag {
+                constraint,
+                axis: Orientation::Horizontal,
+                sign: 1.0,
+                start_constant: 10.0,
+            });
+        }
+        view.design_drag_update(5.0, 0.0);
+        assert_eq!(view.tracked_constraints()[0].constant, 15.0);
+
+        view.set_constant(id, 30.0);
+        view.enable_design_mode(false);
+        let tracked = view.tracked_constraints();
+        assert_eq!(tracked.len(), 1);
+        assert_eq!((tracked[0].id, tracked[0].constant), (id, 30.0));
+        assert_eq!(view.layout().observe_constraints().n_items(), 1);
+    }
+}
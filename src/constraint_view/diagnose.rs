@@ -0,0 +1,334 @@
+//! Diagnostics for the constraints of a [`ConstraintView`], which detect the common mistakes
+//! behind layouts GTK can't satisfy or only satisfies arbitrarily, and name what's involved rather
+//! than leaving only an opaque warning from the solver.
+
+use super::design::target_name;
+use super::parts::ConstraintParts;
+use super::ConstraintView;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::{Cast as _, ObjectExt as _, ObjectType as _};
+use gtk::prelude::{ListModelExt as _, WidgetExt as _};
+use gtk::{ConstraintAttribute, ConstraintRelation, Orientation};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A problem with the constraints of a [`ConstraintView`], found by
+/// [`ConstraintView::check_constraints`]. Widgets are named by their
+/// [widget name](gtk::prelude::WidgetExt::widget_name) and constraints are written in the grammar
+/// of [`constraint!`](crate::constraint), as by [`ConstraintView::design_export`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConstraintIssue {
+    /// A visible child with no constraint on its position along one axis, e.g. only on its width,
+    /// which leaves it placed arbitrarily along that axis.
+    Unconstrained {
+        /// The name of the child.
+        widget: String,
+        /// The axis without position constraints.
+        orientation: Orientation,
+    },
+
+    /// Required constraints between the same pair of attributes which can't all hold, e.g.
+    /// `a.width == 50.0` and `a.width == 60.0`.
+    Conflict {
+        /// The conflicting constraints.
+        constraints: Vec<String>,
+    },
+
+    /// A required constraint which forces the size of a widget or guide below zero.
+    NegativeSize {
+        /// The name of the widget or guide, or `view` for the view itself.
+        target: String,
+        /// The offending constraint.
+        constraint: String,
+    },
+}
+
+impl fmt::Display for ConstraintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unconstrained {
+                widget,
+                orientation,
+            } => {
+                let axis = match orientation {
+                    Orientation::Horizontal => "horizontal",
+                    _ => "vertical",
+                };
+                write!(f, "{} has no {} position constraints", widget, axis)
+            }
+            Self::Conflict { constraints } => {
+                write!(f, "conflicting constraints: {}", constraints.join("; "))
+            }
+            Self::NegativeSize { target, constraint } => {
+                write!(f, "{} forces a negative size on {}", constraint, target)
+            }
+        }
+    }
+}
+
+/// Required constraints between the same pair of attributes, normalized to
+/// `lhs RELATION rhs * multiplier + constant`, and the range of constants allowed by all of them.
+struct PairBounds {
+    lowest: f64,
+    highest: f64,
+    constraints: Vec<String>,
+}
+
+/// Identifies a pair of attributes by the addresses of their targets, `0` for the view itself,
+/// and the multiplier relating them.
+type PairKey = (usize, ConstraintAttribute, usize, ConstraintAttribute, u64);
+
+impl ConstraintView {
+    /// Check the constraints of this view's layout for common mistakes: visible children with no
+    /// horizontal or no vertical position constraints, required constraints between the same attributes
+    /// which contradict each other, and required constraints forcing a negative size. This isn't
+    /// a solver, so it won't find every unsatisfiable layout, only the usual culprits.
+    ///
+    /// Setting the `debug-constraints` property logs the issues at debug level whenever the view
+    /// is mapped.
+    ///
+    /// ```
+    ///    # use springsteel::constraint_view::ConstraintIssue;
+    ///    # use springsteel::{add_constraint, ConstraintView};
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let (title, ok) = (gtk::Label::new(Some("Title")), gtk::Button::new());
+    ///    title.set_widget_name("title");
+    ///    ok.set_widget_name("ok");
+    ///    view.add_child(&title);
+    ///    view.add_child(&ok);
+    ///
+    ///    add_constraint!(view, title.left == left);
+    ///    add_constraint!(view, ok.left == title.right + 8.0);
+    ///    add_constraint!(view, ok.top == top);
+    ///    assert_eq!(
+    ///        view.check_constraints(),
+    ///        [ConstraintIssue::Unconstrained {
+    ///            widget: "title".into(),
+    ///            orientation: gtk::Orientation::Vertical,
+    ///        }],
+    ///    );
+    ///
+    ///    let badge = gtk::Label::new(Some("3"));
+    ///    badge.set_widget_name("badge");
+    ///    view.add_child(&badge);
+    ///    add_constraint!(view, badge.width == 16.0);
+    ///    add_constraint!(view, badge.height == 16.0);
+    ///    assert_eq!(
+    ///        view.check_constraints(),
+    ///        [
+    ///            ConstraintIssue::Unconstrained {
+    ///                widget: "title".into(),
+    ///                orientation: gtk::Orientation::Vertical,
+    ///            },
+    ///            ConstraintIssue::Unconstrained {
+    ///                widget: "badge".into(),
+    ///                orientation: gtk::Orientation::Horizontal,
+    ///            },
+    ///            ConstraintIssue::Unconstrained {
+    ///                widget: "badge".into(),
+    ///                orientation: gtk::Orientation::Vertical,
+    ///            },
+    ///        ],
+    ///    );
+    ///    add_constraint!(view, badge.right == ok.right);
+    ///    add_constraint!(view, badge.center_y == ok.top);
+    ///
+    ///    add_constraint!(view, title.top == top);
+    ///    add_constraint!(view, ok.width == 80.0);
+    ///    add_constraint!(view, ok.width <= 60.0);
+    ///    add_constraint!(view, title.right == title.left - 10.0);
+    ///    let issues: Vec<_> = view.check_constraints().iter().map(|i| i.to_string()).collect();
+    ///    assert_eq!(
+    ///        issues,
+    ///        [
+    ///            "conflicting constraints: ok.width == 80.0 @required; \
+    ///             ok.width <= 60.0 @required",
+    ///            "title.right == title.left - 10.0 @required forces a negative size on title",
+    ///        ],
+    ///    );
+    /// ```
+    pub fn check_constraints(&self) -> Vec<ConstraintIssue> {
+        let name = |target: &gtk::ConstraintTarget| target_name(self, target);
+        let constraints = self.layout().observe_constraints();
+        let all: Vec<ConstraintParts> = (0..constraints.n_items())
+            .filter_map(|i| constraints.item(i)?.downcast::<gtk::Constraint>().ok())
+            .filter(|c| !self.is_probe_constraint(c))
+            .map(|c| ConstraintParts::of(&c))
+            .collect();
+
+        let mut issues = Vec::new();
+        for child in self.children() {
+            if !child.is_visible() {
+                continue;
+            }
+            let target = child.upcast_ref::<gtk::ConstraintTarget>();
+            for orientation in [Orientation::Horizontal, Orientation::Vertical] {
+                if !all
+                    .iter()
+                    .any(|parts| constrains_axis(parts, target, orientation))
+                {
+                    issues.push(ConstraintIssue::Unconstrained {
+                        widget: name(target),
+                        orientation,
+                    });
+                }
+            }
+        }
+
+        let required = all
+            .iter()
+            .filter(|parts| parts.strength == gtk::ffi::GTK_CONSTRAINT_STRENGTH_REQUIRED);
+
+        // Bound the constant of each pair of attributes, in the order pairs are first seen.
+        let mut pairs: Vec<PairBounds> = Vec::new();
+        let mut pair_index: HashMap<PairKey, usize> = HashMap::new();
+        for parts in required.clone() {
+            let (key, relation, constant) = normalize(parts);
+            let index = *pair_index.entry(key).or_insert_with(|| {
+                pairs.push(PairBounds {
+                    lowest: f64::NEG_INFINITY,
+                    highest: f64::INFINITY,
+                    constraints: Vec::new(),
+                });
+                pairs.len() - 1
+            });
+            let pair = &mut pairs[index];
+            if relation != ConstraintRelation::Le {
+                pair.lowest = pair.lowest.max(constant);
+            }
+            if relation != ConstraintRelation::Ge {
+                pair.highest = pair.highest.min(constant);
+            }
+            pair.constraints.push(parts.format(&name));
+        }
+        for pair in pairs {
+            if pair.lowest > pair.highest {
+                issues.push(ConstraintIssue::Conflict {
+                    constraints: pair.constraints,
+                });
+            }
+        }
+
+        for parts in required {
+            if forces_negative_size(parts) {
+                issues.push(ConstraintIssue::NegativeSize {
+                    target: parts
+                        .target
+                        .as_ref()
+                        .map_or_else(|| "view".to_owned(), name),
+                    constraint: parts.format(&name),
+                });
+            }
+        }
+
+        issues
+    }
+
+    /// Set the `debug-constraints` property of this view, which logs the issues found by
+    /// [`check_constraints`](Self::check_constraints) at debug level whenever the view is mapped.
+    pub fn set_debug_constraints(&self, debug: bool) {
+        if self.imp().debug_constraints.replace(debug) != debug {
+            self.notify("debug-constraints");
+        }
+    }
+
+    /// Log the issues found by [`check_constraints`](Self::check_constraints), if the
+    /// `debug-constraints` property is set.
+    pub(super) fn log_constraint_issues(&self) {
+        if !self.imp().debug_constraints.get() {
+            return;
+        }
+        for issue in self.check_constraints() {
+            log::debug!("ConstraintView {}: {}", self.widget_name(), issue);
+        }
+    }
+}
+
+/// Whether the given constraint involves an attribute of `target` which positions it along the
+/// given axis. Sizes don't count, as a child with only its width constrained can still be
+/// anywhere horizontally.
+fn constrains_axis(
+    parts: &ConstraintParts,
+    target: &gtk::ConstraintTarget,
+    orientation: Orientation,
+) -> bool {
+    let on_axis = |attribute: ConstraintAttribute| {
+        axis_of(attribute) == Some(orientation)
+            && !matches!(
+                attribute,
+                ConstraintAttribute::Width | ConstraintAttribute::Height
+            )
+    };
+    (parts.target.as_ref() == Some(target) && on_axis(parts.target_attribute))
+        || (parts.source.as_ref() == Some(target) && on_axis(parts.source_attribute))
+}
+
+/// The axis an attribute lies along, if any.
+fn axis_of(attribute: ConstraintAttribute) -> Option<Orientation> {
+    use ConstraintAttribute::*;
+    match attribute {
+        Left | Right | Start | End | Width | CenterX => Some(Orientation::Horizontal),
+        Top | Bottom | Height | CenterY | Baseline => Some(Orientation::Vertical),
+        _ => Option::None,
+    }
+}
+
+/// Put a constraint in a canonical form, so that constraints written either way around between
+/// the same attributes with a multiplier of one share a key, returning its key, relation, and
+/// constant.
+fn normalize(parts: &ConstraintParts) -> (PairKey, ConstraintRelation, f64) {
+    let address =
+        |target: Option<&gtk::ConstraintTarget>| target.map_or(0, |t| t.as_ptr() as usize);
+    let lhs = (address(parts.target.as_ref()), parts.target_attribute);
+    let rhs = (address(parts.source.as_ref()), parts.source_attribute);
+    let multiplier = if parts.is_constant() {
+        0.0
+    } else {
+        parts.multiplier
+    };
+
+    let swap = !parts.is_constant() && multiplier == 1.0 && rhs < lhs;
+    if swap {
+        let relation = match parts.relation {
+            ConstraintRelation::Le => ConstraintRelation::Ge,
+            ConstraintRelation::Ge => ConstraintRelation::Le,
+            relation => relation,
+        };
+        (
+            (rhs.0, rhs.1, lhs.0, lhs.1, multiplier.to_bits()),
+            relation,
+            -parts.constant,
+        )
+    } else {
+        (
+            (lhs.0, lhs.1, rhs.0, rhs.1, multiplier.to_bits()),
+            parts.relation,
+            parts.constant,
+        )
+    }
+}
+
+/// Whether the given required constraint makes a size negative, either directly, e.g.
+/// `a.width == -5.0`, or by putting the trailing edge of something before its leading edge, e.g.
+/// `a.right == a.left - 5.0`.
+fn forces_negative_size(parts: &ConstraintParts) -> bool {
+    use ConstraintAttribute::*;
+    use ConstraintRelation::{Eq, Ge, Le};
+
+    if parts.is_constant() {
+        return matches!(parts.target_attribute, Width | Height)
+            && matches!(parts.relation, Eq | Le)
+            && parts.constant < 0.0;
+    }
+    if parts.target != parts.source || parts.multiplier != 1.0 {
+        return false;
+    }
+    let edges = (parts.target_attribute, parts.source_attribute);
+    let trailing_first = matches!(edges, (Right, Left) | (End, Start) | (Bottom, Top));
+    let leading_first = matches!(edges, (Left, Right) | (Start, End) | (Top, Bottom));
+    (trailing_first && matches!(parts.relation, Eq | Le) && parts.constant < 0.0)
+        || (leading_first && matches!(parts.relation, Eq | Ge) && parts.constant > 0.0)
+}
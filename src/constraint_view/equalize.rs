@@ -0,0 +1,148 @@
+//! [`EqualizeGroup`], holding children of a [`ConstraintView`] to the same width or height with
+//! tracked constraints, as a [`gtk::SizeGroup`] does for other containers.
+
+use super::parts::attribute_keyword;
+use super::{ConstraintId, ConstraintView};
+use glib::{Cast as _, ObjectExt as _, WeakRef};
+use gtk::ConstraintAttribute;
+use std::fmt;
+
+/// Error from [`ConstraintView::equalize`] when given an attribute other than a size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NotASizeAttribute(pub ConstraintAttribute);
+
+impl fmt::Display for NotASizeAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "can't equalize `{}`, only the size attributes `width` and `height`",
+            attribute_keyword(self.0)
+        )
+    }
+}
+
+impl std::error::Error for NotASizeAttribute {}
+
+/// Widgets of a [`ConstraintView`] held to the same width or height as the first of them, made by
+/// [`ConstraintView::equalize`]. Dropping the group leaves its constraints in place; use
+/// [`dissolve`](Self::dissolve) to remove them.
+pub struct EqualizeGroup {
+    view: WeakRef<ConstraintView>,
+    attribute: ConstraintAttribute,
+
+    /// The widget the others are held to, weakly held so the group never keeps it alive.
+    anchor: WeakRef<gtk::Widget>,
+
+    ids: Vec<ConstraintId>,
+}
+
+impl EqualizeGroup {
+    /// Add a widget to the group, holding it to the size of the first widget. The first widget
+    /// added to an empty group, or to one whose first widget is gone, becomes the one the others
+    /// are held to.
+    ///
+    /// If the widget isn't a child of the view, a warning is logged and it isn't added.
+    pub fn add(&mut self, widget: &impl glib::IsA<gtk::Widget>) {
+        let widget = widget.upcast_ref::<gtk::Widget>();
+        let view = match self.view.upgrade() {
+            Some(view) => view,
+            None => return,
+        };
+        if !view.check_child("equalize", widget) {
+            return;
+        }
+        let anchor = match self.anchor.upgrade() {
+            Some(anchor) => anchor,
+            None => {
+                self.anchor = widget.downgrade();
+                return;
+            }
+        };
+        if &anchor == widget {
+            return;
+        }
+
+        let constraint = gtk::Constraint::new(
+            Some(widget),
+            self.attribute,
+            gtk::ConstraintRelation::Eq,
+            Some(&anchor),
+            self.attribute,
+            1.0,
+            0.0,
+            gtk::ffi::GTK_CONSTRAINT_STRENGTH_REQUIRED,
+        );
+        self.ids.push(view.add_constraint(&constraint));
+    }
+
+    /// Remove the constraints of the group from the view, leaving its widgets to size themselves
+    /// independently again.
+    pub fn dissolve(self) {
+        if let Some(view) = self.view.upgrade() {
+            for id in self.ids {
+                view.remove_constraint(id);
+            }
+        }
+    }
+}
+
+impl ConstraintView {
+    /// Hold the given children of this view to the same `attribute`, which must be
+    /// [`Width`](ConstraintAttribute::Width) or [`Height`](ConstraintAttribute::Height), by adding
+    /// a [tracked](Self::add_constraint) constraint equating each to the first. The returned
+    /// group can have more widgets added later, or be dissolved to remove the constraints.
+    /// Widgets which aren't children of this view are left out with a warning.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use glib::Cast as _;
+    ///    # use gtk::prelude::ListModelExt as _;
+    ///    # use gtk::ConstraintAttribute;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let buttons: Vec<gtk::Widget> = (0..3).map(|_| gtk::Button::new().upcast()).collect();
+    ///    for button in &buttons {
+    ///        view.add_child(button);
+    ///    }
+    ///    let (ok, cancel, help) = (&buttons[0], &buttons[1], &buttons[2]);
+    ///
+    ///    let mut widths = view
+    ///        .equalize(ConstraintAttribute::Width, &[ok, cancel])
+    ///        .expect("width is a size");
+    ///    widths.add(help);
+    ///    assert_eq!(view.layout().observe_constraints().n_items(), 2);
+    ///
+    ///    widths.dissolve();
+    ///    assert_eq!(view.layout().observe_constraints().n_items(), 0);
+    ///
+    ///    let error = view.equalize(ConstraintAttribute::Left, &[ok]).err();
+    ///    assert_eq!(
+    ///        error.map(|e| e.to_string()).as_deref(),
+    ///        Some("can't equalize `left`, only the size attributes `width` and `height`"),
+    ///    );
+    /// ```
+    pub fn equalize(
+        &self,
+        attribute: ConstraintAttribute,
+        widgets: &[&gtk::Widget],
+    ) -> Result<EqualizeGroup, NotASizeAttribute> {
+        if !matches!(
+            attribute,
+            ConstraintAttribute::Width | ConstraintAttribute::Height
+        ) {
+            return Err(NotASizeAttribute(attribute));
+        }
+
+        let mut group = EqualizeGroup {
+            view: self.downgrade(),
+            attribute,
+            anchor: WeakRef::new(),
+            ids: Vec::new(),
+        };
+        for widget in widgets {
+            group.add(*widget);
+        }
+        Ok(group)
+    }
+}
@@ -0,0 +1,94 @@
+//! [`Insets`], and adding a child to a [`ConstraintView`] pinned to its edges by them, which covers
+//! the common cases of filling a view with margins or docking a child to one side.
+
+use super::ConstraintView;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::{Cast as _, IsA};
+use gtk::ConstraintAttribute;
+
+/// Distances to hold a child in from each edge of a view by, with
+/// [`ConstraintView::add_child_pinned`]. An edge without an inset isn't constrained.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Insets {
+    /// Inset from the top edge.
+    pub top: Option<f64>,
+    /// Inset from the bottom edge.
+    pub bottom: Option<f64>,
+    /// Inset from the start edge, the left in left-to-right text directions.
+    pub start: Option<f64>,
+    /// Inset from the end edge, the right in left-to-right text directions.
+    pub end: Option<f64>,
+}
+
+impl Insets {
+    /// The same inset from every edge.
+    pub fn all(inset: f64) -> Self {
+        Self {
+            top: Some(inset),
+            bottom: Some(inset),
+            start: Some(inset),
+            end: Some(inset),
+        }
+    }
+}
+
+impl ConstraintView {
+    /// Add a child to this view, pinned to the edges of the [content guide](Self::content_guide)
+    /// if the view has one, or of the view itself otherwise, by the given insets. Edges without an
+    /// inset are left to other constraints, or the child's natural size. The constraints are
+    /// [tracked](Self::add_constraint), and removed along with the child by
+    /// [`remove_child`](Self::remove_child).
+    ///
+    /// ```
+    ///    # use springsteel::constraint_view::Insets;
+    ///    # use springsteel::ConstraintView;
+    ///    # use gtk::prelude::{ListModelExt as _, WidgetExt as _};
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let body = gtk::Label::new(Some("fills the view"));
+    ///    view.add_child_pinned(&body, Insets::all(8.0));
+    ///    let header = gtk::Label::new(Some("docked to the top"));
+    ///    view.add_child_pinned(&header, Insets { bottom: None, ..Insets::all(0.0) });
+    ///    assert_eq!(view.layout().observe_constraints().n_items(), 7);
+    ///
+    ///    view.remove_child(&body);
+    ///    assert_eq!(view.layout().observe_constraints().n_items(), 3);
+    /// ```
+    pub fn add_child_pinned(&self, child: &impl IsA<gtk::Widget>, insets: Insets) {
+        let child = child.upcast_ref::<gtk::Widget>();
+        let already_parented = child.parent().is_some();
+        self.add_child(child);
+        if already_parented {
+            // add_child warned about it, and a child of something else mustn't be pinned.
+            return;
+        }
+
+        let has_content_guide = self.imp().content.borrow().is_some();
+        let anchor_opt: Option<gtk::ConstraintTarget> =
+            has_content_guide.then(|| self.content_guide().upcast());
+        let edges = [
+            (ConstraintAttribute::Top, insets.top),
+            (
+                ConstraintAttribute::Bottom,
+                insets.bottom.map(|inset| -inset),
+            ),
+            (ConstraintAttribute::Start, insets.start),
+            (ConstraintAttribute::End, insets.end.map(|inset| -inset)),
+        ];
+        for (attribute, constant_opt) in edges {
+            if let Some(constant) = constant_opt {
+                self.add_constraint(&gtk::Constraint::new(
+                    Some(child),
+                    attribute,
+                    gtk::ConstraintRelation::Eq,
+                    anchor_opt.as_ref(),
+                    attribute,
+                    1.0,
+                    constant,
+                    gtk::ffi::GTK_CONSTRAINT_STRENGTH_REQUIRED,
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! The margin guide of [`ConstraintView`], held in from the edges of the view by its own GTK
+//! margins and kept in step as they change, so that code adjusting the margins from outside, e.g.
+//! to keep clear of a notch or an overlaid toolbar, moves whatever is anchored to the guide.
+
+use super::ConstraintView;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::ObjectExt as _;
+use gtk::prelude::WidgetExt as _;
+use gtk::{ConstraintAttribute, ConstraintGuide};
+
+/// Names of the margin properties of [`gtk::Widget`] the guide follows.
+const MARGIN_PROPERTIES: [&str; 4] = ["margin-top", "margin-end", "margin-bottom", "margin-start"];
+
+/// The margin guide of a view, and the constraints presently holding it in from the edges.
+pub(super) struct MarginGuide {
+    guide: ConstraintGuide,
+    constraints: Vec<gtk::Constraint>,
+}
+
+impl ConstraintView {
+    /// Return the margin guide of this view, named `margin`, which is held in from each edge of
+    /// the view by its [`margin-top`](gtk::prelude::WidgetExt::margin_top), `margin-end`,
+    /// `margin-bottom`, and `margin-start` properties, for constraints to anchor children to. It's
+    /// added to the layout the first time it's requested, and its edge constraints are rebuilt
+    /// whenever a margin changes.
+    ///
+    /// GTK applies the margins outside the view's allocation, so the view already sits inside them
+    /// in its parent and the guide insets by them a second time. A child anchored to the guide is
+    /// therefore twice the margins in from the parent's edges, while one anchored to the view's own
+    /// edges is only moved by them once; use the guide for content which must keep clear of
+    /// whatever the margins are reserving space for even when the view is allocated over it.
+    ///
+    /// ```
+    ///    # use springsteel::{add_constraint, ConstraintView};
+    ///    # use glib::Cast as _;
+    ///    # use gtk::prelude::{BoxExt as _, WidgetExt as _};
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let parent = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    ///    parent.append(&view);
+    ///    let safe = view.margin_guide();
+    ///    let label = gtk::Label::new(Some("clear of the notch"));
+    ///    view.add_child(&label);
+    ///    add_constraint!(view, label.start == safe.start);
+    ///    add_constraint!(view, label.top == safe.top);
+    ///
+    ///    let label_origin = |relative_to: &gtk::Widget| {
+    ///        parent.measure(gtk::Orientation::Horizontal, -1);
+    ///        parent.measure(gtk::Orientation::Vertical, -1);
+    ///        parent.size_allocate(&gtk::Allocation::new(0, 0, 300, 200), -1);
+    ///        let bounds = label.compute_bounds(relative_to).expect("compute_bounds");
+    ///        (bounds.x(), bounds.y())
+    ///    };
+    ///    assert_eq!(label_origin(view.upcast_ref()), (0.0, 0.0));
+    ///    assert_eq!(label_origin(parent.upcast_ref()), (0.0, 0.0));
+    ///
+    ///    view.set_margin_start(12);
+    ///    view.set_margin_top(30);
+    ///    assert_eq!(label_origin(view.upcast_ref()), (12.0, 30.0));
+    ///    assert_eq!(label_origin(parent.upcast_ref()), (24.0, 60.0));
+    /// ```
+    ///
+    /// Panics if the view already has another guide named `margin`, e.g. one added by
+    /// [`add_guide_named`](Self::add_guide_named), as [`guide`](Self::guide) could then return
+    /// either:
+    ///
+    /// ```should_panic
+    ///    # use springsteel::ConstraintView;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    view.add_guide_named("margin");
+    ///    view.margin_guide();
+    /// ```
+    pub fn margin_guide(&self) -> ConstraintGuide {
+        if let Some(margin) = self.imp().margin.borrow().as_ref() {
+            return margin.guide.clone();
+        }
+        if self.guide("margin").is_some() {
+            panic!(
+                "ConstraintView::margin_guide: view {} already has a guide named \"margin\"",
+                self.widget_name()
+            );
+        }
+
+        let guide = ConstraintGuide::builder().name("margin").build();
+        self.layout().add_guide(&guide);
+        *self.imp().margin.borrow_mut() = Some(MarginGuide {
+            guide: guide.clone(),
+            constraints: Vec::new(),
+        });
+        self.update_margin_constraints();
+
+        for name in MARGIN_PROPERTIES {
+            let view_weak = self.downgrade();
+            self.connect_notify_local(Some(name), move |_, _| {
+                if let Some(view) = view_weak.upgrade() {
+                    view.update_margin_constraints();
+                }
+            });
+        }
+        guide
+    }
+
+    /// Replace the edge constraints of the margin guide, if there is one, with ones for the
+    /// present margins, as [`gtk::Constraint`]s can't be changed once made.
+    fn update_margin_constraints(&self) {
+        let layout = self.layout();
+        let mut margin_ref = self.imp().margin.borrow_mut();
+        let margin = match margin_ref.as_mut() {
+            Some(margin) => margin,
+            None => return,
+        };
+        for constraint in margin.constraints.drain(..) {
+            layout.remove_constraint(&constraint);
+        }
+
+        let inset = |attribute, constant: i32| {
+            gtk::Constraint::new(
+                Some(&margin.guide),
+                attribute,
+                gtk::ConstraintRelation::Eq,
+                None::<&gtk::ConstraintTarget>,
+                attribute,
+                1.0,
+                f64::from(constant),
+                gtk::ffi::GTK_CONSTRAINT_STRENGTH_REQUIRED,
+            )
+        };
+        margin.constraints = vec![
+            inset(ConstraintAttribute::Top, self.margin_top()),
+            inset(ConstraintAttribute::End, -self.margin_end()),
+            inset(ConstraintAttribute::Bottom, -self.margin_bottom()),
+            inset(ConstraintAttribute::Start, self.margin_start()),
+        ];
+        for constraint in &margin.constraints {
+            layout.add_constraint(constraint);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Stacking order of the children of a [`ConstraintView`]. Children are drawn in the order of the
+//! widget sibling list, first to last, so where constraints let children overlap, a later child is
+//! drawn over an earlier one; these move a child within the list without reparenting it.
+
+use super::ConstraintView;
+use glib::{Cast as _, IsA};
+use gtk::prelude::WidgetExt as _;
+
+impl ConstraintView {
+    /// Move a child of this view to the end of its children, so that it's drawn above all the
+    /// others. If the widget isn't a child of this view, a warning is logged and nothing is done.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use glib::Cast as _;
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let (badge, button) = (gtk::Label::new(Some("3")), gtk::Button::new());
+    ///    view.add_child(&badge);
+    ///    view.add_child(&button);
+    ///
+    ///    view.raise_child(&badge);
+    ///    assert_eq!(view.last_child(), Some(badge.clone().upcast()));
+    ///    view.lower_child(&badge);
+    ///    assert_eq!(view.first_child(), Some(badge.upcast()));
+    /// ```
+    pub fn raise_child(&self, child: &impl IsA<gtk::Widget>) {
+        if self.check_child("raise_child", child.upcast_ref()) {
+            child.insert_before(self, None::<&gtk::Widget>);
+        }
+    }
+
+    /// Move a child of this view to the start of its children, so that it's drawn below all the
+    /// others. If the widget isn't a child of this view, a warning is logged and nothing is done.
+    pub fn lower_child(&self, child: &impl IsA<gtk::Widget>) {
+        if self.check_child("lower_child", child.upcast_ref()) {
+            child.insert_after(self, None::<&gtk::Widget>);
+        }
+    }
+
+    /// Move a child of this view to just after another, so that it's drawn directly above it. If
+    /// either widget isn't a child of this view, a warning is logged and nothing is done, and
+    /// moving a child above itself does nothing either.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use glib::Cast as _;
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let back = gtk::Label::new(Some("back"));
+    ///    let middle = gtk::Label::new(Some("middle"));
+    ///    let front = gtk::Label::new(Some("front"));
+    ///    view.add_child(&back);
+    ///    view.add_child(&middle);
+    ///    view.add_child(&front);
+    ///
+    ///    view.insert_child_above(&back, &middle);
+    ///    assert_eq!(middle.next_sibling(), Some(back.clone().upcast()));
+    ///    assert_eq!(view.last_child(), Some(front.upcast()));
+    ///
+    ///    // Not a child, so nothing happens.
+    ///    view.insert_child_above(&back, &gtk::Label::new(None));
+    ///    assert_eq!(middle.next_sibling(), Some(back.clone().upcast()));
+    ///
+    ///    view.insert_child_above(&back, &back);
+    ///    assert_eq!(middle.next_sibling(), Some(back.upcast()));
+    /// ```
+    pub fn insert_child_above(
+        &self,
+        child: &impl IsA<gtk::Widget>,
+        sibling: &impl IsA<gtk::Widget>,
+    ) {
+        // GTK refuses to insert a widget after itself, and it's already where it'd end up.
+        if child.upcast_ref::<gtk::Widget>() == sibling.upcast_ref::<gtk::Widget>() {
+            return;
+        }
+        if self.check_child("insert_child_above", child.upcast_ref())
+            && self.check_child("insert_child_above", sibling.upcast_ref())
+        {
+            child.insert_after(self, Some(sibling));
+        }
+    }
+
+    /// Return whether the given widget is a child of this view, logging a warning attributed to
+    /// the given method if it isn't.
+    pub(super) fn check_child(&self, method: &str, widget: &gtk::Widget) -> bool {
+        let is_child = widget.parent().as_ref() == Some(self.upcast_ref::<gtk::Widget>());
+        if !is_child {
+            log::warn!(
+                "ConstraintView::{}: widget {} isn't a child of {}",
+                method,
+                widget.widget_name(),
+                self.widget_name()
+            );
+        }
+        is_child
+    }
+}
@@ -0,0 +1,129 @@
+//! Debug overlay for [`ConstraintView`], which draws the guides of its layout and outlines the
+//! allocations of its children on top of them, to see what a layout is actually doing.
+
+use super::probe::GuideProbes;
+use super::ConstraintView;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::{ObjectExt as _, SignalHandlerId};
+use gtk::gsk;
+use gtk::prelude::{ListModelExt as _, WidgetExt as _};
+
+/// Debug overlay state of a [`ConstraintView`], present only while the overlay is enabled.
+pub(super) struct DebugOverlay {
+    /// Probes reading back the geometry of the guides in the layout.
+    probes: GuideProbes,
+
+    /// The guides of the layout, watched to probe guides added or removed while enabled.
+    guides: gio::ListModel,
+    guides_handler: SignalHandlerId,
+}
+
+impl DebugOverlay {
+    /// Enable the overlay on the given view, installing probes for its guides.
+    fn enable(view: &ConstraintView) -> Self {
+        let guides = view.layout().observe_guides();
+        let view_weak = view.downgrade();
+        let guides_handler = guides.connect_items_changed(move |_, _, _, _| {
+            if let Some(view) = view_weak.upgrade() {
+                view.reprobe_debug_overlay();
+            }
+        });
+
+        view.queue_draw();
+        Self {
+            probes: GuideProbes::install(view),
+            guides,
+            guides_handler,
+        }
+    }
+
+    /// Disable the overlay, removing the probes from the given view.
+    pub(super) fn disable(self, view: &ConstraintView) {
+        self.guides.disconnect(self.guides_handler);
+        self.probes.remove(view);
+        view.queue_draw();
+    }
+
+    /// Whether the given constraint was installed by the overlay itself.
+    pub(super) fn is_probe_constraint(&self, constraint: &gtk::Constraint) -> bool {
+        self.probes.is_probe_constraint(constraint)
+    }
+
+    /// Whether the given widget was installed by the overlay itself.
+    pub(super) fn is_probe_widget(&self, widget: &gtk::Widget) -> bool {
+        self.probes.is_probe_widget(widget)
+    }
+
+    /// Draw guide rectangles and child outlines on top of the view's children.
+    pub(super) fn snapshot(&self, view: &ConstraintView, snapshot: &gtk::Snapshot) {
+        let guide_color = gdk::RGBA::new(0.8, 0.2, 0.9, 0.15);
+        for bounds in self.probes.bounds(view) {
+            snapshot.append_color(&guide_color, &bounds);
+        }
+
+        let outline_color = gdk::RGBA::new(0.9, 0.1, 0.1, 0.7);
+        for child in view.children() {
+            if let Some(bounds) = child.compute_bounds(view) {
+                snapshot.append_border(
+                    &gsk::RoundedRect::from_rect(bounds, 0.0),
+                    &[1.0; 4],
+                    &[outline_color; 4],
+                );
+            }
+        }
+    }
+}
+
+impl ConstraintView {
+    /// Enable or disable the debug overlay, a debugging aid which draws the layout's guides as
+    /// translucent rectangles and outlines the allocation of every child, on top of the children
+    /// as usual. It follows the layout as it changes, including guides added or removed while
+    /// it's enabled.
+    ///
+    /// When disabled, nothing is installed and drawing costs nothing extra.
+    pub fn set_debug_overlay(&self, enabled: bool) {
+        let previous_opt = self.imp().debug_overlay.borrow_mut().take();
+        if let Some(previous) = previous_opt {
+            previous.disable(self);
+        }
+
+        if enabled {
+            let overlay = DebugOverlay::enable(self);
+            *self.imp().debug_overlay.borrow_mut() = Some(overlay);
+        }
+    }
+
+    /// Whether the debug overlay is presently enabled.
+    pub fn is_debug_overlay(&self) -> bool {
+        self.imp().debug_overlay.borrow().is_some()
+    }
+
+    /// Whether the given constraint was installed by the debug overlay itself.
+    pub(super) fn is_debug_overlay_constraint(&self, constraint: &gtk::Constraint) -> bool {
+        match self.imp().debug_overlay.borrow().as_ref() {
+            Some(overlay) => overlay.is_probe_constraint(constraint),
+            None => false,
+        }
+    }
+
+    /// Replace the probes of the debug overlay after the guides of the layout changed.
+    fn reprobe_debug_overlay(&self) {
+        let old_opt = self
+            .imp()
+            .debug_overlay
+            .borrow_mut()
+            .as_mut()
+            .map(|overlay| std::mem::take(&mut overlay.probes));
+        let old = match old_opt {
+            Some(old) => old,
+            None => return,
+        };
+        old.remove(self);
+
+        let probes = GuideProbes::install(self);
+        if let Some(overlay) = self.imp().debug_overlay.borrow_mut().as_mut() {
+            overlay.probes = probes;
+        }
+        self.queue_draw();
+    }
+}
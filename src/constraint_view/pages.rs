@@ -0,0 +1,239 @@
+//! Visible-child mode of [`ConstraintView`], which shows one of its children at a time like a
+//! [`gtk::Stack`], optionally crossfading between them over the frame clock.
+
+use super::ConstraintView;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::{Cast as _, ObjectExt as _};
+use gtk::prelude::{WidgetExt as _, WidgetExtManual as _};
+use std::cell::Cell;
+
+/// An in-progress crossfade between the previously and newly visible children.
+pub(super) struct Crossfade {
+    tick_opt: Option<gtk::TickCallbackId>,
+    from_opt: Option<gtk::Widget>,
+    to_opt: Option<gtk::Widget>,
+}
+
+impl ConstraintView {
+    /// Show only the given child of this view, hiding every other one, or hide all of them if
+    /// `None`. Until this is first called, children keep whatever visibility they have; afterwards
+    /// children added to the view start hidden, and removing the visible child leaves none visible.
+    ///
+    /// If the `transition-duration` property is nonzero and the view is mapped, the newly visible
+    /// child fades in over the previous one instead of replacing it immediately.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let pages: Vec<gtk::Label> = ["home", "settings", "about"]
+    ///        .into_iter()
+    ///        .map(|name| {
+    ///            let page = gtk::Label::new(Some(name));
+    ///            page.set_widget_name(name);
+    ///            view.add_child(&page);
+    ///            page
+    ///        })
+    ///        .collect();
+    ///    assert!(pages.iter().all(|page| page.is_visible()));
+    ///
+    ///    view.set_visible_child_name(Some("settings"));
+    ///    let visible: Vec<_> = pages.iter().map(|page| page.is_visible()).collect();
+    ///    assert_eq!(visible, [false, true, false]);
+    ///    assert_eq!(view.visible_child_name().as_deref(), Some("settings"));
+    ///
+    ///    view.remove_child(&pages[1]);
+    ///    assert_eq!(view.visible_child(), None);
+    ///    assert!(!pages[0].is_visible() && !pages[2].is_visible());
+    /// ```
+    pub fn set_visible_child(&self, child_opt: Option<&gtk::Widget>) {
+        if let Some(child) = child_opt {
+            if child.parent().as_ref() != Some(self.upcast_ref::<gtk::Widget>()) {
+                log::warn!(
+                    "ConstraintView::set_visible_child: widget {} isn't a child of {}",
+                    child.widget_name(),
+                    self.widget_name()
+                );
+                return;
+            }
+        }
+
+        self.finish_crossfade();
+        let imp = self.imp();
+        let was_paged = imp.paged.replace(true);
+        let previous_opt = imp.visible_child.replace(child_opt.cloned());
+        if was_paged && previous_opt.as_ref() == child_opt {
+            return;
+        }
+
+        let duration = imp.transition_duration.get();
+        if was_paged && duration > 0 && self.is_mapped() {
+            self.start_crossfade(previous_opt, child_opt.cloned(), duration);
+        } else {
+            self.show_visible_child();
+        }
+        self.notify("visible-child-name");
+    }
+
+    /// Show only the child of this view with the given
+    /// [widget name](gtk::prelude::WidgetExt::widget_name), as
+    /// [`set_visible_child`](Self::set_visible_child), or hide all of them if `None`. This is also
+    /// the `visible-child-name` property, so a stream of page names can drive navigation. If no
+    /// child has the name, a warning is logged and nothing is done.
+    pub fn set_visible_child_name(&self, name_opt: Option<&str>) {
+        match name_opt {
+            Some(name) => match self.child_by_name(name) {
+                Some(child) => self.set_visible_child(Some(&child)),
+                None => log::warn!(
+                    "ConstraintView::set_visible_child_name: {} has no child named {}",
+                    self.widget_name(),
+                    name
+                ),
+            },
+            None => self.set_visible_child(None),
+        }
+    }
+
+    /// Return the child made visible with [`set_visible_child`](Self::set_visible_child), if any
+    /// and it's still a child of this view.
+    pub fn visible_child(&self) -> Option<gtk::Widget> {
+        self.imp()
+            .visible_child
+            .borrow()
+            .clone()
+            .filter(|child| child.parent().as_ref() == Some(self.upcast_ref::<gtk::Widget>()))
+    }
+
+    /// Return the [widget name](gtk::prelude::WidgetExt::widget_name) of the
+    /// [visible child](Self::visible_child), if any.
+    pub fn visible_child_name(&self) -> Option<String> {
+        self.visible_child().map(|child| child.widget_name().into())
+    }
+
+    /// Set the `transition-duration` property of this view, how many milliseconds
+    /// [`set_visible_child`](Self::set_visible_child) takes to crossfade between children. Zero,
+    /// the default, switches immediately.
+    pub fn set_transition_duration(&self, millis: u32) {
+        if self.imp().transition_duration.replace(millis) != millis {
+            self.notify("transition-duration");
+        }
+    }
+
+    /// Stop showing the given child once it's removed from this view, if it's the visible child.
+    pub(super) fn forget_visible_child(&self, child: &gtk::Widget) {
+        let is_visible_child = self.imp().visible_child.borrow().as_ref() == Some(child);
+        let is_fading = self
+            .imp()
+            .crossfade
+            .borrow()
+            .as_ref()
+            .map_or(false, |fade| {
+                fade.from_opt.as_ref() == Some(child) || fade.to_opt.as_ref() == Some(child)
+            });
+        if is_visible_child || is_fading {
+            self.finish_crossfade();
+        }
+        if is_visible_child {
+            self.imp().visible_child.replace(None);
+            self.notify("visible-child-name");
+        }
+    }
+
+    /// Carry visible-child mode over from a child to the one replacing it, making it the visible
+    /// child if the old one was.
+    pub(super) fn replace_visible_child(&self, old: &gtk::Widget, new: &gtk::Widget) {
+        if !self.imp().paged.get() {
+            return;
+        }
+        self.finish_crossfade();
+        let is_visible_child = self.imp().visible_child.borrow().as_ref() == Some(old);
+        new.set_visible(is_visible_child);
+        if is_visible_child {
+            self.imp().visible_child.replace(Some(new.clone()));
+            self.notify("visible-child-name");
+        }
+    }
+
+    /// Make the visible child the only one of the children shown, leaving design mode and debug
+    /// overlay probes alone.
+    fn show_visible_child(&self) {
+        let visible_opt = self.imp().visible_child.borrow().clone();
+        for child in self.children() {
+            child.set_visible(visible_opt.as_ref() == Some(&child));
+        }
+    }
+
+    /// Fade `to_opt` in over `from_opt` in the given number of milliseconds.
+    fn start_crossfade(
+        &self,
+        from_opt: Option<gtk::Widget>,
+        to_opt: Option<gtk::Widget>,
+        duration: u32,
+    ) {
+        for child in self.children() {
+            let fading = from_opt.as_ref() == Some(&child) || to_opt.as_ref() == Some(&child);
+            child.set_visible(fading);
+        }
+        if let Some(to) = to_opt.as_ref() {
+            to.set_opacity(0.0);
+        }
+
+        let start_opt = Cell::new(None);
+        let duration_us = f64::from(duration) * 1000.0;
+        let tick = self.add_tick_callback(move |view, frame_clock| {
+            let now = frame_clock.frame_time();
+            let start = start_opt.get().unwrap_or_else(|| {
+                start_opt.set(Some(now));
+                now
+            });
+            let progress = ((now - start) as f64 / duration_us).min(1.0);
+
+            let done = {
+                let fade_ref = view.imp().crossfade.borrow();
+                let fade = match fade_ref.as_ref() {
+                    Some(fade) => fade,
+                    None => return glib::Continue(false),
+                };
+                if let Some(to) = fade.to_opt.as_ref() {
+                    to.set_opacity(progress);
+                }
+                if let Some(from) = fade.from_opt.as_ref() {
+                    from.set_opacity(1.0 - progress);
+                }
+                progress >= 1.0
+            };
+
+            if done {
+                // The callback is removed by returning, so don't remove it again.
+                if let Some(fade) = view.imp().crossfade.borrow_mut().as_mut() {
+                    fade.tick_opt = None;
+                }
+                view.finish_crossfade();
+            }
+            glib::Continue(!done)
+        });
+
+        *self.imp().crossfade.borrow_mut() = Some(Crossfade {
+            tick_opt: Some(tick),
+            from_opt,
+            to_opt,
+        });
+    }
+
+    /// Jump to the end of the crossfade in progress, if any.
+    pub(super) fn finish_crossfade(&self) {
+        let fade = match self.imp().crossfade.borrow_mut().take() {
+            Some(fade) => fade,
+            None => return,
+        };
+        if let Some(tick) = fade.tick_opt {
+            tick.remove();
+        }
+        for child in fade.from_opt.iter().chain(fade.to_opt.iter()) {
+            child.set_opacity(1.0);
+        }
+        self.show_visible_child();
+    }
+}
@@ -0,0 +1,164 @@
+//! [`ConstraintParts`], the plain decomposition of an immutable [`gtk::Constraint`] which lets
+//! [`ConstraintView`](super::ConstraintView) rebuild constraints with some part changed, and print
+//! them in the grammar of [`constraint!`](crate::constraint).
+
+use gtk::ConstraintAttribute;
+use std::fmt::Write as _;
+
+/// Every part of a [`gtk::Constraint`] as a plain value.
+#[derive(Clone, Debug)]
+pub(crate) struct ConstraintParts {
+    pub target: Option<gtk::ConstraintTarget>,
+    pub target_attribute: ConstraintAttribute,
+    pub relation: gtk::ConstraintRelation,
+    pub source: Option<gtk::ConstraintTarget>,
+    pub source_attribute: ConstraintAttribute,
+    pub multiplier: f64,
+    pub constant: f64,
+    pub strength: i32,
+}
+
+impl ConstraintParts {
+    /// Decompose the given constraint.
+    pub fn of(constraint: &gtk::Constraint) -> Self {
+        Self {
+            target: constraint.target(),
+            target_attribute: constraint.target_attribute(),
+            relation: constraint.relation(),
+            source: constraint.source(),
+            source_attribute: constraint.source_attribute(),
+            multiplier: constraint.multiplier(),
+            constant: constraint.constant(),
+            strength: constraint.strength(),
+        }
+    }
+
+    /// Whether this is a constant constraint, i.e. one with no source attribute.
+    pub fn is_constant(&self) -> bool {
+        self.source.is_none() && self.source_attribute == ConstraintAttribute::None
+    }
+
+    /// Whether the given widget or guide is the target or source.
+    pub fn refers_to(&self, target: &gtk::ConstraintTarget) -> bool {
+        self.target.as_ref() == Some(target) || self.source.as_ref() == Some(target)
+    }
+
+    /// Build a new [`gtk::Constraint`] from these parts.
+    pub fn build(&self) -> gtk::Constraint {
+        if self.is_constant() {
+            gtk::Constraint::new_constant(
+                self.target.as_ref(),
+                self.target_attribute,
+                self.relation,
+                self.constant,
+                self.strength,
+            )
+        } else {
+            gtk::Constraint::new(
+                self.target.as_ref(),
+                self.target_attribute,
+                self.relation,
+                self.source.as_ref(),
+                self.source_attribute,
+                self.multiplier,
+                self.constant,
+                self.strength,
+            )
+        }
+    }
+
+    /// Format these parts in the grammar accepted by [`constraint!`](crate::constraint), using
+    /// `name` to name targets and sources other than the container.
+    pub fn format(&self, name: &dyn Fn(&gtk::ConstraintTarget) -> String) -> String {
+        let mut out = format_side(self.target.as_ref(), self.target_attribute, name);
+        out.push(' ');
+        out.push_str(relation_keyword(self.relation));
+        out.push(' ');
+
+        if self.is_constant() {
+            let _ = write!(out, "{:?}", self.constant);
+        } else {
+            out.push_str(&format_side(
+                self.source.as_ref(),
+                self.source_attribute,
+                name,
+            ));
+            if self.multiplier != 1.0 {
+                let _ = write!(out, " * {:?}", self.multiplier);
+            }
+            if self.constant > 0.0 {
+                let _ = write!(out, " + {:?}", self.constant);
+            } else if self.constant < 0.0 {
+                let _ = write!(out, " - {:?}", -self.constant);
+            }
+        }
+
+        out.push_str(" @");
+        out.push_str(&strength_keyword(self.strength));
+        out
+    }
+}
+
+/// Format one side of a constraint, `name.attr` or just `attr` for the container.
+fn format_side(
+    target: Option<&gtk::ConstraintTarget>,
+    attribute: ConstraintAttribute,
+    name: &dyn Fn(&gtk::ConstraintTarget) -> String,
+) -> String {
+    match target {
+        Some(t) => format!("{}.{}", name(t), attribute_keyword(attribute)),
+        None => attribute_keyword(attribute).to_owned(),
+    }
+}
+
+/// The [`constraint_attribute!`](crate::constraint_attribute) keyword for an attribute.
+pub(crate) fn attribute_keyword(attribute: ConstraintAttribute) -> &'static str {
+    match attribute {
+        ConstraintAttribute::Left => "left",
+        ConstraintAttribute::Right => "right",
+        ConstraintAttribute::Top => "top",
+        ConstraintAttribute::Bottom => "bottom",
+        ConstraintAttribute::Start => "start",
+        ConstraintAttribute::End => "end",
+        ConstraintAttribute::Width => "width",
+        ConstraintAttribute::Height => "height",
+        ConstraintAttribute::CenterX => "center_x",
+        ConstraintAttribute::CenterY => "center_y",
+        ConstraintAttribute::Baseline => "baseline",
+        _ => "none",
+    }
+}
+
+/// The [`constraint_relation!`](crate::constraint_relation) operator for a relation.
+pub(crate) fn relation_keyword(relation: gtk::ConstraintRelation) -> &'static str {
+    match relation {
+        gtk::ConstraintRelation::Le => "<=",
+        gtk::ConstraintRelation::Ge => ">=",
+        _ => "==",
+    }
+}
+
+/// The [`constraint_strength!`](crate::constraint_strength) keyword for a strength, or the number
+/// itself if it isn't one of the named strengths.
+pub(crate) fn strength_keyword(strength: i32) -> String {
+    match strength {
+        gtk::ffi::GTK_CONSTRAINT_STRENGTH_REQUIRED => "required".to_owned(),
+        gtk::ffi::GTK_CONSTRAINT_STRENGTH_STRONG => "strong".to_owned(),
+        gtk::ffi::GTK_CONSTRAINT_STRENGTH_MEDIUM => "medium".to_owned(),
+        gtk::ffi::GTK_CONSTRAINT_STRENGTH_WEAK => "weak".to_owned(),
+        s => s.to_string(),
+    }
+}
+
+/// Turn an arbitrary widget or guide name into something usable as an identifier in the
+/// [`constraint!`](crate::constraint) grammar.
+pub(crate) fn identifier_for(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    ident
+}
@@ -0,0 +1,122 @@
+//! [`GuideProbes`], invisible widgets constrained to coincide with the guides of a
+//! [`ConstraintView`] so that the guides' otherwise inaccessible geometry can be read back, for
+//! design mode and the debug overlay to draw.
+
+use super::ConstraintView;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::Cast as _;
+use gtk::prelude::{ListModelExt as _, WidgetExt as _};
+use gtk::{graphene, Orientation};
+
+/// An invisible widget tracking the geometry of a guide.
+struct Probe {
+    guide: gtk::ConstraintGuide,
+    widget: gtk::Widget,
+    constraints: Vec<gtk::Constraint>,
+}
+
+/// A [`Probe`] for each guide a view's layout had when they were installed.
+#[derive(Default)]
+pub(super) struct GuideProbes(Vec<Probe>);
+
+impl GuideProbes {
+    /// Install a probe for each guide in the layout of the given view.
+    pub(super) fn install(view: &ConstraintView) -> Self {
+        let layout = view.layout();
+        let guides = layout.observe_guides();
+        let mut probes = Vec::new();
+
+        for i in 0..guides.n_items() {
+            let guide = match guides
+                .item(i)
+                .and_then(|o| o.downcast::<gtk::ConstraintGuide>().ok())
+            {
+                Some(guide) => guide,
+                None => continue,
+            };
+
+            let widget: gtk::Widget = gtk::Box::new(Orientation::Horizontal, 0).upcast();
+            widget.set_can_target(false);
+            widget.set_can_focus(false);
+            widget.set_parent(view);
+
+            let constraints = vec![
+                crate::constraint!(widget.left == guide.left),
+                crate::constraint!(widget.top == guide.top),
+                crate::constraint!(widget.width == guide.width),
+                crate::constraint!(widget.height == guide.height),
+            ];
+            for c in &constraints {
+                layout.add_constraint(c);
+            }
+
+            probes.push(Probe {
+                guide,
+                widget,
+                constraints,
+            });
+        }
+
+        Self(probes)
+    }
+
+    /// Remove the probes from the given view.
+    pub(super) fn remove(self, view: &ConstraintView) {
+        let layout = view.layout();
+        for probe in self.0 {
+            for c in &probe.constraints {
+                layout.remove_constraint(c);
+            }
+            probe.widget.unparent();
+        }
+    }
+
+    /// Whether the given constraint holds one of the probes to its guide.
+    pub(super) fn is_probe_constraint(&self, constraint: &gtk::Constraint) -> bool {
+        self.0
+            .iter()
+            .any(|p| p.constraints.iter().any(|c| c == constraint))
+    }
+
+    /// Whether the given widget is one of the probes.
+    pub(super) fn is_probe_widget(&self, widget: &gtk::Widget) -> bool {
+        self.0.iter().any(|p| &p.widget == widget)
+    }
+
+    /// The bounds of the given guide relative to the view, if it has a probe.
+    pub(super) fn guide_bounds(
+        &self,
+        view: &ConstraintView,
+        guide: &gtk::ConstraintTarget,
+    ) -> Option<graphene::Rect> {
+        self.0
+            .iter()
+            .find(|p| p.guide.upcast_ref::<gtk::ConstraintTarget>() == guide)
+            .and_then(|p| p.widget.compute_bounds(view))
+    }
+
+    /// The bounds of every probed guide relative to the view.
+    pub(super) fn bounds(&self, view: &ConstraintView) -> Vec<graphene::Rect> {
+        self.0
+            .iter()
+            .filter_map(|p| p.widget.compute_bounds(view))
+            .collect()
+    }
+}
+
+impl ConstraintView {
+    /// Whether the given constraint holds a probe of design mode or the debug overlay to its guide.
+    pub(super) fn is_probe_constraint(&self, constraint: &gtk::Constraint) -> bool {
+        let imp = self.imp();
+        matches!(imp.design.borrow().as_ref(), Some(d) if d.is_probe_constraint(constraint))
+            || self.is_debug_overlay_constraint(constraint)
+    }
+
+    /// Whether the given child is a probe of design mode or the debug overlay rather than a child
+    /// added by the user.
+    pub(super) fn is_probe_widget(&self, widget: &gtk::Widget) -> bool {
+        let imp = self.imp();
+        matches!(imp.design.borrow().as_ref(), Some(d) if d.is_probe_widget(widget))
+            || matches!(imp.debug_overlay.borrow().as_ref(), Some(o) if o.is_probe_widget(widget))
+    }
+}
@@ -0,0 +1,187 @@
+//! [`ConstraintSet`], a collection of constraints put into and taken out of a layout together, for
+//! switching a layout between modes, e.g. viewing and editing, or phone and desktop.
+
+use super::ConstraintView;
+use glib::{Cast as _, ObjectExt as _, WeakRef};
+use gtk::prelude::ListModelExt as _;
+use std::cell::RefCell;
+use std::fmt;
+
+/// Something constraints can be added to: a [`gtk::ConstraintLayout`], or a [`ConstraintView`]
+/// standing for its layout.
+pub trait AsConstraintLayout {
+    /// Return the layout to add constraints to.
+    fn as_constraint_layout(&self) -> gtk::ConstraintLayout;
+}
+
+impl AsConstraintLayout for gtk::ConstraintLayout {
+    fn as_constraint_layout(&self) -> gtk::ConstraintLayout {
+        self.clone()
+    }
+}
+
+impl AsConstraintLayout for ConstraintView {
+    fn as_constraint_layout(&self) -> gtk::ConstraintLayout {
+        self.layout()
+    }
+}
+
+/// A named collection of constraints, e.g. made with [`constraint!`](crate::constraint) or
+/// [`constraint_set!`](crate::constraint_set), which are [activated](Self::activate_on) in a layout
+/// and [deactivated](Self::deactivate) again all at once.
+///
+/// ```
+///    # use springsteel::{constraint, constraint_set, ConstraintView};
+///    # use gtk::prelude::ListModelExt as _;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let view = ConstraintView::new();
+///    let (title, entry) = (gtk::Label::new(Some("title")), gtk::Entry::new());
+///    view.add_child(&title);
+///    view.add_child(&entry);
+///
+///    let mut editing = constraint_set!["editing";
+///        constraint!(entry.top == title.bottom + 8.0),
+///        constraint!(entry.left == title.left),
+///    ];
+///    editing.push(constraint!(entry.width >= 200.0));
+///
+///    editing.activate_on(&view);
+///    editing.activate_on(&view);
+///    assert!(editing.is_active());
+///    assert_eq!(view.layout().observe_constraints().n_items(), 3);
+///
+///    // Constraints removed individually are left be, even once added to another layout.
+///    let moved = &editing.constraints()[1];
+///    view.layout().remove_constraint(moved);
+///    let other = ConstraintView::new();
+///    other.layout().add_constraint(moved);
+///    editing.deactivate();
+///    assert!(!editing.is_active());
+///    assert_eq!(view.layout().observe_constraints().n_items(), 0);
+///    assert_eq!(other.layout().observe_constraints().n_items(), 1);
+/// ```
+pub struct ConstraintSet {
+    name: String,
+    constraints: Vec<gtk::Constraint>,
+
+    /// The layout the set is active in, if it is.
+    layout_opt: RefCell<Option<WeakRef<gtk::ConstraintLayout>>>,
+}
+
+impl fmt::Debug for ConstraintSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ConstraintSet")
+            .field("name", &self.name)
+            .field("constraints", &self.constraints)
+            .field("active", &self.is_active())
+            .finish()
+    }
+}
+
+impl ConstraintSet {
+    /// Make an empty set with the given name.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            constraints: Vec::new(),
+            layout_opt: RefCell::new(None),
+        }
+    }
+
+    /// Add a constraint to the set, returning the set.
+    pub fn constraint(mut self, constraint: gtk::Constraint) -> Self {
+        self.push(constraint);
+        self
+    }
+
+    /// Add a constraint to the set, and to the layout the set is active in, if it is.
+    pub fn push(&mut self, constraint: gtk::Constraint) {
+        if let Some(layout) = self.active_layout() {
+            layout.add_constraint(&constraint);
+        }
+        self.constraints.push(constraint);
+    }
+
+    /// Return the name of the set.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the constraints of the set, in the order they were added.
+    pub fn constraints(&self) -> &[gtk::Constraint] {
+        &self.constraints
+    }
+
+    /// Return whether the set is active in a layout.
+    pub fn is_active(&self) -> bool {
+        self.active_layout().is_some()
+    }
+
+    /// Add every constraint of the set to the given layout, or the layout of the given view,
+    /// except those already in a layout. If the set is already active in the layout, nothing is
+    /// done, and if it's active in another one, it's deactivated there first.
+    pub fn activate_on(&self, target: &impl AsConstraintLayout) {
+        let layout = target.as_constraint_layout();
+        match self.active_layout() {
+            Some(active) if active == layout => return,
+            Some(_) => self.deactivate(),
+            None => {}
+        }
+
+        for constraint in self.constraints.iter().filter(|c| !c.is_attached()) {
+            layout.add_constraint(constraint);
+        }
+        self.layout_opt.replace(Some(layout.downgrade()));
+    }
+
+    /// Remove every constraint of the set from the layout it's active in, if it is, leaving be
+    /// any which have been removed individually meanwhile, even if they've since been added to
+    /// another layout.
+    pub fn deactivate(&self) {
+        let layout = match self.layout_opt.take().and_then(|weak| weak.upgrade()) {
+            Some(layout) => layout,
+            None => return,
+        };
+        let in_layout = layout_constraints(&layout);
+        for constraint in self.constraints.iter().filter(|c| in_layout.contains(c)) {
+            layout.remove_constraint(constraint);
+        }
+    }
+
+    /// Drop the constraints of the set which refer to the given widget or guide, removing them
+    /// from the layout the set is active in, if it is.
+    pub(super) fn forget_constraints_for(&mut self, target: &gtk::ConstraintTarget) {
+        let layout_opt = self.active_layout();
+        let in_layout = layout_opt
+            .as_ref()
+            .map(layout_constraints)
+            .unwrap_or_default();
+        self.constraints.retain(|constraint| {
+            let refers = constraint.target().as_ref() == Some(target)
+                || constraint.source().as_ref() == Some(target);
+            if refers && in_layout.contains(constraint) {
+                if let Some(layout) = layout_opt.as_ref() {
+                    layout.remove_constraint(constraint);
+                }
+            }
+            !refers
+        });
+    }
+
+    /// Return the layout the set is active in, if it is and the layout still exists.
+    fn active_layout(&self) -> Option<gtk::ConstraintLayout> {
+        self.layout_opt
+            .borrow()
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+    }
+}
+
+/// Return the constraints presently in the given layout.
+fn layout_constraints(layout: &gtk::ConstraintLayout) -> Vec<gtk::Constraint> {
+    let constraints = layout.observe_constraints();
+    (0..constraints.n_items())
+        .filter_map(|i| constraints.item(i)?.downcast::<gtk::Constraint>().ok())
+        .collect()
+}
@@ -0,0 +1,219 @@
+//! Size limit properties of [`ConstraintView`], which hold the view itself to a minimum or maximum
+//! width or height with constraints on the layout, so that the limits take part in measuring the
+//! view like any other constraint, and the adjustment of what the layout measures to respect them.
+
+use super::ConstraintView;
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::ObjectExt as _;
+use gtk::prelude::WidgetExt as _;
+use gtk::{ConstraintAttribute, ConstraintRelation, Orientation};
+
+/// Names of the size limit properties, in the order the limits are stored.
+pub(super) const SIZE_PROPERTIES: [&str; 4] = [
+    "min-content-width",
+    "min-content-height",
+    "max-content-width",
+    "max-content-height",
+];
+
+/// A size limit of a view, and the constraint presently enforcing it if it's set.
+pub(super) struct SizeLimit {
+    size: f32,
+    constraint_opt: Option<gtk::Constraint>,
+}
+
+impl Default for SizeLimit {
+    fn default() -> Self {
+        Self {
+            size: -1.0,
+            constraint_opt: None,
+        }
+    }
+}
+
+impl ConstraintView {
+    /// Set the `min-content-width` and `min-content-height` properties of this view, which hold
+    /// the view to at least the given width and height with constraints on its layout. A limit of
+    /// `-1`, the default, removes it.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use glib::ObjectExt as _;
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # use std::{cell::Cell, rc::Rc};
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let notified = Rc::new(Cell::new(0));
+    ///    let notified_ = notified.clone();
+    ///    view.connect_notify_local(Some("min-content-width"), move |_, _| {
+    ///        notified_.set(notified_.get() + 1);
+    ///    });
+    ///
+    ///    view.set_min_content_size(120.0, 80.0);
+    ///    view.set_min_content_size(120.0, 90.0);
+    ///    assert_eq!(notified.get(), 1);
+    ///    assert_eq!(view.property::<f32>("min-content-height"), 90.0);
+    ///    assert_eq!(view.measure(gtk::Orientation::Horizontal, -1).0, 120);
+    ///    assert_eq!(view.measure(gtk::Orientation::Vertical, -1).0, 90);
+    ///
+    ///    view.set_property("min-content-width", -1.0f32);
+    ///    assert_eq!(notified.get(), 2);
+    ///    assert_eq!(view.measure(gtk::Orientation::Horizontal, -1).0, 0);
+    /// ```
+    pub fn set_min_content_size(&self, width: f32, height: f32) {
+        let _notify_guard = self.freeze_notify();
+        self.set_size_limit("min-content-width", width);
+        self.set_size_limit("min-content-height", height);
+    }
+
+    /// Set the `max-content-width` and `max-content-height` properties of this view, which hold
+    /// the view to at most the given width and height with constraints on its layout. A limit of
+    /// `-1`, the default, removes it.
+    ///
+    /// A maximum less than the corresponding minimum can't be satisfied, and leaves the layout to
+    /// complain.
+    pub fn set_max_content_size(&self, width: f32, height: f32) {
+        let _notify_guard = self.freeze_notify();
+        self.set_size_limit("max-content-width", width);
+        self.set_size_limit("max-content-height", height);
+    }
+
+    /// Set the `raw-measure` property of this view. While it's unset, as it is by default, the
+    /// size the layout measures for the view is adjusted before being reported: the natural size
+    /// falls back to the largest natural size among the children when the layout measures
+    /// nothing, as it does when no constraint relates the children to the view, and both sizes
+    /// are clamped to the `min-content-*` and `max-content-*` limits. Setting it reports what the
+    /// layout measures unchanged, as views did before.
+    ///
+    /// ```
+    ///    # use springsteel::ConstraintView;
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let label = gtk::Label::new(Some("unconstrained"));
+    ///    view.add_child(&label);
+    ///    let natural_width = |view: &ConstraintView| {
+    ///        view.measure(gtk::Orientation::Horizontal, -1).1
+    ///    };
+    ///    let label_width = label.measure(gtk::Orientation::Horizontal, -1).1;
+    ///
+    ///    assert_eq!(natural_width(&view), label_width);
+    ///    view.set_max_content_size(10.0, -1.0);
+    ///    assert_eq!(natural_width(&view), 10);
+    ///
+    ///    view.set_raw_measure(true);
+    ///    assert!(natural_width(&view) < label_width);
+    /// ```
+    pub fn set_raw_measure(&self, raw: bool) {
+        if self.imp().raw_measure.replace(raw) != raw {
+            self.queue_resize();
+            self.notify("raw-measure");
+        }
+    }
+
+    /// Adjust the `(minimum, natural, minimum_baseline, natural_baseline)` the layout measured for
+    /// this view in the given orientation, as described for
+    /// [`set_raw_measure`](Self::set_raw_measure).
+    pub(super) fn adjust_measure(
+        &self,
+        orientation: Orientation,
+        (mut minimum, mut natural, minimum_baseline, natural_baseline): (i32, i32, i32, i32),
+    ) -> (i32, i32, i32, i32) {
+        let (min_limit, max_limit) = {
+            let limits = self.imp().size_limits.borrow();
+            match orientation {
+                Orientation::Horizontal => (limits[0].size, limits[2].size),
+                _ => (limits[1].size, limits[3].size),
+            }
+        };
+
+        if natural == 0 {
+            natural = self
+                .children()
+                .filter(|child| child.should_layout())
+                .map(|child| child.measure(orientation, -1).1)
+                .max()
+                .unwrap_or(0);
+        }
+        if min_limit >= 0.0 {
+            minimum = minimum.max(min_limit.ceil() as i32);
+        }
+        if max_limit >= 0.0 {
+            natural = natural.min(max_limit.floor() as i32);
+        }
+
+        (
+            minimum,
+            natural.max(minimum),
+            minimum_baseline,
+            natural_baseline,
+        )
+    }
+
+    /// Return the size limit with the given property name.
+    pub(super) fn size_limit(&self, name: &str) -> f32 {
+        self.imp().size_limits.borrow()[size_index(name)].size
+    }
+
+    /// Set the size limit with the given property name, replacing the constraint enforcing it if
+    /// it changed.
+    pub(super) fn set_size_limit(&self, name: &str, size: f32) {
+        let index = size_index(name);
+        let layout = self.layout();
+        {
+            let mut limits = self.imp().size_limits.borrow_mut();
+            let limit = &mut limits[index];
+            if limit.size == size {
+                return;
+            }
+            limit.size = size;
+            if let Some(constraint) = limit.constraint_opt.take() {
+                layout.remove_constraint(&constraint);
+            }
+            if size >= 0.0 {
+                let constraint = limit_constraint(index, size);
+                layout.add_constraint(&constraint);
+                limit.constraint_opt = Some(constraint);
+            }
+        }
+        self.notify(name);
+    }
+
+    /// Remove the constraints enforcing the size limits from the layout, leaving the limits unset.
+    pub(super) fn clear_size_limits(&self) {
+        let limits = std::mem::take(&mut *self.imp().size_limits.borrow_mut());
+        let layout = self.layout();
+        for constraint in limits.into_iter().filter_map(|l| l.constraint_opt) {
+            layout.remove_constraint(&constraint);
+        }
+    }
+}
+
+/// Return the index into the stored limits of the property with the given name.
+fn size_index(name: &str) -> usize {
+    SIZE_PROPERTIES
+        .iter()
+        .position(|p| *p == name)
+        .unwrap_or_else(|| panic!("ConstraintView has no size property {}", name))
+}
+
+/// Make the constraint holding the view to the limit at the given index.
+fn limit_constraint(index: usize, size: f32) -> gtk::Constraint {
+    let attribute = match index % 2 {
+        0 => ConstraintAttribute::Width,
+        _ => ConstraintAttribute::Height,
+    };
+    let relation = match index / 2 {
+        0 => ConstraintRelation::Ge,
+        _ => ConstraintRelation::Le,
+    };
+    gtk::Constraint::new_constant(
+        None::<&gtk::ConstraintTarget>,
+        attribute,
+        relation,
+        f64::from(size),
+        gtk::ffi::GTK_CONSTRAINT_STRENGTH_REQUIRED,
+    )
+}
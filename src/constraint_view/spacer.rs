@@ -0,0 +1,114 @@
+//! Spacers in a [`ConstraintView`], guides sized along one axis to put room between children,
+//! without setting the sizes of a [`gtk::ConstraintGuide`] by hand.
+
+use super::ConstraintView;
+use gtk::{ConstraintAttribute, ConstraintGuide, Orientation};
+
+impl ConstraintView {
+    /// Add a guide to the layout of this view with the given minimum, natural, and maximum size
+    /// along the given axis, and no size across it, to put between children. Sizes are rounded to
+    /// whole pixels, and an infinite maximum leaves the size unlimited.
+    ///
+    /// ```
+    ///    # use springsteel::testing::LayoutHarness;
+    ///    # use springsteel::{add_constraint, ConstraintView};
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let (a, b) = (gtk::Button::new(), gtk::Button::new());
+    ///    view.add_child(&a);
+    ///    view.add_child(&b);
+    ///    let gap = view.add_spacer(gtk::Orientation::Horizontal, 10.0, 20.0, 40.0);
+    ///    add_constraint!(view, a.left == left);
+    ///    add_constraint!(view, a.width == 50.0);
+    ///    add_constraint!(view, gap.left == a.right);
+    ///    add_constraint!(view, b.left == gap.right);
+    ///    add_constraint!(view, b.width == 50.0);
+    ///    add_constraint!(view, b.right <= right);
+    ///
+    ///    let mut harness = LayoutHarness::new(&view, 400, 50);
+    ///    assert_eq!(harness.allocation_of(&b).x(), 70);
+    ///    harness.resize(115, 50);
+    ///    assert_eq!(harness.allocation_of(&b).x(), 65);
+    /// ```
+    pub fn add_spacer(&self, axis: Orientation, min: f64, nat: f64, max: f64) -> ConstraintGuide {
+        let guide = ConstraintGuide::new();
+        size_spacer(&guide, axis, min, nat, max);
+        self.layout().add_guide(&guide);
+        guide
+    }
+
+    /// Add a guide to the layout of this view which grows along the given axis to take up
+    /// whatever room the other constraints leave, held to the size of the view by a
+    /// [tracked](Self::add_constraint) weak constraint.
+    ///
+    /// ```
+    ///    # use springsteel::testing::LayoutHarness;
+    ///    # use springsteel::{add_constraint, ConstraintView};
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let (back, next) = (gtk::Button::new(), gtk::Button::new());
+    ///    view.add_child(&back);
+    ///    view.add_child(&next);
+    ///    let filler = view.flexible_spacer(gtk::Orientation::Horizontal);
+    ///    add_constraint!(view, back.left == left);
+    ///    add_constraint!(view, back.width == 60.0);
+    ///    add_constraint!(view, filler.left == back.right);
+    ///    add_constraint!(view, next.left == filler.right);
+    ///    add_constraint!(view, next.width == 60.0);
+    ///    add_constraint!(view, next.right <= right);
+    ///
+    ///    let mut harness = LayoutHarness::new(&view, 300, 40);
+    ///    assert_eq!(harness.allocation_of(&next).x(), 240);
+    ///    harness.resize(500, 40);
+    ///    assert_eq!(harness.allocation_of(&next).x(), 440);
+    /// ```
+    pub fn flexible_spacer(&self, axis: Orientation) -> ConstraintGuide {
+        let guide = self.add_spacer(axis, 0.0, 0.0, f64::INFINITY);
+        let attribute = match axis {
+            Orientation::Horizontal => ConstraintAttribute::Width,
+            _ => ConstraintAttribute::Height,
+        };
+        self.add_constraint(&gtk::Constraint::new(
+            Some(&guide),
+            attribute,
+            gtk::ConstraintRelation::Eq,
+            None::<&gtk::ConstraintTarget>,
+            attribute,
+            1.0,
+            0.0,
+            gtk::ffi::GTK_CONSTRAINT_STRENGTH_WEAK,
+        ));
+        guide
+    }
+}
+
+/// Set the sizes of the given guide along the given axis, and none across it.
+pub(super) fn size_spacer(
+    guide: &ConstraintGuide,
+    axis: Orientation,
+    min: f64,
+    nat: f64,
+    max: f64,
+) {
+    let pixels = |size: f64| {
+        if size.is_finite() {
+            size.round().clamp(0.0, f64::from(i32::MAX)) as i32
+        } else {
+            i32::MAX
+        }
+    };
+    match axis {
+        Orientation::Horizontal => {
+            guide.set_min_size(pixels(min), 0);
+            guide.set_nat_size(pixels(nat), 0);
+            guide.set_max_size(pixels(max), 0);
+        }
+        _ => {
+            guide.set_min_size(0, pixels(min));
+            guide.set_nat_size(0, pixels(nat));
+            guide.set_max_size(0, pixels(max));
+        }
+    }
+}
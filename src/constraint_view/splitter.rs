@@ -0,0 +1,207 @@
+//! Draggable splitters in a [`ConstraintView`], which move a tracked constraint by dragging a
+//! handle, like the divider of a [`gtk::Paned`] but able to drive any constraint of the layout.
+
+use super::tracked::ConstraintId;
+use super::ConstraintView;
+use crate::controller_streams::{drag_events, ControllerGuard, DragEvent};
+use crate::ValueStream;
+use futures::stream::{Stream, StreamExt as _};
+use glib::{Cast as _, IsA, ObjectExt as _};
+use gtk::prelude::WidgetExt as _;
+
+/// Options for [`ConstraintView::add_splitter`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SplitterOptions {
+    /// The least the constant of the constraint can be dragged to, if limited.
+    pub min_opt: Option<f64>,
+
+    /// The most the constant of the constraint can be dragged to, if limited.
+    pub max_opt: Option<f64>,
+}
+
+/// A splitter installed by [`ConstraintView::add_splitter`].
+pub struct Splitter {
+    /// The constant the constraint was left at by the end of each drag, e.g. to persist.
+    pub positions: ValueStream<f64>,
+
+    /// Guard which removes the drag gesture when dropped.
+    pub guard: ControllerGuard,
+}
+
+impl ConstraintView {
+    /// Let the given handle, a child of this view, be dragged along the given axis to
+    /// [set the constant](Self::set_constant) of the constraint added with
+    /// [`add_constraint`](Self::add_constraint) under the given id, moving it by as much as the
+    /// pointer moves, within the limits of `options`. Typically the constraint positions a guide
+    /// or an edge between two parts of the view, and the handle is held at that position too.
+    ///
+    /// The drag gesture is installed on the view rather than the handle, as the handle moves along
+    /// with the drag, and only drags beginning on the handle move the constraint. It's driven by a
+    /// future of the [view's spawner](Self::spawner), and stays installed until
+    /// [`Splitter::guard`] is dropped, or for as long as the view if the guard is
+    /// [kept](ControllerGuard::keep).
+    ///
+    /// ```
+    ///    # use springsteel::constraint_view::SplitterOptions;
+    ///    # use springsteel::{add_constraint, constraint, ConstraintView};
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let sidebar = gtk::Label::new(None);
+    ///    let handle = gtk::Separator::new(gtk::Orientation::Vertical);
+    ///    view.add_child(&sidebar);
+    ///    view.add_child(&handle);
+    ///    add_constraint!(view, sidebar.left == left);
+    ///    add_constraint!(view, handle.left == sidebar.right);
+    ///    let split = view.add_constraint(&constraint!(sidebar.width == 200.0));
+    ///
+    ///    let options = SplitterOptions { min_opt: Some(100.0), max_opt: Some(400.0) };
+    ///    let splitter = view.add_splitter(&handle, gtk::Orientation::Horizontal, split, options);
+    ///    splitter.guard.keep();
+    /// ```
+    pub fn add_splitter(
+        &self,
+        handle: &impl IsA<gtk::Widget>,
+        axis: gtk::Orientation,
+        id: ConstraintId,
+        options: SplitterOptions,
+    ) -> Splitter {
+        let drags = drag_events(self);
+        Splitter {
+            positions: self.drive_splitter(handle, axis, id, options, drags.events),
+            guard: drags.guard,
+        }
+    }
+
+    /// Move the constraint added under the given id by the drags of a stream of [`DragEvent`]s
+    /// relative to this view, as [`add_splitter`](Self::add_splitter) does with the drags of the
+    /// gesture it installs, returning a stream of the constant the constraint was left at by the
+    /// end of each drag. This lets a splitter be driven by a gesture installed elsewhere, or by
+    /// made up drags in tests:
+    ///
+    /// ```
+    ///    # use springsteel::constraint_view::{ConstraintId, SplitterOptions};
+    ///    # use springsteel::controller_streams::DragEvent;
+    ///    # use springsteel::{add_constraint, assert_yields, constraint, ConstraintView};
+    ///    # use springsteel::ValueStream;
+    ///    # use gtk::prelude::{GtkWindowExt as _, WidgetExt as _};
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let sidebar = gtk::Label::new(None);
+    ///    let handle = gtk::Separator::new(gtk::Orientation::Vertical);
+    ///    view.add_child(&sidebar);
+    ///    view.add_child(&handle);
+    ///    add_constraint!(view, sidebar.left == left);
+    ///    add_constraint!(view, handle.left == sidebar.right);
+    ///    add_constraint!(view, handle.width == 8.0);
+    ///    add_constraint!(view, handle.top == top);
+    ///    add_constraint!(view, handle.bottom == bottom);
+    ///    let split = view.add_constraint(&constraint!(sidebar.width == 200.0));
+    ///
+    ///    // Drags are only picked up when they begin on the handle, so it must be mapped.
+    ///    let window = gtk::Window::new();
+    ///    window.set_default_size(600, 300);
+    ///    window.set_child(Some(&view));
+    ///    window.present();
+    ///    let context = glib::MainContext::default();
+    ///    while !handle.is_mapped() || handle.width() == 0 {
+    ///        context.iteration(true);
+    ///    }
+    ///
+    ///    let drags = ValueStream::new();
+    ///    let options = SplitterOptions { min_opt: Some(100.0), max_opt: Some(400.0) };
+    ///    let axis = gtk::Orientation::Horizontal;
+    ///    let mut positions = view.drive_splitter(&handle, axis, split, options, drags.clone());
+    ///    let constant_of = |id: ConstraintId| {
+    ///        while context.iteration(false) {}
+    ///        view.tracked_constraints().iter().find(|t| t.id == id).map(|t| t.constant)
+    ///    };
+    ///
+    ///    let on_handle = handle.compute_bounds(&view).expect("compute_bounds");
+    ///    let (x, y) = (on_handle.x() + on_handle.width() / 2.0, on_handle.y() + 10.0);
+    ///    drags.push(DragEvent::Begin { x: f64::from(x), y: f64::from(y) });
+    ///    drags.push(DragEvent::Update { dx: 500.0, dy: 0.0 });
+    ///    assert_eq!(constant_of(split), Some(400.0));
+    ///    drags.push(DragEvent::Update { dx: -500.0, dy: 0.0 });
+    ///    assert_eq!(constant_of(split), Some(100.0));
+    ///    springsteel::assert_pending!(positions);
+    ///
+    ///    drags.push(DragEvent::End { dx: 50.0, dy: 20.0 });
+    ///    assert_eq!(constant_of(split), Some(250.0));
+    ///    assert_eq!(assert_yields!(positions, 1), vec![250.0]);
+    ///
+    ///    // Drags beginning off the handle leave the constraint alone.
+    ///    drags.push(DragEvent::Begin { x: 1.0, y: 1.0 });
+    ///    drags.push(DragEvent::End { dx: 50.0, dy: 0.0 });
+    ///    assert_eq!(constant_of(split), Some(250.0));
+    ///    assert_eq!(assert_yields!(positions, 0), Vec::<f64>::new());
+    ///    window.destroy();
+    /// ```
+    pub fn drive_splitter<S>(
+        &self,
+        handle: &impl IsA<gtk::Widget>,
+        axis: gtk::Orientation,
+        id: ConstraintId,
+        options: SplitterOptions,
+        events: S,
+    ) -> ValueStream<f64>
+    where
+        S: Stream<Item = DragEvent> + 'static,
+    {
+        let positions = ValueStream::new();
+
+        let view_weak = self.downgrade();
+        let handle_weak = handle.upcast_ref::<gtk::Widget>().downgrade();
+        let positions_for_drags = positions.clone();
+        let mut events = Box::pin(events);
+        self.spawner().spawn(async move {
+            // The constant at the start of the drag in progress, if it began on the handle.
+            let mut start_opt = None;
+            while let Some(event) = events.next().await {
+                let (view, handle) = match (view_weak.upgrade(), handle_weak.upgrade()) {
+                    (Some(view), Some(handle)) => (view, handle),
+                    _ => break,
+                };
+                let (dx, dy) = match event {
+                    DragEvent::Begin { x, y } => {
+                        let picked_opt = view.pick(x, y, gtk::PickFlags::DEFAULT);
+                        let on_handle =
+                            picked_opt.map_or(false, |w| w == handle || w.is_ancestor(&handle));
+                        start_opt = if on_handle {
+                            view.tracked_constant(id)
+                        } else {
+                            None
+                        };
+                        continue;
+                    }
+                    DragEvent::Update { dx, dy } | DragEvent::End { dx, dy } => (dx, dy),
+                };
+                let start = match start_opt {
+                    Some(start) => start,
+                    None => continue,
+                };
+
+                let delta = match axis {
+                    gtk::Orientation::Horizontal => dx,
+                    _ => dy,
+                };
+                let mut position = start + delta;
+                if let Some(max) = options.max_opt {
+                    position = position.min(max);
+                }
+                if let Some(min) = options.min_opt {
+                    position = position.max(min);
+                }
+                view.set_constant(id, position);
+
+                if let DragEvent::End { .. } = event {
+                    start_opt = None;
+                    positions_for_drags.push(position);
+                }
+            }
+        });
+
+        positions
+    }
+}
@@ -0,0 +1,247 @@
+//! Stacking helpers for [`ConstraintView`], which chain children one after another along an axis
+//! with tracked constraints, so that a view can stand in for a [`gtk::Box`].
+
+use super::{ConstraintId, ConstraintView};
+use glib::Cast as _;
+use gtk::{ConstraintAttribute, ConstraintRelation, Orientation};
+
+/// How children of a stack are placed across the axis they're stacked along.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StackAlign {
+    /// Stretch each child to both cross edges of the anchor.
+    Fill,
+    /// Hold each child to the leading cross edge (start or top) of the anchor.
+    Start,
+    /// Center each child across the anchor.
+    Center,
+    /// Hold each child to the trailing cross edge (end or bottom) of the anchor.
+    End,
+    /// Add no constraints across the axis, for the caller to place the children.
+    None,
+}
+
+/// Options for [`ConstraintView::stack_vertical`] and [`ConstraintView::stack_horizontal`].
+#[derive(Clone, Debug)]
+pub struct StackOptions {
+    /// What the stack is pinned and aligned to, or `None` for the view itself. The
+    /// [content guide](ConstraintView::content_guide) is a common choice.
+    pub anchor_opt: Option<gtk::ConstraintTarget>,
+
+    /// Whether the first child is pinned to the leading edge (top or start) of the anchor.
+    pub pin_leading: bool,
+
+    /// Whether the last child is pinned to the trailing edge (bottom or end) of the anchor.
+    pub pin_trailing: bool,
+
+    /// Whether every child is held to the same size along the axis as the first.
+    pub equal_sizes: bool,
+
+    /// How the children are placed across the axis.
+    pub cross_align: StackAlign,
+}
+
+impl Default for StackOptions {
+    /// Pin both ends to the view itself and fill across it, without equal sizes.
+    fn default() -> Self {
+        Self {
+            anchor_opt: None,
+            pin_leading: true,
+            pin_trailing: true,
+            equal_sizes: false,
+            cross_align: StackAlign::Fill,
+        }
+    }
+}
+
+/// The attributes involved in stacking along one axis.
+struct Axis {
+    leading: ConstraintAttribute,
+    trailing: ConstraintAttribute,
+    size: ConstraintAttribute,
+    cross_leading: ConstraintAttribute,
+    cross_trailing: ConstraintAttribute,
+    cross_center: ConstraintAttribute,
+}
+
+impl Axis {
+    fn of(orientation: Orientation) -> Self {
+        use ConstraintAttribute::*;
+        match orientation {
+            Orientation::Horizontal => Self {
+                leading: Start,
+                trailing: End,
+                size: Width,
+                cross_leading: Top,
+                cross_trailing: Bottom,
+                cross_center: CenterY,
+            },
+            _ => Self {
+                leading: Top,
+                trailing: Bottom,
+                size: Height,
+                cross_leading: Start,
+                cross_trailing: End,
+                cross_center: CenterX,
+            },
+        }
+    }
+}
+
+impl ConstraintView {
+    /// Stack the given children of this view from top to bottom with `spacing` between each, by
+    /// adding the chain of constraints between them along with any pins and alignment `opts` asks
+    /// for. The constraints are [tracked](Self::add_constraint), and their ids returned so that
+    /// the stack can be removed and made again when the children change.
+    ///
+    /// ```
+    ///    # use springsteel::constraint_view::{StackAlign, StackOptions};
+    ///    # use springsteel::ConstraintView;
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let rows: Vec<gtk::Widget> = (0..3)
+    ///        .map(|_| {
+    ///            let row = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    ///            row.set_size_request(50, 20);
+    ///            view.add_child(&row);
+    ///            row.into()
+    ///        })
+    ///        .collect();
+    ///    let rows: Vec<&gtk::Widget> = rows.iter().collect();
+    ///
+    ///    let opts = StackOptions { pin_trailing: false, ..StackOptions::default() };
+    ///    let ids = view.stack_vertical(&rows, 8.0, &opts);
+    ///    assert_eq!(ids.len(), 1 + 2 + 3 * 2);
+    ///
+    ///    view.measure(gtk::Orientation::Horizontal, -1);
+    ///    view.measure(gtk::Orientation::Vertical, -1);
+    ///    view.size_allocate(&gtk::Allocation::new(0, 0, 100, 100), -1);
+    ///    let bounds: Vec<_> = rows
+    ///        .iter()
+    ///        .map(|row| row.compute_bounds(&view).expect("compute_bounds"))
+    ///        .map(|b| (b.y(), b.width()))
+    ///        .collect();
+    ///    assert_eq!(bounds, vec![(0.0, 100.0), (28.0, 100.0), (56.0, 100.0)]);
+    ///
+    ///    for id in ids {
+    ///        view.remove_constraint(id);
+    ///    }
+    ///    let opts = StackOptions { cross_align: StackAlign::Center, ..StackOptions::default() };
+    ///    view.stack_vertical(&rows[..2], 0.0, &opts);
+    /// ```
+    pub fn stack_vertical(
+        &self,
+        children: &[&gtk::Widget],
+        spacing: f64,
+        opts: &StackOptions,
+    ) -> Vec<ConstraintId> {
+        self.stack(Orientation::Vertical, children, spacing, opts)
+    }
+
+    /// Stack the given children of this view from start to end with `spacing` between each, as
+    /// [`stack_vertical`](Self::stack_vertical) does from top to bottom. Start is the left in
+    /// left-to-right text directions, so the stack mirrors in right-to-left ones.
+    pub fn stack_horizontal(
+        &self,
+        children: &[&gtk::Widget],
+        spacing: f64,
+        opts: &StackOptions,
+    ) -> Vec<ConstraintId> {
+        self.stack(Orientation::Horizontal, children, spacing, opts)
+    }
+
+    /// Add and track the constraints stacking the children along the given axis.
+    fn stack(
+        &self,
+        orientation: Orientation,
+        children: &[&gtk::Widget],
+        spacing: f64,
+        opts: &StackOptions,
+    ) -> Vec<ConstraintId> {
+        let axis = Axis::of(orientation);
+        let anchor_opt = opts.anchor_opt.as_ref();
+        let (first, last) = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return Vec::new(),
+        };
+
+        let mut ids = Vec::new();
+        let mut add = |target: &gtk::Widget,
+                       attribute: ConstraintAttribute,
+                       relation: ConstraintRelation,
+                       source_opt: Option<&gtk::ConstraintTarget>,
+                       source_attribute: ConstraintAttribute,
+                       constant: f64| {
+            let constraint = gtk::Constraint::new(
+                Some(target),
+                attribute,
+                relation,
+                source_opt,
+                source_attribute,
+                1.0,
+                constant,
+                gtk::ffi::GTK_CONSTRAINT_STRENGTH_REQUIRED,
+            );
+            ids.push(self.add_constraint(&constraint));
+        };
+
+        use ConstraintRelation::{Eq, Ge, Le};
+        if opts.pin_leading {
+            add(first, axis.leading, Eq, anchor_opt, axis.leading, 0.0);
+        }
+        for pair in children.windows(2) {
+            let previous = pair[0].upcast_ref::<gtk::ConstraintTarget>();
+            add(
+                pair[1],
+                axis.leading,
+                Eq,
+                Some(previous),
+                axis.trailing,
+                spacing,
+            );
+        }
+        if opts.pin_trailing {
+            add(last, axis.trailing, Eq, anchor_opt, axis.trailing, 0.0);
+        }
+        if opts.equal_sizes {
+            let first_target = first.upcast_ref::<gtk::ConstraintTarget>();
+            for &child in &children[1..] {
+                add(child, axis.size, Eq, Some(first_target), axis.size, 0.0);
+            }
+        }
+
+        let (cross_leading, cross_trailing) = (axis.cross_leading, axis.cross_trailing);
+        for &child in children {
+            match opts.cross_align {
+                StackAlign::Fill => {
+                    add(child, cross_leading, Eq, anchor_opt, cross_leading, 0.0);
+                    add(child, cross_trailing, Eq, anchor_opt, cross_trailing, 0.0);
+                }
+                StackAlign::Start => {
+                    add(child, cross_leading, Eq, anchor_opt, cross_leading, 0.0);
+                    add(child, cross_trailing, Le, anchor_opt, cross_trailing, 0.0);
+                }
+                StackAlign::Center => {
+                    add(
+                        child,
+                        axis.cross_center,
+                        Eq,
+                        anchor_opt,
+                        axis.cross_center,
+                        0.0,
+                    );
+                    add(child, cross_leading, Ge, anchor_opt, cross_leading, 0.0);
+                    add(child, cross_trailing, Le, anchor_opt, cross_trailing, 0.0);
+                }
+                StackAlign::End => {
+                    add(child, cross_leading, Ge, anchor_opt, cross_leading, 0.0);
+                    add(child, cross_trailing, Eq, anchor_opt, cross_trailing, 0.0);
+                }
+                StackAlign::None => {}
+            }
+        }
+
+        ids
+    }
+}
@@ -0,0 +1,100 @@
+//! Subclassing [`ConstraintView`], e.g. to make a reusable widget from a composite template whose
+//! children are laid out by the view's constraints.
+
+use super::ConstraintView;
+use glib::subclass::prelude::IsSubclassable;
+use gtk::subclass::prelude::WidgetImpl;
+
+/// Trait for the implementation of a subclass of [`ConstraintView`], set as the `ParentType` of
+/// its [`ObjectSubclass`](glib::subclass::types::ObjectSubclass). Subclasses inherit the
+/// [`gtk::ConstraintLayout`], CSS name, and accessible role of the view, and the `<constraints>`
+/// and `<guides>` elements in GtkBuilder XML, including in their own composite templates. A
+/// subclass overriding [`measure`](WidgetImpl::measure) should chain up to keep the
+/// [content size](ConstraintView::set_min_content_size) limits.
+///
+/// ```
+///    # use springsteel::ConstraintView;
+///    # use glib::{Cast as _, StaticType as _};
+///    # use gtk::prelude::{ListModelExt as _, WidgetExt as _};
+///    # use gtk::subclass::prelude::ObjectSubclassIsExt as _;
+///    #
+///    mod imp {
+///        use gtk::subclass::prelude::*;
+///        use springsteel::constraint_view::ConstraintViewImpl;
+///
+///        #[derive(Default, gtk::CompositeTemplate)]
+///        #[template(string = r#"
+///            <interface>
+///              <template class="FormRow" parent="SpringsteelWorkbenchConstraintView">
+///                <child>
+///                  <object class="GtkLabel" id="caption">
+///                    <property name="label">Name</property>
+///                  </object>
+///                </child>
+///                <child>
+///                  <object class="GtkEntry" id="entry"/>
+///                </child>
+///                <constraints>
+///                  <constraint target="caption" target-attribute="start" relation="eq"
+///                              source="super" source-attribute="start"/>
+///                  <constraint target="entry" target-attribute="start" relation="eq"
+///                              source="caption" source-attribute="end" constant="8"/>
+///                  <constraint target="entry" target-attribute="end" relation="eq"
+///                              source="super" source-attribute="end"/>
+///                </constraints>
+///              </template>
+///            </interface>
+///        "#)]
+///        pub struct FormRow {
+///            #[template_child]
+///            pub caption: TemplateChild<gtk::Label>,
+///            #[template_child]
+///            pub entry: TemplateChild<gtk::Entry>,
+///        }
+///
+///        #[glib::object_subclass]
+///        impl ObjectSubclass for FormRow {
+///            const NAME: &'static str = "FormRow";
+///            type Type = super::FormRow;
+///            type ParentType = springsteel::ConstraintView;
+///
+///            fn class_init(klass: &mut Self::Class) {
+///                klass.bind_template();
+///            }
+///
+///            fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+///                obj.init_template();
+///            }
+///        }
+///
+///        impl ObjectImpl for FormRow {}
+///        impl WidgetImpl for FormRow {}
+///        impl ConstraintViewImpl for FormRow {}
+///    }
+///
+///    glib::wrapper! {
+///        pub struct FormRow(ObjectSubclass<imp::FormRow>)
+///            @extends ConstraintView, gtk::Widget,
+///            @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+///    }
+///
+///    # fn main() {
+///    # gtk::init().expect("gtk::init");
+///    FormRow::static_type();
+///    let builder = gtk::Builder::from_string(
+///        r#"
+///        <interface>
+///          <object class="FormRow" id="row"/>
+///        </interface>
+///        "#,
+///    );
+///
+///    let row: FormRow = builder.object("row").expect("row");
+///    assert_eq!(row.imp().entry.parent(), Some(row.clone().upcast()));
+///    assert_eq!(row.css_name(), "constraintview");
+///    assert_eq!(row.upcast_ref::<ConstraintView>().layout().observe_constraints().n_items(), 3);
+///    # }
+/// ```
+pub trait ConstraintViewImpl: WidgetImpl {}
+
+unsafe impl<T: ConstraintViewImpl> IsSubclassable<T> for ConstraintView {}
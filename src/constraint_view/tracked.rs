@@ -0,0 +1,597 @@
+//! Constraints added through [`ConstraintView::add_constraint`], which the view keeps track of so
+//! that they can be removed again by [`ConstraintId`], by what they refer to, or all at once.
+
+use super::design::target_name;
+use super::parts::ConstraintParts;
+use super::ConstraintView;
+use crate::glib_future::{glib_run_stream, TaskHandle};
+use crate::signal_future::signal_future;
+use futures::stream::{Stream, StreamExt as _};
+use glib::subclass::prelude::ObjectSubclassIsExt as _;
+use glib::{Cast as _, IsA, ObjectExt as _, WeakRef};
+use gtk::prelude::WidgetExt as _;
+
+/// Identifies a constraint added with [`ConstraintView::add_constraint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ConstraintId(u64);
+
+/// A constraint added with [`ConstraintView::add_constraint`] as plain values, returned by
+/// [`ConstraintView::tracked_constraints`], as the getters of [`gtk::Constraint`] are awkward to
+/// inspect or print.
+#[derive(Clone, Debug)]
+pub struct TrackedConstraint {
+    /// The id the constraint was added under.
+    pub id: ConstraintId,
+    /// The widget or guide constrained, or `None` for the view itself.
+    pub target: Option<gtk::ConstraintTarget>,
+    /// The attribute of the target constrained.
+    pub target_attribute: gtk::ConstraintAttribute,
+    /// The relation between the target attribute and the rest.
+    pub relation: gtk::ConstraintRelation,
+    /// The widget or guide the target is constrained to, or `None` for the view itself.
+    pub source: Option<gtk::ConstraintTarget>,
+    /// The attribute of the source, or [`None`](gtk::ConstraintAttribute::None) for a constant.
+    pub source_attribute: gtk::ConstraintAttribute,
+    /// The factor applied to the source attribute.
+    pub multiplier: f64,
+    /// The constant added to the source attribute, or compared against for a constant.
+    pub constant: f64,
+    /// The strength, one of the `GTK_CONSTRAINT_STRENGTH_*` constants of [`gtk::ffi`] or a number.
+    pub strength: i32,
+    /// Whether the constraint is out of the layout while a child it refers to is collapsed, see
+    /// [`ConstraintView::set_collapse_when_hidden`].
+    pub parked: bool,
+}
+
+/// An entry of the registry of constraints added with [`ConstraintView::add_constraint`]. Only a
+/// weak reference is kept, as the layout holds the constraint while it's attached, so the registry
+/// never keeps a constraint alive.
+pub(super) struct TrackedEntry {
+    id: ConstraintId,
+    constraint: WeakRef<gtk::Constraint>,
+
+    /// The parts of the constraint while it's parked outside the layout, to be rebuilt when it's
+    /// unparked.
+    parked_opt: Option<ConstraintParts>,
+}
+
+impl TrackedEntry {
+    /// Whether the constraint is still tracked, i.e. attached to the layout or parked.
+    fn is_live(&self) -> bool {
+        self.parked_opt.is_some() || self.constraint.upgrade().map_or(false, |c| c.is_attached())
+    }
+
+    /// Whether the given widget or guide is the target or source of the constraint.
+    fn refers_to(&self, target: &gtk::ConstraintTarget) -> bool {
+        match self.parked_opt.as_ref() {
+            Some(parts) => parts.refers_to(target),
+            None => self.constraint.upgrade().map_or(false, |c| {
+                c.target().as_ref() == Some(target) || c.source().as_ref() == Some(target)
+            }),
+        }
+    }
+}
+
+impl ConstraintView {
+    /// Add a constraint to the layout of this view, keeping track of it so that it can be removed
+    /// later by the returned [`ConstraintId`], with [`remove_constraints_for`] when removing
+    /// whatever it refers to, or with [`clear_constraints`]. [`add_constraint!`] accepts a view
+    /// in place of a layout to add constraints this way:
+    ///
+    /// ```
+    ///    # use springsteel::{add_constraint, constraint, ConstraintView};
+    ///    # use gtk::prelude::ListModelExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let (label, button) = (gtk::Label::new(None), gtk::Button::new());
+    ///    view.add_child(&label);
+    ///    view.add_child(&button);
+    ///    let layout = view.layout();
+    ///
+    ///    let label_left = view.add_constraint(&constraint!(label.left == left));
+    ///    add_constraint!(view, button.left == label.right + 10.0);
+    ///    add_constraint!(view, button.right == right);
+    ///
+    ///    view.remove_constraint(label_left);
+    ///    assert_eq!(layout.observe_constraints().n_items(), 2);
+    ///
+    ///    view.remove_constraints_for(&label);
+    ///    assert_eq!(layout.observe_constraints().n_items(), 1);
+    ///
+    ///    view.clear_constraints();
+    ///    assert_eq!(layout.observe_constraints().n_items(), 0);
+    /// ```
+    ///
+    /// Constraints added directly to the [`layout`](Self::layout) aren't tracked.
+    ///
+    /// [`remove_constraints_for`]: Self::remove_constraints_for
+    /// [`clear_constraints`]: Self::clear_constraints
+    /// [`add_constraint!`]: crate::add_constraint
+    pub fn add_constraint(&self, constraint: &gtk::Constraint) -> ConstraintId {
+        let imp = self.imp();
+        let id = ConstraintId(imp.next_constraint_id.get());
+        imp.next_constraint_id.set(id.0 + 1);
+
+        self.layout().add_constraint(constraint);
+
+        let mut tracked = imp.tracked.borrow_mut();
+        tracked.retain(TrackedEntry::is_live);
+        tracked.push(TrackedEntry {
+            id,
+            constraint: constraint.downgrade(),
+            parked_opt: None,
+        });
+        id
+    }
+
+    /// Remove a constraint added with [`add_constraint`](Self::add_constraint) from the layout of
+    /// this view, if it's still there.
+    pub fn remove_constraint(&self, id: ConstraintId) {
+        self.remove_tracked(|t| t.id == id);
+    }
+
+    /// Remove every constraint added with [`add_constraint`](Self::add_constraint) whose target
+    /// or source is the given widget or guide.
+    pub fn remove_constraints_for(&self, target: &impl IsA<gtk::ConstraintTarget>) {
+        let target = target.upcast_ref::<gtk::ConstraintTarget>();
+        self.remove_tracked(|t| t.refers_to(target));
+    }
+
+    /// Remove every constraint added with [`add_constraint`](Self::add_constraint).
+    pub fn clear_constraints(&self) {
+        self.remove_tracked(|_| true);
+    }
+
+    /// Replace the child `old` of this view with `new` in the same place among its siblings,
+    /// carrying over every constraint added with [`add_constraint`](Self::add_constraint) which
+    /// refers to `old`, including those relating it to itself, with `new` in its place and the
+    /// same relation, multiplier, constant, strength, and [`ConstraintId`]. Untracked constraints
+    /// referring to `old` are removed along with it as by [`remove_child`](Self::remove_child).
+    ///
+    /// This suits swapping a placeholder for the real thing once it has loaded:
+    ///
+    /// ```
+    ///    # use springsteel::{add_constraint, ConstraintView};
+    ///    # use glib::Cast as _;
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let spinner = gtk::Spinner::new();
+    ///    view.add_child(&spinner);
+    ///    add_constraint!(view, spinner.left == left + 10.0);
+    ///    add_constraint!(view, spinner.top == top + 10.0);
+    ///    add_constraint!(view, spinner.width == 50.0);
+    ///    add_constraint!(view, spinner.width == spinner.height);
+    ///
+    ///    let allocate = |view: &ConstraintView, child: &gtk::Widget| {
+    ///        view.measure(gtk::Orientation::Horizontal, -1);
+    ///        view.measure(gtk::Orientation::Vertical, -1);
+    ///        view.size_allocate(&gtk::Allocation::new(0, 0, 200, 100), -1);
+    ///        let bounds = child.compute_bounds(view).expect("compute_bounds");
+    ///        (bounds.x(), bounds.y(), bounds.width(), bounds.height())
+    ///    };
+    ///    let before = allocate(&view, spinner.upcast_ref());
+    ///
+    ///    let image = gtk::Image::new();
+    ///    view.replace_child(&spinner, &image);
+    ///    assert_eq!(spinner.parent(), None);
+    ///    assert_eq!(allocate(&view, image.upcast_ref()), before);
+    /// ```
+    ///
+    /// Constraints parked while another child is [collapsed](Self::set_collapse_when_hidden) are
+    /// carried over too, and put back referring to `new` when that child is shown again. Whether
+    /// `old` itself collapses isn't carried over, so anything it parked is put back first.
+    ///
+    /// ```
+    ///    # use springsteel::{add_constraint, ConstraintView};
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let [placeholder, label] = [(), ()].map(|()| {
+    ///        let cell = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    ///        cell.set_size_request(20, 20);
+    ///        view.add_child(&cell);
+    ///        add_constraint!(view, cell.top == top);
+    ///        add_constraint!(view, cell.width == 20.0);
+    ///        cell
+    ///    });
+    ///    add_constraint!(view, placeholder.left == left);
+    ///    add_constraint!(view, label.left == placeholder.right);
+    ///    view.set_collapse_when_hidden(&label, true);
+    ///
+    ///    label.set_visible(false);
+    ///    view.replace_child(&placeholder, &gtk::Image::new());
+    ///    label.set_visible(true);
+    ///
+    ///    view.measure(gtk::Orientation::Horizontal, -1);
+    ///    view.measure(gtk::Orientation::Vertical, -1);
+    ///    view.size_allocate(&gtk::Allocation::new(0, 0, 200, 50), -1);
+    ///    assert_eq!(label.compute_bounds(&view).expect("compute_bounds").x(), 20.0);
+    /// ```
+    ///
+    /// If `old` isn't a child of this view or `new` already has a parent, a warning is logged and
+    /// nothing is done.
+    pub fn replace_child(&self, old: &impl IsA<gtk::Widget>, new: &impl IsA<gtk::Widget>) {
+        let (old, new) = (
+            old.upcast_ref::<gtk::Widget>(),
+            new.upcast_ref::<gtk::Widget>(),
+        );
+        if old.parent().as_ref() != Some(self.upcast_ref::<gtk::Widget>()) {
+            log::warn!(
+                "ConstraintView::replace_child: widget {} isn't a child of {}",
+                old.widget_name(),
+                self.widget_name()
+            );
+            return;
+        }
+        if let Some(parent) = new.parent() {
+            log::warn!(
+                "ConstraintView::replace_child: widget {} already has a parent {}",
+                new.widget_name(),
+                parent.widget_name()
+            );
+            return;
+        }
+
+        self.forget_collapse(old);
+
+        // Take the tracked constraints referring to the old widget out of the layout, so that
+        // removing the old widget only removes untracked ones.
+        let old_target = old.upcast_ref::<gtk::ConstraintTarget>();
+        let new_target = new.upcast_ref::<gtk::ConstraintTarget>();
+        let substitute = |parts: &mut ConstraintParts| {
+            let mut refers = false;
+            for target in [&mut parts.target, &mut parts.source] {
+                if target.as_ref() == Some(old_target) {
+                    *target = Some(new_target.clone());
+                    refers = true;
+                }
+            }
+            refers
+        };
+        let layout = self.layout();
+        let mut carried = Vec::new();
+        for tracked in self.imp().tracked.borrow_mut().iter_mut() {
+            // Parked constraints are rebuilt from their parts when put back, so only the parts
+            // need the new widget.
+            if let Some(parts) = tracked.parked_opt.as_mut() {
+                substitute(parts);
+                continue;
+            }
+            let constraint = match tracked.constraint.upgrade() {
+                Some(c) if c.is_attached() => c,
+                _ => continue,
+            };
+            let mut parts = ConstraintParts::of(&constraint);
+            if substitute(&mut parts) {
+                // Forget the old constraint, so that removing the old widget leaves the entry be.
+                layout.remove_constraint(&constraint);
+                tracked.constraint = WeakRef::new();
+                carried.push((tracked.id, parts));
+            }
+        }
+
+        new.insert_after(self, Some(old));
+        self.replace_visible_child(old, new);
+        self.remove_child(old);
+
+        let mut tracked = self.imp().tracked.borrow_mut();
+        for (id, parts) in carried {
+            let constraint = parts.build();
+            layout.add_constraint(&constraint);
+            if let Some(t) = tracked.iter_mut().find(|t| t.id == id) {
+                t.constraint = constraint.downgrade();
+            }
+        }
+    }
+
+    /// Take the attached tracked constraints referring to `target` and matching `park` out of the
+    /// layout, keeping them tracked under the same ids to be put back by
+    /// [`unpark_constraints`](Self::unpark_constraints).
+    pub(super) fn park_constraints(
+        &self,
+        target: &gtk::ConstraintTarget,
+        park: impl Fn(&ConstraintParts) -> bool,
+    ) {
+        let layout = self.layout();
+        for tracked in self.imp().tracked.borrow_mut().iter_mut() {
+            let constraint = match tracked.constraint.upgrade() {
+                Some(c) if tracked.parked_opt.is_none() && c.is_attached() => c,
+                _ => continue,
+            };
+            let parts = ConstraintParts::of(&constraint);
+            if parts.refers_to(target) && park(&parts) {
+                layout.remove_constraint(&constraint);
+                tracked.parked_opt = Some(parts);
+            }
+        }
+    }
+
+    /// Put the parked constraints referring to `target` back into the layout, in the order they
+    /// were added, except those for which `keep_parked` is true.
+    pub(super) fn unpark_constraints(
+        &self,
+        target: &gtk::ConstraintTarget,
+        keep_parked: impl Fn(&ConstraintParts) -> bool,
+    ) {
+        let layout = self.layout();
+        for tracked in self.imp().tracked.borrow_mut().iter_mut() {
+            let unpark = match tracked.parked_opt.as_ref() {
+                Some(parts) => parts.refers_to(target) && !keep_parked(parts),
+                None => false,
+            };
+            if unpark {
+                let constraint = tracked.parked_opt.take().expect("parked").build();
+                layout.add_constraint(&constraint);
+                tracked.constraint = constraint.downgrade();
+            }
+        }
+    }
+
+    /// Return the constant of the given tracked constraint, if it's still tracked.
+    pub(super) fn tracked_constant(&self, id: ConstraintId) -> Option<f64> {
+        let tracked = self.imp().tracked.borrow();
+        let entry = tracked.iter().find(|t| t.id == id && t.is_live())?;
+        match entry.parked_opt.as_ref() {
+            Some(parts) => Some(parts.constant),
+            None => entry.constraint.upgrade().map(|c| c.constant()),
+        }
+    }
+
+    /// Return the id the given constraint is tracked under, if it was added with
+    /// [`add_constraint`](Self::add_constraint) and is attached to the layout.
+    pub(super) fn tracked_id(&self, constraint: &gtk::Constraint) -> Option<ConstraintId> {
+        self.imp()
+            .tracked
+            .borrow()
+            .iter()
+            .find(|t| t.parked_opt.is_none() && t.constraint.upgrade().as_ref() == Some(constraint))
+            .map(|t| t.id)
+    }
+
+    /// Return the constraint presently in the layout for the given tracked id, if it's attached.
+    pub(super) fn tracked_attached(&self, id: ConstraintId) -> Option<gtk::Constraint> {
+        self.imp()
+            .tracked
+            .borrow()
+            .iter()
+            .find(|t| t.id == id && t.parked_opt.is_none())
+            .and_then(|t| t.constraint.upgrade())
+            .filter(|c| c.is_attached())
+    }
+
+    /// Replace the given tracked constraint with one changed by `update`, under the same id,
+    /// returning whether it's still tracked. A parked constraint is only changed for when it's put
+    /// back.
+    pub(super) fn update_tracked(
+        &self,
+        id: ConstraintId,
+        update: impl FnOnce(&mut ConstraintParts),
+    ) -> bool {
+        let mut tracked = self.imp().tracked.borrow_mut();
+        let entry = match tracked.iter_mut().find(|t| t.id == id && t.is_live()) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        if let Some(parts) = entry.parked_opt.as_mut() {
+            update(parts);
+            return true;
+        }
+
+        let old = entry.constraint.upgrade().expect("live constraint");
+        let mut parts = ConstraintParts::of(&old);
+        update(&mut parts);
+        let new = parts.build();
+        let layout = self.layout();
+        layout.remove_constraint(&old);
+        layout.add_constraint(&new);
+        entry.constraint = new.downgrade();
+        true
+    }
+
+    /// Change the constant of the constraint added with [`add_constraint`](Self::add_constraint)
+    /// under the given id, by replacing it in the layout with one identical but for the constant,
+    /// under the same id. This stops any [animation](Self::animate_constant) of the constant.
+    ///
+    /// ```
+    ///    # use springsteel::{constraint, ConstraintView};
+    ///    # use gtk::prelude::ListModelExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let (sidebar, body) = (gtk::Label::new(None), gtk::Label::new(None));
+    ///    view.add_child(&sidebar);
+    ///    view.add_child(&body);
+    ///    let split = view.add_constraint(&constraint!(body.left == sidebar.right + 200.0));
+    ///
+    ///    for position in [180.0, 240.0, 260.0] {
+    ///        view.set_constant(split, position);
+    ///    }
+    ///    view.set_strength(split, gtk::ffi::GTK_CONSTRAINT_STRENGTH_STRONG);
+    ///
+    ///    let tracked = view.tracked_constraints();
+    ///    assert_eq!(tracked.len(), 1);
+    ///    assert_eq!((tracked[0].id, tracked[0].constant), (split, 260.0));
+    ///    assert_eq!(tracked[0].strength, gtk::ffi::GTK_CONSTRAINT_STRENGTH_STRONG);
+    ///    assert_eq!(view.layout().observe_constraints().n_items(), 1);
+    /// ```
+    ///
+    /// If the constraint isn't tracked, a warning is logged and nothing is done.
+    pub fn set_constant(&self, id: ConstraintId, constant: f64) {
+        self.interrupt_animation(id);
+        self.update_or_warn("set_constant", id, |parts| parts.constant = constant);
+    }
+
+    /// Change the strength of the constraint added with [`add_constraint`](Self::add_constraint)
+    /// under the given id, as [`set_constant`](Self::set_constant).
+    pub fn set_strength(&self, id: ConstraintId, strength: i32) {
+        self.update_or_warn("set_strength", id, |parts| parts.strength = strength);
+    }
+
+    /// Change the multiplier of the constraint added with [`add_constraint`](Self::add_constraint)
+    /// under the given id, as [`set_constant`](Self::set_constant).
+    pub fn set_multiplier(&self, id: ConstraintId, multiplier: f64) {
+        self.update_or_warn("set_multiplier", id, |parts| parts.multiplier = multiplier);
+    }
+
+    /// [Set the constant](Self::set_constant) of the given constraint to each value of a stream,
+    /// e.g. the position of a splitter being dragged, until the stream ends, the returned handle
+    /// is aborted, or the view is destroyed.
+    ///
+    /// ```
+    ///    # use springsteel::{constraint, ConstraintView, ValueStream};
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let panel = gtk::Label::new(None);
+    ///    view.add_child(&panel);
+    ///    let height = view.add_constraint(&constraint!(panel.height == 100.0));
+    ///
+    ///    let heights = ValueStream::new();
+    ///    let task = view.bind_constant(height, heights.clone());
+    ///    heights.push(120.0);
+    ///    heights.push(140.0);
+    ///    while glib::MainContext::default().iteration(false) {}
+    ///
+    ///    let tracked = view.tracked_constraints();
+    ///    assert_eq!(tracked.len(), 1);
+    ///    assert_eq!(tracked[0].constant, 140.0);
+    ///
+    ///    drop(tracked);
+    ///    drop(view);
+    ///    while glib::MainContext::default().iteration(false) {}
+    ///    assert!(task.is_finished());
+    /// ```
+    pub fn bind_constant<S>(&self, id: ConstraintId, constants: S) -> TaskHandle
+    where
+        S: Stream<Item = f64> + 'static,
+    {
+        // Ending the stream on destroy lets the task finish, rather than carry on consuming it.
+        let view = self.downgrade();
+        let constants = constants.take_until(signal_future(self, "destroy"));
+        glib_run_stream(constants, move |constant| {
+            if let Some(view) = view.upgrade() {
+                view.set_constant(id, constant);
+            }
+        })
+    }
+
+    /// Update the given tracked constraint, logging a warning naming the caller if it isn't.
+    fn update_or_warn(
+        &self,
+        caller: &str,
+        id: ConstraintId,
+        update: impl FnOnce(&mut ConstraintParts),
+    ) {
+        if !self.update_tracked(id, update) {
+            log::warn!(
+                "ConstraintView::{}: {} has no tracked constraint {:?}",
+                caller,
+                self.widget_name(),
+                id
+            );
+        }
+    }
+
+    /// Return every constraint presently tracked, in the order they were added.
+    pub fn tracked_constraints(&self) -> Vec<TrackedConstraint> {
+        self.imp()
+            .tracked
+            .borrow()
+            .iter()
+            .filter_map(|t| {
+                let parts = match t.parked_opt.as_ref() {
+                    Some(parts) => parts.clone(),
+                    None => match t.constraint.upgrade() {
+                        Some(c) if c.is_attached() => ConstraintParts::of(&c),
+                        _ => return None,
+                    },
+                };
+                Some(TrackedConstraint {
+                    id: t.id,
+                    target: parts.target,
+                    target_attribute: parts.target_attribute,
+                    relation: parts.relation,
+                    source: parts.source,
+                    source_attribute: parts.source_attribute,
+                    multiplier: parts.multiplier,
+                    constant: parts.constant,
+                    strength: parts.strength,
+                    parked: t.parked_opt.is_some(),
+                })
+            })
+            .collect()
+    }
+
+    /// Print every constraint presently [tracked](Self::tracked_constraints), one per line in the
+    /// grammar of [`constraint!`](crate::constraint), e.g. to attach to a report of a layout bug.
+    /// Widgets are named by their [widget name](gtk::prelude::WidgetExt::widget_name) and guides
+    /// by their name or, if unnamed, `guideN` in order of addition, as by
+    /// [`design_export`](Self::design_export), which prints untracked constraints as well.
+    ///
+    /// ```
+    ///    # use springsteel::{add_constraint, ConstraintView};
+    ///    # use gtk::prelude::WidgetExt as _;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let view = ConstraintView::new();
+    ///    let display = gtk::Label::new(None);
+    ///    display.set_widget_name("display");
+    ///    view.add_child(&display);
+    ///    let spacer = gtk::ConstraintGuide::new();
+    ///    view.layout().add_guide(&spacer);
+    ///
+    ///    add_constraint!(view, display.left == spacer.right);
+    ///    add_constraint!(view, display.width >= spacer.width * 2.0 - 4.0 @weak);
+    ///    add_constraint!(view.layout(), display.top == top);
+    ///    assert_eq!(
+    ///        view.dump_constraints(),
+    ///        "display.left == guide0.right @required\n\
+    ///         display.width >= guide0.width * 2.0 - 4.0 @weak\n",
+    ///    );
+    ///
+    ///    let tracked = view.tracked_constraints();
+    ///    assert_eq!(tracked[1].multiplier, 2.0);
+    ///    assert_eq!(tracked[1].relation, gtk::ConstraintRelation::Ge);
+    /// ```
+    pub fn dump_constraints(&self) -> String {
+        let name = |t: &gtk::ConstraintTarget| target_name(self, t);
+        let mut out = String::new();
+        for tracked in self.tracked_constraints() {
+            let parts = ConstraintParts {
+                target: tracked.target,
+                target_attribute: tracked.target_attribute,
+                relation: tracked.relation,
+                source: tracked.source,
+                source_attribute: tracked.source_attribute,
+                multiplier: tracked.multiplier,
+                constant: tracked.constant,
+                strength: tracked.strength,
+            };
+            out.push_str(&parts.format(&name));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Stop tracking the constraints matching `remove`, removing those still attached from the
+    /// layout.
+    fn remove_tracked(&self, remove: impl Fn(&TrackedEntry) -> bool) {
+        let removed: Vec<_> = {
+            let mut tracked = self.imp().tracked.borrow_mut();
+            let (removed, kept) = std::mem::take(&mut *tracked).into_iter().partition(remove);
+            *tracked = kept;
+            removed
+        };
+
+        let layout = self.layout();
+        for constraint in removed.iter().filter_map(|t| t.constraint.upgrade()) {
+            if constraint.is_attached() {
+                layout.remove_constraint(&constraint);
+            }
+        }
+    }
+}
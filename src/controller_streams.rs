@@ -0,0 +1,491 @@
+//! Adapters which install [`gtk::EventController`]s on widgets and turn the events they report into
+//! [`ValueStream`]s and [`ImpulseStream`](crate::ImpulseStream)s.
+
+use crate::impulse_stream::ImpulseStream;
+use crate::value_stream::ValueStream;
+use glib::object::IsA;
+use glib::{Cast as _, ObjectExt as _, StaticType as _};
+use gtk::prelude::{GestureDragExt as _, RootExt as _, WidgetExt as _};
+use std::fmt;
+
+/// Guard for an event controller installed on a widget by one of these adapters, which removes
+/// the controller from the widget when dropped.
+///
+/// Only a weak reference to the widget is held, so the guard doesn't keep it alive. Use
+/// [`keep`](Self::keep) to leave the controller installed for the lifetime of the widget.
+#[must_use = "dropping the guard removes the controller immediately"]
+pub struct ControllerGuard {
+    widget: glib::WeakRef<gtk::Widget>,
+    controller: gtk::EventController,
+}
+
+impl ControllerGuard {
+    /// Guard the given controller, which should already have been added to the given widget.
+    pub fn new(
+        widget: &impl IsA<gtk::Widget>,
+        controller: &impl IsA<gtk::EventController>,
+    ) -> Self {
+        Self {
+            widget: widget.upcast_ref::<gtk::Widget>().downgrade(),
+            controller: controller.clone().upcast(),
+        }
+    }
+
+    /// Return the guarded controller.
+    pub fn controller(&self) -> &gtk::EventController {
+        &self.controller
+    }
+
+    /// Drop the guard without removing the controller, leaving it installed for the lifetime of
+    /// the widget. Unlike [`std::mem::forget`], this doesn't leak the guard's reference to the
+    /// controller, which would keep it alive after the widget is gone.
+    pub fn keep(mut self) {
+        self.widget = glib::WeakRef::new();
+    }
+}
+
+impl Drop for ControllerGuard {
+    fn drop(&mut self) {
+        if let Some(widget) = self.widget.upgrade() {
+            widget.remove_controller(&self.controller);
+        }
+    }
+}
+
+/// A key press reported by [`key_presses`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// The key pressed, after keyboard layout translation.
+    pub keyval: gdk::Key,
+
+    /// The raw hardware keycode.
+    pub keycode: u32,
+
+    /// The modifiers held down at the time of the press.
+    pub state: gdk::ModifierType,
+}
+
+/// Key presses on a widget, made by [`key_presses`] or [`KeyPressesBuilder::build`].
+pub struct KeyPresses {
+    /// Every key press handled, in order.
+    pub presses: ValueStream<KeyEvent>,
+
+    /// Guard which removes the [`gtk::EventControllerKey`] when dropped.
+    pub guard: ControllerGuard,
+}
+
+/// Builder for [`KeyPresses`] with an optional filter. See [`key_presses`].
+#[derive(Default)]
+pub struct KeyPressesBuilder {
+    filter_opt: Option<Box<dyn Fn(&KeyEvent) -> bool>>,
+}
+
+impl KeyPressesBuilder {
+    /// Create a new builder with no filter, which would handle every key.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only handle key presses matching the given predicate. Others propagate normally to other
+    /// handlers and are not output.
+    pub fn filter(mut self, filter: impl Fn(&KeyEvent) -> bool + 'static) -> Self {
+        self.filter_opt = Some(Box::new(filter));
+        self
+    }
+
+    /// Install a [`gtk::EventControllerKey`] on the given widget and return the handled key
+    /// presses.
+    pub fn build(self, widget: &impl IsA<gtk::Widget>) -> KeyPresses {
+        let values = ValueStream::new();
+        let controller = gtk::EventControllerKey::new();
+
+        let values_for_pressed = values.clone();
+        let filter_opt = self.filter_opt;
+        controller.connect_key_pressed(move |_, keyval, keycode, state| {
+            let event = KeyEvent {
+                keyval,
+                keycode,
+                state,
+            };
+            let handled = filter_opt.as_ref().map_or(true, |f| f(&event));
+            if handled {
+                values_for_pressed.push(event);
+            }
+            gtk::Inhibit(handled)
+        });
+
+        widget.add_controller(&controller);
+        KeyPresses {
+            presses: values,
+            guard: ControllerGuard::new(widget, &controller),
+        }
+    }
+}
+
+/// Make a [`ValueStream`] of every key pressed while the given widget (or a descendant) has focus,
+/// by installing a [`gtk::EventControllerKey`] on it. Every key press is handled and so stops
+/// propagating; use [`KeyPressesBuilder::filter`] to handle only some keys, e.g. for keyboard
+/// navigation:
+///
+/// ```
+///    # use springsteel::controller_streams::KeyPressesBuilder;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let content = springsteel::ConstraintView::new();
+///    let arrows = KeyPressesBuilder::new()
+///        .filter(|e| e.keyval == gdk::Key::Left || e.keyval == gdk::Key::Right)
+///        .build(&content);
+/// ```
+///
+/// The controller stays installed until [`KeyPresses::guard`] is dropped.
+pub fn key_presses(widget: &impl IsA<gtk::Widget>) -> KeyPresses {
+    KeyPressesBuilder::new().build(widget)
+}
+
+/// Streams of pointer events over a widget, made by [`pointer_streams`].
+pub struct PointerStreams {
+    /// Pointer position relative to the widget whenever it moves. This stream is
+    /// [coalescing](ValueStream::coalescing), so a consumer which falls behind sees only the
+    /// latest position rather than every intermediate one.
+    pub motion: ValueStream<(f64, f64)>,
+
+    /// Triggered whenever the pointer enters the widget.
+    pub enter: ImpulseStream,
+
+    /// Triggered whenever the pointer leaves the widget.
+    pub leave: ImpulseStream,
+
+    /// Guard which removes the [`gtk::EventControllerMotion`] when dropped.
+    pub guard: ControllerGuard,
+}
+
+/// Install a [`gtk::EventControllerMotion`] on the given widget and return streams of its pointer
+/// motion, enter, and leave events, e.g. for hover effects:
+///
+/// ```
+///    # use futures::stream::StreamExt as _;
+///    # use springsteel::controller_streams::pointer_streams;
+///    # use springsteel::glib_run_future;
+///    # use gtk::prelude::WidgetExt as _;
+///    # use std::future::ready;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let button = gtk::Button::with_label("Hover me");
+///    let pointer = pointer_streams(&button);
+///    let button_for_enter = button.clone();
+///    glib_run_future(pointer.enter.for_each(move |()| {
+///        button_for_enter.add_css_class("hovered");
+///        ready(())
+///    }));
+/// ```
+///
+/// The controller stays installed until [`PointerStreams::guard`] is dropped.
+pub fn pointer_streams(widget: &impl IsA<gtk::Widget>) -> PointerStreams {
+    let controller = gtk::EventControllerMotion::new();
+    widget.add_controller(&controller);
+
+    let streams = PointerStreams {
+        motion: ValueStream::coalescing(),
+        enter: ImpulseStream::new(),
+        leave: ImpulseStream::new(),
+        guard: ControllerGuard::new(widget, &controller),
+    };
+
+    let motion = streams.motion.clone();
+    controller.connect_motion(move |_, x, y| motion.push((x, y)));
+
+    let enter = streams.enter.clone();
+    controller.connect_enter(move |_, _, _| enter.trigger());
+
+    let leave = streams.leave.clone();
+    controller.connect_leave(move |_| leave.trigger());
+
+    streams
+}
+
+/// Whether keyboard focus is within a widget, made by [`focus_changes`].
+pub struct FocusChanges {
+    /// Whether focus is within the widget, output first with the current state and then whenever
+    /// it changes.
+    pub focus_within: ValueStream<bool>,
+
+    /// Guard which removes the [`gtk::EventControllerFocus`] when dropped.
+    pub guard: ControllerGuard,
+}
+
+/// Install a [`gtk::EventControllerFocus`] on the given widget and return a [`ValueStream`] of
+/// whether keyboard focus is within it (on the widget or any descendant), output whenever that
+/// changes. The current state is output first, so consumers never start in an unknown state.
+///
+/// The controller stays installed until [`FocusChanges::guard`] is dropped.
+pub fn focus_changes(widget: &impl IsA<gtk::Widget>) -> FocusChanges {
+    let values = ValueStream::new();
+    let widget = widget.upcast_ref::<gtk::Widget>();
+    let focus_within = widget
+        .root()
+        .and_then(|root| root.focus())
+        .map_or(false, |focus| &focus == widget || focus.is_ancestor(widget));
+    values.push(focus_within);
+
+    let controller = gtk::EventControllerFocus::new();
+
+    let values_for_enter = values.clone();
+    controller.connect_enter(move |_| values_for_enter.push(true));
+
+    let values_for_leave = values.clone();
+    controller.connect_leave(move |_| values_for_leave.push(false));
+
+    widget.add_controller(&controller);
+    FocusChanges {
+        focus_within: values,
+        guard: ControllerGuard::new(widget, &controller),
+    }
+}
+
+/// A stage of a drag over a widget, reported by [`drag_events`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DragEvent {
+    /// The drag began at the given point, relative to the widget.
+    Begin {
+        /// Horizontal position of the start of the drag.
+        x: f64,
+        /// Vertical position of the start of the drag.
+        y: f64,
+    },
+
+    /// The pointer moved by the given offset from the start of the drag.
+    Update {
+        /// Horizontal offset from the start of the drag.
+        dx: f64,
+        /// Vertical offset from the start of the drag.
+        dy: f64,
+    },
+
+    /// The drag ended at the given offset from its start.
+    End {
+        /// Horizontal offset from the start of the drag.
+        dx: f64,
+        /// Vertical offset from the start of the drag.
+        dy: f64,
+    },
+}
+
+/// Drags over a widget, made by [`drag_events`].
+pub struct DragEvents {
+    /// Every stage of every drag, in order.
+    pub events: ValueStream<DragEvent>,
+
+    /// Guard which removes the [`gtk::GestureDrag`] when dropped.
+    pub guard: ControllerGuard,
+}
+
+/// Install a [`gtk::GestureDrag`] on the given widget and return a [`ValueStream`] of the stages of
+/// each drag over it, e.g. to move something by the offsets.
+///
+/// The offsets are relative to the widget as it is at each event, so if the drag moves the widget
+/// itself, install the gesture on something which stays put, such as its parent.
+///
+/// ```
+///    # use springsteel::controller_streams::{drag_events, DragEvent};
+///    # use springsteel::glib_run_stream;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let canvas = gtk::DrawingArea::new();
+///    let drags = drag_events(&canvas);
+///    glib_run_stream(drags.events, |event| {
+///        if let DragEvent::End { dx, dy } = event {
+///            log::info!("dragged by {}, {}", dx, dy);
+///        }
+///    });
+///    drags.guard.keep();
+/// ```
+pub fn drag_events(widget: &impl IsA<gtk::Widget>) -> DragEvents {
+    let events = ValueStream::new();
+    let gesture = gtk::GestureDrag::new();
+
+    let events_for_begin = events.clone();
+    gesture.connect_drag_begin(move |_, x, y| events_for_begin.push(DragEvent::Begin { x, y }));
+
+    let events_for_update = events.clone();
+    gesture
+        .connect_drag_update(move |_, dx, dy| events_for_update.push(DragEvent::Update { dx, dy }));
+
+    let events_for_end = events.clone();
+    gesture.connect_drag_end(move |_, dx, dy| events_for_end.push(DragEvent::End { dx, dy }));
+
+    widget.add_controller(&gesture);
+    DragEvents {
+        events,
+        guard: ControllerGuard::new(widget, &gesture),
+    }
+}
+
+/// Error from [`shortcut_impulses`] when the trigger string can't be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidShortcutTrigger(pub String);
+
+impl fmt::Display for InvalidShortcutTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid shortcut trigger `{}`", self.0)
+    }
+}
+
+impl std::error::Error for InvalidShortcutTrigger {}
+
+/// A keyboard shortcut installed by [`shortcut_impulses`].
+pub struct ShortcutImpulses {
+    /// Triggered every time the shortcut is activated.
+    pub impulses: ImpulseStream,
+
+    /// Guard which removes the [`gtk::ShortcutController`] when dropped.
+    pub guard: ControllerGuard,
+}
+
+/// Install a [`gtk::ShortcutController`] on the given widget with a single shortcut which
+/// triggers an [`ImpulseStream`], so keyboard shortcuts can feed the same pipelines as buttons.
+///
+/// `trigger` is parsed with [`gtk::ShortcutTrigger::parse_string`], e.g. `"<Control>k"`, and an
+/// error is returned if it's invalid. The shortcut is active while focus is within the widget;
+/// install it on the window to make it global to that window.
+///
+/// ```
+///    # use springsteel::controller_streams::shortcut_impulses;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let content = springsteel::ConstraintView::new();
+///    let searches = shortcut_impulses(&content, "<Control>k").expect("valid trigger");
+///    assert!(shortcut_impulses(&content, "<Control>").is_err());
+/// ```
+pub fn shortcut_impulses(
+    widget: &impl IsA<gtk::Widget>,
+    trigger: &str,
+) -> Result<ShortcutImpulses, InvalidShortcutTrigger> {
+    let parsed = gtk::ShortcutTrigger::parse_string(trigger)
+        .ok_or_else(|| InvalidShortcutTrigger(trigger.to_owned()))?;
+
+    let impulses = ImpulseStream::new();
+    let impulses_for_action = impulses.clone();
+    let action = gtk::CallbackAction::new(move |_, _| {
+        impulses_for_action.trigger();
+        true
+    });
+
+    let controller = gtk::ShortcutController::new();
+    controller.add_shortcut(&gtk::Shortcut::new(Some(parsed), Some(action)));
+    widget.add_controller(&controller);
+
+    Ok(ShortcutImpulses {
+        impulses,
+        guard: ControllerGuard::new(widget, &controller),
+    })
+}
+
+/// Options for [`scroll_deltas`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ScrollOptions {
+    /// Report whole discrete steps (e.g. mouse wheel clicks) rather than smooth scrolling deltas
+    /// in surface units.
+    pub discrete: bool,
+
+    /// Claim the scroll events, stopping them from propagating further, e.g. to a surrounding
+    /// [`gtk::ScrolledWindow`].
+    pub claim: bool,
+}
+
+/// Scroll deltas over a widget, made by [`scroll_deltas`].
+pub struct ScrollDeltas {
+    /// The `(dx, dy)` deltas scrolled, summed between polls.
+    pub deltas: ValueStream<(f64, f64)>,
+
+    /// Guard which removes the [`gtk::EventControllerScroll`] when dropped.
+    pub guard: ControllerGuard,
+}
+
+/// Install a [`gtk::EventControllerScroll`] on the given widget and return a [`ValueStream`] of its
+/// `(dx, dy)` scroll deltas.
+///
+/// Smooth scrolling reports deltas at a high rate, so the stream is
+/// [accumulating](ValueStream::accumulating): all deltas reported between two polls are summed
+/// into one, rather than queued without bound. This suits e.g. implementing zoom on a canvas,
+/// where the total scroll amount since the last redraw is what matters.
+///
+/// The controller stays installed until [`ScrollDeltas::guard`] is dropped.
+pub fn scroll_deltas(widget: &impl IsA<gtk::Widget>, options: ScrollOptions) -> ScrollDeltas {
+    let values = ValueStream::accumulating(|(dx, dy): &mut (f64, f64), (ddx, ddy)| {
+        *dx += ddx;
+        *dy += ddy;
+    });
+
+    let mut flags = gtk::EventControllerScrollFlags::BOTH_AXES;
+    if options.discrete {
+        flags |= gtk::EventControllerScrollFlags::DISCRETE;
+    }
+    let controller = gtk::EventControllerScroll::new(flags);
+
+    let values_for_scroll = values.clone();
+    controller.connect_scroll(move |_, dx, dy| {
+        values_for_scroll.push((dx, dy));
+        gtk::Inhibit(options.claim)
+    });
+
+    widget.add_controller(&controller);
+    ScrollDeltas {
+        deltas: values,
+        guard: ControllerGuard::new(widget, &controller),
+    }
+}
+
+/// Streams of file drag-and-drop events over a widget, made by [`file_drops`].
+pub struct FileDrops {
+    /// The files of each drop onto the widget. Drops which don't contain any files aren't output.
+    pub files: ValueStream<Vec<gio::File>>,
+
+    /// Triggered whenever a drag carrying files enters the widget, e.g. to show a highlight.
+    pub enter: ImpulseStream,
+
+    /// Triggered whenever a drag leaves the widget, including just before a drop.
+    pub leave: ImpulseStream,
+
+    /// Guard which removes the [`gtk::DropTarget`] when dropped.
+    pub guard: ControllerGuard,
+}
+
+/// Install a [`gtk::DropTarget`] accepting lists of files ([`gdk::FileList`]) by copy on the given
+/// widget, and return streams of the files dropped and of drags entering and leaving.
+///
+/// The drop target stays installed until [`FileDrops::guard`] is dropped.
+pub fn file_drops(widget: &impl IsA<gtk::Widget>) -> FileDrops {
+    let target = gtk::DropTarget::new(gdk::FileList::static_type(), gdk::DragAction::COPY);
+    widget.add_controller(&target);
+
+    let drops = FileDrops {
+        files: ValueStream::new(),
+        enter: ImpulseStream::new(),
+        leave: ImpulseStream::new(),
+        guard: ControllerGuard::new(widget, &target),
+    };
+
+    let files = drops.files.clone();
+    target.connect_drop(move |_, value, _, _| {
+        let dropped = match value.get::<gdk::FileList>() {
+            Ok(file_list) => file_list.files(),
+            Err(_) => return false,
+        };
+        if dropped.is_empty() {
+            return false;
+        }
+        files.push(dropped);
+        true
+    });
+
+    let enter = drops.enter.clone();
+    target.connect_enter(move |_, _, _| {
+        enter.trigger();
+        gdk::DragAction::COPY
+    });
+
+    let leave = drops.leave.clone();
+    target.connect_leave(move |_| leave.trigger());
+
+    drops
+}
@@ -0,0 +1,213 @@
+//! Debugging aids for finding lifecycle bugs: a registry of the tasks, shared stream subscribers,
+//! and bindings which are presently alive, and [`report_on_shutdown`] to print whatever is still
+//! alive when an application exits.
+
+use crate::glib_future::{after_shutdown, bind_to_application};
+use glib::object::IsA;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::panic::Location;
+use std::rc::Rc;
+
+/// What kind of thing a [`LiveEntry`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LiveKind {
+    /// A future spawned on the glib executor, e.g. by [`glib_run_future`](crate::glib_run_future).
+    Task,
+
+    /// A subscriber of a [`SharedStream`](crate::shared_stream::SharedStream).
+    SharedSubscriber,
+
+    /// A binding from a stream to a widget, e.g. by
+    /// [`bind_text_diff`](crate::text_binding::bind_text_diff).
+    Binding,
+}
+
+/// One live task, subscriber, or binding.
+#[derive(Clone, Debug)]
+pub struct LiveEntry {
+    /// What kind of thing this is.
+    pub kind: LiveKind,
+
+    /// Descriptive name, e.g. the name of the function which created it.
+    pub name: String,
+
+    /// Source location which created it.
+    pub location: &'static Location<'static>,
+
+    /// Backtrace captured at creation, only with the `backtraces` feature.
+    #[cfg(feature = "backtraces")]
+    pub backtrace: std::sync::Arc<std::backtrace::Backtrace>,
+}
+
+impl fmt::Display for LiveEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} {} created at {}",
+            self.kind, self.name, self.location
+        )?;
+        #[cfg(feature = "backtraces")]
+        write!(f, "\n{}", self.backtrace)?;
+        Ok(())
+    }
+}
+
+/// The live registry for the current thread.
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    entries: BTreeMap<u64, LiveEntry>,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+}
+
+/// Registration of something in the live registry, which is removed when this is dropped.
+pub(crate) struct LiveToken(u64);
+
+impl Drop for LiveToken {
+    fn drop(&mut self) {
+        // The registry may already be gone if this is dropped during thread teardown.
+        let _ = REGISTRY.try_with(|r| r.borrow_mut().entries.remove(&self.0));
+    }
+}
+
+/// Register something as live until the returned token is dropped.
+pub(crate) fn register_live(
+    kind: LiveKind,
+    name: &str,
+    location: &'static Location<'static>,
+) -> LiveToken {
+    REGISTRY.with(|r| {
+        let mut r = r.borrow_mut();
+        let id = r.next_id;
+        r.next_id += 1;
+        r.entries.insert(
+            id,
+            LiveEntry {
+                kind,
+                name: name.to_owned(),
+                location,
+                #[cfg(feature = "backtraces")]
+                backtrace: std::sync::Arc::new(std::backtrace::Backtrace::force_capture()),
+            },
+        );
+        LiveToken(id)
+    })
+}
+
+/// Return the entry registered with the given token, if it was registered on this thread.
+pub(crate) fn live_entry(token: &LiveToken) -> Option<LiveEntry> {
+    REGISTRY.with(|r| r.borrow().entries.get(&token.0).cloned())
+}
+
+/// Return everything presently registered as live on this thread, oldest first.
+pub fn live_entries() -> Vec<LiveEntry> {
+    REGISTRY.with(|r| r.borrow().entries.values().cloned().collect())
+}
+
+/// What [`report_on_shutdown`] should do if anything is still alive at shutdown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShutdownReport {
+    /// Print the live entries to standard error.
+    Print,
+
+    /// Print the live entries to standard error and have [`ShutdownAudit::exit_status`] fail with
+    /// status 1, for use in CI smoke tests.
+    FailOnLeaks,
+}
+
+/// What [`report_on_shutdown`] found still alive once the application shut down.
+#[derive(Clone)]
+pub struct ShutdownAudit {
+    mode: ShutdownReport,
+    leaks: Rc<RefCell<Vec<LiveEntry>>>,
+}
+
+impl ShutdownAudit {
+    /// Return everything found still alive at shutdown, which is nothing until the application
+    /// has shut down.
+    pub fn leaks(&self) -> Vec<LiveEntry> {
+        self.leaks.borrow().clone()
+    }
+
+    /// Return the status the process should exit with given the status the application returned
+    /// from `run`: 1 if anything was found alive in [`FailOnLeaks`](ShutdownReport::FailOnLeaks)
+    /// mode, or else the status given.
+    pub fn exit_status(&self, status: i32) -> i32 {
+        if self.mode == ShutdownReport::FailOnLeaks && !self.leaks.borrow().is_empty() {
+            1
+        } else {
+            status
+        }
+    }
+}
+
+/// Hook the shutdown of the given application so that once it shuts down, every task, shared
+/// stream subscriber, and binding still alive is printed with the location which created it, and
+/// returned by the [`ShutdownAudit`].
+///
+/// This [binds](bind_to_application) the application, and the report is made after its tasks have
+/// been aborted, so tasks still pending at shutdown aren't reported as leaks; only whatever
+/// outlives the graceful shutdown is. The process isn't exited from the shutdown handler; use
+/// [`ShutdownAudit::exit_status`] with the status returned by `run` to fail on leaks.
+///
+/// Entries are captured with their creation [`Location`]; enabling the `backtraces` feature also
+/// captures and prints a full backtrace for each, at some cost per registration.
+///
+/// ```
+///    # use gio::prelude::{ApplicationExt as _, ApplicationExtManual as _};
+///    # use springsteel::debug::{report_on_shutdown, LiveKind, ShutdownAudit, ShutdownReport};
+///    # use springsteel::glib_run_future;
+///    # use springsteel::shared_stream::share;
+///    # use springsteel::ImpulseStream;
+///    // Run an application which leaves a task pending, and also leaks a shared stream subscriber
+///    // if `leak`, returning the exit status and the audit.
+///    fn run_app(leak: bool) -> (i32, ShutdownAudit) {
+///        let app = gio::Application::new(None, gio::ApplicationFlags::NON_UNIQUE);
+///        let audit = report_on_shutdown(&app, ShutdownReport::FailOnLeaks);
+///        app.connect_activate(move |_| {
+///            glib_run_future(std::future::pending()).detach();
+///            if leak {
+///                std::mem::forget(share(ImpulseStream::new()));
+///            }
+///        });
+///        let status = app.run_with_args::<&str>(&[]);
+///        (audit.exit_status(status), audit)
+///    }
+///
+///    let (status, audit) = run_app(false);
+///    assert_eq!(status, 0);
+///    assert!(audit.leaks().is_empty());
+///
+///    let (status, audit) = run_app(true);
+///    assert_eq!(status, 1);
+///    let leaks = audit.leaks();
+///    assert_eq!(leaks.len(), 1);
+///    assert_eq!(leaks[0].kind, LiveKind::SharedSubscriber);
+/// ```
+pub fn report_on_shutdown(app: &impl IsA<gio::Application>, mode: ShutdownReport) -> ShutdownAudit {
+    let audit = ShutdownAudit {
+        mode,
+        leaks: Rc::default(),
+    };
+
+    bind_to_application(app);
+    let leaks = audit.leaks.clone();
+    after_shutdown(move || {
+        let entries = live_entries();
+        if entries.is_empty() {
+            return;
+        }
+
+        eprintln!("springsteel: {} still alive at shutdown:", entries.len());
+        for entry in &entries {
+            eprintln!("  {}", entry);
+        }
+        *leaks.borrow_mut() = entries;
+    });
+    audit
+}
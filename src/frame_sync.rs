@@ -0,0 +1,270 @@
+//! Provides [`glib_run_future_frame_synced`], which runs a future on the glib main loop polled
+//! during the update phase of a widget's frame clock rather than from an idle callback, so that
+//! whatever it animates is applied before the frame is painted.
+
+use crate::glib_future::{glib_run_future, TaskHandle};
+use glib::object::{IsA, ObjectExt as _};
+use glib::{SignalHandlerId, WeakRef};
+use gtk::prelude::WidgetExt as _;
+use std::any::Any;
+use std::cell::RefCell;
+use std::future::Future;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll, Waker};
+
+/// State of a [`FrameSynced`] shared with its frame clock update handler.
+struct FrameSyncInner {
+    /// The future, except while it's being polled or once it's done.
+    fut_opt: Option<Pin<Box<dyn Future<Output = ()>>>>,
+
+    /// Whether the future has completed or panicked.
+    done: bool,
+
+    /// The panic payload if the future panicked while polled by the update handler, to resume in
+    /// the task so the executor reports it like any other.
+    panic_opt: Option<Box<dyn Any + Send>>,
+
+    /// The waker of the task, while it's waiting for the update phase to poll the future.
+    waker_opt: Option<Waker>,
+
+    /// The frame clock and its connected update handler, once the widget has been mapped.
+    update_opt: Option<(gdk::FrameClock, SignalHandlerId)>,
+
+    /// The handler connected to the widget's `unmap` signal, once the widget has been mapped.
+    unmap_opt: Option<SignalHandlerId>,
+}
+
+/// The future run by the executor for [`glib_run_future_frame_synced`], which requests the update
+/// phase whenever it's woken and lets the update handler poll the wrapped future.
+struct FrameSynced {
+    widget: WeakRef<gtk::Widget>,
+    inner: Rc<RefCell<FrameSyncInner>>,
+}
+
+/// Run a given future on the glib main loop until it completes, polling it during the update phase
+/// of the given widget's frame clock, before layout and painting, whenever it's woken. This keeps
+/// animations driven by the future in step with frames rather than lagging one behind, as they can
+/// when polled from an idle callback.
+///
+/// While the widget isn't mapped, and so has no frames, the future is polled from an idle callback
+/// like any other. If the widget is unmapped while the future waits for an update phase, which
+/// then might never come, it's polled from an idle callback instead:
+///
+/// ```
+///    # use springsteel::frame_sync::glib_run_future_frame_synced;
+///    # use springsteel::glib_run_future;
+///    # use gtk::prelude::{GtkWindowExt as _, WidgetExt as _};
+///    # use std::cell::Cell;
+///    # use std::rc::Rc;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let label = gtk::Label::new(Some("mapped"));
+///    let window = gtk::Window::new();
+///    window.set_child(Some(&label));
+///    window.present();
+///    let context = glib::MainContext::default();
+///    while !label.is_mapped() {
+///        context.iteration(true);
+///    }
+///
+///    // Both tasks are polled in the same idle callback, so the window is hidden after the first
+///    // has requested an update phase but before the phase comes round.
+///    let polled = Rc::new(Cell::new(false));
+///    let polled_for_task = polled.clone();
+///    let task = glib_run_future_frame_synced(&label, async move { polled_for_task.set(true) });
+///    let window_for_hide = window.clone();
+///    let _hide = glib_run_future(Box::pin(async move { window_for_hide.hide() }));
+///
+///    while context.iteration(false) {}
+///    assert!(!label.is_mapped());
+///    assert!(polled.get());
+///    assert!(task.is_finished());
+/// ```
+///
+/// ```
+///    # use springsteel::frame_sync::glib_run_future_frame_synced;
+///    # use std::cell::Cell;
+///    # use std::rc::Rc;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let label = gtk::Label::new(Some("unmapped"));
+///    let polled = Rc::new(Cell::new(false));
+///    let polled_for_task = polled.clone();
+///    let task = glib_run_future_frame_synced(&label, async move { polled_for_task.set(true) });
+///
+///    while glib::MainContext::default().iteration(false) {}
+///    assert!(polled.get());
+///    assert!(task.is_finished());
+/// ```
+#[track_caller]
+pub fn glib_run_future_frame_synced<F>(widget: &impl IsA<gtk::Widget>, fut: F) -> TaskHandle
+where
+    F: Future<Output = ()> + 'static,
+{
+    glib_run_future(FrameSynced {
+        widget: widget.upcast_ref::<gtk::Widget>().downgrade(),
+        inner: Rc::new(RefCell::new(FrameSyncInner {
+            fut_opt: Some(Box::pin(fut)),
+            done: false,
+            panic_opt: None,
+            waker_opt: None,
+            update_opt: None,
+            unmap_opt: None,
+        })),
+    })
+}
+
+impl FrameSynced {
+    /// Make sure the update handler is connected to the given frame clock, rather than some other
+    /// the widget used before being remapped.
+    fn connect_update(&self, clock: &gdk::FrameClock) {
+        let mut inner = self.inner.borrow_mut();
+        if let Some((connected, _)) = &inner.update_opt {
+            if connected == clock {
+                return;
+            }
+        }
+        if let Some((old_clock, handler)) = inner.update_opt.take() {
+            old_clock.disconnect(handler);
+        }
+
+        let inner_for_update = Rc::downgrade(&self.inner);
+        let handler = clock.connect_update(move |_| poll_at_update(&inner_for_update));
+        inner.update_opt = Some((clock.clone(), handler));
+    }
+
+    /// Make sure the unmap handler is connected to the given widget, so the task isn't left
+    /// waiting for an update phase once the widget stops having frames.
+    fn connect_unmap(&self, widget: &gtk::Widget) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.unmap_opt.is_some() {
+            return;
+        }
+
+        let inner_for_unmap = Rc::downgrade(&self.inner);
+        let handler = widget.connect_unmap(move |_| wake_at_unmap(&inner_for_unmap));
+        inner.unmap_opt = Some(handler);
+    }
+}
+
+impl Future for FrameSynced {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        {
+            let mut inner = self.inner.borrow_mut();
+            if let Some(payload) = inner.panic_opt.take() {
+                drop(inner);
+                resume_unwind(payload);
+            }
+            if inner.done {
+                return Poll::Ready(());
+            }
+        }
+
+        let mapped_opt = self
+            .widget
+            .upgrade()
+            .filter(|widget| widget.is_mapped())
+            .and_then(|widget| Some((widget.frame_clock()?, widget)));
+        if let Some((clock, widget)) = mapped_opt {
+            self.connect_update(&clock);
+            self.connect_unmap(&widget);
+            self.inner.borrow_mut().waker_opt = Some(cx.waker().clone());
+            clock.request_phase(gdk::FrameClockPhase::UPDATE);
+            return Poll::Pending;
+        }
+
+        // Not mapped, so poll now. Take the future out so it isn't borrowed while polled.
+        let fut_opt = self.inner.borrow_mut().fut_opt.take();
+        let mut fut = match fut_opt {
+            Some(fut) => fut,
+            None => return Poll::Pending,
+        };
+        match fut.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                self.inner.borrow_mut().done = true;
+                Poll::Ready(())
+            }
+            Poll::Pending => {
+                self.inner.borrow_mut().fut_opt = Some(fut);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for FrameSynced {
+    fn drop(&mut self) {
+        let (update_opt, unmap_opt, fut_opt) = {
+            let mut inner = self.inner.borrow_mut();
+            (
+                inner.update_opt.take(),
+                inner.unmap_opt.take(),
+                inner.fut_opt.take(),
+            )
+        };
+        if let Some((clock, handler)) = update_opt {
+            clock.disconnect(handler);
+        }
+        if let (Some(widget), Some(handler)) = (self.widget.upgrade(), unmap_opt) {
+            widget.disconnect(handler);
+        }
+        drop(fut_opt);
+    }
+}
+
+/// Wake the task if it's waiting for an update phase when the widget is unmapped, so that it polls
+/// the future itself rather than wait for a frame which might never come.
+fn wake_at_unmap(inner: &Weak<RefCell<FrameSyncInner>>) {
+    let waker_opt = inner
+        .upgrade()
+        .and_then(|inner| inner.borrow_mut().waker_opt.take());
+    if let Some(waker) = waker_opt {
+        waker.wake();
+    }
+}
+
+/// Poll the future during the frame clock's update phase, if its task is waiting for that.
+fn poll_at_update(inner: &Weak<RefCell<FrameSyncInner>>) {
+    let inner = match inner.upgrade() {
+        Some(inner) => inner,
+        None => return,
+    };
+
+    let (fut_opt, waker_opt) = {
+        let mut state = inner.borrow_mut();
+        match state.waker_opt.take() {
+            Some(waker) => (state.fut_opt.take(), Some(waker)),
+            // Another widget sharing the clock requested this phase.
+            None => (None, None),
+        }
+    };
+    let (mut fut, waker) = match (fut_opt, waker_opt) {
+        (Some(fut), Some(waker)) => (fut, waker),
+        _ => return,
+    };
+
+    let poll_result = catch_unwind(AssertUnwindSafe(|| {
+        fut.as_mut().poll(&mut Context::from_waker(&waker))
+    }));
+    match poll_result {
+        Ok(Poll::Pending) => inner.borrow_mut().fut_opt = Some(fut),
+        Ok(Poll::Ready(())) => {
+            inner.borrow_mut().done = true;
+            drop(fut);
+            waker.wake();
+        }
+        Err(payload) => {
+            {
+                let mut state = inner.borrow_mut();
+                state.done = true;
+                state.panic_opt = Some(payload);
+            }
+            drop(fut);
+            waker.wake();
+        }
+    }
+}
@@ -1,167 +1,2439 @@
 //! Provides a [`Future`] executor which runs in the glib main loop, suitable for doing GTK UI
-//! side effects: [`glib_run_future`].
+//! side effects: [`glib_run_future`], which returns a [`TaskHandle`] to abort the future with, and
+//! [`glib_spawn`] for futures with output, which returns an awaitable [`GlibJoinHandle`]. For the
+//! common case of handling every item of a stream there's [`glib_run_stream`].
+//!
+//! For apps which also run another executor, e.g. tokio for networking, [`glib_spawn_remote`]
+//! spawns a future on the main loop whose output can be awaited from any thread, and
+//! [`forward_to_glib`] handles the items of a stream fed from another thread on the main loop.
+//!
+//! With the `tracing` feature enabled, every spawn, wake, poll (with its duration), completion,
+//! and abort of a task is logged at debug level via the `log` crate, using the name given to
+//! [`glib_run_future_named`], [`active_tasks`] lists the tasks still running, and
+//! [`dump_stuck_tasks`] reports those which look like they'll never complete.
 
-use glib::source::{idle_add_local, Continue, SourceId};
+use crate::debug::{live_entry, register_live, LiveKind, LiveToken};
+#[cfg(feature = "stats")]
+use crate::ValueStream;
+use futures::channel::oneshot;
+use futures::future::{select, Either};
+use futures::stream::{Stream, StreamExt as _};
+use futures::task::{waker_ref, ArcWake};
+use gio::prelude::{ApplicationExt as _, CancellableExt as _, CancellableExtManual as _};
+use glib::object::{Cast as _, IsA, ObjectExt as _};
+use glib::source::{
+    idle_source_new, timeout_source_new, Continue, PRIORITY_DEFAULT, PRIORITY_DEFAULT_IDLE,
+};
 use std::boxed::Box;
-use std::future::Future;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::{ready, Future};
+use std::marker::PhantomData;
 use std::mem::drop;
+use std::panic::{catch_unwind, AssertUnwindSafe, Location};
 use std::pin::Pin;
-use std::sync::{Arc, Mutex};
-use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::rc::Rc;
+#[cfg(feature = "stats")]
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
 
 /// Internal state for the glib executor, with the state of the future computation along with
 /// scheduling details.
+///
+/// Wakers may be used from any thread, so the scheduling flags they touch are atomic. Everything
+/// else is only ever used on the thread which spawned the future, which is asserted wherever it's
+/// not guaranteed by the types, and so lives in `RefCell`s. The future and the rest of the state
+/// are borrowed separately so that the future can wake or abort its own task while it's being
+/// polled.
 struct GlibWaker {
-    /// Contains the future being iterated.
-    fut: Box<dyn Future<Output = ()> + Unpin>,
+    /// The run queue of the main context the future is run on.
+    queue: Arc<RunQueue>,
+
+    /// Source location which spawned the future, for reporting panics.
+    location: &'static Location<'static>,
+
+    /// Name of the task, for logging and [`active_tasks`].
+    #[cfg(feature = "tracing")]
+    name: String,
+
+    /// When the task was spawned.
+    #[cfg(feature = "tracing")]
+    spawned: Instant,
+
+    /// The thread which spawned the future, and so the only one which may poll or drop it.
+    thread: ThreadId,
+
+    /// Whether the task is in its [`RunQueue`] waiting to be polled.
+    queued: AtomicBool,
+
+    /// Whether the future has completed or panicked.
+    finished: AtomicBool,
+
+    /// Whether the task has been [aborted](TaskHandle::abort).
+    aborted: AtomicBool,
+
+    /// Whether the task is [paused](TaskHandle::pause).
+    paused: AtomicBool,
+
+    /// Whether the task was woken while paused, so it needs polling once resumed.
+    woken_while_paused: AtomicBool,
+
+    /// Contains the future being iterated, or `None` once it has been aborted.
+    fut: RefCell<Option<Box<dyn Future<Output = ()> + Unpin>>>,
+
+    /// Details of the task only used on the spawning thread.
+    state: RefCell<TaskState>,
+}
+
+// SAFETY: Only the queue, the immutable details, and the atomic flags are used by wakers, which
+// may be on any thread. The future and the task state are only used by the step, which checks it's
+// on the spawning thread before touching them however the main context came to be dispatched
+// elsewhere, by TaskHandle, which isn't Send so is always on the spawning thread,
+// and by Drop, which leaks them rather than drop them on any other thread.
+unsafe impl Send for GlibWaker {}
+unsafe impl Sync for GlibWaker {}
+
+impl GlibWaker {
+    /// Return whether called on the thread which spawned the future, as the future and task
+    /// state may only be used there.
+    fn on_spawning_thread(&self) -> bool {
+        thread::current().id() == self.thread
+    }
+
+    /// Return whether the future has completed, panicked, or been aborted, after which it's never
+    /// queued or polled again.
+    fn is_done(&self) -> bool {
+        self.finished.load(Ordering::SeqCst) || self.aborted.load(Ordering::SeqCst)
+    }
+}
+
+impl ArcWake for GlibWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        glib_waker_schedule(arc_self);
+    }
+}
+
+impl Drop for GlibWaker {
+    fn drop(&mut self) {
+        if self.on_spawning_thread() {
+            return;
+        }
+
+        // The last waker was dropped on some other thread without waking, so the future can never
+        // be polled again. Dropping it or the live registration here would touch state belonging
+        // to the spawning thread from the wrong thread, so leak them instead.
+        if let Some(fut) = self.fut.get_mut().take() {
+            std::mem::forget(fut);
+        }
+        if let Some(live) = self.state.get_mut().live_opt.take() {
+            std::mem::forget(live);
+        }
+    }
+}
+
+/// Details of a [`GlibWaker`] only used on the thread which spawned it.
+struct TaskState {
+    /// Registration of the task in the [live registry](crate::debug), until it completes.
+    live_opt: Option<LiveToken>,
+
+    /// The panic message if the future panicked while being polled.
+    panic_opt: Option<String>,
+
+    /// The [cancellable](TaskHandle::cancellable) to cancel when the task is aborted, once
+    /// requested.
+    cancellable_opt: Option<gio::Cancellable>,
+
+    /// How many times the future has been polled.
+    #[cfg(feature = "tracing")]
+    polls: u64,
+
+    /// When the future was last polled, if it has been.
+    #[cfg(feature = "tracing")]
+    last_polled_opt: Option<Instant>,
+}
+
+/// Details of a task still running on the glib main loop, returned by [`active_tasks`] when the
+/// `tracing` feature is enabled.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Debug)]
+pub struct TaskInfo {
+    /// Name given to [`glib_run_future_named`], or the name of the function which spawned it.
+    pub name: String,
+
+    /// Source location which spawned the future.
+    pub location: &'static Location<'static>,
+
+    /// When the task was spawned.
+    pub spawned: Instant,
+
+    /// How many times the future has been polled so far.
+    pub polls: u64,
+
+    /// When the future was last polled, or `None` if it hasn't been yet.
+    pub last_polled: Option<Instant>,
+}
+
+thread_local! {
+    /// Every task spawned on this thread which is still referenced, for [`active_tasks`] and
+    /// [`bind_to_application`].
+    static TASKS: RefCell<Vec<Weak<GlibWaker>>> = RefCell::new(Vec::new());
+
+    /// Where the application [bound](bind_to_application) on this thread is in its lifecycle.
+    static APP_STATE: Cell<AppState> = Cell::new(AppState::Unbound);
+
+    /// Tasks spawned while the bound application is starting, to schedule once it has started.
+    static DEFERRED: RefCell<Vec<Arc<GlibWaker>>> = RefCell::new(Vec::new());
+
+    /// The application [bound](bind_to_application) on this thread, so binding it again can be
+    /// told apart from binding another.
+    static BOUND_APP: RefCell<Option<glib::WeakRef<gio::Application>>> = RefCell::new(None);
+
+    /// Hooks to run once the bound application has shut down and its tasks have been aborted.
+    static SHUTDOWN_HOOKS: RefCell<Vec<Box<dyn FnOnce()>>> = RefCell::new(Vec::new());
+}
+
+/// Where the application [bound](bind_to_application) on some thread is in its lifecycle, which
+/// determines what happens to futures spawned there.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AppState {
+    /// No application is bound, so futures are scheduled straight away.
+    Unbound,
+
+    /// The application is bound but hasn't started up, so futures are deferred until it has.
+    Starting,
+
+    /// The application has started up, so futures are scheduled straight away.
+    Running,
+
+    /// The application has shut down, so futures are dropped without being run.
+    ShutDown,
+}
+
+/// Tie the futures spawned on this thread to the lifecycle of the given application.
+///
+/// Futures spawned before the application has started up, e.g. in `main` before
+/// [`run`](gio::prelude::ApplicationExtManual::run), aren't polled until it has, so they never
+/// run before GTK is initialized or in the middle of startup:
+///
+/// ```
+///    # use gio::prelude::ApplicationExt as _;
+///    # use springsteel::glib_future::bind_to_application;
+///    # use springsteel::glib_run_future;
+///    # use std::cell::Cell;
+///    # use std::future::poll_fn;
+///    # use std::rc::Rc;
+///    # use std::task::Poll;
+///    let app = gio::Application::new(
+///        Some("com.dridus.springsteel.startup-test"),
+///        gio::ApplicationFlags::NON_UNIQUE,
+///    );
+///    bind_to_application(&app);
+///    bind_to_application(&app);
+///
+///    let polled = Rc::new(Cell::new(false));
+///    let polled_for_task = polled.clone();
+///    glib_run_future(poll_fn(move |_| {
+///        polled_for_task.set(true);
+///        Poll::Ready(())
+///    }));
+///
+///    let context = glib::MainContext::default();
+///    while context.iteration(false) {}
+///    assert!(!polled.get());
+///
+///    app.register(gio::Cancellable::NONE).expect("register");
+///    while context.iteration(false) {}
+///    assert!(polled.get());
+/// ```
+///
+/// For a [`gtk::Application`], GTK is initialized by its startup, so futures may be spawned
+/// before [`gtk::init`] and will find it initialized when they're first polled:
+///
+/// ```
+///    # use gio::prelude::{ApplicationExt as _, ApplicationExtManual as _};
+///    # use springsteel::glib_future::{bind_to_application, glib_run_future_detached};
+///    # use std::cell::Cell;
+///    # use std::rc::Rc;
+///    let app = gtk::Application::new(
+///        Some("com.dridus.springsteel.gtk-startup-test"),
+///        gio::ApplicationFlags::NON_UNIQUE,
+///    );
+///    bind_to_application(&app);
+///    app.connect_activate(|_| ());
+///
+///    let initialized = Rc::new(Cell::new(None));
+///    let initialized_for_task = initialized.clone();
+///    let hold = app.hold();
+///    glib_run_future_detached(Box::pin(async move {
+///        initialized_for_task.set(Some(gtk::is_initialized_main_thread()));
+///        drop(hold);
+///    }));
+///    assert!(!gtk::is_initialized_main_thread());
+///
+///    app.run_with_args::<&str>(&[]);
+///    assert_eq!(initialized.get(), Some(true));
+/// ```
+///
+/// Without a bound application nothing is deferred, so a future spawned before [`gtk::init`] is
+/// polled the next time the main context is iterated, initialized or not. Bind the application
+/// before spawning anything which touches widgets.
+///
+/// When the application shuts down, every task spawned on this thread is aborted, and any future
+/// spawned afterwards is dropped straight away, logging a warning, rather than run. Otherwise
+/// futures woken while the main loop winds down can be polled during teardown and touch half
+/// destroyed widgets.
+///
+/// Futures run by [`glib_block_on`] are never deferred, but those it awaits may be, so it mustn't
+/// be used to wait for other futures between binding and startup. Neither are those run on an
+/// explicit context by [`glib_run_future_on`], e.g. a worker thread's, which the application's
+/// startup has nothing to do with.
+///
+/// Binding the same application again does nothing. Only one application can be bound on a
+/// thread, so this panics if another is bound which is still alive.
+pub fn bind_to_application(app: &impl IsA<gio::Application>) {
+    let app = app.upcast_ref::<gio::Application>();
+    let bound_opt = BOUND_APP.with(|b| b.borrow().as_ref().and_then(|bound| bound.upgrade()));
+    match bound_opt {
+        Some(bound) if bound == *app => return,
+        Some(bound) => panic!(
+            "bind_to_application: application {:?} is already bound on this thread",
+            bound.application_id()
+        ),
+        None => BOUND_APP.with(|b| *b.borrow_mut() = Some(app.downgrade())),
+    }
+
+    let state = if app.is_registered() {
+        AppState::Running
+    } else {
+        AppState::Starting
+    };
+    APP_STATE.with(|s| s.set(state));
+
+    app.connect_startup(|_| {
+        APP_STATE.with(|s| s.set(AppState::Running));
+        for task in DEFERRED.with(|d| std::mem::take(&mut *d.borrow_mut())) {
+            glib_waker_schedule(&task);
+        }
+    });
+
+    app.connect_shutdown(|_| {
+        APP_STATE.with(|s| s.set(AppState::ShutDown));
+
+        // Collect the tasks first, as aborting drops futures which might spawn in turn.
+        let tasks: Vec<_> = TASKS.with(|tasks| {
+            tasks
+                .borrow_mut()
+                .drain(..)
+                .filter_map(|task| task.upgrade())
+                .collect()
+        });
+        for task in tasks {
+            TaskHandle(task, PhantomData).abort();
+        }
+        drop(DEFERRED.with(|d| std::mem::take(&mut *d.borrow_mut())));
+
+        for hook in SHUTDOWN_HOOKS.with(|h| std::mem::take(&mut *h.borrow_mut())) {
+            hook();
+        }
+    });
+}
+
+/// Run `hook` once the application [bound](bind_to_application) on this thread has shut down and
+/// every task spawned on the thread has been aborted.
+pub(crate) fn after_shutdown(hook: impl FnOnce() + 'static) {
+    SHUTDOWN_HOOKS.with(|h| h.borrow_mut().push(Box::new(hook)));
+}
+
+/// Return the details of every task spawned on this thread which hasn't yet completed or been
+/// aborted, oldest first, e.g. to list them in a debug window.
+#[cfg(feature = "tracing")]
+pub fn active_tasks() -> Vec<TaskInfo> {
+    TASKS.with(|tasks| {
+        let mut tasks = tasks.borrow_mut();
+        tasks.retain(|task| task.strong_count() > 0);
+        tasks
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|task| !task.is_done())
+            .map(|task| {
+                let state = task.state.borrow();
+                TaskInfo {
+                    name: task.name.clone(),
+                    location: task.location,
+                    spawned: task.spawned,
+                    polls: state.polls,
+                    last_polled: state.last_polled_opt,
+                }
+            })
+            .collect()
+    })
+}
+
+/// Log a warning via the `log` crate for every [active task](active_tasks) which hasn't been polled
+/// for at least `older_than`, or hasn't been polled at all since spawned that long ago, and return
+/// their details, oldest first.
+///
+/// Such tasks are usually waiting on something which will never happen, e.g. a `for_each` over an
+/// [`ImpulseStream`](crate::ImpulseStream) whose triggerers were all dropped along with their
+/// widgets, and keep whatever their futures captured alive invisibly. Naming tasks with
+/// [`glib_run_future_named`] makes the report easier to act on.
+///
+/// ```
+///    # use futures::stream::StreamExt as _;
+///    # use springsteel::glib_future::{dump_stuck_tasks, glib_run_future_named};
+///    # use springsteel::ImpulseStream;
+///    # use std::future::ready;
+///    # use std::time::Duration;
+///    glib_run_future_named("orphaned", ImpulseStream::new().for_each(|()| ready(())));
+///    while glib::MainContext::default().iteration(false) {}
+///
+///    let stuck = dump_stuck_tasks(Duration::ZERO);
+///    assert!(stuck.iter().any(|task| task.name == "orphaned"));
+///    assert!(dump_stuck_tasks(Duration::from_secs(3600)).is_empty());
+/// ```
+#[cfg(feature = "tracing")]
+pub fn dump_stuck_tasks(older_than: Duration) -> Vec<TaskInfo> {
+    let stuck: Vec<_> = active_tasks()
+        .into_iter()
+        .filter(|task| task.last_polled.unwrap_or(task.spawned).elapsed() >= older_than)
+        .collect();
+    for task in &stuck {
+        log::warn!(
+            "task {} ({}) not polled for {:?} after {} polls",
+            task.name,
+            task.location,
+            task.last_polled.unwrap_or(task.spawned).elapsed(),
+            task.polls,
+        );
+    }
+    stuck
+}
+
+/// Counters of the executor's work across every thread, updated when the `stats` feature is
+/// enabled, for [`executor_stats_stream`].
+#[cfg(feature = "stats")]
+static STATS_POLLS: AtomicU64 = AtomicU64::new(0);
+#[cfg(feature = "stats")]
+static STATS_WAKES: AtomicU64 = AtomicU64::new(0);
+#[cfg(feature = "stats")]
+static STATS_LONGEST_POLL_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// Snapshot of the executor's work, output periodically by [`executor_stats_stream`] when the
+/// `stats` feature is enabled.
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExecutorStats {
+    /// How many tasks spawned on this thread haven't yet completed or been aborted.
+    pub live_tasks: usize,
+
+    /// How many times any future was polled during the last interval.
+    pub polls: u64,
+
+    /// How many times any future was woken, in total.
+    pub wakes: u64,
+
+    /// The longest single poll of any future during the last interval. Anything over a few
+    /// milliseconds means some future is blocking the main loop.
+    pub longest_poll: Duration,
+}
+
+/// Make a [`ValueStream`] which outputs an [`ExecutorStats`] every `interval`, e.g. to show in a
+/// debug pane, using a glib timeout on the thread default main context.
+///
+/// The stream is [coalescing](ValueStream::coalescing), so only the latest stats are output if
+/// it's polled less often than `interval`. The timeout runs for the rest of the program, so this
+/// is best called once at startup. If several of these streams exist, the longest poll is split
+/// between them, as each reset it.
+///
+/// ```
+///    # use futures::stream::StreamExt as _;
+///    # use springsteel::glib_future::{executor_stats_stream, glib_block_on};
+///    # use std::time::Duration;
+///    let mut stats = executor_stats_stream(Duration::from_millis(10));
+///    let first = glib_block_on(async move { stats.next().await });
+///    assert!(first.is_some());
+/// ```
+#[cfg(feature = "stats")]
+pub fn executor_stats_stream(interval: Duration) -> ValueStream<ExecutorStats> {
+    let stats = ValueStream::coalescing();
+    let stats_for_timeout = stats.clone();
+    let mut last_polls = STATS_POLLS.load(Ordering::Relaxed);
+    glib::timeout_add_local(interval, move || {
+        let polls = STATS_POLLS.load(Ordering::Relaxed);
+        let live_tasks = TASKS.with(|tasks| {
+            tasks
+                .borrow()
+                .iter()
+                .filter_map(Weak::upgrade)
+                .filter(|task| !task.is_done())
+                .count()
+        });
+        stats_for_timeout.push(ExecutorStats {
+            live_tasks,
+            polls: polls - last_polls,
+            wakes: STATS_WAKES.load(Ordering::Relaxed),
+            longest_poll: Duration::from_nanos(STATS_LONGEST_POLL_NANOS.swap(0, Ordering::Relaxed)),
+        });
+        last_polls = polls;
+        Continue(true)
+    });
+    stats
+}
+
+/// Handle to a future running on the glib main loop, returned by [`glib_run_future`].
+///
+/// Dropping the handle does not abort the future, it just carries on running detached, but as
+/// that's more often a mistake than not the handle is `#[must_use]`: use [`TaskHandle::detach`]
+/// or [`glib_run_future_detached`] to run a future detached on purpose.
+///
+/// The handle isn't [`Send`], as the future may only be dropped on the thread which spawned it.
+#[must_use = "dropping a TaskHandle leaves its future running detached, use detach() if that's intended"]
+pub struct TaskHandle(Arc<GlibWaker>, PhantomData<Rc<()>>);
+
+impl TaskHandle {
+    /// Abort the future, removing any scheduled step and dropping it. Dropping the future drops
+    /// whatever it captured, such as widget references, so a long-lived `for_each` over the events
+    /// of some view should be aborted when that view goes away.
+    ///
+    /// If called from within the future itself, the future is dropped as soon as its present poll
+    /// returns. Aborting a future which has already finished or been aborted does nothing.
+    ///
+    /// ```
+    ///    # use futures::stream::StreamExt as _;
+    ///    # use springsteel::{glib_run_future, ImpulseStream};
+    ///    # use std::cell::Cell;
+    ///    # use std::future::ready;
+    ///    # use std::rc::Rc;
+    ///    struct SetOnDrop(Rc<Cell<bool>>);
+    ///    impl Drop for SetOnDrop {
+    ///        fn drop(&mut self) {
+    ///            self.0.set(true);
+    ///        }
+    ///    }
+    ///
+    ///    let dropped = Rc::new(Cell::new(false));
+    ///    let handled = Rc::new(Cell::new(0));
+    ///    let impulses = ImpulseStream::new();
+    ///
+    ///    let guard = SetOnDrop(dropped.clone());
+    ///    let handled_for_task = handled.clone();
+    ///    let task = glib_run_future(impulses.clone().for_each(move |()| {
+    ///        let _ = &guard;
+    ///        handled_for_task.set(handled_for_task.get() + 1);
+    ///        ready(())
+    ///    }));
+    ///    while glib::MainContext::default().iteration(false) {}
+    ///
+    ///    task.abort();
+    ///    assert!(dropped.get());
+    ///    assert!(task.is_finished());
+    ///
+    ///    impulses.trigger();
+    ///    while glib::MainContext::default().iteration(false) {}
+    ///    assert_eq!(handled.get(), 0);
+    /// ```
+    pub fn abort(&self) {
+        if self.0.is_done() {
+            return;
+        }
+        // If the task is queued it stays there, but is skipped once its future is gone.
+        self.0.aborted.store(true, Ordering::SeqCst);
+        let (live_opt, cancellable_opt) = {
+            let mut state = self.0.state.borrow_mut();
+            (state.live_opt.take(), state.cancellable_opt.clone())
+        };
+        drop(live_opt);
+
+        // Cancel only after releasing the borrow, as cancelled handlers might use this task.
+        if let Some(cancellable) = cancellable_opt {
+            cancellable.cancel();
+        }
+
+        #[cfg(feature = "tracing")]
+        log::debug!("task {} ({}) aborted", self.0.name, self.0.location);
+
+        // If the future is borrowed then it's being polled, presumably by whatever is calling
+        // this, and the step will drop it when the poll returns.
+        let fut_opt = match self.0.fut.try_borrow_mut() {
+            Ok(mut fut_opt) => fut_opt.take(),
+            Err(_) => None,
+        };
+
+        // Drop the future only after releasing the borrow, as dropping it might wake this task.
+        drop(fut_opt);
+    }
+
+    /// Pause the task, so that it isn't polled when woken until it's [resumed](Self::resume). The
+    /// future just sees a longer wait, e.g. to stop a tick driven animation while its widget is
+    /// unmapped:
+    ///
+    /// ```
+    ///    # use futures::stream::StreamExt as _;
+    ///    # use springsteel::{glib_run_future, ImpulseStream};
+    ///    # use std::cell::Cell;
+    ///    # use std::future::poll_fn;
+    ///    # use std::rc::Rc;
+    ///    # use std::task::Poll;
+    ///    let ticks = ImpulseStream::new();
+    ///    let polls = Rc::new(Cell::new(0));
+    ///
+    ///    let mut ticks_for_task = ticks.clone();
+    ///    let polls_for_task = polls.clone();
+    ///    let task = glib_run_future(poll_fn(move |cx| {
+    ///        polls_for_task.set(polls_for_task.get() + 1);
+    ///        while let Poll::Ready(Some(())) = ticks_for_task.poll_next_unpin(cx) {}
+    ///        Poll::Pending
+    ///    }));
+    ///
+    ///    let context = glib::MainContext::default();
+    ///    while context.iteration(false) {}
+    ///    assert_eq!(polls.get(), 1);
+    ///
+    ///    task.pause();
+    ///    ticks.trigger();
+    ///    ticks.trigger();
+    ///    while context.iteration(false) {}
+    ///    assert_eq!(polls.get(), 1);
+    ///
+    ///    task.resume();
+    ///    while context.iteration(false) {}
+    ///    assert_eq!(polls.get(), 2);
+    /// ```
+    pub fn pause(&self) {
+        self.0.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Resume the task after it was [paused](Self::pause), polling it once if it was woken in the
+    /// meantime.
+    pub fn resume(&self) {
+        self.0.paused.store(false, Ordering::SeqCst);
+        if self.0.woken_while_paused.swap(false, Ordering::SeqCst) {
+            glib_waker_schedule(&self.0);
+        }
+    }
+
+    /// Return whether the task is [paused](Self::pause).
+    pub fn is_paused(&self) -> bool {
+        self.0.paused.load(Ordering::SeqCst)
+    }
+
+    /// Return a [`gio::Cancellable`] which is cancelled when the task is aborted, made the first
+    /// time this is called, to pass to gio asynchronous operations the future awaits so that they
+    /// stop along with it:
+    ///
+    /// ```
+    ///    # use gio::prelude::CancellableExt as _;
+    ///    # use springsteel::glib_run_future;
+    ///    # use springsteel::time::sleep;
+    ///    # use std::time::Duration;
+    ///    let task = glib_run_future(sleep(Duration::from_secs(60)));
+    ///    let cancellable = task.cancellable();
+    ///
+    ///    while glib::MainContext::default().iteration(false) {}
+    ///    assert!(!cancellable.is_cancelled());
+    ///
+    ///    task.abort();
+    ///    assert!(cancellable.is_cancelled());
+    /// ```
+    ///
+    /// If the task was already aborted, the cancellable is already cancelled.
+    pub fn cancellable(&self) -> gio::Cancellable {
+        let cancellable = self
+            .0
+            .state
+            .borrow_mut()
+            .cancellable_opt
+            .get_or_insert_with(gio::Cancellable::new)
+            .clone();
+        if self.0.aborted.load(Ordering::SeqCst) {
+            cancellable.cancel();
+        }
+        cancellable
+    }
+
+    /// Return whether the future has completed, been aborted, or panicked.
+    pub fn is_finished(&self) -> bool {
+        self.0.is_done()
+    }
+
+    /// Return the panic message if the future panicked while being polled.
+    pub fn panic_message(&self) -> Option<String> {
+        self.0.state.borrow().panic_opt.clone()
+    }
+
+    /// Turn the handle into a guard which aborts the future when dropped, so that storing it in
+    /// some struct, e.g. the state of a view, ties the future to that struct's lifetime:
+    ///
+    /// ```
+    ///    # use futures::stream::StreamExt as _;
+    ///    # use springsteel::glib_future::AbortOnDrop;
+    ///    # use springsteel::{glib_run_future, ImpulseStream};
+    ///    # use std::cell::Cell;
+    ///    # use std::future::ready;
+    ///    # use std::rc::Rc;
+    ///    struct Page {
+    ///        _tasks: [AbortOnDrop; 3],
+    ///    }
+    ///
+    ///    let impulses = ImpulseStream::new();
+    ///    let handled = Rc::new(Cell::new(0));
+    ///    let spawn = || {
+    ///        let handled = handled.clone();
+    ///        glib_run_future(impulses.clone().for_each(move |()| {
+    ///            handled.set(handled.get() + 1);
+    ///            ready(())
+    ///        }))
+    ///        .abort_on_drop()
+    ///    };
+    ///
+    ///    let page = Page { _tasks: [spawn(), spawn(), spawn()] };
+    ///    impulses.trigger();
+    ///    while glib::MainContext::default().iteration(false) {}
+    ///    assert_eq!(handled.get(), 3);
+    ///
+    ///    drop(page);
+    ///    impulses.trigger();
+    ///    while glib::MainContext::default().iteration(false) {}
+    ///    assert_eq!(handled.get(), 3);
+    /// ```
+    ///
+    /// [Forgetting](std::mem::forget) the guard leaves the future running detached, like dropping
+    /// a plain `TaskHandle` does.
+    pub fn abort_on_drop(self) -> AbortOnDrop {
+        AbortOnDrop(self)
+    }
+
+    /// Drop the handle, leaving the future running detached until it ends by itself. This only
+    /// spells out that losing the handle is intended, for tasks which live as long as the
+    /// application does.
+    pub fn detach(self) {}
+}
+
+/// Guard which [aborts](TaskHandle::abort) a future running on the glib main loop when dropped,
+/// made by [`TaskHandle::abort_on_drop`].
+///
+/// Like `TaskHandle` it isn't [`Send`], so it's always dropped on the thread which spawned the
+/// future.
+#[must_use = "dropping an AbortOnDrop aborts its future at once"]
+pub struct AbortOnDrop(TaskHandle);
+
+impl AbortOnDrop {
+    /// Return the handle of the guarded future.
+    pub fn task(&self) -> &TaskHandle {
+        &self.0
+    }
+}
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// A panic of a future run on the glib main loop, passed to the
+/// [task panic handler](set_task_panic_handler).
+#[derive(Clone, Debug)]
+pub struct TaskPanic {
+    /// The panic message, if the panic payload was a string.
+    pub message: String,
+
+    /// Source location which spawned the future.
+    pub location: &'static Location<'static>,
+}
+
+impl fmt::Display for TaskPanic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "future spawned at {} panicked: {}",
+            self.location, self.message
+        )
+    }
+}
+
+thread_local! {
+    static TASK_PANIC_HANDLER: RefCell<Option<Rc<dyn Fn(&TaskPanic)>>> = RefCell::new(None);
+}
+
+/// Set the handler called on this thread when a future run on the glib main loop panics while
+/// being polled, replacing the default which logs the panic with [`log::error!`].
+///
+/// A panicking future is never polled again and is dropped, and its handle reports the panic:
+/// [`TaskHandle::panic_message`] and [`JoinError::Panicked`]. The panic doesn't unwind into the
+/// main loop, so other futures keep running:
+///
+/// ```
+///    # use springsteel::glib_future::{glib_spawn, set_task_panic_handler};
+///    # use std::cell::Cell;
+///    # use std::rc::Rc;
+///    let panics = Rc::new(Cell::new(0));
+///    let panics_for_handler = panics.clone();
+///    set_task_panic_handler(move |_| panics_for_handler.set(panics_for_handler.get() + 1));
+///
+///    let doomed = glib_spawn(async { panic!("oh no") });
+///    let fine = glib_spawn(async { 42 });
+///
+///    let results = glib_spawn(async { (doomed.await.is_err(), fine.await.ok()) });
+///    while glib::MainContext::default().iteration(false) {}
+///    assert_eq!(panics.get(), 1);
+///    assert!(results.is_finished());
+/// ```
+pub fn set_task_panic_handler(handler: impl Fn(&TaskPanic) + 'static) {
+    TASK_PANIC_HANDLER.with(|h| *h.borrow_mut() = Some(Rc::new(handler)));
+}
 
-    /// Contains the `Some(`[`SourceId`]`)` of the scheduled idle callback step or `None` if no 
-    /// step is presently scheduled.
-    pending_idle_opt: Option<SourceId>,
+/// Report a panic to the [task panic handler](set_task_panic_handler).
+fn report_task_panic(panic: &TaskPanic) {
+    let handler_opt = TASK_PANIC_HANDLER.with(|h| h.borrow().clone());
+    match handler_opt {
+        Some(handler) => handler(panic),
+        None => log::error!("{}", panic),
+    }
 }
 
-/// Run a given future on the glib main loop until it becomes `Ready`.
+/// Run a given future on the glib main loop until it becomes `Ready`, returning a [`TaskHandle`]
+/// which can be used to abort it, or dropped to let it run detached.
 ///
 /// Because this is running on the glib main loop, it's especially imperative for the
 /// responsiveness of the user interface that the future never blocks but instead always pends.
-pub fn glib_run_future<F>(fut: F)
+///
+/// The future is polled once per wake, and never again once it's ready:
+///
+/// ```
+///    # use springsteel::glib_run_future;
+///    # use std::cell::Cell;
+///    # use std::future::poll_fn;
+///    # use std::rc::Rc;
+///    # use std::task::Poll;
+///    let polls = Rc::new(Cell::new(0));
+///    let polls_for_task = polls.clone();
+///    let task = glib_run_future(poll_fn(move |_| {
+///        polls_for_task.set(polls_for_task.get() + 1);
+///        Poll::Ready(())
+///    }));
+///
+///    let context = glib::MainContext::default();
+///    while context.iteration(false) {}
+///    assert!(task.is_finished());
+///    assert!(!context.pending());
+///    assert_eq!(polls.get(), 1);
+/// ```
+///
+/// The future may be woken from any thread, and is then resumed on the main loop, e.g. when
+/// awaiting the result of some work done on another thread:
+///
+/// ```
+///    # use springsteel::glib_future::glib_block_on_within;
+///    # use std::time::Duration;
+///    let (sender, receiver) = futures::channel::oneshot::channel();
+///    let worker = std::thread::spawn(move || sender.send(42).expect("send"));
+///    assert_eq!(glib_block_on_within(receiver, Duration::from_secs(5)), Ok(42));
+///    worker.join().expect("worker");
+/// ```
+///
+/// Wakers may be cloned, woken, and dropped in any combination. Any number of wakes during a poll
+/// lead to only one more poll, and waking a future which has completed does nothing:
+///
+/// ```
+///    # use springsteel::glib_run_future;
+///    # use std::cell::{Cell, RefCell};
+///    # use std::future::poll_fn;
+///    # use std::rc::Rc;
+///    # use std::task::{Poll, Waker};
+///    let polls = Rc::new(Cell::new(0));
+///    let stashed: Rc<RefCell<Option<Waker>>> = Rc::new(RefCell::new(None));
+///
+///    let polls_for_task = polls.clone();
+///    let stashed_for_task = stashed.clone();
+///    let task = glib_run_future(poll_fn(move |cx| {
+///        polls_for_task.set(polls_for_task.get() + 1);
+///        if polls_for_task.get() == 1 {
+///            let waker = cx.waker().clone();
+///            let other = waker.clone();
+///            waker.wake_by_ref();
+///            drop(waker);
+///            other.wake();
+///            Poll::Pending
+///        } else {
+///            *stashed_for_task.borrow_mut() = Some(cx.waker().clone());
+///            Poll::Ready(())
+///        }
+///    }));
+///
+///    let context = glib::MainContext::default();
+///    while context.iteration(false) {}
+///    assert_eq!(polls.get(), 2);
+///    assert!(task.is_finished());
+///
+///    stashed.borrow_mut().take().expect("stashed waker").wake();
+///    while context.iteration(false) {}
+///    assert_eq!(polls.get(), 2);
+/// ```
+#[track_caller]
+pub fn glib_run_future<F>(fut: F) -> TaskHandle
+where
+    F: Future<Output = ()> + Unpin + 'static,
+{
+    spawn_on(
+        &glib::MainContext::ref_thread_default(),
+        "glib_run_future",
+        Location::caller(),
+        fut,
+        true,
+    )
+}
+
+/// Run a given future on the glib main loop until it becomes `Ready`, like [`glib_run_future`],
+/// but without a handle to abort it by, for futures which end by themselves or live as long as
+/// the application does.
+///
+/// ```
+///    # use springsteel::glib_future::glib_run_future_detached;
+///    # use std::{cell::Cell, rc::Rc};
+///    let ran = Rc::new(Cell::new(false));
+///    let ran_for_task = ran.clone();
+///    glib_run_future_detached(Box::pin(async move { ran_for_task.set(true) }));
+///
+///    while glib::MainContext::default().iteration(false) {}
+///    assert!(ran.get());
+/// ```
+#[track_caller]
+pub fn glib_run_future_detached<F>(fut: F)
+where
+    F: Future<Output = ()> + Unpin + 'static,
+{
+    glib_run_future(fut).detach();
+}
+
+/// Run a given future on the given main context until it becomes `Ready`, like
+/// [`glib_run_future`] does on the thread default main context. Every step of the future is
+/// scheduled on that context, even when the future is woken from another thread.
+///
+/// The future is only ever polled by whichever thread iterates the context, so that must be this
+/// thread: this panics if the context is owned by another thread. If another thread later takes
+/// over the context, the future is never polled again rather than being polled there, and an
+/// error is logged. Unlike with [`glib_run_future`], the future isn't deferred until the
+/// [bound application](bind_to_application) has started up.
+///
+/// ```
+///    # use springsteel::glib_future::glib_run_future_on;
+///    # use std::cell::Cell;
+///    # use std::future::poll_fn;
+///    # use std::rc::Rc;
+///    # use std::task::Poll;
+///    let worker = glib::MainContext::new();
+///    let ran = Rc::new(Cell::new(false));
+///    let ran_for_task = ran.clone();
+///    glib_run_future_on(&worker, poll_fn(move |_| {
+///        ran_for_task.set(true);
+///        Poll::Ready(())
+///    }));
+///
+///    while glib::MainContext::default().iteration(false) {}
+///    assert!(!ran.get());
+///
+///    while worker.iteration(false) {}
+///    assert!(ran.get());
+/// ```
+#[track_caller]
+pub fn glib_run_future_on<F>(context: &glib::MainContext, fut: F) -> TaskHandle
+where
+    F: Future<Output = ()> + Unpin + 'static,
+{
+    spawn_on(context, "glib_run_future", Location::caller(), fut, false)
+}
+
+/// Run a given future on the glib main loop like [`glib_run_future`], giving the task a name which
+/// identifies it in the [live registry](crate::debug) and, with the `tracing` feature, in the log
+/// and [`active_tasks`].
+///
+/// ```
+///    # use futures::stream::StreamExt as _;
+///    # use springsteel::glib_future::glib_run_future_named;
+///    # use springsteel::ImpulseStream;
+///    # use std::future::ready;
+///    let refreshes = ImpulseStream::new();
+///    glib_run_future_named("refresh listing", refreshes.for_each(|()| ready(())));
+///
+///    # #[cfg(feature = "tracing")]
+///    # {
+///    let tasks = springsteel::glib_future::active_tasks();
+///    assert!(tasks.iter().any(|task| task.name == "refresh listing"));
+///    # }
+/// ```
+#[track_caller]
+pub fn glib_run_future_named<F>(name: &str, fut: F) -> TaskHandle
+where
+    F: Future<Output = ()> + Unpin + 'static,
+{
+    spawn_on(
+        &glib::MainContext::ref_thread_default(),
+        name,
+        Location::caller(),
+        fut,
+        true,
+    )
+}
+
+/// Run a given future on the glib main loop like [`glib_run_future`], aborting it when the given
+/// [`gio::Cancellable`] is cancelled, e.g. by a "Cancel" button which also cancels gio operations
+/// the future started:
+///
+/// ```
+///    # use gio::prelude::CancellableExt as _;
+///    # use springsteel::glib_future::glib_run_future_cancellable;
+///    # use springsteel::time::sleep;
+///    # use std::time::Duration;
+///    let cancellable = gio::Cancellable::new();
+///    let task = glib_run_future_cancellable(sleep(Duration::from_secs(60)), &cancellable);
+///
+///    while glib::MainContext::default().iteration(false) {}
+///    assert!(!task.is_finished());
+///
+///    cancellable.cancel();
+///    assert!(task.is_finished());
+/// ```
+///
+/// If the cancellable is already cancelled the future is dropped without being polled. The
+/// cancellable must be cancelled on the thread running the future.
+#[track_caller]
+pub fn glib_run_future_cancellable<F>(fut: F, cancellable: &gio::Cancellable) -> TaskHandle
+where
+    F: Future<Output = ()> + Unpin + 'static,
+{
+    let task = spawn_on(
+        &glib::MainContext::ref_thread_default(),
+        "glib_run_future_cancellable",
+        Location::caller(),
+        fut,
+        true,
+    );
+
+    let weak = Arc::downgrade(&task.0);
+    cancellable.connect_cancelled_local(move |_| {
+        if let Some(arc) = weak.upgrade() {
+            TaskHandle(arc, PhantomData).abort();
+        }
+    });
+    task
+}
+
+/// Implement [`glib_run_future`], [`glib_run_future_on`], [`glib_run_future_named`], and
+/// [`glib_run_future_cancellable`], deferring the task until the
+/// [bound application](bind_to_application) has started up if `deferrable`.
+fn spawn_on<F>(
+    context: &glib::MainContext,
+    name: &str,
+    location: &'static Location<'static>,
+    fut: F,
+    deferrable: bool,
+) -> TaskHandle
 where
     F: Future<Output = ()> + Unpin + 'static,
 {
-    glib_waker_schedule(&Arc::new(Mutex::new(GlibWaker {
-        fut: Box::new(fut),
-        pending_idle_opt: None,
+    if let Err(err) = context.acquire() {
+        panic!(
+            "main context to run future on is owned by another thread: {}",
+            err
+        );
+    }
+
+    let app_state = APP_STATE.with(Cell::get);
+    let shut_down = app_state == AppState::ShutDown;
+    if shut_down {
+        log::warn!(
+            "task {} spawned at {} after application shutdown, dropping it",
+            name,
+            location
+        );
+    }
+
+    let arc = Arc::new(GlibWaker {
+        queue: RunQueue::for_context(context),
+        location,
+        #[cfg(feature = "tracing")]
+        name: name.to_owned(),
+        #[cfg(feature = "tracing")]
+        spawned: Instant::now(),
+        thread: thread::current().id(),
+        queued: AtomicBool::new(false),
+        finished: AtomicBool::new(false),
+        aborted: AtomicBool::new(shut_down),
+        paused: AtomicBool::new(false),
+        woken_while_paused: AtomicBool::new(false),
+        fut: RefCell::new(if shut_down {
+            drop(fut);
+            None
+        } else {
+            Some(Box::new(fut))
+        }),
+        state: RefCell::new(TaskState {
+            live_opt: (!shut_down).then(|| register_live(LiveKind::Task, name, location)),
+            panic_opt: None,
+            cancellable_opt: None,
+            #[cfg(feature = "tracing")]
+            polls: 0,
+            #[cfg(feature = "tracing")]
+            last_polled_opt: None,
+        }),
+    });
+
+    if shut_down {
+        return TaskHandle(arc, PhantomData);
+    }
+
+    #[cfg(feature = "tracing")]
+    log::debug!("task {} ({}) spawned", name, location);
+
+    TASKS.with(|tasks| {
+        let mut tasks = tasks.borrow_mut();
+        tasks.retain(|task| task.strong_count() > 0);
+        tasks.push(Arc::downgrade(&arc));
+    });
+
+    if deferrable && app_state == AppState::Starting {
+        DEFERRED.with(|d| d.borrow_mut().push(arc.clone()));
+    } else {
+        glib_waker_schedule(&arc);
+    }
+    TaskHandle(arc, PhantomData)
+}
+
+/// Run a handler for every item of a stream on the glib main loop, until the stream ends. This is
+/// shorthand for `glib_run_future(stream.for_each(...))` with a handler which doesn't await:
+///
+/// ```
+///    # use springsteel::glib_future::glib_run_stream;
+///    # use springsteel::ImpulseStream;
+///    # use gtk::prelude::ButtonExt as _;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let clicks = ImpulseStream::new();
+///    let button = gtk::Button::with_label("0");
+///    button.connect_clicked(clicks.triggerer());
+///
+///    let mut count = 0;
+///    let button_for_clicks = button.clone();
+///    glib_run_stream(clicks, move |()| {
+///        count += 1;
+///        button_for_clicks.set_label(&count.to_string());
+///    });
+/// ```
+#[track_caller]
+pub fn glib_run_stream<S, F>(stream: S, mut handler: F) -> TaskHandle
+where
+    S: Stream + 'static,
+    F: FnMut(S::Item) + 'static,
+{
+    glib_run_future(Box::pin(stream.for_each(move |item| {
+        handler(item);
+        ready(())
     })))
 }
 
-/// Given a [`GlibWaker`] state pointer, make the [`RawWaker`] instance by untyping the pointer
-/// and supplying the vtable.
-fn glib_raw_waker(arc: Arc<Mutex<GlibWaker>>) -> RawWaker {
-    let raw_waker = RawWaker::new(Arc::into_raw(arc).cast(), &GLIB_WAKER_VTABLE);
-    raw_waker
-}
-
-/// Given a [`GlibWaker`] state pointer, make a [`Waker`] instance.
-fn glib_waker(arc: Arc<Mutex<GlibWaker>>) -> Waker {
-    // SAFETY: "The behavior of the returned Waker is undefined if the contract defined in
-    // RawWaker's and RawWakerVTable's documentation is not upheld."
-    //
-    // Individual obligations are discharged in each of the vtable functions, below.
-
-    // SAFETY: "Implements Clone, Send, and Sync; therefore, a waker may be invoked from any
-    // thread, including ones not in any way managed by the executor."
-    //
-    // The data is retained in an Arc<Mutex<..>> so that it is safely counted in a multithreaded
-    // context and access to the underlying future state and schedule is only updated
-    // single-threaded.
-    //
-    // The future state is also only ever permuted inside glib_waker_step, which should only ever
-    // be run on the glib main loop thread, meaning that all future effects are on that thread
-    // unless a future specifically spawns or causes to be spawned a separate thread. This makes it
-    // difficult to accidentally do GTK/GDK/GLIB things not on the main thread.
-
-    let waker = unsafe { Waker::from_raw(glib_raw_waker(arc)) };
-    waker
-}
-
-/// Implement [`RawWakerVTable`] `clone` function by increasing the strong reference count of the
-/// state pointer.
-unsafe fn glib_waker_clone(arc_ptr: *const ()) -> RawWaker {
-    // SAFETY: "the implementation of this function must retain all resources that are required for
-    // this additional instance"
-    //
-    // A strong count is present on entry, by contract with RawWaker, so we add one for the new
-    // RawWaker.
-
-    // SAFETY: "calling wake on the resulting RawWaker should result in a wakeup of the same
-    // task that would have been awoken by the original RawWaker"
-    //
-    // The resulting RawWaker is exactly the same as the new one, modulo type conversions, with one
-    // more strong reference.
-
-    Arc::increment_strong_count(arc_ptr);
-    let arc = Arc::from_raw(arc_ptr.cast::<Mutex<GlibWaker>>());
-    let raw_waker = glib_raw_waker(arc);
-    raw_waker
-}
-
-/// Make the closure that should be run during the idle callback. A closure-making separate
-/// function just to make it a top level notion.
-fn glib_waker_step(arc: Arc<Mutex<GlibWaker>>) -> impl Fn() -> Continue {
-    move || {
-        let mut inner = arc.lock().unwrap();
-        let waker = glib_waker(arc.clone());
-        let poll = Pin::new(&mut inner.fut).poll(&mut Context::from_waker(&waker));
-        let cont = matches!(poll, Poll::Ready(()));
-        if !cont {
-            inner.pending_idle_opt = None;
-        }
-        Continue(cont)
-    }
-}
-
-/// Ensure that the idle callback is scheduled, because either the waker was triggered indicating
-/// that there's work waiting to do, or from the initial step to get things as pending as possible.
-fn glib_waker_schedule(arc: &Arc<Mutex<GlibWaker>>) {
-    let mut inner = arc.lock().unwrap();
-    if inner.pending_idle_opt.is_some() {
-        return;
+/// Run an asynchronous handler for every item of a stream on the glib main loop, until the stream
+/// ends. Each item is handled only once the handler has finished with the previous one.
+#[track_caller]
+pub fn glib_run_stream_async<S, F, Fut>(stream: S, handler: F) -> TaskHandle
+where
+    S: Stream + 'static,
+    F: FnMut(S::Item) -> Fut + 'static,
+    Fut: Future<Output = ()> + 'static,
+{
+    glib_run_future(Box::pin(stream.for_each(handler)))
+}
+
+/// An error output by a fallible future run on the glib main loop, passed to the
+/// [task error handler](set_task_error_handler).
+#[derive(Clone, Debug)]
+pub struct TaskError {
+    /// The error, as displayed.
+    pub message: String,
+
+    /// Source location which spawned the future.
+    pub location: &'static Location<'static>,
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "future spawned at {} failed: {}",
+            self.location, self.message
+        )
+    }
+}
+
+thread_local! {
+    static TASK_ERROR_HANDLER: RefCell<Option<Rc<dyn Fn(&TaskError)>>> = RefCell::new(None);
+}
+
+/// Set the handler called on this thread with the error of a future run by
+/// [`glib_run_fallible_default`], replacing the default which logs the error with
+/// [`log::error!`], e.g. to route every error of the UI's futures to one info bar.
+pub fn set_task_error_handler(handler: impl Fn(&TaskError) + 'static) {
+    TASK_ERROR_HANDLER.with(|h| *h.borrow_mut() = Some(Rc::new(handler)));
+}
+
+/// Report an error to the [task error handler](set_task_error_handler).
+fn report_task_error(error: &TaskError) {
+    let handler_opt = TASK_ERROR_HANDLER.with(|h| h.borrow().clone());
+    match handler_opt {
+        Some(handler) => handler(error),
+        None => log::error!("{}", error),
+    }
+}
+
+/// How a future run by [`glib_run_fallible`] ended, if it has.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FallibleOutcome {
+    /// The future hasn't ended yet.
+    Running,
+
+    /// The future output `Ok`.
+    Ok,
+
+    /// The future output `Err`, which was passed to the error handler.
+    Err,
+
+    /// The future was aborted before it ended.
+    Aborted,
+
+    /// The future panicked while being polled.
+    Panicked,
+}
+
+/// Handle to a fallible future running on the glib main loop, returned by [`glib_run_fallible`].
+///
+/// Dropping the handle does not abort the future, it just carries on running detached.
+#[must_use = "dropping a FallibleHandle leaves its future running detached"]
+pub struct FallibleHandle {
+    task: TaskHandle,
+    outcome: Rc<Cell<FallibleOutcome>>,
+}
+
+impl FallibleHandle {
+    /// Abort the future. See [`TaskHandle::abort`].
+    pub fn abort(&self) {
+        self.task.abort()
+    }
+
+    /// Return how the future ended, if it has.
+    pub fn outcome(&self) -> FallibleOutcome {
+        match self.outcome.get() {
+            FallibleOutcome::Running if self.task.panic_message().is_some() => {
+                FallibleOutcome::Panicked
+            }
+            FallibleOutcome::Running if self.task.is_finished() => FallibleOutcome::Aborted,
+            outcome => outcome,
+        }
     }
 
-    inner.pending_idle_opt = Some(idle_add_local(glib_waker_step(arc.clone())));
-}
-
-/// Implement [`RawWakerVTable`] `wake` function by triggering a wake via
-/// [`glib_waker_schedule`], then dropping the waker.
-unsafe fn glib_waker_wake(arc_ptr: *const ()) {
-    // SAFETY: "the implementation of this function must release any resources that are associated
-    // with this RawWaker"
-    //
-    // We convert the raw pointer into an Arc, retaining the same strong count, then drop the Arc
-    // which decrements the count. We don't hold any other resources.
-    //
-    // glib_waker_schedule will increment the reference count so it ensures that the resources live
-    // until the idle callback.
-
-    let arc = Arc::from_raw(arc_ptr.cast::<Mutex<GlibWaker>>());
-    glib_waker_schedule(&arc);
-    drop(arc);
-}
-
-/// Implement [`RawWakerVTable`] `wake_by_ref` function by triggering a wake via
-/// [`glib_waker_schedule`].
-unsafe fn glib_waker_wake_by_ref(arc_ptr: *const ()) {
-    // SAFETY: "this function is similar to wake, but but not consume the provided data pointer"
-    //
-    // We convert the raw pointer into an Arc, retaining the same strong count, then increase it by
-    // one so that when the Arc is dropped and decreases the strong count, the overall refernce
-    // count remains the same.
-    //
-    // glib_waker_schedule will increment the reference count so it ensures that the resources live
-    // until the idle callback.
-
-    let arc = Arc::from_raw(arc_ptr.cast::<Mutex<GlibWaker>>());
-    Arc::increment_strong_count(arc_ptr);
-    glib_waker_schedule(&arc);
-}
-
-/// Implement [`RawWakerVTable`] `drop` function by decrementing the state strong reference count.
-unsafe fn glib_waker_drop(arc_ptr: *const ()) {
-    // SAFETY: "the implementation of this function must release any resources that are associated
-    // with this instance of a RawWaker"
-    //
-    // We convert the raw pointer into an Arc, retaining the same strong count, then drop the
-    // Arc which decrements the strong count.
-
-    let arc = Arc::from_raw(arc_ptr.cast::<Mutex<GlibWaker>>());
-    drop(arc);
-}
-
-/// The [`RawWakerVTable`] that implements a [`RawWaker`]/[`Waker`] which schedules on the glib
-/// main loop via [`idle_add_local`].
-static GLIB_WAKER_VTABLE: RawWakerVTable = RawWakerVTable::new(
-    glib_waker_clone,
-    glib_waker_wake,
-    glib_waker_wake_by_ref,
-    glib_waker_drop,
-);
+    /// Return the handle of the underlying task.
+    pub fn task(&self) -> &TaskHandle {
+        &self.task
+    }
+}
+
+/// Run a fallible future on the glib main loop until it completes, calling `on_err` with its
+/// error if it fails rather than silently dropping it, and returning a [`FallibleHandle`] which
+/// reports how it ended:
+///
+/// ```
+///    # use springsteel::glib_future::{glib_run_fallible, FallibleOutcome};
+///    # use std::cell::RefCell;
+///    # use std::rc::Rc;
+///    let shown = Rc::new(RefCell::new(None));
+///    let shown_for_err = shown.clone();
+///    let task = glib_run_fallible(
+///        async { "forty-two".parse::<u32>().map(drop) },
+///        move |err| *shown_for_err.borrow_mut() = Some(err.to_string()),
+///    );
+///
+///    while glib::MainContext::default().iteration(false) {}
+///    assert_eq!(task.outcome(), FallibleOutcome::Err);
+///    assert!(shown.borrow().is_some());
+/// ```
+#[track_caller]
+pub fn glib_run_fallible<E, F, H>(fut: F, on_err: H) -> FallibleHandle
+where
+    E: fmt::Display + 'static,
+    F: Future<Output = Result<(), E>> + 'static,
+    H: FnOnce(E) + 'static,
+{
+    let outcome = Rc::new(Cell::new(FallibleOutcome::Running));
+    let outcome_for_task = outcome.clone();
+    let task = glib_run_future(Box::pin(async move {
+        match fut.await {
+            Ok(()) => outcome_for_task.set(FallibleOutcome::Ok),
+            Err(err) => {
+                outcome_for_task.set(FallibleOutcome::Err);
+                on_err(err);
+            }
+        }
+    }));
+    FallibleHandle { task, outcome }
+}
+
+/// Run a fallible future on the glib main loop like [`glib_run_fallible`], passing its error to
+/// the [task error handler](set_task_error_handler) if it fails.
+#[track_caller]
+pub fn glib_run_fallible_default<E, F>(fut: F) -> FallibleHandle
+where
+    E: fmt::Display + 'static,
+    F: Future<Output = Result<(), E>> + 'static,
+{
+    let location = Location::caller();
+    glib_run_fallible(fut, move |err| {
+        report_task_error(&TaskError {
+            message: err.to_string(),
+            location,
+        })
+    })
+}
+
+/// Run every given future on the glib main loop, returning a [`TaskHandle`] which finishes once
+/// they all have, and whose [abort](TaskHandle::abort) aborts every one still running:
+///
+/// ```
+///    # use springsteel::glib_future::glib_run_all;
+///    # use springsteel::time::sleep;
+///    # use std::time::Duration;
+///    let all = glib_run_all(vec![sleep(Duration::ZERO), sleep(Duration::from_secs(60))]);
+///
+///    while glib::MainContext::default().iteration(false) {}
+///    assert!(!all.is_finished());
+///
+///    all.abort();
+///    assert!(all.is_finished());
+/// ```
+///
+/// Each future is spawned as a child of a [`glib_scope`](crate::glib_scope::glib_scope), so if
+/// one panics the rest are aborted.
+#[track_caller]
+pub fn glib_run_all<I>(futs: I) -> TaskHandle
+where
+    I: IntoIterator,
+    I::IntoIter: 'static,
+    I::Item: Future<Output = ()> + 'static,
+{
+    let futs = futs.into_iter();
+    glib_run_future(Box::pin(async move {
+        let _ = crate::glib_scope::glib_scope(move |scope| async move {
+            for fut in futs {
+                scope.spawn(fut);
+            }
+        })
+        .await;
+    }))
+}
+
+/// Run every given fallible future on the glib main loop like [`glib_run_all`], except that as
+/// soon as one fails its error is passed to the [task error handler](set_task_error_handler) and
+/// the rest are aborted, along with the returned [`TaskHandle`]:
+///
+/// ```
+///    # use springsteel::glib_future::{glib_try_run_all, set_task_error_handler};
+///    # use springsteel::time::sleep;
+///    # use std::cell::Cell;
+///    # use std::rc::Rc;
+///    # use std::time::Duration;
+///    let errors = Rc::new(Cell::new(0));
+///    let errors_for_handler = errors.clone();
+///    set_task_error_handler(move |_| errors_for_handler.set(errors_for_handler.get() + 1));
+///
+///    let slow_finished = Rc::new(Cell::new(false));
+///    let slow_finished_for_task = slow_finished.clone();
+///    let all = glib_try_run_all(vec![
+///        Box::pin(async move {
+///            sleep(Duration::from_secs(60)).await;
+///            slow_finished_for_task.set(true);
+///            Ok(())
+///        }) as std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), String>>>>,
+///        Box::pin(async { Err("no thumbnail".to_owned()) }),
+///    ]);
+///
+///    while glib::MainContext::default().iteration(false) {}
+///    assert_eq!(errors.get(), 1);
+///    assert!(all.is_finished());
+///    assert!(!slow_finished.get());
+/// ```
+#[track_caller]
+pub fn glib_try_run_all<I, E>(futs: I) -> TaskHandle
+where
+    I: IntoIterator,
+    I::IntoIter: 'static,
+    I::Item: Future<Output = Result<(), E>> + 'static,
+    E: fmt::Display + 'static,
+{
+    let location = Location::caller();
+    let futs = futs.into_iter();
+
+    // The children abort the whole lot through this once it's spawned.
+    let all_weak: Rc<RefCell<Weak<GlibWaker>>> = Rc::new(RefCell::new(Weak::new()));
+    let all_weak_for_task = all_weak.clone();
+    let all = glib_run_future(Box::pin(async move {
+        let _ = crate::glib_scope::glib_scope(move |scope| async move {
+            for fut in futs {
+                let all_weak = all_weak_for_task.clone();
+                scope.spawn(async move {
+                    if let Err(err) = fut.await {
+                        report_task_error(&TaskError {
+                            message: err.to_string(),
+                            location,
+                        });
+                        let all_opt = all_weak.borrow().upgrade();
+                        if let Some(all) = all_opt {
+                            TaskHandle(all, PhantomData).abort();
+                        }
+                    }
+                });
+            }
+        })
+        .await;
+    }));
+    *all_weak.borrow_mut() = Arc::downgrade(&all.0);
+    all
+}
+
+/// A [`Future`] which pends once to let the main loop run, made by [`yield_now`].
+pub struct YieldNow {
+    yielded: bool,
+}
+
+/// Make a [`Future`] which pends once before completing, waking its task from a separate idle
+/// callback so that the main loop gets to handle input, redraw, and run other idle callbacks in
+/// between, rather than the task being polled again straight away from the same
+/// [run queue](set_poll_budget) dispatch. Awaiting it every so often keeps a long computation in
+/// a future from freezing the UI; see also [`chunked_for_each`].
+///
+/// ```
+///    # use springsteel::glib_future::yield_now;
+///    # use springsteel::glib_run_future;
+///    # use std::cell::RefCell;
+///    # use std::rc::Rc;
+///    let log = Rc::new(RefCell::new(Vec::new()));
+///
+///    let log_for_task = log.clone();
+///    let task = glib_run_future(Box::pin(async move {
+///        for step in 0..3 {
+///            log_for_task.borrow_mut().push(format!("step {}", step));
+///            yield_now().await;
+///        }
+///    }));
+///
+///    let log_for_idle = log.clone();
+///    glib::idle_add_local_once(move || log_for_idle.borrow_mut().push("other".to_owned()));
+///
+///    let context = glib::MainContext::default();
+///    while !task.is_finished() {
+///        context.iteration(false);
+///    }
+///    assert_eq!(*log.borrow(), vec!["step 0", "other", "step 1", "step 2"]);
+/// ```
+///
+/// The idle callback goes on the main context of the task, so this works the same in a future run
+/// with [`glib_run_future_on`] on a context other than the thread default:
+///
+/// ```
+///    # use springsteel::glib_future::{glib_run_future_on, yield_now};
+///    let context = glib::MainContext::new();
+///    let task = glib_run_future_on(&context, Box::pin(yield_now()));
+///
+///    while context.iteration(false) {}
+///    assert!(task.is_finished());
+/// ```
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+
+        self.yielded = true;
+        let mut waker_opt = Some(cx.waker().clone());
+        idle_source_new(None, PRIORITY_DEFAULT_IDLE, move || {
+            if let Some(waker) = waker_opt.take() {
+                waker.wake();
+            }
+            Continue(false)
+        })
+        .attach(Some(&task_context()));
+        Poll::Pending
+    }
+}
+
+/// Call `f` with every item of `iter`, [yielding](yield_now) to the main loop after every
+/// `chunk_size` items, so that a large batch of synchronous work done by a future, e.g. filling
+/// a list model, doesn't freeze the UI.
+///
+/// ```
+///    # use springsteel::glib_future::{chunked_for_each, glib_block_on};
+///    let rows = glib_block_on(async {
+///        let mut rows = Vec::new();
+///        chunked_for_each(0..1000, 100, |n| rows.push(n.to_string())).await;
+///        rows
+///    });
+///    assert_eq!(rows.len(), 1000);
+/// ```
+///
+/// Panics if `chunk_size` is zero.
+pub async fn chunked_for_each<I, F>(iter: I, chunk_size: usize, mut f: F)
+where
+    I: IntoIterator,
+    F: FnMut(I::Item),
+{
+    assert!(
+        chunk_size > 0,
+        "chunked_for_each chunk_size must be positive"
+    );
+    for (index, item) in iter.into_iter().enumerate() {
+        if index > 0 && index % chunk_size == 0 {
+            yield_now().await;
+        }
+        f(item);
+    }
+}
+
+/// A [`Future`] which completes once an idle callback has run, made by [`next_idle`].
+pub struct NextIdle {
+    /// Whether the idle callback has run, once it's added.
+    fired_opt: Option<Arc<AtomicBool>>,
+}
+
+/// Make a [`Future`] which completes once the main loop next dispatches idle callbacks, i.e. after
+/// the current signal emission and any pending higher priority work such as layout and drawing.
+/// This suits GTK operations which mustn't happen within some handler, like resizing during
+/// `size-allocate` or unparenting during `dispose`.
+///
+/// Unlike [`yield_now`], which completes the next time it's polled for whatever reason, e.g. a
+/// sibling in a `select` being woken during the same dispatch, this only completes once its own
+/// idle callback has run.
+///
+/// ```
+///    # use springsteel::glib_future::{glib_spawn, next_idle};
+///    # use std::cell::Cell;
+///    # use std::rc::Rc;
+///    let resumed = Rc::new(Cell::new(false));
+///    let resumed_for_task = resumed.clone();
+///    glib_spawn(async move {
+///        next_idle().await;
+///        resumed_for_task.set(true);
+///    });
+///
+///    while glib::MainContext::default().iteration(false) {}
+///    assert!(resumed.get());
+/// ```
+///
+/// The idle callback goes on the main context of the task, as for [`yield_now`]:
+///
+/// ```
+///    # use springsteel::glib_future::{glib_run_future_on, next_idle};
+///    let context = glib::MainContext::new();
+///    let task = glib_run_future_on(&context, Box::pin(next_idle()));
+///
+///    while context.iteration(false) {}
+///    assert!(task.is_finished());
+/// ```
+pub fn next_idle() -> NextIdle {
+    NextIdle { fired_opt: None }
+}
+
+impl Future for NextIdle {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        match &self.fired_opt {
+            Some(fired) if fired.load(Ordering::SeqCst) => Poll::Ready(()),
+            Some(_) => Poll::Pending,
+            None => {
+                let fired = Arc::new(AtomicBool::new(false));
+                let fired_for_idle = fired.clone();
+                let waker = cx.waker().clone();
+                idle_source_new(None, PRIORITY_DEFAULT_IDLE, move || {
+                    fired_for_idle.store(true, Ordering::SeqCst);
+                    waker.wake_by_ref();
+                    Continue(false)
+                })
+                .attach(Some(&task_context()));
+                self.fired_opt = Some(fired);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Call `f` once the main loop next dispatches idle callbacks, like awaiting [`next_idle`] in a
+/// future run by [`glib_run_future`], returning the [`TaskHandle`] of that future so the call can
+/// be cancelled by aborting it. Being run by the executor, a panic in `f` is isolated and reported
+/// like that of any other future, and `f` isn't called after the
+/// [bound application](bind_to_application) shuts down.
+///
+/// ```
+///    # use springsteel::glib_future::run_at_idle;
+///    # use std::cell::Cell;
+///    # use std::rc::Rc;
+///    let called = Rc::new(Cell::new(false));
+///    let called_for_idle = called.clone();
+///    run_at_idle(move || called_for_idle.set(true));
+///    assert!(!called.get());
+///
+///    while glib::MainContext::default().iteration(false) {}
+///    assert!(called.get());
+/// ```
+#[track_caller]
+pub fn run_at_idle(f: impl FnOnce() + 'static) -> TaskHandle {
+    glib_run_future(Box::pin(async move {
+        next_idle().await;
+        f();
+    }))
+}
+
+/// Run a future to completion on the thread default main context, iterating the context until
+/// it completes, and return its output. Intended for tests and startup code only.
+///
+/// This must never be called from within the main loop, e.g. from a signal handler or another
+/// future, as iterating the context re-entrantly would run other handlers in the middle of this
+/// one; it panics if the context is already owned, as it is while the main loop is running. It
+/// also panics if the future panics.
+///
+/// ```
+///    # use springsteel::glib_future::glib_block_on;
+///    # use springsteel::time::sleep;
+///    # use std::time::Duration;
+///    let answer = glib_block_on(async {
+///        sleep(Duration::from_millis(10)).await;
+///        42
+///    });
+///    assert_eq!(answer, 42);
+/// ```
+#[track_caller]
+pub fn glib_block_on<F>(fut: F) -> F::Output
+where
+    F: Future + 'static,
+{
+    block_on_within(fut, None)
+}
+
+/// Like [`glib_block_on`], but panic if the future doesn't complete within `deadline`.
+#[track_caller]
+pub fn glib_block_on_within<F>(fut: F, deadline: Duration) -> F::Output
+where
+    F: Future + 'static,
+{
+    block_on_within(fut, Some(deadline))
+}
+
+/// Implement [`glib_block_on`] and [`glib_block_on_within`].
+#[track_caller]
+fn block_on_within<F>(fut: F, deadline_opt: Option<Duration>) -> F::Output
+where
+    F: Future + 'static,
+{
+    let context = glib::MainContext::ref_thread_default();
+    if context.is_owner() {
+        panic!("glib_block_on called while the main context is owned, e.g. from the main loop");
+    }
+
+    let output = Rc::new(RefCell::new(None));
+    let output_for_task = output.clone();
+    let task = spawn_on(
+        &context,
+        "glib_block_on",
+        Location::caller(),
+        Box::pin(async move {
+            let value = fut.await;
+            *output_for_task.borrow_mut() = Some(value);
+        }),
+        false,
+    );
+
+    let started = Instant::now();
+    if let Some(deadline) = deadline_opt {
+        // Make sure blocking iteration wakes up in time to notice the deadline has passed.
+        timeout_source_new(deadline, None, PRIORITY_DEFAULT, || Continue(false))
+            .attach(Some(&context));
+    }
+
+    loop {
+        if let Some(value) = output.borrow_mut().take() {
+            return value;
+        }
+        if let Some(message) = task.panic_message() {
+            panic!("future run by glib_block_on panicked: {}", message);
+        }
+        if let Some(deadline) = deadline_opt {
+            if started.elapsed() >= deadline {
+                task.abort();
+                panic!(
+                    "future run by glib_block_on didn't complete within {:?}",
+                    deadline
+                );
+            }
+        }
+        context.iteration(true);
+    }
+}
+
+/// Error output by a [`GlibJoinHandle`] whose task didn't complete.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JoinError {
+    /// The task was aborted before it completed.
+    Aborted,
+
+    /// The future panicked while being polled, with the given message.
+    Panicked(String),
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Aborted => write!(f, "task was aborted"),
+            Self::Panicked(message) => write!(f, "task panicked: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for JoinError {}
+
+/// State shared between a task spawned by [`glib_spawn`] and its [`GlibJoinHandle`].
+struct JoinState<T> {
+    /// The output of the task, once it has completed and until the handle takes it.
+    output_opt: Option<T>,
+
+    /// Whether the task's future was dropped, either by completing or by being aborted.
+    done: bool,
+
+    /// Waker of the task awaiting the [`GlibJoinHandle`], woken once the task is done.
+    waker_opt: Option<Waker>,
+}
+
+/// Held by the future of a task spawned by [`glib_spawn`] to deliver its output, which wakes the
+/// [`GlibJoinHandle`] when dropped so that it finds out if the task was aborted.
+struct JoinSender<T>(Rc<RefCell<JoinState<T>>>);
+
+impl<T> Drop for JoinSender<T> {
+    fn drop(&mut self) {
+        let waker_opt = {
+            let mut state = self.0.borrow_mut();
+            state.done = true;
+            state.waker_opt.take()
+        };
+        if let Some(w) = waker_opt {
+            w.wake();
+        }
+    }
+}
+
+/// Handle to a future spawned on the glib main loop by [`glib_spawn`], which is itself a future
+/// of the spawned future's output.
+///
+/// The handle only shares state with the spawned task through a reference counted cell, not the
+/// executor's internal locks, so it can be awaited by another task on the same main loop.
+/// Dropping the handle does not abort the task.
+pub struct GlibJoinHandle<T> {
+    state: Rc<RefCell<JoinState<T>>>,
+    task: TaskHandle,
+}
+
+impl<T> GlibJoinHandle<T> {
+    /// Abort the task, after which awaiting the handle outputs [`JoinError::Aborted`]. See
+    /// [`TaskHandle::abort`].
+    pub fn abort(&self) {
+        self.task.abort()
+    }
+
+    /// Return whether the task has completed or been aborted.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+/// A [`GlibJoinHandle`] can be unpinned as its state is reference counted pointers.
+impl<T> Unpin for GlibJoinHandle<T> {}
+
+impl<T> Future for GlibJoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T, JoinError>> {
+        let mut state = self.state.borrow_mut();
+        if let Some(output) = state.output_opt.take() {
+            Poll::Ready(Ok(output))
+        } else if state.done {
+            Poll::Ready(Err(match self.task.panic_message() {
+                Some(message) => JoinError::Panicked(message),
+                None => JoinError::Aborted,
+            }))
+        } else {
+            state.waker_opt = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// Run a given future on the glib main loop until it completes, returning a [`GlibJoinHandle`]
+/// which can be awaited for its output, e.g. by another task:
+///
+/// ```
+///    # use springsteel::glib_future::glib_spawn;
+///    # use std::cell::Cell;
+///    # use std::rc::Rc;
+///    let answer = glib_spawn(async { 6 * 7 });
+///
+///    let seen = Rc::new(Cell::new(None));
+///    let seen_for_task = seen.clone();
+///    glib_spawn(async move { seen_for_task.set(answer.await.ok()) });
+///
+///    while glib::MainContext::default().iteration(false) {}
+///    assert_eq!(seen.get(), Some(42));
+/// ```
+///
+/// Unlike [`glib_run_future`], the future need not be [`Unpin`] nor output `()`.
+#[track_caller]
+pub fn glib_spawn<T, F>(fut: F) -> GlibJoinHandle<T>
+where
+    T: 'static,
+    F: Future<Output = T> + 'static,
+{
+    let state = Rc::new(RefCell::new(JoinState {
+        output_opt: None,
+        done: false,
+        waker_opt: None,
+    }));
+
+    let sender = JoinSender(state.clone());
+    let task = glib_run_future(Box::pin(async move {
+        let output = fut.await;
+        sender.0.borrow_mut().output_opt = Some(output);
+        drop(sender);
+    }));
+
+    GlibJoinHandle { state, task }
+}
+
+/// Handle to a future spawned on the glib main loop by [`glib_spawn_remote`], which is itself a
+/// [`Send`] future of the spawned future's output so it can be awaited on another executor.
+///
+/// Unlike [`GlibJoinHandle`], dropping the handle aborts the task, wherever it's dropped.
+pub struct RemoteJoinHandle<T>(oneshot::Receiver<Result<T, JoinError>>);
+
+impl<T> Future for RemoteJoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T, JoinError>> {
+        match Pin::new(&mut self.0).poll(cx) {
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            // The task forwarding the output was itself aborted, e.g. along with every other task.
+            Poll::Ready(Err(oneshot::Canceled)) => Poll::Ready(Err(JoinError::Aborted)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Run a given future on the glib main loop until it completes, like [`glib_spawn`], returning a
+/// [`RemoteJoinHandle`] which can be sent to and awaited on another thread, e.g. by a tokio task.
+/// The future itself needn't be [`Send`], only its output.
+///
+/// Dropping the handle aborts the future: the main loop is woken and the future dropped there.
+///
+/// ```
+///    # use springsteel::glib_future::glib_spawn_remote;
+///    let answer = glib_spawn_remote(async { 6 * 7 });
+///    let waiter = std::thread::spawn(move || futures::executor::block_on(answer));
+///
+///    let context = glib::MainContext::default();
+///    while !waiter.is_finished() {
+///        context.iteration(false);
+///    }
+///    assert_eq!(waiter.join().expect("waiter"), Ok(42));
+/// ```
+#[track_caller]
+pub fn glib_spawn_remote<T, F>(fut: F) -> RemoteJoinHandle<T>
+where
+    T: Send + 'static,
+    F: Future<Output = T> + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    let mut join = glib_spawn(fut);
+    glib_run_future_detached(Box::pin(async move {
+        let mut sender = sender;
+        let result_opt = match select(&mut join, sender.cancellation()).await {
+            Either::Left((result, _)) => Some(result),
+            Either::Right(_) => None,
+        };
+        match result_opt {
+            Some(result) => {
+                let _ = sender.send(result);
+            }
+            // The handle was dropped, so nobody wants the output.
+            None => join.abort(),
+        }
+    }));
+    RemoteJoinHandle(receiver)
+}
+
+/// Run a handler on the glib main loop for every item of a stream fed from another thread, e.g.
+/// the receiver of a channel whose sender lives in a tokio task, until the stream ends.
+///
+/// The stream is polled on the main loop and woken from whichever thread feeds it, so the handler
+/// can update widgets directly:
+///
+/// ```
+///    # use springsteel::glib_future::forward_to_glib;
+///    # use std::cell::RefCell;
+///    # use std::rc::Rc;
+///    let (sender, receiver) = futures::channel::mpsc::unbounded();
+///    let received = Rc::new(RefCell::new(Vec::new()));
+///    let received_for_task = received.clone();
+///    let task = forward_to_glib(receiver, move |n| received_for_task.borrow_mut().push(n));
+///
+///    let feeder = std::thread::spawn(move || {
+///        for n in 0..3 {
+///            sender.unbounded_send(n).expect("send");
+///        }
+///    });
+///
+///    let context = glib::MainContext::default();
+///    while !task.is_finished() {
+///        context.iteration(true);
+///    }
+///    feeder.join().expect("feeder");
+///    assert_eq!(*received.borrow(), vec![0, 1, 2]);
+/// ```
+#[track_caller]
+pub fn forward_to_glib<S, F>(stream: S, handler: F) -> TaskHandle
+where
+    S: Stream + Send + 'static,
+    F: FnMut(S::Item) + 'static,
+{
+    glib_run_stream(stream, handler)
+}
+
+/// Run one step of the future, when its [`RunQueue`] is dispatched.
+fn glib_waker_step(arc: &Arc<GlibWaker>) {
+    if !arc.on_spawning_thread() {
+        // The main context was acquired by another thread since the future was spawned, e.g. by a
+        // main loop run there. The future isn't Send so it can't be polled here; leave the task
+        // queued so further wakes don't keep trying, stranding it rather than panicking inside
+        // the dispatch.
+        log::error!(
+            "future spawned at {} not polled as its main context is now run by another thread",
+            arc.location
+        );
+        return;
+    }
+
+    let mut fut_opt = arc.fut.borrow_mut();
+    let fut = match fut_opt.as_mut() {
+        Some(fut) => fut,
+        None => return,
+    };
+
+    // The task is no longer queued once it's running, so a wake during the poll queues it again.
+    arc.queued.store(false, Ordering::SeqCst);
+
+    // The task may have been paused after it was queued, in which case it's polled on resume.
+    if arc.paused.load(Ordering::SeqCst) {
+        arc.woken_while_paused.store(true, Ordering::SeqCst);
+        return;
+    }
+
+    let waker = waker_ref(arc);
+    let warn_threshold_opt = POLL_WARN_THRESHOLD.with(Cell::get);
+    let timed = cfg!(any(feature = "stats", feature = "tracing")) || warn_threshold_opt.is_some();
+    let started_opt = timed.then(Instant::now);
+    let poll_result = catch_unwind(AssertUnwindSafe(|| {
+        Pin::new(fut).poll(&mut Context::from_waker(&waker))
+    }));
+    let poll_duration = started_opt.map_or(Duration::ZERO, |started| started.elapsed());
+
+    #[cfg(feature = "stats")]
+    {
+        STATS_POLLS.fetch_add(1, Ordering::Relaxed);
+        STATS_LONGEST_POLL_NANOS.fetch_max(poll_duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    let mut state = arc.state.borrow_mut();
+    if let Some(threshold) = warn_threshold_opt {
+        if poll_duration > threshold {
+            warn_slow_poll(arc, &state, poll_duration);
+        }
+    }
+    #[cfg(feature = "tracing")]
+    {
+        state.polls += 1;
+        state.last_polled_opt = Some(Instant::now());
+        log::debug!(
+            "task {} ({}) polled in {:?}: {}",
+            arc.name,
+            arc.location,
+            poll_duration,
+            match &poll_result {
+                Ok(Poll::Ready(())) => "completed",
+                Ok(Poll::Pending) => "pending",
+                Err(_) => "panicked",
+            }
+        );
+    }
+    let (done, panic_opt) = match poll_result {
+        Ok(poll) => (poll.is_ready(), None),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|m| m.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "Box<dyn Any>".to_owned());
+            state.panic_opt = Some(message.clone());
+            (true, Some(message))
+        }
+    };
+
+    if arc.aborted.load(Ordering::SeqCst) || done {
+        // Aborted from within the poll, completed, or panicked, so drop the future now that it's
+        // no longer in use. It must never be polled again.
+        let live_opt = if done {
+            arc.finished.store(true, Ordering::SeqCst);
+            state.live_opt.take()
+        } else {
+            None
+        };
+        let done_fut_opt = fut_opt.take();
+        drop(state);
+        drop(fut_opt);
+        drop(live_opt);
+        drop(done_fut_opt);
+    } else {
+        drop(state);
+        drop(fut_opt);
+    }
+
+    if let Some(message) = panic_opt {
+        report_task_panic(&TaskPanic {
+            message,
+            location: arc.location,
+        });
+    }
+}
+
+thread_local! {
+    static POLL_WARN_THRESHOLD: Cell<Option<Duration>> = Cell::new(None);
+}
+
+/// Log a warning via the `log` crate whenever a single poll of a future on this thread takes
+/// longer than the given threshold, or stop warning if `None`, which is the default.
+///
+/// Futures on the main loop must never block, as nothing else happens on the main loop while
+/// they're being polled, but nothing stops them from e.g. doing synchronous file IO in a stream
+/// handler. The warning names the task and where it was spawned, which with the `backtraces`
+/// feature includes the full backtrace. Each poll is timed while this is enabled.
+///
+/// ```
+///    # use springsteel::glib_future::set_poll_warn_threshold;
+///    # use std::time::Duration;
+///    if cfg!(debug_assertions) {
+///        set_poll_warn_threshold(Some(Duration::from_millis(10)));
+///    }
+/// ```
+pub fn set_poll_warn_threshold(threshold_opt: Option<Duration>) {
+    POLL_WARN_THRESHOLD.with(|t| t.set(threshold_opt));
+}
+
+/// Warn that a poll of the given task blocked the main loop for `duration`.
+fn warn_slow_poll(arc: &GlibWaker, state: &TaskState, duration: Duration) {
+    match state.live_opt.as_ref().and_then(live_entry) {
+        Some(entry) => log::warn!("poll blocked the main loop for {:?}: {}", duration, entry),
+        None => log::warn!(
+            "poll blocked the main loop for {:?}: future spawned at {}",
+            duration,
+            arc.location
+        ),
+    }
+}
+
+/// Ensure that the task is queued to be polled, because either the waker was triggered indicating
+/// that there's work waiting to do, or from the initial step to get things as pending as possible.
+///
+/// This may be called from any thread, e.g. by a channel sender on a worker thread. Attaching a
+/// source to a context is thread-safe and wakes the context up if it's blocked waiting for events,
+/// so the step always runs on the thread iterating the future's context whichever thread woke it.
+fn glib_waker_schedule(arc: &Arc<GlibWaker>) {
+    #[cfg(feature = "stats")]
+    STATS_WAKES.fetch_add(1, Ordering::Relaxed);
+
+    if arc.is_done() {
+        return;
+    }
+
+    if arc.paused.load(Ordering::SeqCst) {
+        arc.woken_while_paused.store(true, Ordering::SeqCst);
+        // The task may have been resumed on another thread before the wake was remembered, in
+        // which case nothing else will schedule it.
+        if arc.paused.load(Ordering::SeqCst)
+            || !arc.woken_while_paused.swap(false, Ordering::SeqCst)
+        {
+            return;
+        }
+    }
+
+    if arc.queued.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    #[cfg(feature = "tracing")]
+    log::debug!("task {} ({}) woken", arc.name, arc.location);
+
+    arc.queue.push(arc.clone());
+}
+
+/// How long a [`RunQueue`] polls tasks for by default before yielding back to the main loop.
+pub const DEFAULT_POLL_BUDGET: Duration = Duration::from_millis(2);
+
+thread_local! {
+    static POLL_BUDGET: Cell<Duration> = Cell::new(DEFAULT_POLL_BUDGET);
+
+    /// The run queue of each main context with futures spawned on it from this thread.
+    static RUN_QUEUES: RefCell<Vec<Weak<RunQueue>>> = RefCell::new(Vec::new());
+
+    /// The context whose run queue is being dispatched on this thread, if any, for futures which
+    /// add sources to the context their task runs on.
+    static DISPATCHING: RefCell<Option<glib::MainContext>> = RefCell::new(None);
+}
+
+/// Return the main context of the task being polled, or the thread default main context if no
+/// task is being polled.
+fn task_context() -> glib::MainContext {
+    DISPATCHING
+        .with(|d| d.borrow().clone())
+        .unwrap_or_else(glib::MainContext::ref_thread_default)
+}
+
+/// Guard which records the context being dispatched for [`task_context`] while it's alive,
+/// restoring whichever was recorded before when dropped, as dispatches can nest.
+struct Dispatching(Option<glib::MainContext>);
+
+impl Dispatching {
+    fn enter(context: &glib::MainContext) -> Self {
+        Self(DISPATCHING.with(|d| d.replace(Some(context.clone()))))
+    }
+}
+
+impl Drop for Dispatching {
+    fn drop(&mut self) {
+        DISPATCHING.with(|d| *d.borrow_mut() = self.0.take());
+    }
+}
+
+/// Set how long the executor keeps polling woken futures on this thread before yielding back to
+/// the main loop, [`DEFAULT_POLL_BUDGET`] by default.
+///
+/// Futures woken on the same main context share a queue serviced by a single idle callback, which
+/// polls them one after another, including any woken by those polls, until the queue is empty or
+/// the budget is spent. So a chain of futures waking each other settles in one main loop
+/// iteration rather than one per future, while a long chain still lets GTK draw frames and handle
+/// input in between. A larger budget settles more work at once at the expense of responsiveness.
+///
+/// ```
+///    # use springsteel::glib_future::{set_poll_budget, DEFAULT_POLL_BUDGET};
+///    # use springsteel::glib_run_stream;
+///    # use springsteel::ValueStream;
+///    # use std::cell::Cell;
+///    # use std::rc::Rc;
+///    set_poll_budget(DEFAULT_POLL_BUDGET);
+///
+///    let stages: Vec<ValueStream<u32>> = (0..=100).map(|_| ValueStream::new()).collect();
+///    for window in stages.windows(2) {
+///        let next = window[1].clone();
+///        glib_run_stream(window[0].clone(), move |v| next.push(v + 1));
+///    }
+///
+///    let output = Rc::new(Cell::new(None));
+///    let output_for_task = output.clone();
+///    glib_run_stream(stages[100].clone(), move |v| output_for_task.set(Some(v)));
+///
+///    let context = glib::MainContext::default();
+///    while context.iteration(false) {}
+///
+///    stages[0].push(0);
+///    let mut iterations = 0;
+///    while context.iteration(false) {
+///        iterations += 1;
+///    }
+///    assert_eq!(output.get(), Some(100));
+///    assert!(iterations < 10, "settled after {} iterations", iterations);
+/// ```
+pub fn set_poll_budget(budget: Duration) {
+    POLL_BUDGET.with(|b| b.set(budget));
+}
+
+/// Queue of the tasks on some main context which have been woken and are waiting to be polled,
+/// shared by all the tasks spawned on that context from one thread and serviced by a single idle
+/// callback.
+///
+/// Nearly every wake happens on the thread which spawned the task, so those go straight into a
+/// `RefCell` without locking. Only wakes from other threads take the lock, handing their tasks
+/// over to be moved into the local queue when it's next dispatched.
+struct RunQueue {
+    context: glib::MainContext,
+
+    /// The thread which spawned the tasks, and so the only one which may touch `local`.
+    thread: ThreadId,
+
+    /// Woken tasks in the order they were woken, only used on the spawning thread.
+    local: RefCell<VecDeque<Arc<GlibWaker>>>,
+
+    /// Tasks woken on other threads, waiting to be moved into `local`.
+    remote: Mutex<Vec<Arc<GlibWaker>>>,
+
+    /// Whether `remote` may have tasks in it, so dispatch only takes the lock when it does.
+    remote_pending: AtomicBool,
+
+    /// Whether the idle callback servicing the queue is attached.
+    dispatching: AtomicBool,
+}
+
+// SAFETY: `local` is only used on the spawning thread, by push checking which thread it's on and
+// by dispatch doing likewise however the main context came to be dispatched elsewhere. Everything
+// else is thread-safe. It's never dropped with tasks in `local` as those hold the queue alive.
+unsafe impl Send for RunQueue {}
+unsafe impl Sync for RunQueue {}
+
+impl RunQueue {
+    /// Return the run queue of the given context, making it if there's none on this thread.
+    fn for_context(context: &glib::MainContext) -> Arc<Self> {
+        RUN_QUEUES.with(|queues| {
+            let mut queues = queues.borrow_mut();
+            queues.retain(|queue| queue.strong_count() > 0);
+
+            let existing_opt = queues
+                .iter()
+                .filter_map(Weak::upgrade)
+                .find(|queue| queue.context == *context);
+            existing_opt.unwrap_or_else(|| {
+                let queue = Arc::new(Self {
+                    context: context.clone(),
+                    thread: thread::current().id(),
+                    local: RefCell::new(VecDeque::new()),
+                    remote: Mutex::new(Vec::new()),
+                    remote_pending: AtomicBool::new(false),
+                    dispatching: AtomicBool::new(false),
+                });
+                queues.push(Arc::downgrade(&queue));
+                queue
+            })
+        })
+    }
+
+    /// Return whether called on the thread which spawned the queue's tasks.
+    fn on_spawning_thread(&self) -> bool {
+        thread::current().id() == self.thread
+    }
+
+    /// Add a woken task to the back of the queue, attaching the idle callback if it isn't already.
+    fn push(self: &Arc<Self>, arc: Arc<GlibWaker>) {
+        if self.on_spawning_thread() {
+            self.local.borrow_mut().push_back(arc);
+        } else {
+            self.remote.lock().unwrap().push(arc);
+            self.remote_pending.store(true, Ordering::SeqCst);
+        }
+
+        if !self.dispatching.swap(true, Ordering::SeqCst) {
+            let queue = self.clone();
+            idle_source_new(None, PRIORITY_DEFAULT_IDLE, move || queue.dispatch())
+                .attach(Some(&self.context));
+        }
+    }
+
+    /// Take the tasks woken on other threads, if there may be any.
+    fn take_remote(&self) -> Vec<Arc<GlibWaker>> {
+        if self.remote_pending.swap(false, Ordering::SeqCst) {
+            std::mem::take(&mut *self.remote.lock().unwrap())
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Poll queued tasks until the queue is empty or the [poll budget](set_poll_budget) is spent,
+    /// during the idle callback.
+    fn dispatch(&self) -> Continue {
+        let _dispatching = Dispatching::enter(&self.context);
+
+        if !self.on_spawning_thread() {
+            // None of the tasks can be polled here, so only the remote ones are stepped to report
+            // them; the local ones are stranded along with them.
+            for task in self.take_remote() {
+                glib_waker_step(&task);
+            }
+            self.dispatching.store(false, Ordering::SeqCst);
+            return Continue(false);
+        }
+
+        let started = Instant::now();
+        let budget = POLL_BUDGET.with(Cell::get);
+        loop {
+            let task_opt = {
+                let mut local = self.local.borrow_mut();
+                local.extend(self.take_remote());
+                local.pop_front()
+            };
+            let task = match task_opt {
+                Some(task) => task,
+                None => return Continue(self.finish()),
+            };
+
+            glib_waker_step(&task);
+            drop(task);
+
+            if started.elapsed() >= budget {
+                // Yield to the main loop, carrying on next idle if there's still work queued.
+                if !self.local.borrow().is_empty() || self.remote_pending.load(Ordering::SeqCst) {
+                    return Continue(true);
+                }
+                return Continue(self.finish());
+            }
+        }
+    }
+
+    /// Mark the idle callback as detached once the queue is empty, returning whether it should
+    /// carry on after all. A task woken on another thread since the queue was last checked may
+    /// have seen the callback still attached and so not attached another.
+    fn finish(&self) -> bool {
+        self.dispatching.store(false, Ordering::SeqCst);
+        self.remote_pending.load(Ordering::SeqCst) && !self.dispatching.swap(true, Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::task::waker;
+    use std::future::pending;
+
+    /// Spawn a future which never completes on a fresh main context and poll it once, returning
+    /// the context to iterate and the handle whose state is counted.
+    fn spawn_pending() -> (glib::MainContext, TaskHandle) {
+        let context = glib::MainContext::new();
+        let task = glib_run_future_on(&context, pending());
+        while context.iteration(false) {}
+        (context, task)
+    }
+
+    #[test]
+    fn clone_and_drop_balance_the_count() {
+        let (_context, task) = spawn_pending();
+        let before = Arc::strong_count(&task.0);
+
+        let w = waker(task.0.clone());
+        assert_eq!(Arc::strong_count(&task.0), before + 1);
+        let w2 = w.clone();
+        assert_eq!(Arc::strong_count(&task.0), before + 2);
+
+        drop(w2);
+        assert_eq!(Arc::strong_count(&task.0), before + 1);
+        drop(w);
+        assert_eq!(Arc::strong_count(&task.0), before);
+    }
+
+    #[test]
+    fn wake_by_ref_balances_the_count_once_polled() {
+        let (context, task) = spawn_pending();
+        let before = Arc::strong_count(&task.0);
+
+        let w = waker(task.0.clone());
+        w.wake_by_ref();
+        w.wake_by_ref();
+        // The run queue holds the task until it's polled, once however many times it was woken.
+        assert_eq!(Arc::strong_count(&task.0), before + 2);
+
+        while context.iteration(false) {}
+        assert_eq!(Arc::strong_count(&task.0), before + 1);
+        drop(w);
+        assert_eq!(Arc::strong_count(&task.0), before);
+    }
+
+    #[test]
+    fn wake_balances_the_count_once_polled() {
+        let (context, task) = spawn_pending();
+        let before = Arc::strong_count(&task.0);
+
+        let w = waker(task.0.clone());
+        let w2 = w.clone();
+        w.wake();
+        assert_eq!(Arc::strong_count(&task.0), before + 2);
+        w2.wake();
+        assert_eq!(Arc::strong_count(&task.0), before + 1);
+
+        while context.iteration(false) {}
+        assert_eq!(Arc::strong_count(&task.0), before);
+    }
+
+    #[test]
+    fn wake_from_another_thread_balances_the_count_once_polled() {
+        let (context, task) = spawn_pending();
+        let before = Arc::strong_count(&task.0);
+
+        let w = waker(task.0.clone());
+        thread::spawn(move || w.wake()).join().expect("join");
+        // The task waits in the remote half of the run queue until it's dispatched.
+        assert_eq!(Arc::strong_count(&task.0), before + 1);
+
+        while context.iteration(false) {}
+        assert_eq!(Arc::strong_count(&task.0), before);
+    }
+
+    #[test]
+    fn only_thread_default_spawns_are_deferred() {
+        let app = gio::Application::new(None, gio::ApplicationFlags::NON_UNIQUE);
+        bind_to_application(&app);
+
+        let main = glib::MainContext::new();
+        let worker = glib::MainContext::new();
+        main.with_thread_default(|| {
+            let on_main = glib_run_future(Box::pin(async {}));
+            let on_worker = glib_run_future_on(&worker, Box::pin(async {}));
+            while main.iteration(false) {}
+            while worker.iteration(false) {}
+            assert!(!on_main.is_finished());
+            assert!(on_worker.is_finished());
+        })
+        .expect("with_thread_default");
+    }
+
+    #[test]
+    fn dropping_the_last_reference_drops_the_future() {
+        struct SetOnDrop(Rc<Cell<bool>>);
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let context = glib::MainContext::new();
+        let dropped = Rc::new(Cell::new(false));
+        let guard = SetOnDrop(dropped.clone());
+        let task = glib_run_future_on(
+            &context,
+            Box::pin(async move {
+                let _guard = guard;
+                pending::<()>().await;
+            }),
+        );
+        while context.iteration(false) {}
+
+        let w = waker(task.0.clone());
+        drop(task);
+        assert!(!dropped.get());
+        drop(w);
+        assert!(dropped.get());
+    }
+}
@@ -0,0 +1,201 @@
+//! Provides [`glib_scope`], structured concurrency for futures run on the glib main loop: a
+//! [`GlibScope`] owns the futures spawned into it, only completes once they all have, and aborts
+//! them all when dropped.
+
+use crate::glib_future::{glib_run_future, JoinError, TaskHandle};
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll, Waker};
+
+/// State of a [`Scope`] shared with its [`GlibScope`] and children.
+#[derive(Default)]
+struct ScopeInner {
+    children: Vec<TaskHandle>,
+
+    /// Whether the scope has completed or been dropped, after which futures aren't spawned at all.
+    closed: bool,
+
+    waker_opt: Option<Waker>,
+}
+
+/// Handle to spawn children into a [`GlibScope`], passed to the body given to [`glib_scope`].
+///
+/// Clones refer to the same scope, so children can be handed one to spawn siblings.
+#[derive(Clone)]
+pub struct Scope(Rc<RefCell<ScopeInner>>);
+
+/// Held by the future of each child of a [`Scope`], which wakes the [`GlibScope`] when dropped so
+/// that it notices the child completed, panicked, or was aborted.
+struct ChildGuard(Weak<RefCell<ScopeInner>>);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        let waker_opt = match self.0.upgrade() {
+            Some(inner) => inner.borrow_mut().waker_opt.take(),
+            None => None,
+        };
+        if let Some(w) = waker_opt {
+            w.wake();
+        }
+    }
+}
+
+impl Scope {
+    /// Run a future on the glib main loop as a child of this scope, which the scope waits for and
+    /// aborts along with the rest of its children. If the scope has already completed or been
+    /// dropped, the future is dropped without being run.
+    ///
+    /// Children may be spawned at any time until the scope completes, including after the body has
+    /// finished, e.g. by other children.
+    #[track_caller]
+    pub fn spawn<F>(&self, fut: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        if self.0.borrow().closed {
+            return;
+        }
+
+        let guard = ChildGuard(Rc::downgrade(&self.0));
+        let task = glib_run_future(Box::pin(async move {
+            let _guard = guard;
+            fut.await;
+        }));
+
+        // Keep panicked children until the scope notices them.
+        let mut inner = self.0.borrow_mut();
+        inner
+            .children
+            .retain(|child| !child.is_finished() || child.panic_message().is_some());
+        inner.children.push(task);
+    }
+}
+
+/// A [`Future`] of the output of the body given to [`glib_scope`], which completes once the body
+/// and every child spawned into its [`Scope`] has.
+///
+/// Dropping it, e.g. by aborting the task awaiting it, aborts every child.
+pub struct GlibScope<T> {
+    scope: Scope,
+
+    /// The body, until it completes.
+    body_opt: Option<Pin<Box<dyn Future<Output = T>>>>,
+
+    /// The output of the body, once it completes and until the children have too.
+    output_opt: Option<T>,
+}
+
+/// A [`GlibScope`] can be unpinned as its body is boxed.
+impl<T> Unpin for GlibScope<T> {}
+
+/// Make a [`GlibScope`] which runs the future made by `body` and waits for every child it spawns
+/// into the given [`Scope`], e.g. to tie the stream consumers, timers, and loaders of a page to
+/// the page so that navigating away tears them all down at once:
+///
+/// ```
+///    # use springsteel::glib_future::glib_spawn;
+///    # use springsteel::glib_scope::glib_scope;
+///    # use springsteel::time::sleep;
+///    # use std::cell::Cell;
+///    # use std::rc::Rc;
+///    # use std::time::Duration;
+///    let loaded = Rc::new(Cell::new(0));
+///    let loaded_for_page = loaded.clone();
+///    let page = glib_spawn(glib_scope(move |scope| async move {
+///        for _ in 0..2 {
+///            let loaded = loaded_for_page.clone();
+///            scope.spawn(async move { loaded.set(loaded.get() + 1) });
+///        }
+///        scope.spawn(sleep(Duration::from_secs(60)));
+///        "page"
+///    }));
+///
+///    while glib::MainContext::default().iteration(false) {}
+///    assert_eq!(loaded.get(), 2);
+///    assert!(!page.is_finished());
+///
+///    page.abort();
+///    while glib::MainContext::default().iteration(false) {}
+///    assert!(page.is_finished());
+/// ```
+///
+/// If a child panics, its siblings are aborted and the scope outputs [`JoinError::Panicked`]. The
+/// panic is reported to the [task panic handler](crate::glib_future::set_task_panic_handler) like
+/// that of any other future, and doesn't unwind into whatever awaits the scope.
+pub fn glib_scope<F, Fut>(body: F) -> GlibScope<Fut::Output>
+where
+    F: FnOnce(Scope) -> Fut,
+    Fut: Future + 'static,
+{
+    let scope = Scope(Rc::new(RefCell::new(ScopeInner::default())));
+    let body_fut = body(scope.clone());
+    GlibScope {
+        scope,
+        body_opt: Some(Box::pin(body_fut)),
+        output_opt: None,
+    }
+}
+
+impl<T> GlibScope<T> {
+    /// Close the scope and abort every child.
+    fn abort_children(&self) {
+        // Take the children first, as aborting drops futures which wake the scope.
+        let children = {
+            let mut inner = self.scope.0.borrow_mut();
+            inner.closed = true;
+            std::mem::take(&mut inner.children)
+        };
+        for child in children {
+            child.abort();
+        }
+    }
+}
+
+impl<T> Future for GlibScope<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T, JoinError>> {
+        if let Some(body) = self.body_opt.as_mut() {
+            if let Poll::Ready(output) = body.as_mut().poll(cx) {
+                self.body_opt = None;
+                self.output_opt = Some(output);
+            }
+        }
+
+        let panic_opt = self
+            .scope
+            .0
+            .borrow()
+            .children
+            .iter()
+            .find_map(TaskHandle::panic_message);
+        if let Some(message) = panic_opt {
+            self.body_opt = None;
+            self.abort_children();
+            return Poll::Ready(Err(JoinError::Panicked(message)));
+        }
+
+        let mut inner = self.scope.0.borrow_mut();
+        inner.children.retain(|child| !child.is_finished());
+        if self.body_opt.is_none() && inner.children.is_empty() {
+            inner.closed = true;
+            drop(inner);
+            let output = self
+                .output_opt
+                .take()
+                .expect("GlibScope polled after completion");
+            return Poll::Ready(Ok(output));
+        }
+
+        inner.waker_opt = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for GlibScope<T> {
+    fn drop(&mut self) {
+        self.abort_children();
+    }
+}
@@ -2,9 +2,10 @@
 //! example by a button click or similar "it happened" event with no additional detail.
 
 use futures::stream::Stream;
+use std::any::Any;
 use std::cell::RefCell;
 use std::pin::Pin;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::task::{Context, Poll};
 
 /// Inner state of an [`ImpulseStream`].
@@ -12,6 +13,39 @@ struct ImpulseStreamInner {
     /// How many impulse events are waiting to be dequeued
     pending: usize,
     waker_opt: Option<std::task::Waker>,
+
+    /// Guards kept for as long as the stream, e.g. to disconnect the signal handlers triggering it
+    /// once nothing can poll it any more.
+    held: Vec<Box<dyn Any>>,
+
+    /// Name given by [`set_name`](ImpulseStream::set_name), if any.
+    #[cfg(feature = "stats")]
+    name_opt: Option<String>,
+
+    #[cfg(feature = "stats")]
+    stats: ImpulseStreamStats,
+}
+
+/// Counters kept for every [`ImpulseStream`] when the `stats` feature is enabled, returned by
+/// [`ImpulseStream::stats`].
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImpulseStreamStats {
+    /// How many times the stream was triggered.
+    pub triggers: u64,
+
+    /// How many times the stream was polled, whether or not it was ready.
+    pub polls: u64,
+
+    /// The most triggers ever waiting to be dequeued at once. Values above 1 mean the consumer
+    /// fell behind the trigger rate.
+    pub max_pending: usize,
+}
+
+#[cfg(feature = "stats")]
+thread_local! {
+    /// Every stream on this thread which has been given a name, for [`named_stats`].
+    static NAMED: RefCell<Vec<Weak<RefCell<ImpulseStreamInner>>>> = RefCell::new(Vec::new());
 }
 
 /// A infinite [`Stream`] implementation which generates `()`s whenever it's
@@ -39,6 +73,19 @@ struct ImpulseStreamInner {
 #[derive(Clone)]
 pub struct ImpulseStream(Rc<RefCell<ImpulseStreamInner>>);
 
+/// A weak reference to an [`ImpulseStream`], for triggering it only while something else still
+/// holds the stream.
+pub(crate) struct WeakImpulseStream(Weak<RefCell<ImpulseStreamInner>>);
+
+impl WeakImpulseStream {
+    /// [`Trigger`](ImpulseStream::trigger) the stream if it's still held.
+    pub(crate) fn trigger(&self) {
+        if let Some(inner) = self.0.upgrade() {
+            ImpulseStream(inner).trigger();
+        }
+    }
+}
+
 /// An [`ImpulseStream`] can be unpinned as its state is a reference counted pointer.
 impl Unpin for ImpulseStream {}
 
@@ -49,18 +96,60 @@ impl ImpulseStream {
         Self(Rc::new(RefCell::new(ImpulseStreamInner {
             pending: 0,
             waker_opt: None,
+            held: Vec::new(),
+            #[cfg(feature = "stats")]
+            name_opt: None,
+            #[cfg(feature = "stats")]
+            stats: ImpulseStreamStats::default(),
         })))
     }
 
+    /// Name the `ImpulseStream` so its `stats` are included in `named_stats` and `dump_stats`. Does
+    /// nothing unless the `stats` feature is enabled, so calls can be left in place at no cost.
+    #[inline]
+    pub fn set_name(&self, name: &str) {
+        #[cfg(feature = "stats")]
+        {
+            let mut inner = self.0.borrow_mut();
+            if inner.name_opt.is_none() {
+                NAMED.with(|n| n.borrow_mut().push(Rc::downgrade(&self.0)));
+            }
+            inner.name_opt = Some(name.to_owned());
+        }
+        #[cfg(not(feature = "stats"))]
+        let _ = name;
+    }
+
+    /// Return the counters of triggers and polls of this `ImpulseStream` so far.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> ImpulseStreamStats {
+        self.0.borrow().stats
+    }
+
     /// Trigger the `ImpulseStream`, making it become ready with `()`.
     pub fn trigger(&self) {
         let mut inner = self.0.borrow_mut();
         inner.pending += 1;
+        #[cfg(feature = "stats")]
+        {
+            inner.stats.triggers += 1;
+            inner.stats.max_pending = inner.stats.max_pending.max(inner.pending);
+        }
         if let Some(w) = inner.waker_opt.take() {
             w.wake();
         }
     }
 
+    /// Keep the given guard for as long as the stream, dropping it along with the last clone.
+    pub(crate) fn hold(&self, guard: impl Any) {
+        self.0.borrow_mut().held.push(Box::new(guard));
+    }
+
+    /// Make a weak reference to this stream, which doesn't keep it alive.
+    pub(crate) fn downgrade(&self) -> WeakImpulseStream {
+        WeakImpulseStream(Rc::downgrade(&self.0))
+    }
+
     /// Make a closure which can be called with a single parameter of any reference type, ignoring
     /// that parameter and just calling [`trigger`](Self::trigger). Useful for e.g.
     /// [`Button::connect_clicked`](gtk::prelude::ButtonExt::connect_clicked).
@@ -75,6 +164,10 @@ impl Stream for ImpulseStream {
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<()>> {
         let mut inner = self.0.borrow_mut();
+        #[cfg(feature = "stats")]
+        {
+            inner.stats.polls += 1;
+        }
         if inner.pending > 0 {
             inner.pending -= 1;
             Poll::Ready(Some(()))
@@ -85,3 +178,38 @@ impl Stream for ImpulseStream {
     }
 }
 
+/// Return the name and [`stats`](ImpulseStream::stats) of every named [`ImpulseStream`] still alive
+/// on this thread.
+#[cfg(feature = "stats")]
+pub fn named_stats() -> Vec<(String, ImpulseStreamStats)> {
+    NAMED.with(|n| {
+        let mut named = n.borrow_mut();
+        named.retain(|w| w.strong_count() > 0);
+        named
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter_map(|inner| {
+                let inner = inner.borrow();
+                let name = inner.name_opt.clone()?;
+                Some((name, inner.stats))
+            })
+            .collect()
+    })
+}
+
+/// Log the [`named_stats`] at info level via the `log` crate, busiest stream first, to help find
+/// out what's making an application recompute so much.
+#[cfg(feature = "stats")]
+pub fn dump_stats() {
+    let mut stats = named_stats();
+    stats.sort_by(|(_, a), (_, b)| b.triggers.cmp(&a.triggers));
+    for (name, s) in stats {
+        log::info!(
+            "impulse stream {}: {} triggers, {} polls, at most {} pending",
+            name,
+            s.triggers,
+            s.polls,
+            s.max_pending
+        );
+    }
+}
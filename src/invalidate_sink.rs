@@ -0,0 +1,304 @@
+//! Provides [`InvalidateSink`], a [`Sink`] which coalesces any number of "redraw please" items into
+//! at most one [`queue_draw`](gtk::prelude::WidgetExt::queue_draw) per frame of the widget's
+//! [`gdk::FrameClock`].
+//!
+//! The frames come from the [`TickDriver`] of the thread which made the sink, which is
+//! [`FrameClockTicks`] unless replaced with [`set_tick_driver`], e.g. by
+//! [`ManualTicks`](crate::testing::ManualTicks) so that tests step through frames without showing
+//! a window.
+
+use futures::sink::Sink;
+use glib::Cast as _;
+use gtk::prelude::{ObjectExt as _, WidgetExt as _, WidgetExtManual as _};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+/// Source of the frame ticks which [`InvalidateSink`]s queue their draws at.
+pub trait TickDriver {
+    /// Call `callback` once, at the next frame of `widget`.
+    fn add_tick(&self, widget: &gtk::Widget, callback: Box<dyn FnOnce()>);
+}
+
+/// The default [`TickDriver`], using a tick callback on the frame clock of each widget, so ticks
+/// only come while the widget is mapped.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameClockTicks;
+
+impl TickDriver for FrameClockTicks {
+    fn add_tick(&self, widget: &gtk::Widget, callback: Box<dyn FnOnce()>) {
+        let callback_opt = Cell::new(Some(callback));
+        widget.add_tick_callback(move |_, _| {
+            if let Some(callback) = callback_opt.take() {
+                callback();
+            }
+            glib::Continue(false)
+        });
+    }
+}
+
+thread_local! {
+    /// The [`TickDriver`] used by sinks made on this thread.
+    static DRIVER: RefCell<Rc<dyn TickDriver>> = RefCell::new(Rc::new(FrameClockTicks));
+}
+
+/// Use the given [`TickDriver`] for [`InvalidateSink`]s made on this thread from now on, returning
+/// the one used until now. Those made already keep the driver they were made with.
+pub fn set_tick_driver(driver: Rc<dyn TickDriver>) -> Rc<dyn TickDriver> {
+    DRIVER.with(|d| std::mem::replace(&mut *d.borrow_mut(), driver))
+}
+
+/// Return the [`TickDriver`] used by sinks made on this thread.
+pub fn tick_driver() -> Rc<dyn TickDriver> {
+    DRIVER.with(|d| d.borrow().clone())
+}
+
+/// Damage accumulated by an [`InvalidateSink`] since the last time it was
+/// [taken](InvalidateSink::take_damage).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Damage {
+    /// The whole widget should be redrawn, because at least one `()` item was sent.
+    Full,
+
+    /// Only the given area of the widget, the union of all [`gdk::Rectangle`] items sent, needs
+    /// to be redrawn.
+    Region(gdk::Rectangle),
+}
+
+impl Damage {
+    /// Combine two damages into one which covers both.
+    fn union(self, other: Damage) -> Damage {
+        match (self, other) {
+            (Damage::Region(a), Damage::Region(b)) => Damage::Region(a.union(&b)),
+            _ => Damage::Full,
+        }
+    }
+}
+
+/// Error produced by an [`InvalidateSink`] when the widget it invalidates no longer exists, which
+/// terminates e.g. a [`forward`](futures::stream::StreamExt::forward) into the sink.
+///
+/// ```
+///    # use futures::stream::StreamExt as _;
+///    # use springsteel::glib_future::{glib_block_on, glib_spawn};
+///    # use springsteel::invalidate_sink::{InvalidateSink, WidgetGone};
+///    # use springsteel::ImpulseStream;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let meter = gtk::DrawingArea::new();
+///    let levels_changed = ImpulseStream::new();
+///    let sink = InvalidateSink::new(&meter);
+///    let forwarding = glib_spawn(levels_changed.clone().map(Ok).forward(sink));
+///
+///    levels_changed.trigger();
+///    while glib::MainContext::default().iteration(false) {}
+///    assert!(!forwarding.is_finished());
+///
+///    drop(meter);
+///    levels_changed.trigger();
+///    assert_eq!(glib_block_on(forwarding), Ok(Err(WidgetGone)));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WidgetGone;
+
+impl fmt::Display for WidgetGone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the widget being invalidated no longer exists")
+    }
+}
+
+impl std::error::Error for WidgetGone {}
+
+/// Inner state of an [`InvalidateSink`].
+struct InvalidateSinkInner {
+    /// The widget to queue draws on, weakly held so the sink never keeps it alive.
+    widget: glib::WeakRef<gtk::Widget>,
+
+    /// Damage accumulated since the last [`take_damage`](InvalidateSink::take_damage), if any.
+    damage_opt: Option<Damage>,
+
+    /// Where the ticks to issue draws at come from.
+    ticks: Rc<dyn TickDriver>,
+
+    /// Whether a tick is presently pending to issue the draw.
+    tick_pending: bool,
+
+    /// How many times a draw has been queued, for diagnostics and tests.
+    draws_queued: u64,
+}
+
+/// A [`Sink`] of `()` (redraw everything) or [`gdk::Rectangle`] (redraw some area) items which
+/// invalidates a widget at most once per frame, no matter how quickly items arrive.
+///
+/// Each item only records damage; the actual [`queue_draw`](gtk::prelude::WidgetExt::queue_draw)
+/// is issued from a tick callback on the widget's frame clock, so a burst of thousands of items
+/// between two frames causes exactly one redraw. This makes it suitable for widgets which render
+/// from rapidly changing external state such as audio meters or log tails, e.g.
+///
+/// ```
+///    # use futures::stream::StreamExt as _;
+///    # use springsteel::{glib_run_future, ImpulseStream};
+///    # use springsteel::invalidate_sink::InvalidateSink;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let meter = gtk::DrawingArea::new();
+///    let levels_changed = ImpulseStream::new();
+///    glib_run_future(Box::pin(async move {
+///        let _ = levels_changed.map(Ok).forward(InvalidateSink::new(&meter)).await;
+///    }));
+/// ```
+///
+/// GTK 4 has no partial invalidation, so [`gdk::Rectangle`] items still queue a full draw but the
+/// union of the areas is kept and can be consulted by the widget's snapshot code via
+/// [`take_damage`](Self::take_damage) to skip unchanged regions.
+///
+/// Only a weak reference to the widget is held. Once the widget is gone the sink fails with
+/// [`WidgetGone`], terminating whatever is feeding it.
+#[derive(Clone)]
+pub struct InvalidateSink(Rc<RefCell<InvalidateSinkInner>>);
+
+/// An [`InvalidateSink`] can be unpinned as its state is a reference counted pointer.
+impl Unpin for InvalidateSink {}
+
+impl InvalidateSink {
+    /// Create a new `InvalidateSink` which invalidates the given widget.
+    pub fn new(widget: &impl glib::IsA<gtk::Widget>) -> Self {
+        Self(Rc::new(RefCell::new(InvalidateSinkInner {
+            widget: widget.upcast_ref::<gtk::Widget>().downgrade(),
+            damage_opt: None,
+            ticks: tick_driver(),
+            tick_pending: false,
+            draws_queued: 0,
+        })))
+    }
+
+    /// Take the damage accumulated since the last call, or `None` if nothing was sent since.
+    pub fn take_damage(&self) -> Option<Damage> {
+        self.0.borrow_mut().damage_opt.take()
+    }
+
+    /// Return how many draws this sink has queued on the widget so far.
+    ///
+    /// ```
+    ///    # use futures::sink::SinkExt as _;
+    ///    # use springsteel::invalidate_sink::{Damage, InvalidateSink};
+    ///    # use springsteel::testing::ManualTicks;
+    ///    # gtk::init().expect("gtk::init");
+    ///    #
+    ///    let ticks = ManualTicks::install();
+    ///    let meter = gtk::DrawingArea::new();
+    ///    let mut sink = InvalidateSink::new(&meter);
+    ///    futures::executor::block_on(async {
+    ///        sink.feed(gdk::Rectangle::new(0, 0, 10, 10)).await.unwrap();
+    ///        for _ in 0..10_000 {
+    ///            sink.feed(()).await.unwrap();
+    ///        }
+    ///    });
+    ///    assert_eq!(sink.draws_queued(), 0);
+    ///    assert_eq!(ticks.pending_ticks(), 1);
+    ///
+    ///    ticks.tick();
+    ///    assert_eq!(sink.draws_queued(), 1);
+    ///    assert_eq!(sink.take_damage(), Some(Damage::Full));
+    ///    ticks.tick();
+    ///    assert_eq!(sink.draws_queued(), 1);
+    ///
+    ///    futures::executor::block_on(sink.feed(())).unwrap();
+    ///    ticks.tick();
+    ///    assert_eq!(sink.draws_queued(), 2);
+    /// ```
+    pub fn draws_queued(&self) -> u64 {
+        self.0.borrow().draws_queued
+    }
+
+    /// Record the given damage and make sure a tick is pending to queue the draw on the next
+    /// frame.
+    fn invalidate(&self, damage: Damage) -> Result<(), WidgetGone> {
+        let mut inner = self.0.borrow_mut();
+        let widget = inner.widget.upgrade().ok_or(WidgetGone)?;
+
+        inner.damage_opt = Some(match inner.damage_opt {
+            Some(d) => d.union(damage),
+            None => damage,
+        });
+
+        if inner.tick_pending {
+            return Ok(());
+        }
+
+        inner.tick_pending = true;
+        let ticks = inner.ticks.clone();
+        drop(inner);
+
+        let inst = self.clone();
+        ticks.add_tick(
+            &widget,
+            Box::new(move || {
+                let mut inner = inst.0.borrow_mut();
+                inner.tick_pending = false;
+                let widget_opt = inner.widget.upgrade();
+                if widget_opt.is_some() {
+                    inner.draws_queued += 1;
+                }
+                drop(inner);
+
+                if let Some(widget) = widget_opt {
+                    widget.queue_draw();
+                }
+            }),
+        );
+
+        Ok(())
+    }
+
+    /// Ready as long as the widget is still alive.
+    fn poll_alive(&self) -> Poll<Result<(), WidgetGone>> {
+        if self.0.borrow().widget.upgrade().is_some() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Ready(Err(WidgetGone))
+        }
+    }
+}
+
+impl Sink<()> for InvalidateSink {
+    type Error = WidgetGone;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), WidgetGone>> {
+        self.poll_alive()
+    }
+
+    fn start_send(self: Pin<&mut Self>, _item: ()) -> Result<(), WidgetGone> {
+        self.invalidate(Damage::Full)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), WidgetGone>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), WidgetGone>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Sink<gdk::Rectangle> for InvalidateSink {
+    type Error = WidgetGone;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), WidgetGone>> {
+        self.poll_alive()
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: gdk::Rectangle) -> Result<(), WidgetGone> {
+        self.invalidate(Damage::Region(item))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), WidgetGone>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), WidgetGone>> {
+        Poll::Ready(Ok(()))
+    }
+}
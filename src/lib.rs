@@ -6,12 +6,45 @@
 #[macro_use]
 pub mod constraint_macros;
 
+pub mod clipboard;
+
 pub mod constraint_view;
 pub use constraint_view::ConstraintView;
 
+pub mod controller_streams;
+
+pub mod debug;
+
+pub mod frame_sync;
+
 pub mod glib_future;
-pub use glib_future::glib_run_future;
+pub use glib_future::{glib_run_future, glib_run_stream};
+
+pub mod glib_scope;
 
 pub mod impulse_stream;
 pub use impulse_stream::ImpulseStream;
 
+pub mod invalidate_sink;
+pub use invalidate_sink::InvalidateSink;
+
+pub mod monitor_streams;
+
+pub mod property_stream;
+
+pub mod shared_stream;
+
+pub mod signal_future;
+
+pub mod testing;
+
+pub mod text_binding;
+
+pub mod time;
+
+pub mod value_stream;
+pub use value_stream::ValueStream;
+
+pub mod widget_spawner;
+
+pub mod widget_streams;
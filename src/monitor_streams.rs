@@ -0,0 +1,156 @@
+//! Streams of the monitors of a [`gdk::Display`] and of the monitor a window is on, so that an
+//! application can adapt its layout when monitors come and go or a window moves between them.
+
+use crate::signal_future::SignalGuard;
+use crate::value_stream::ValueStream;
+use gio::prelude::ListModelExt as _;
+use glib::object::IsA;
+use glib::{Cast as _, ObjectExt as _};
+use gtk::prelude::{NativeExt as _, WidgetExt as _};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A change to the monitors of a display output by [`monitor_changes`].
+#[derive(Clone, Debug)]
+pub enum MonitorChange {
+    /// All the monitors connected at the time of subscribing, always output first.
+    Current(Vec<gdk::Monitor>),
+
+    /// A monitor was connected.
+    Added(gdk::Monitor),
+
+    /// A monitor was disconnected.
+    Removed(gdk::Monitor),
+}
+
+/// Make a [`ValueStream`] of the monitors of the given display: first the current list as
+/// [`MonitorChange::Current`], then every monitor connected or disconnected.
+///
+/// GTK 4 reports these as changes to the [`gdk::Display::monitors`] list model rather than with
+/// signals, so a copy of the list is kept to know which monitors were removed. The handler is
+/// disconnected when the stream is dropped.
+///
+/// ```
+///    # use springsteel::monitor_streams::monitor_changes;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let monitors = monitor_changes(&gdk::Display::default().expect("Display::default"));
+/// ```
+pub fn monitor_changes(display: &gdk::Display) -> ValueStream<MonitorChange> {
+    let values = ValueStream::new();
+    let model = display.monitors();
+    let monitors = Rc::new(RefCell::new(model_monitors(&model)));
+    values.push(MonitorChange::Current(monitors.borrow().clone()));
+
+    let values_for_items = values.downgrade();
+    let items_changed = model.connect_items_changed(move |model, position, removed, added| {
+        let position = position as usize;
+        let mut monitors = monitors.borrow_mut();
+        let added_monitors = (position..position + added as usize)
+            .filter_map(|i| model.item(i as u32))
+            .filter_map(|o| o.downcast::<gdk::Monitor>().ok())
+            .collect::<Vec<_>>();
+        let removed_monitors = monitors
+            .splice(
+                position..position + removed as usize,
+                added_monitors.iter().cloned(),
+            )
+            .collect::<Vec<_>>();
+
+        for monitor in removed_monitors {
+            values_for_items.push(MonitorChange::Removed(monitor));
+        }
+        for monitor in added_monitors {
+            values_for_items.push(MonitorChange::Added(monitor));
+        }
+    });
+    values.hold(SignalGuard::new(&model, vec![items_changed]));
+
+    values
+}
+
+/// Return every monitor in a [`gdk::Display::monitors`] list model.
+fn model_monitors(model: &gio::ListModel) -> Vec<gdk::Monitor> {
+    (0..model.n_items())
+        .filter_map(|i| model.item(i))
+        .filter_map(|o| o.downcast::<gdk::Monitor>().ok())
+        .collect()
+}
+
+/// The monitor being watched by [`window_monitor_geometry`], and its geometry notify handler.
+type WatchedMonitor = Option<(gdk::Monitor, glib::SignalHandlerId)>;
+
+/// Guard which disconnects the geometry notify handler of the [`WatchedMonitor`] when dropped.
+struct WatchedMonitorGuard(Rc<RefCell<WatchedMonitor>>);
+
+impl Drop for WatchedMonitorGuard {
+    fn drop(&mut self) {
+        if let Some((monitor, handler)) = self.0.borrow_mut().take() {
+            monitor.disconnect(handler);
+        }
+    }
+}
+
+/// Make a [`ValueStream`] of the geometry of the monitor the given window is on, output when the
+/// window is first shown on a monitor, whenever it moves to another monitor, and whenever the
+/// monitor's geometry changes, e.g. because its resolution or scale was changed.
+///
+/// The current geometry is output immediately if the window is already realized. `None` is output
+/// when the window is unrealized. The handlers are disconnected when the stream is dropped.
+pub fn window_monitor_geometry(
+    window: &impl IsA<gtk::Window>,
+) -> ValueStream<Option<gdk::Rectangle>> {
+    let values = ValueStream::new();
+    let watched: Rc<RefCell<WatchedMonitor>> = Rc::new(RefCell::new(None));
+
+    let watch = {
+        let values = values.downgrade();
+        let watched = watched.clone();
+        move |monitor_opt: Option<gdk::Monitor>| {
+            if let Some((monitor, handler)) = watched.borrow_mut().take() {
+                monitor.disconnect(handler);
+            }
+
+            // Once the stream is gone, stop watching rather than connecting another handler.
+            let values = match values.upgrade() {
+                Some(values) => values,
+                None => return,
+            };
+            values.push(monitor_opt.as_ref().map(|m| m.geometry()));
+
+            if let Some(monitor) = monitor_opt {
+                let values_for_geometry = values.downgrade();
+                let handler = monitor.connect_geometry_notify(move |monitor| {
+                    values_for_geometry.push(Some(monitor.geometry()));
+                });
+                *watched.borrow_mut() = Some((monitor, handler));
+            }
+        }
+    };
+    let watch = Rc::new(watch);
+
+    let on_realize = {
+        let watch = watch.clone();
+        move |window: &gtk::Widget| {
+            let surface = window.native().expect("realized window").surface();
+            watch(window.display().monitor_at_surface(&surface));
+
+            let watch_for_enter = watch.clone();
+            surface.connect_enter_monitor(move |_, monitor| {
+                watch_for_enter(Some(monitor.clone()));
+            });
+        }
+    };
+    let on_realize = Rc::new(on_realize);
+
+    let window = window.upcast_ref::<gtk::Widget>();
+    if window.is_realized() {
+        on_realize(window);
+    }
+    let realize = window.connect_realize(move |window| on_realize(window));
+    let unrealize = window.connect_unrealize(move |_| watch(None));
+    values.hold(SignalGuard::new(window, vec![realize, unrealize]));
+    values.hold(WatchedMonitorGuard(watched));
+
+    values
+}
@@ -0,0 +1,132 @@
+//! Provides [`property_stream`], a [`ValueStream`] of the values of any [`glib::Object`] property,
+//! which subsumes per-widget adapters for things like `visible`, `sensitive`, or `label`, and
+//! [`prefer_dark_changes`] built on it.
+
+use crate::signal_future::SignalGuard;
+use crate::value_stream::ValueStream;
+use glib::object::IsA;
+use glib::value::FromValue;
+use glib::{ObjectExt as _, StaticType};
+use std::fmt;
+
+/// Error setting up a [`property_stream`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PropertyStreamError {
+    /// The object has no property with the given name.
+    NoSuchProperty {
+        /// Type name of the object.
+        object_type: glib::Type,
+
+        /// The property name which was requested.
+        property: String,
+    },
+
+    /// The property's values can't be read as the requested type.
+    TypeMismatch {
+        /// The property name which was requested.
+        property: String,
+
+        /// The type requested.
+        expected: glib::Type,
+
+        /// The actual type of the property.
+        actual: glib::Type,
+    },
+}
+
+impl fmt::Display for PropertyStreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoSuchProperty {
+                object_type,
+                property,
+            } => write!(f, "{} has no property `{}`", object_type, property),
+            Self::TypeMismatch {
+                property,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "property `{}` is of type {} which can't be read as {}",
+                property, actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PropertyStreamError {}
+
+/// Make a [`ValueStream`] of the values of the named property of `obj`, output every time the
+/// property is notified (`notify::<name>`), and once initially if `emit_initial` is true. The
+/// notify handler is disconnected when the stream is dropped.
+///
+/// The property name and type are checked here, so that a typo or wrong type is reported as an
+/// error when setting up rather than as a panic deep inside a signal handler later:
+///
+/// ```
+///    # use springsteel::property_stream::property_stream;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let button = gtk::Button::with_label("Go");
+///    let sensitivity = property_stream::<bool>(&button, "sensitive", true).expect("sensitive");
+///    assert!(property_stream::<bool>(&button, "sensitiv", true).is_err());
+///    assert!(property_stream::<String>(&button, "sensitive", true).is_err());
+/// ```
+pub fn property_stream<T>(
+    obj: &impl IsA<glib::Object>,
+    name: &str,
+    emit_initial: bool,
+) -> Result<ValueStream<T>, PropertyStreamError>
+where
+    T: for<'a> FromValue<'a> + StaticType + 'static,
+{
+    let pspec = obj
+        .find_property(name)
+        .ok_or_else(|| PropertyStreamError::NoSuchProperty {
+            object_type: obj.type_(),
+            property: name.to_owned(),
+        })?;
+
+    if !pspec.value_type().is_a(T::static_type()) {
+        return Err(PropertyStreamError::TypeMismatch {
+            property: name.to_owned(),
+            expected: T::static_type(),
+            actual: pspec.value_type(),
+        });
+    }
+
+    let values = ValueStream::new();
+    let name = pspec.name().to_owned();
+    let read = move |o: &glib::Object| o.property_value(&name).get::<T>().ok();
+
+    if emit_initial {
+        if let Some(value) = read(obj.upcast_ref()) {
+            values.push(value);
+        }
+    }
+
+    let object = obj.upcast_ref::<glib::Object>();
+    let values_for_notify = values.downgrade();
+    let notify = object.connect_notify_local(Some(pspec.name()), move |o, _| {
+        if let Some(value) = read(o) {
+            values_for_notify.push(value);
+        }
+    });
+    values.hold(SignalGuard::new(object, vec![notify]));
+
+    Ok(values)
+}
+
+/// Make a [`ValueStream`] of the `gtk-application-prefer-dark-theme` setting of the default
+/// [`gtk::Settings`], output initially and whenever it changes, e.g. to swap CSS providers or
+/// icon sets.
+///
+/// If there are no default settings, such as in headless tests without a display, the stream
+/// never outputs anything rather than panicking.
+pub fn prefer_dark_changes() -> ValueStream<bool> {
+    gtk::Settings::default()
+        .and_then(|settings| {
+            property_stream(&settings, "gtk-application-prefer-dark-theme", true).ok()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,323 @@
+//! Provides [`SharedStream`], which lets any number of subscribers each receive every item of one
+//! underlying [`Stream`], optionally replaying the last few items to subscribers which arrive late:
+//! [`share`], [`share_with_replay`], and [`replay_latest`].
+
+use crate::debug::{register_live, LiveKind, LiveToken};
+use futures::stream::Stream;
+use futures::task::{waker, ArcWake};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::panic::Location;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Options for [`share_with`].
+#[derive(Clone, Copy, Debug)]
+pub struct ShareOptions {
+    /// How many of the most recent items to retain and replay to each new subscriber before it
+    /// receives live items. `0` by default.
+    pub replay: usize,
+
+    /// How many live items may be waiting for a subscriber which isn't keeping up before the
+    /// oldest are dropped, counted as [`lagged`](SharedStream::lagged). Replayed items don't count
+    /// against this. `1024` by default.
+    pub live_capacity: usize,
+}
+
+impl Default for ShareOptions {
+    fn default() -> Self {
+        Self {
+            replay: 0,
+            live_capacity: 1024,
+        }
+    }
+}
+
+/// State of one subscriber of a [`SharedStream`].
+struct Subscriber<T> {
+    /// Retained items copied at subscription time, output before any live item.
+    replay: VecDeque<T>,
+
+    /// Live items not yet output.
+    live: VecDeque<T>,
+
+    /// How many live items were dropped because `live` was full.
+    lagged: u64,
+
+    waker_opt: Option<Waker>,
+
+    /// Registration of the subscriber in the [live registry](crate::debug).
+    _live: LiveToken,
+}
+
+/// [`ArcWake`] implementation used when polling the underlying stream, so that whichever
+/// subscribers are waiting get woken no matter which of them last polled.
+#[derive(Default)]
+struct FanoutWaker(Mutex<Vec<Waker>>);
+
+impl ArcWake for FanoutWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        let wakers = std::mem::take(&mut *arc_self.0.lock().unwrap());
+        for w in wakers {
+            w.wake();
+        }
+    }
+}
+
+/// State shared by all subscribers of a [`SharedStream`].
+struct SharedInner<T> {
+    /// The underlying stream, or `None` once it has ended.
+    source_opt: Option<Pin<Box<dyn Stream<Item = T>>>>,
+
+    /// The most recent items, at most `options.replay` of them.
+    history: VecDeque<T>,
+
+    options: ShareOptions,
+    subscribers: Vec<Weak<RefCell<Subscriber<T>>>>,
+    fanout: Arc<FanoutWaker>,
+}
+
+/// A [`Stream`] which is one subscriber of some shared underlying stream. Every subscriber
+/// receives every item the underlying stream produces after it subscribed, preceded by any
+/// retained history, and the stream ends for all subscribers when the underlying stream ends.
+///
+/// [`Clone`]ing a `SharedStream` (or calling [`subscribe`](Self::subscribe)) creates a new
+/// subscriber, which starts from the retained history rather than from where the original
+/// subscriber is. This makes a `SharedStream` created with [`replay_latest`] a poor-man's property
+/// for arbitrary item types: a page constructed lazily can subscribe and immediately get the
+/// current value, then every subsequent one.
+///
+/// ```
+///    # use futures::stream::{self, StreamExt as _};
+///    # use springsteel::shared_stream::share_with_replay;
+///    # futures::executor::block_on(async {
+///    let counts = share_with_replay(stream::iter(1..=5), 2);
+///    let early = counts.subscribe();
+///    assert_eq!(early.collect::<Vec<_>>().await, vec![1, 2, 3, 4, 5]);
+///
+///    let late = counts.subscribe();
+///    assert_eq!(late.collect::<Vec<_>>().await, vec![4, 5]);
+///    # });
+/// ```
+pub struct SharedStream<T> {
+    shared: Rc<RefCell<SharedInner<T>>>,
+    subscriber: Rc<RefCell<Subscriber<T>>>,
+}
+
+/// A [`SharedStream`] can be unpinned as its state is reference counted pointers.
+impl<T> Unpin for SharedStream<T> {}
+
+/// Share a stream between any number of subscribers, without retaining any history.
+///
+/// The underlying stream is only polled as far as the polling subscriber needs, so a stream which
+/// is always ready can be shared, and a subscriber which keeps up never lags:
+///
+/// ```
+///    # use futures::stream::{self, StreamExt as _};
+///    # use springsteel::shared_stream::share;
+///    # futures::executor::block_on(async {
+///    let sevens = share(stream::repeat(7));
+///    assert_eq!(sevens.take(3).collect::<Vec<_>>().await, vec![7, 7, 7]);
+///
+///    let mut numbers = share(stream::iter(0..2000));
+///    assert_eq!((&mut numbers).collect::<Vec<_>>().await, (0..2000).collect::<Vec<_>>());
+///    assert_eq!(numbers.lagged(), 0);
+///    # });
+/// ```
+#[track_caller]
+pub fn share<S>(stream: S) -> SharedStream<S::Item>
+where
+    S: Stream + 'static,
+    S::Item: Clone,
+{
+    share_with(stream, ShareOptions::default())
+}
+
+/// Share a stream between any number of subscribers, retaining the last `n` items to replay to
+/// each new subscriber before live items.
+///
+/// ```
+///    # use futures::channel::mpsc;
+///    # use futures::stream::StreamExt as _;
+///    # use springsteel::shared_stream::share_with_replay;
+///    # futures::executor::block_on(async {
+///    let (sender, receiver) = mpsc::unbounded();
+///    let mut early = share_with_replay(receiver, 2);
+///    for n in 1..=3 {
+///        sender.unbounded_send(n).unwrap();
+///        assert_eq!(early.next().await, Some(n));
+///    }
+///
+///    let mut late = early.subscribe();
+///    sender.unbounded_send(4).unwrap();
+///    assert_eq!(late.next().await, Some(2));
+///    assert_eq!(late.next().await, Some(3));
+///    assert_eq!(late.next().await, Some(4));
+///    assert_eq!(early.next().await, Some(4));
+///
+///    drop(sender);
+///    assert_eq!(late.next().await, None);
+///    assert_eq!(early.next().await, None);
+///    # });
+/// ```
+#[track_caller]
+pub fn share_with_replay<S>(stream: S, n: usize) -> SharedStream<S::Item>
+where
+    S: Stream + 'static,
+    S::Item: Clone,
+{
+    share_with(
+        stream,
+        ShareOptions {
+            replay: n,
+            ..ShareOptions::default()
+        },
+    )
+}
+
+/// Share a stream between any number of subscribers, each new subscriber first receiving the
+/// latest item if there has been one. Equivalent to `share_with_replay(stream, 1)`.
+#[track_caller]
+pub fn replay_latest<S>(stream: S) -> SharedStream<S::Item>
+where
+    S: Stream + 'static,
+    S::Item: Clone,
+{
+    share_with_replay(stream, 1)
+}
+
+/// Share a stream between any number of subscribers with explicit [`ShareOptions`].
+#[track_caller]
+pub fn share_with<S>(stream: S, options: ShareOptions) -> SharedStream<S::Item>
+where
+    S: Stream + 'static,
+    S::Item: Clone,
+{
+    let shared = Rc::new(RefCell::new(SharedInner {
+        source_opt: Some(Box::pin(stream)),
+        history: VecDeque::with_capacity(options.replay),
+        options,
+        subscribers: Vec::new(),
+        fanout: Arc::new(FanoutWaker::default()),
+    }));
+    SharedStream::subscribe_to(shared, Location::caller())
+}
+
+impl<T: Clone> SharedStream<T> {
+    /// Create a new subscriber to the same underlying stream, starting with the retained history.
+    #[track_caller]
+    pub fn subscribe(&self) -> Self {
+        Self::subscribe_to(self.shared.clone(), Location::caller())
+    }
+
+    /// Return how many live items this subscriber missed because it fell more than
+    /// [`live_capacity`](ShareOptions::live_capacity) items behind.
+    pub fn lagged(&self) -> u64 {
+        self.subscriber.borrow().lagged
+    }
+
+    fn subscribe_to(
+        shared: Rc<RefCell<SharedInner<T>>>,
+        location: &'static Location<'static>,
+    ) -> Self {
+        let subscriber = Rc::new(RefCell::new(Subscriber {
+            replay: shared.borrow().history.clone(),
+            live: VecDeque::new(),
+            lagged: 0,
+            waker_opt: None,
+            _live: register_live(LiveKind::SharedSubscriber, "SharedStream", location),
+        }));
+        shared
+            .borrow_mut()
+            .subscribers
+            .push(Rc::downgrade(&subscriber));
+        Self { shared, subscriber }
+    }
+}
+
+impl<T: Clone> Clone for SharedStream<T> {
+    #[track_caller]
+    fn clone(&self) -> Self {
+        self.subscribe()
+    }
+}
+
+impl<T: Clone> SharedInner<T> {
+    /// Poll the underlying stream until the polling subscriber has a live item, or the stream
+    /// pends or ends, distributing every item to the history and all live subscribers.
+    fn pump(&mut self, polling: &RefCell<Subscriber<T>>, cx_waker: &Waker) {
+        {
+            let mut wakers = self.fanout.0.lock().unwrap();
+            if !wakers.iter().any(|w| w.will_wake(cx_waker)) {
+                wakers.push(cx_waker.clone());
+            }
+        }
+
+        let fanout = waker(self.fanout.clone());
+        let mut cx = Context::from_waker(&fanout);
+
+        while let Some(source) = self.source_opt.as_mut() {
+            if !polling.borrow().live.is_empty() {
+                break;
+            }
+
+            match source.as_mut().poll_next(&mut cx) {
+                Poll::Ready(Some(item)) => {
+                    if self.options.replay > 0 {
+                        if self.history.len() == self.options.replay {
+                            self.history.pop_front();
+                        }
+                        self.history.push_back(item.clone());
+                    }
+
+                    self.subscribers.retain(|s| s.strong_count() > 0);
+                    for subscriber in self.subscribers.iter().filter_map(Weak::upgrade) {
+                        let mut subscriber = subscriber.borrow_mut();
+                        if subscriber.live.len() >= self.options.live_capacity {
+                            subscriber.live.pop_front();
+                            subscriber.lagged += 1;
+                        }
+                        subscriber.live.push_back(item.clone());
+                        if let Some(w) = subscriber.waker_opt.take() {
+                            w.wake();
+                        }
+                    }
+                }
+                Poll::Ready(None) => {
+                    self.source_opt = None;
+                    for subscriber in self.subscribers.iter().filter_map(Weak::upgrade) {
+                        if let Some(w) = subscriber.borrow_mut().waker_opt.take() {
+                            w.wake();
+                        }
+                    }
+                }
+                Poll::Pending => break,
+            }
+        }
+    }
+}
+
+impl<T: Clone> Stream for SharedStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        if let Some(item) = self.subscriber.borrow_mut().replay.pop_front() {
+            return Poll::Ready(Some(item));
+        }
+
+        self.shared.borrow_mut().pump(&self.subscriber, cx.waker());
+
+        let mut subscriber = self.subscriber.borrow_mut();
+        if let Some(item) = subscriber.live.pop_front() {
+            return Poll::Ready(Some(item));
+        }
+        if self.shared.borrow().source_opt.is_none() {
+            return Poll::Ready(None);
+        }
+
+        subscriber.waker_opt = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
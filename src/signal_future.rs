@@ -0,0 +1,170 @@
+//! Provides [`signal_future`], a [`Future`] which resolves on the next emission of a signal, for
+//! waiting on one-off events like a window being mapped inside a future run by
+//! [`glib_run_future`](crate::glib_run_future), and [`map_signal_future`] to convert the
+//! signal's arguments.
+
+use futures::future::{FutureExt as _, Map};
+use glib::object::{IsA, ObjectExt as _};
+use glib::{Object, SignalHandlerId, Value, WeakRef};
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+/// State of a [`SignalFuture`] shared with its signal handler.
+#[derive(Default)]
+struct SignalFutureInner {
+    /// The arguments of the emission, once it happens and until the future outputs them.
+    values_opt: Option<Vec<Value>>,
+
+    /// The signal handler, until it's disconnected by the emission or by dropping the future.
+    handler_opt: Option<SignalHandlerId>,
+
+    waker_opt: Option<Waker>,
+}
+
+/// A [`Future`] of the arguments of the next emission of a signal, made by [`signal_future`].
+pub struct SignalFuture {
+    object: WeakRef<Object>,
+    inner: Rc<RefCell<SignalFutureInner>>,
+}
+
+/// A [`SignalFuture`] can be unpinned as its state is a reference counted pointer.
+impl Unpin for SignalFuture {}
+
+/// Make a [`Future`] which resolves on the next emission of the named signal of the given object,
+/// outputting the signal's arguments, not including the object itself. The handler is connected
+/// straight away and disconnects itself on the emission, or when the future is dropped.
+///
+/// ```
+///    # use gtk::prelude::ButtonExt as _;
+///    # use springsteel::glib_future::glib_spawn;
+///    # use springsteel::signal_future::signal_future;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let button = gtk::Button::with_label("Go");
+///    let clicked = glib_spawn(signal_future(&button, "clicked"));
+///
+///    while glib::MainContext::default().iteration(false) {}
+///    assert!(!clicked.is_finished());
+///
+///    button.emit_clicked();
+///    while glib::MainContext::default().iteration(false) {}
+///    assert!(clicked.is_finished());
+/// ```
+///
+/// The handler returns no value, so this can only be used with signals which don't return one.
+/// Panics if the object has no such signal.
+pub fn signal_future(obj: &impl IsA<Object>, name: &str) -> SignalFuture {
+    let object = obj.upcast_ref::<Object>();
+    let inner = Rc::new(RefCell::new(SignalFutureInner::default()));
+
+    let inner_for_signal = inner.clone();
+    let object_for_signal = object.downgrade();
+    let handler = object.connect_local(name, false, move |values| {
+        let (handler_opt, waker_opt) = {
+            let mut inner = inner_for_signal.borrow_mut();
+            if inner.values_opt.is_some() {
+                return None;
+            }
+            inner.values_opt = Some(values[1..].to_vec());
+            (inner.handler_opt.take(), inner.waker_opt.take())
+        };
+
+        if let (Some(handler), Some(object)) = (handler_opt, object_for_signal.upgrade()) {
+            object.disconnect(handler);
+        }
+        if let Some(w) = waker_opt {
+            w.wake();
+        }
+        None
+    });
+    inner.borrow_mut().handler_opt = Some(handler);
+
+    SignalFuture {
+        object: object.downgrade(),
+        inner,
+    }
+}
+
+/// Make a [`Future`] which resolves on the next emission of the named signal of the given object
+/// like [`signal_future`], outputting the signal's arguments converted by `f`.
+///
+/// ```
+///    # use gio::prelude::ActionExt as _;
+///    # use glib::ToVariant as _;
+///    # use springsteel::glib_future::glib_block_on;
+///    # use springsteel::signal_future::map_signal_future;
+///    let action = gio::SimpleAction::new("go", Some(glib::VariantTy::INT32));
+///    let activated = map_signal_future(&action, "activate", |values| {
+///        values[0]
+///            .get::<Option<glib::Variant>>()
+///            .ok()
+///            .flatten()
+///            .and_then(|v| v.get::<i32>())
+///    });
+///
+///    action.activate(Some(&7.to_variant()));
+///    assert_eq!(glib_block_on(activated), Some(7));
+/// ```
+pub fn map_signal_future<T, F>(obj: &impl IsA<Object>, name: &str, f: F) -> Map<SignalFuture, F>
+where
+    F: FnOnce(Vec<Value>) -> T,
+{
+    signal_future(obj, name).map(f)
+}
+
+impl Future for SignalFuture {
+    type Output = Vec<Value>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Vec<Value>> {
+        let mut inner = self.inner.borrow_mut();
+        match inner.values_opt.take() {
+            Some(values) => Poll::Ready(values),
+            None => {
+                inner.waker_opt = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for SignalFuture {
+    fn drop(&mut self) {
+        let handler_opt = self.inner.borrow_mut().handler_opt.take();
+        if let (Some(handler), Some(object)) = (handler_opt, self.object.upgrade()) {
+            object.disconnect(handler);
+        }
+    }
+}
+
+/// Guard for signal handlers connected to an object, which disconnects them when dropped. The
+/// stream adapters give one to the stream their handlers push into, so that the handlers go when
+/// the stream does.
+///
+/// Only a weak reference to the object is held, so the guard doesn't keep it alive.
+pub(crate) struct SignalGuard {
+    object: WeakRef<Object>,
+    handlers: Vec<SignalHandlerId>,
+}
+
+impl SignalGuard {
+    /// Guard the given handlers, which should have been connected to the given object.
+    pub(crate) fn new(obj: &impl IsA<Object>, handlers: Vec<SignalHandlerId>) -> Self {
+        Self {
+            object: obj.upcast_ref::<Object>().downgrade(),
+            handlers,
+        }
+    }
+}
+
+impl Drop for SignalGuard {
+    fn drop(&mut self) {
+        if let Some(object) = self.object.upgrade() {
+            for handler in self.handlers.drain(..) {
+                object.disconnect(handler);
+            }
+        }
+    }
+}
@@ -0,0 +1,503 @@
+//! Helpers for testing stream pipelines headlessly, without `gtk::init` or a running main loop.
+//!
+//! Streams are polled with a no-op waker ([`futures::task::noop_waker`]), so nothing is ever
+//! scheduled; instead tests trigger or push explicitly and then poll to see what came out:
+//!
+//! ```
+//!    # use futures::stream::StreamExt as _;
+//!    # use springsteel::{assert_pending, assert_yields};
+//!    # use springsteel::impulse_stream::ImpulseStream;
+//!    let clicks = ImpulseStream::new();
+//!    let mut counts = clicks.clone().scan(0, |n, ()| {
+//!        *n += 1;
+//!        std::future::ready(Some(*n))
+//!    });
+//!    assert_pending!(counts);
+//!
+//!    clicks.trigger();
+//!    clicks.trigger();
+//!    assert_eq!(assert_yields!(counts, 2), vec![1, 2]);
+//!    assert_pending!(counts);
+//! ```
+//!
+//! Pipelines using the timers of [`time`](crate::time) can be tested the same way after installing
+//! [`MockTime`], which only moves time on when told to, and those feeding an
+//! [`InvalidateSink`](crate::invalidate_sink::InvalidateSink) after installing [`ManualTicks`],
+//! which only runs a frame when told to.
+//!
+//! Layouts of a [`ConstraintView`] can be tested with a [`LayoutHarness`], which does need
+//! `gtk::init`.
+
+use crate::invalidate_sink::{set_tick_driver, TickDriver};
+use crate::time::{set_time_driver, TimeDriver, Timer};
+use crate::ConstraintView;
+use futures::stream::{Stream, StreamExt as _};
+use futures::task::noop_waker;
+use glib::{Cast as _, IsA, ObjectExt as _};
+use gtk::prelude::{GtkWindowExt as _, WidgetExt as _};
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Poll the given stream once with a no-op waker.
+pub fn poll_once<S: Stream + Unpin + ?Sized>(stream: &mut S) -> Poll<Option<S::Item>> {
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    stream.poll_next_unpin(&mut cx)
+}
+
+/// Poll the given stream with a no-op waker until it pends or ends, returning every item it
+/// output.
+pub fn take_ready<S: Stream + Unpin + ?Sized>(stream: &mut S) -> Vec<S::Item> {
+    let mut items = Vec::new();
+    while let Poll::Ready(Some(item)) = poll_once(stream) {
+        items.push(item);
+    }
+    items
+}
+
+/// Poll the given future with a no-op waker up to `max_polls` times, returning its output if it
+/// completed or `None` if it was still pending after the last poll.
+///
+/// As nothing runs between polls, this is only useful for futures which make progress by
+/// themselves when polled again, e.g. ones which trigger or push into the streams they await.
+pub fn drive_until<F: Future>(fut: F, max_polls: usize) -> Option<F::Output> {
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut fut = Box::pin(fut);
+    for _ in 0..max_polls {
+        if let Poll::Ready(output) = Pin::as_mut(&mut fut).poll(&mut cx) {
+            return Some(output);
+        }
+    }
+    None
+}
+
+/// Assert that polling the given stream (which must be [`Unpin`]) pends.
+#[macro_export]
+macro_rules! assert_pending {
+    ($stream:expr) => {
+        match $crate::testing::poll_once(&mut $stream) {
+            ::std::task::Poll::Pending => {}
+            ::std::task::Poll::Ready(::std::option::Option::Some(_)) => {
+                panic!(
+                    "expected {} to pend, but it output an item",
+                    stringify!($stream)
+                )
+            }
+            ::std::task::Poll::Ready(::std::option::Option::None) => {
+                panic!("expected {} to pend, but it ended", stringify!($stream))
+            }
+        }
+    };
+}
+
+/// Assert that the given stream (which must be [`Unpin`]) outputs exactly `n` items before pending
+/// or ending, evaluating to a `Vec` of those items.
+#[macro_export]
+macro_rules! assert_yields {
+    ($stream:expr, $n:expr) => {{
+        let items = $crate::testing::take_ready(&mut $stream);
+        assert_eq!(
+            items.len(),
+            $n,
+            "expected {} to output {} items",
+            stringify!($stream),
+            $n
+        );
+        items
+    }};
+}
+
+/// A timer added to [`MockTime`] which hasn't fired or been cancelled.
+struct MockTimer {
+    due: Instant,
+
+    /// Identifies the timer to cancel it, and orders timers due at the same time by when they
+    /// were added.
+    id: u64,
+
+    callback: Box<dyn FnOnce()>,
+}
+
+/// State of a [`MockTime`] shared with the cancellers of its timers.
+struct MockTimeInner {
+    now: Instant,
+    next_id: u64,
+    timers: Vec<MockTimer>,
+}
+
+/// The [`TimeDriver`] installed by [`MockTime`].
+struct MockDriver(Rc<RefCell<MockTimeInner>>);
+
+impl TimeDriver for MockDriver {
+    fn now(&self) -> Instant {
+        self.0.borrow().now
+    }
+
+    fn add_timer(&self, duration: Duration, callback: Box<dyn FnOnce()>) -> Timer {
+        let id = {
+            let mut inner = self.0.borrow_mut();
+            let id = inner.next_id;
+            inner.next_id += 1;
+            let due = inner.now + duration;
+            inner.timers.push(MockTimer { due, id, callback });
+            id
+        };
+
+        let inner_for_cancel = Rc::downgrade(&self.0);
+        Timer::new(move || cancel_mock_timer(&inner_for_cancel, id))
+    }
+}
+
+/// Remove the timer with the given id from a [`MockTime`], if it hasn't fired yet.
+fn cancel_mock_timer(inner: &Weak<RefCell<MockTimeInner>>, id: u64) {
+    let timer_opt = inner.upgrade().and_then(|inner| {
+        let mut inner = inner.borrow_mut();
+        let index = inner.timers.iter().position(|timer| timer.id == id)?;
+        Some(inner.timers.remove(index))
+    });
+    // Drop the callback only once the state isn't borrowed, as it may hold timers of its own.
+    drop(timer_opt);
+}
+
+/// Virtual time for the timers of [`time`](crate::time), which stands still until moved on with
+/// [`advance`](Self::advance), firing whichever timers fall due there and then. Tests of sleeps,
+/// intervals, timeouts, and debouncing thus run in no time at all however long the durations
+/// involved, and always see timers fire in the same order:
+///
+/// ```
+///    # use futures::future::FutureExt as _;
+///    # use springsteel::testing::MockTime;
+///    # use springsteel::time::{interval, sleep};
+///    # use springsteel::{assert_pending, assert_yields};
+///    # use std::time::Duration;
+///    let mock = MockTime::install();
+///    let mut nap = sleep(Duration::from_secs(3600));
+///    let mut ticks = interval(Duration::from_secs(600));
+///    assert_eq!((&mut nap).now_or_never(), None);
+///    assert_pending!(ticks);
+///
+///    mock.advance(Duration::from_secs(3599));
+///    assert_eq!((&mut nap).now_or_never(), None);
+///    assert_yields!(ticks, 1);
+///
+///    mock.advance(Duration::from_secs(1));
+///    assert_eq!(nap.now_or_never(), Some(()));
+///    assert_yields!(ticks, 1);
+/// ```
+///
+/// Only combinators made after installing it on the same thread use it, and the previous
+/// [`TimeDriver`] is reinstated when it's dropped.
+pub struct MockTime {
+    inner: Rc<RefCell<MockTimeInner>>,
+
+    /// The driver replaced by [`install`](Self::install), to reinstate when dropped.
+    previous_opt: Option<Rc<dyn TimeDriver>>,
+}
+
+impl MockTime {
+    /// Install a new `MockTime` as the [`TimeDriver`] of this thread, starting at the present.
+    pub fn install() -> Self {
+        let inner = Rc::new(RefCell::new(MockTimeInner {
+            now: Instant::now(),
+            next_id: 0,
+            timers: Vec::new(),
+        }));
+        let previous = set_time_driver(Rc::new(MockDriver(inner.clone())));
+        Self {
+            inner,
+            previous_opt: Some(previous),
+        }
+    }
+
+    /// Return the virtual present.
+    pub fn now(&self) -> Instant {
+        self.inner.borrow().now
+    }
+
+    /// Return how many timers are waiting to fire.
+    pub fn pending_timers(&self) -> usize {
+        self.inner.borrow().timers.len()
+    }
+
+    /// Move time on by `duration`, calling the callback of every timer which falls due on the way
+    /// in order of when it's due, with [`now`](Self::now) at that time. Timers added by callbacks
+    /// which fall due by the end fire too, so a zero period [`interval`](crate::time::interval)
+    /// would keep this from ever returning.
+    ///
+    /// Callbacks wake the tasks waiting on the timers rather than poll them, so anything run with
+    /// [`glib_run_future`](crate::glib_run_future) still needs the main loop to iterate afterwards.
+    pub fn advance(&self, duration: Duration) {
+        let end = self.now() + duration;
+        loop {
+            let timer_opt = {
+                let mut inner = self.inner.borrow_mut();
+                let next_opt = inner
+                    .timers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, timer)| timer.due <= end)
+                    .min_by_key(|(_, timer)| (timer.due, timer.id))
+                    .map(|(index, _)| index);
+                next_opt.map(|index| {
+                    let timer = inner.timers.remove(index);
+                    inner.now = inner.now.max(timer.due);
+                    timer
+                })
+            };
+            match timer_opt {
+                Some(timer) => (timer.callback)(),
+                None => break,
+            }
+        }
+        self.inner.borrow_mut().now = end;
+    }
+}
+
+impl Drop for MockTime {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous_opt.take() {
+            set_time_driver(previous);
+        }
+    }
+}
+
+/// The tick callbacks waiting for the next frame of a [`ManualTicks`].
+type PendingTicks = Rc<RefCell<Vec<Box<dyn FnOnce()>>>>;
+
+/// The [`TickDriver`] installed by [`ManualTicks`].
+struct ManualTickDriver(PendingTicks);
+
+impl TickDriver for ManualTickDriver {
+    fn add_tick(&self, _widget: &gtk::Widget, callback: Box<dyn FnOnce()>) {
+        self.0.borrow_mut().push(callback);
+    }
+}
+
+/// Frame ticks for [`InvalidateSink`](crate::invalidate_sink::InvalidateSink)s which only come
+/// when run with [`tick`](Self::tick), whether or not the widgets are shown, so that tests can
+/// check what happens per frame without a window or waiting on the frame clock. See
+/// [`draws_queued`](crate::invalidate_sink::InvalidateSink::draws_queued) for an example.
+///
+/// Only sinks made after installing it on the same thread use it, and the previous
+/// [`TickDriver`] is reinstated when it's dropped.
+pub struct ManualTicks {
+    pending: PendingTicks,
+
+    /// The driver replaced by [`install`](Self::install), to reinstate when dropped.
+    previous_opt: Option<Rc<dyn TickDriver>>,
+}
+
+impl ManualTicks {
+    /// Install a new `ManualTicks` as the [`TickDriver`] of this thread.
+    pub fn install() -> Self {
+        let pending = PendingTicks::default();
+        let previous = set_tick_driver(Rc::new(ManualTickDriver(pending.clone())));
+        Self {
+            pending,
+            previous_opt: Some(previous),
+        }
+    }
+
+    /// Return how many tick callbacks are waiting for the next frame.
+    pub fn pending_ticks(&self) -> usize {
+        self.pending.borrow().len()
+    }
+
+    /// Run a frame, calling every tick callback added before it in the order they were added.
+    /// Callbacks added by those wait for the next frame, as with a frame clock.
+    pub fn tick(&self) {
+        let callbacks = self.pending.take();
+        for callback in callbacks {
+            callback();
+        }
+    }
+}
+
+impl Drop for ManualTicks {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous_opt.take() {
+            set_tick_driver(previous);
+        }
+    }
+}
+
+/// Lays out a [`ConstraintView`] at a given size inside a [`gtk::Window`] which is never shown, to
+/// test where its children end up, with assertions whose failure messages include every
+/// constraint of the view as by [`design_export`](ConstraintView::design_export):
+///
+/// ```
+///    # use springsteel::testing::LayoutHarness;
+///    # use springsteel::{add_constraint, ConstraintView};
+///    # use gtk::prelude::WidgetExt as _;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let view = ConstraintView::new();
+///    let (icon, title) = (gtk::Image::new(), gtk::Label::new(Some("Title")));
+///    icon.set_widget_name("icon");
+///    title.set_widget_name("title");
+///    view.add_child(&icon);
+///    view.add_child(&title);
+///    add_constraint!(view, icon.left == left + 8.0);
+///    add_constraint!(view, icon.top == top + 8.0);
+///    add_constraint!(view, icon.width == 32.0);
+///    add_constraint!(view, icon.height == 32.0);
+///    add_constraint!(view, title.left == icon.right + 8.0);
+///    add_constraint!(view, title.top == icon.top);
+///
+///    let mut harness = LayoutHarness::new(&view, 320, 200);
+///    harness.assert_size(&icon, 32, 32);
+///    harness.assert_left_of(&icon, &title);
+///    assert_eq!(harness.allocation_of(&title).x(), 48);
+///
+///    harness.resize(100, 50);
+///    assert_eq!(harness.allocation_of(&icon).y(), 8);
+/// ```
+///
+/// The view is laid out afresh, after iterating the default main context until it's idle, each
+/// time an allocation is asked for, so changes to it in between are reflected. It's taken out of
+/// the window again when the harness is dropped.
+pub struct LayoutHarness {
+    window: gtk::Window,
+    view: ConstraintView,
+    width: i32,
+    height: i32,
+}
+
+impl LayoutHarness {
+    /// Put the given view, which mustn't have a parent, into a window to lay it out at the given
+    /// size.
+    pub fn new(view: &ConstraintView, width: i32, height: i32) -> Self {
+        if let Some(parent) = view.parent() {
+            panic!(
+                "LayoutHarness::new: view {} already has a parent {}",
+                view.widget_name(),
+                parent.widget_name()
+            );
+        }
+
+        let window = gtk::Window::new();
+        window.set_default_size(width, height);
+        window.set_child(Some(view));
+        let harness = Self {
+            window,
+            view: view.clone(),
+            width,
+            height,
+        };
+        harness.relayout();
+        harness
+    }
+
+    /// Return the view being laid out.
+    pub fn view(&self) -> &ConstraintView {
+        &self.view
+    }
+
+    /// Lay out the view at a new size.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.width = width;
+        self.height = height;
+        self.window.set_default_size(width, height);
+        self.relayout();
+    }
+
+    /// Lay out the view at its size, first iterating the default main context until it's idle so
+    /// that pending work, e.g. of streams driving constraints, is done.
+    pub fn relayout(&self) {
+        while glib::MainContext::default().iteration(false) {}
+        self.view.measure(gtk::Orientation::Horizontal, -1);
+        self.view.measure(gtk::Orientation::Vertical, -1);
+        self.view
+            .size_allocate(&gtk::Allocation::new(0, 0, self.width, self.height), -1);
+    }
+
+    /// [Lay out](Self::relayout) the view and return the bounds of the given widget relative to
+    /// it, rounded to whole pixels.
+    ///
+    /// Panics if the widget isn't in the view.
+    pub fn allocation_of(&self, widget: &impl IsA<gtk::Widget>) -> gtk::Allocation {
+        self.relayout();
+        let bounds = match widget.compute_bounds(&self.view) {
+            Some(bounds) => bounds,
+            None => self.fail(&format!("{} isn't in the view", describe(widget))),
+        };
+        gtk::Allocation::new(
+            bounds.x().round() as i32,
+            bounds.y().round() as i32,
+            bounds.width().round() as i32,
+            bounds.height().round() as i32,
+        )
+    }
+
+    /// Assert that the right edge of `a` is at or left of the left edge of `b`.
+    pub fn assert_left_of(&self, a: &impl IsA<gtk::Widget>, b: &impl IsA<gtk::Widget>) {
+        let (a_bounds, b_bounds) = (self.allocation_of(a), self.allocation_of(b));
+        if a_bounds.x() + a_bounds.width() > b_bounds.x() {
+            self.fail(&format!(
+                "expected {} (right edge at {}) to be left of {} (left edge at {})",
+                describe(a),
+                a_bounds.x() + a_bounds.width(),
+                describe(b),
+                b_bounds.x()
+            ));
+        }
+    }
+
+    /// Assert that the bottom edge of `a` is at or above the top edge of `b`.
+    pub fn assert_above(&self, a: &impl IsA<gtk::Widget>, b: &impl IsA<gtk::Widget>) {
+        let (a_bounds, b_bounds) = (self.allocation_of(a), self.allocation_of(b));
+        if a_bounds.y() + a_bounds.height() > b_bounds.y() {
+            self.fail(&format!(
+                "expected {} (bottom edge at {}) to be above {} (top edge at {})",
+                describe(a),
+                a_bounds.y() + a_bounds.height(),
+                describe(b),
+                b_bounds.y()
+            ));
+        }
+    }
+
+    /// Assert that the given widget is allocated the given size.
+    pub fn assert_size(&self, widget: &impl IsA<gtk::Widget>, width: i32, height: i32) {
+        let bounds = self.allocation_of(widget);
+        if (bounds.width(), bounds.height()) != (width, height) {
+            self.fail(&format!(
+                "expected {} to be {}x{}, but it's {}x{}",
+                describe(widget),
+                width,
+                height,
+                bounds.width(),
+                bounds.height()
+            ));
+        }
+    }
+
+    /// Panic with the given message followed by the constraints of the view.
+    fn fail(&self, message: &str) -> ! {
+        panic!(
+            "{}\nat {}x{}, with constraints:\n{}",
+            message,
+            self.width,
+            self.height,
+            self.view.design_export()
+        )
+    }
+}
+
+impl Drop for LayoutHarness {
+    fn drop(&mut self) {
+        self.window.set_child(None::<&gtk::Widget>);
+        self.window.destroy();
+    }
+}
+
+/// Describe a widget for a failure message by its widget name and type.
+fn describe(widget: &impl IsA<gtk::Widget>) -> String {
+    let widget = widget.upcast_ref::<gtk::Widget>();
+    format!("{} ({})", widget.widget_name(), widget.type_().name())
+}
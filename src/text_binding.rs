@@ -0,0 +1,535 @@
+//! Provides bindings from a [`Stream`] of strings to text widgets which apply each new string as a
+//! minimal edit rather than replacing the whole text: [`bind_text_diff`] for
+//! [`gtk::TextView`]/[`gtk::TextBuffer`] and [`bind_label_text`] for [`gtk::Label`]; and the
+//! reverse, [`buffer_changes`] to make a stream of the edits made to a [`gtk::TextBuffer`].
+
+use crate::debug::{register_live, LiveKind};
+use crate::signal_future::SignalGuard;
+use crate::value_stream::ValueStream;
+use futures::stream::{Stream, StreamExt as _};
+use gtk::prelude::{ObjectExt as _, TextBufferExt as _, TextViewExt as _};
+use std::cell::Cell;
+use std::future::Future;
+use std::ops::Range;
+use std::panic::Location;
+use std::pin::Pin;
+use std::rc::Rc;
+
+/// Something which has a [`gtk::TextBuffer`] to bind text into, i.e. the buffer itself or a
+/// [`gtk::TextView`] showing it.
+pub trait TextBufferSource {
+    /// Return the buffer to edit.
+    fn text_buffer(&self) -> gtk::TextBuffer;
+}
+
+impl TextBufferSource for gtk::TextBuffer {
+    fn text_buffer(&self) -> gtk::TextBuffer {
+        self.clone()
+    }
+}
+
+impl TextBufferSource for gtk::TextView {
+    fn text_buffer(&self) -> gtk::TextBuffer {
+        self.buffer()
+    }
+}
+
+/// Options for [`bind_text_diff_with`] and [`apply_text_diff`].
+#[derive(Clone, Copy, Debug)]
+pub struct TextDiffOptions {
+    /// Fraction of the new text (by characters) which may change before giving up on a minimal
+    /// edit and replacing the whole buffer instead. `0.5` by default.
+    pub max_change_ratio: f64,
+
+    /// Upper bound on the number of line pairs compared when diffing the changed middle of the
+    /// text. Larger changed regions are replaced as a single edit. `250_000` by default.
+    pub max_diff_cells: usize,
+}
+
+impl Default for TextDiffOptions {
+    fn default() -> Self {
+        Self {
+            max_change_ratio: 0.5,
+            max_diff_cells: 250_000,
+        }
+    }
+}
+
+/// One edit of a line diff: replace the characters `old` of the old text with the characters
+/// `new` of the new text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct TextEdit {
+    old: Range<usize>,
+    new: Range<usize>,
+}
+
+/// Bind a [`Stream`] of strings to a [`gtk::TextBuffer`] (or the buffer of a [`gtk::TextView`]),
+/// using [`apply_text_diff`] with default [`TextDiffOptions`] for each string.
+///
+/// The returned future should be run with [`glib_run_future`](crate::glib_run_future), and
+/// completes when the stream ends or the buffer no longer exists, e.g.
+///
+/// ```
+///    # use springsteel::glib_run_future;
+///    # use springsteel::text_binding::bind_text_diff;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let log_view = gtk::TextView::new();
+///    let (log_sender, log_texts) = futures::channel::mpsc::unbounded::<String>();
+///    glib_run_future(bind_text_diff(&log_view, log_texts));
+///    # drop(log_sender);
+/// ```
+///
+/// Appending to the text leaves the cursor and the scroll position where they were:
+///
+/// ```
+///    # use springsteel::glib_run_future;
+///    # use springsteel::text_binding::bind_text_diff;
+///    # use gtk::prelude::{AdjustmentExt as _, TextBufferExt as _, WidgetExt as _};
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let log_view = gtk::TextView::new();
+///    let scrolled = gtk::ScrolledWindow::new();
+///    scrolled.set_child(Some(&log_view));
+///    let (log_sender, log_texts) = futures::channel::mpsc::unbounded::<String>();
+///    glib_run_future(bind_text_diff(&log_view, log_texts));
+///
+///    let context = glib::MainContext::default();
+///    let lay_out = || {
+///        scrolled.measure(gtk::Orientation::Horizontal, -1);
+///        scrolled.measure(gtk::Orientation::Vertical, -1);
+///        scrolled.size_allocate(&gtk::Allocation::new(0, 0, 300, 100), -1);
+///        while context.iteration(false) {}
+///    };
+///
+///    let mut log: String = (1..=100).map(|n| format!("line {}\n", n)).collect();
+///    log_sender.unbounded_send(log.clone()).unwrap();
+///    lay_out();
+///
+///    let buffer = log_view.buffer();
+///    buffer.place_cursor(&buffer.iter_at_line_offset(20, 3).expect("line 20"));
+///    let vadjustment = scrolled.vadjustment();
+///    vadjustment.set_value(200.0);
+///    lay_out();
+///    let scrolled_to = vadjustment.value();
+///
+///    log.push_str("line 101\n");
+///    log_sender.unbounded_send(log.clone()).unwrap();
+///    lay_out();
+///
+///    let cursor = buffer.iter_at_mark(&buffer.get_insert());
+///    assert_eq!((cursor.line(), cursor.line_offset()), (20, 3));
+///    assert_eq!(vadjustment.value(), scrolled_to);
+///    let (start, end) = buffer.bounds();
+///    assert_eq!(buffer.text(&start, &end, true).as_str(), log);
+/// ```
+#[track_caller]
+pub fn bind_text_diff<T, S>(target: &T, stream: S) -> impl Future<Output = ()> + Unpin
+where
+    T: TextBufferSource,
+    S: Stream<Item = String> + 'static,
+{
+    bind_text_diff_with(target, stream, TextDiffOptions::default())
+}
+
+/// Like [`bind_text_diff`] but with explicit [`TextDiffOptions`].
+#[track_caller]
+pub fn bind_text_diff_with<T, S>(
+    target: &T,
+    stream: S,
+    options: TextDiffOptions,
+) -> impl Future<Output = ()> + Unpin
+where
+    T: TextBufferSource,
+    S: Stream<Item = String> + 'static,
+{
+    let buffer_weak = target.text_buffer().downgrade();
+    let live = register_live(LiveKind::Binding, "bind_text_diff", Location::caller());
+    Box::pin(async move {
+        let _live = live;
+        let mut stream = Box::pin(stream);
+        while let Some(text) = stream.next().await {
+            let buffer = match buffer_weak.upgrade() {
+                Some(buffer) => buffer,
+                None => break,
+            };
+            apply_text_diff(&buffer, &text, &options);
+        }
+    }) as Pin<Box<dyn Future<Output = ()>>>
+}
+
+/// Replace the content of `buffer` with `text` using the fewest line-wise insertions and deletions
+/// practical, so that marks (including the cursor and selection) and the scroll position of any
+/// view are preserved wherever the edits don't touch them.
+///
+/// If more than [`max_change_ratio`](TextDiffOptions::max_change_ratio) of the text would change,
+/// the whole buffer is replaced instead, as the minimal edit is unlikely to preserve anything
+/// meaningful.
+///
+/// Either way the buffer ends up with the same text as if it had been set outright:
+///
+/// ```
+///    # use springsteel::text_binding::{apply_text_diff, TextDiffOptions};
+///    # use gtk::prelude::TextBufferExt as _;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let texts = [
+///        "one\ntwo\nthree\n",
+///        "one\ntwo\nthree\nfour\n",
+///        "zero\none\ntwo\nthree\nfour",
+///        "zero\none\n2\nthree\nfour",
+///        "zero\nthree\nfour\n",
+///        "",
+///        "ünïcödé\n\n\nlines\n",
+///        "ünïcödé\nlines\n",
+///    ];
+///    let text_of = |buffer: &gtk::TextBuffer| {
+///        let (start, end) = buffer.bounds();
+///        buffer.text(&start, &end, true).to_string()
+///    };
+///
+///    let always_diff = TextDiffOptions {
+///        max_change_ratio: f64::INFINITY,
+///        ..TextDiffOptions::default()
+///    };
+///    for options in [TextDiffOptions::default(), always_diff] {
+///        let diffed = gtk::TextBuffer::new(None);
+///        let reference = gtk::TextBuffer::new(None);
+///        for text in texts {
+///            apply_text_diff(&diffed, text, &options);
+///            reference.set_text(text);
+///            assert_eq!(text_of(&diffed), text_of(&reference));
+///        }
+///    }
+/// ```
+pub fn apply_text_diff(buffer: &gtk::TextBuffer, text: &str, options: &TextDiffOptions) {
+    let (start, end) = buffer.bounds();
+    let current = buffer.text(&start, &end, true);
+    if current.as_str() == text {
+        return;
+    }
+
+    let edits = line_diff(current.as_str(), text, options.max_diff_cells);
+    let changed: usize = edits.iter().map(|e| e.old.len().max(e.new.len())).sum();
+    let total = text.chars().count().max(1);
+
+    if changed as f64 / total as f64 > options.max_change_ratio {
+        buffer.set_text(text);
+        return;
+    }
+
+    let new_chars: Vec<char> = text.chars().collect();
+    buffer.begin_user_action();
+
+    // Apply from the end so that earlier offsets stay valid.
+    for edit in edits.iter().rev() {
+        let mut old_start = buffer.iter_at_offset(edit.old.start as i32);
+        if !edit.old.is_empty() {
+            let mut old_end = buffer.iter_at_offset(edit.old.end as i32);
+            buffer.delete(&mut old_start, &mut old_end);
+        }
+        if !edit.new.is_empty() {
+            let inserted: String = new_chars[edit.new.clone()].iter().collect();
+            buffer.insert(&mut old_start, &inserted);
+        }
+    }
+
+    buffer.end_user_action();
+}
+
+/// Compute the line-wise edits turning `old` into `new`, as character ranges.
+///
+/// Common leading and trailing lines are trimmed first, which makes the common append-only and
+/// single-region edits cheap. The remaining middle is diffed by longest common subsequence unless
+/// it is larger than `max_cells` line pairs, in which case it is treated as a single edit.
+fn line_diff(old: &str, new: &str, max_cells: usize) -> Vec<TextEdit> {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+
+    let prefix = old_lines
+        .iter()
+        .zip(new_lines.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_mid = &old_lines[prefix..old_lines.len() - suffix];
+    let new_mid = &new_lines[prefix..new_lines.len() - suffix];
+
+    let char_len = |lines: &[&str]| -> usize { lines.iter().map(|l| l.chars().count()).sum() };
+    let old_base = char_len(&old_lines[..prefix]);
+    let new_base = char_len(&new_lines[..prefix]);
+
+    if old_mid.is_empty() && new_mid.is_empty() {
+        return Vec::new();
+    }
+
+    if old_mid.is_empty()
+        || new_mid.is_empty()
+        || old_mid.len().saturating_mul(new_mid.len()) > max_cells
+    {
+        return vec![TextEdit {
+            old: old_base..old_base + char_len(old_mid),
+            new: new_base..new_base + char_len(new_mid),
+        }];
+    }
+
+    // lcs[i][j] is the length of the longest common subsequence of old_mid[i..] and new_mid[j..]
+    let (n, m) = (old_mid.len(), new_mid.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut old_pos, mut new_pos) = (old_base, new_base);
+    let mut pending: Option<TextEdit> = None;
+
+    while i < n || j < m {
+        if i < n && j < m && old_mid[i] == new_mid[j] {
+            edits.extend(pending.take());
+            let len = old_mid[i].chars().count();
+            old_pos += len;
+            new_pos += len;
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        let edit = pending.get_or_insert(TextEdit {
+            old: old_pos..old_pos,
+            new: new_pos..new_pos,
+        });
+
+        if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            new_pos += new_mid[j].chars().count();
+            edit.new.end = new_pos;
+            j += 1;
+        } else {
+            old_pos += old_mid[i].chars().count();
+            edit.old.end = old_pos;
+            i += 1;
+        }
+    }
+    edits.extend(pending);
+
+    edits
+}
+
+/// Bind a [`Stream`] of strings to the text of a [`gtk::Label`], skipping strings identical to
+/// what's already shown so that a selection in a selectable label survives redundant updates.
+///
+/// If the label [uses markup](gtk::Label::uses_markup) the strings are treated as plain text and
+/// escaped before being set, so that arbitrary content (e.g. JSON with `<` and `&`) can't break or
+/// inject markup.
+///
+/// The returned future should be run with [`glib_run_future`](crate::glib_run_future), and
+/// completes when the stream ends or the label no longer exists.
+#[track_caller]
+pub fn bind_label_text<S>(label: &gtk::Label, stream: S) -> impl Future<Output = ()> + Unpin
+where
+    S: Stream<Item = String> + 'static,
+{
+    let label_weak = label.downgrade();
+    let live = register_live(LiveKind::Binding, "bind_label_text", Location::caller());
+    Box::pin(async move {
+        let _live = live;
+        let mut stream = Box::pin(stream);
+        while let Some(text) = stream.next().await {
+            let label = match label_weak.upgrade() {
+                Some(label) => label,
+                None => break,
+            };
+
+            if label.text().as_str() == text {
+                continue;
+            }
+
+            if label.uses_markup() {
+                label.set_markup(&glib::markup_escape_text(&text));
+            } else {
+                label.set_text(&text);
+            }
+        }
+    }) as Pin<Box<dyn Future<Output = ()>>>
+}
+
+/// How [`buffer_changes`] reports changes to a [`gtk::TextBuffer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferChangeMode {
+    /// Output the whole text as [`BufferChange::Text`] after every change. Simple, but costs a
+    /// copy of the whole buffer per change.
+    Full,
+
+    /// Output each insertion and deletion as [`BufferChange::Insert`] and
+    /// [`BufferChange::Delete`], so large buffers aren't copied.
+    Incremental,
+}
+
+/// A change to a [`gtk::TextBuffer`] output by [`buffer_changes`]. Offsets are in characters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BufferChange {
+    /// The whole text after a change, in [`BufferChangeMode::Full`].
+    Text(String),
+
+    /// `text` was inserted at `offset`.
+    Insert {
+        /// Character offset of the start of the inserted text.
+        offset: i32,
+
+        /// The inserted text.
+        text: String,
+    },
+
+    /// `len` characters were deleted starting at `offset`.
+    Delete {
+        /// Character offset of the start of the deleted range.
+        offset: i32,
+
+        /// How many characters were deleted.
+        len: i32,
+    },
+}
+
+impl BufferChange {
+    /// Apply this change to a shadow copy of the buffer text, which should have had every prior
+    /// change applied.
+    pub fn apply(&self, shadow: &mut String) {
+        let byte_index = |s: &str, offset: i32| {
+            s.char_indices()
+                .nth(offset as usize)
+                .map_or(s.len(), |(i, _)| i)
+        };
+        match self {
+            Self::Text(text) => *shadow = text.clone(),
+            Self::Insert { offset, text } => {
+                let at = byte_index(shadow, *offset);
+                shadow.insert_str(at, text);
+            }
+            Self::Delete { offset, len } => {
+                let start = byte_index(shadow, *offset);
+                let end = byte_index(shadow, offset + len);
+                shadow.replace_range(start..end, "");
+            }
+        }
+    }
+}
+
+/// Make a [`ValueStream`] of the changes to a [`gtk::TextBuffer`] (or the buffer of a
+/// [`gtk::TextView`]), either the whole text per change or each insertion and deletion.
+///
+/// Incremental changes are output from handlers connected after the default ones, so are output
+/// in the order they were applied and each change's offsets are relative to the text after all
+/// prior changes, so applying them in order to a copy of the initial text tracks the buffer:
+///
+/// ```
+///    # use springsteel::testing::take_ready;
+///    # use springsteel::text_binding::{buffer_changes, BufferChangeMode};
+///    # use gtk::prelude::TextBufferExt as _;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let buffer = gtk::TextBuffer::new(None);
+///    buffer.set_text("hello world");
+///    let mut changes = buffer_changes(&buffer, BufferChangeMode::Incremental);
+///
+///    let mut shadow = String::from("hello world");
+///    buffer.insert(&mut buffer.start_iter(), "¡");
+///    buffer.delete(&mut buffer.iter_at_offset(6), &mut buffer.iter_at_offset(7));
+///    buffer.insert(&mut buffer.end_iter(), "!");
+///    for change in take_ready(&mut changes) {
+///        change.apply(&mut shadow);
+///    }
+///
+///    let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), true);
+///    assert_eq!(shadow, text.as_str());
+///    assert_eq!(shadow, "¡helloworld!");
+/// ```
+///
+/// The signal handlers only hold the stream weakly, and are disconnected when it's dropped:
+///
+/// ```
+///    # use springsteel::text_binding::{buffer_changes, BufferChangeMode};
+///    # use glib::StaticType as _;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let buffer = gtk::TextBuffer::new(None);
+///    let changed = glib::subclass::SignalId::lookup("changed", gtk::TextBuffer::static_type())
+///        .expect("changed signal");
+///    let connected = || glib::signal::signal_has_handler_pending(&buffer, changed, None, true);
+///
+///    let changes = buffer_changes(&buffer, BufferChangeMode::Full);
+///    assert!(connected());
+///    drop(changes);
+///    assert!(!connected());
+/// ```
+pub fn buffer_changes<T: TextBufferSource>(
+    target: &T,
+    mode: BufferChangeMode,
+) -> ValueStream<BufferChange> {
+    let buffer = target.text_buffer();
+    let changes = ValueStream::new();
+
+    let handlers = match mode {
+        BufferChangeMode::Full => {
+            let changes_for_changed = changes.downgrade();
+            let changed = buffer.connect_changed(move |buffer| {
+                let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), true);
+                changes_for_changed.push(BufferChange::Text(text.into()));
+            });
+            vec![changed]
+        }
+
+        BufferChangeMode::Incremental => {
+            // After the default handler the location iter has been revalidated to the end of the
+            // inserted text.
+            let changes_for_insert = changes.downgrade();
+            let insert = buffer.connect_local("insert-text", true, move |args| {
+                let end = args[1]
+                    .get::<gtk::TextIter>()
+                    .expect("insert-text location");
+                let text = args[2].get::<String>().expect("insert-text text");
+                let offset = end.offset() - text.chars().count() as i32;
+                changes_for_insert.push(BufferChange::Insert { offset, text });
+                None
+            });
+
+            // After the default handler both iters point at where the range was, so its length
+            // has to be captured before.
+            let deleting = Rc::new(Cell::new(0));
+            let deleting_for_before = deleting.clone();
+            let before_delete = buffer.connect_delete_range(move |_, start, end| {
+                deleting_for_before.set(end.offset() - start.offset());
+            });
+
+            let changes_for_delete = changes.downgrade();
+            let delete = buffer.connect_local("delete-range", true, move |args| {
+                let start = args[1].get::<gtk::TextIter>().expect("delete-range start");
+                let len = deleting.take();
+                if len != 0 {
+                    changes_for_delete.push(BufferChange::Delete {
+                        offset: start.offset(),
+                        len,
+                    });
+                }
+                None
+            });
+
+            vec![insert, before_delete, delete]
+        }
+    };
+    changes.hold(SignalGuard::new(&buffer, handlers));
+
+    changes
+}
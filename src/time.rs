@@ -0,0 +1,565 @@
+//! Timer [`Stream`]s and [`Future`]s which run on the glib main loop, so no separate async runtime
+//! is needed: [`interval`], [`interval_with`], [`sleep`], [`with_timeout`], and
+//! [`retry_with_backoff`].
+//!
+//! Their timers come from the [`TimeDriver`] of the thread which made them, which is [`GlibTime`]
+//! unless replaced with [`set_time_driver`], e.g. by [`MockTime`](crate::testing::MockTime) so that
+//! tests control time rather than wait for it.
+
+use futures::stream::Stream;
+use glib::thread_guard::ThreadGuard;
+use glib::Continue;
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Source of the current time and of one-shot timers for the combinators in this module.
+pub trait TimeDriver {
+    /// Return the current time.
+    fn now(&self) -> Instant;
+
+    /// Call `callback` once, `duration` from now, unless the returned [`Timer`] is dropped first.
+    fn add_timer(&self, duration: Duration, callback: Box<dyn FnOnce()>) -> Timer;
+}
+
+/// A timer added by [`TimeDriver::add_timer`], which is cancelled if it's dropped before it fires.
+pub struct Timer(Option<Box<dyn FnOnce()>>);
+
+impl Timer {
+    /// Make a `Timer` which calls `cancel` when dropped. Dropping it from or after its callback
+    /// must be harmless, so `cancel` has to do nothing once the timer has fired.
+    pub fn new(cancel: impl FnOnce() + 'static) -> Self {
+        Self(Some(Box::new(cancel)))
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(cancel) = self.0.take() {
+            cancel();
+        }
+    }
+}
+
+/// The default [`TimeDriver`], using the system clock and glib timeouts attached to the thread
+/// default main context, which is the one [`glib_run_future`](crate::glib_run_future) runs futures
+/// on, rather than the global default context.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GlibTime;
+
+impl TimeDriver for GlibTime {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn add_timer(&self, duration: Duration, callback: Box<dyn FnOnce()>) -> Timer {
+        // The source is held rather than its id, which would have to be looked up again in the
+        // context it was attached to. The guard lets the callback go in a source, which has to be
+        // `Send`, and it's only ever called or dropped on this thread.
+        let mut callback_opt = Some(ThreadGuard::new(callback));
+        let source = glib::timeout_source_new(duration, None, glib::PRIORITY_DEFAULT, move || {
+            if let Some(callback) = callback_opt.take() {
+                (callback.into_inner())();
+            }
+            Continue(false)
+        });
+        source.attach(Some(&glib::MainContext::ref_thread_default()));
+        Timer::new(move || {
+            // The source is destroyed once it's fired, so it mustn't be destroyed again.
+            if !source.is_destroyed() {
+                source.destroy();
+            }
+        })
+    }
+}
+
+thread_local! {
+    /// The [`TimeDriver`] used by combinators made on this thread.
+    static DRIVER: RefCell<Rc<dyn TimeDriver>> = RefCell::new(Rc::new(GlibTime));
+}
+
+/// Use the given [`TimeDriver`] for the combinators in this module made on this thread from now
+/// on, returning the one used until now. Those made already keep the driver they were made with.
+pub fn set_time_driver(driver: Rc<dyn TimeDriver>) -> Rc<dyn TimeDriver> {
+    DRIVER.with(|d| std::mem::replace(&mut *d.borrow_mut(), driver))
+}
+
+/// Return the [`TimeDriver`] used by combinators made on this thread.
+pub fn time_driver() -> Rc<dyn TimeDriver> {
+    DRIVER.with(|d| d.borrow().clone())
+}
+
+/// What an [`Interval`] does with ticks which elapsed while nothing was polling it, or while the
+/// main loop was too busy to dispatch its timeout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissedTicks {
+    /// Output a single `()` however many ticks were missed. This is the default, and suits timers
+    /// which refresh some state, where catching up would only redo the same work.
+    Skip,
+
+    /// Output one `()` for every tick missed, back to back. This suits timers which count time.
+    Burst,
+}
+
+impl Default for MissedTicks {
+    fn default() -> Self {
+        Self::Skip
+    }
+}
+
+/// State of an [`Interval`] shared with its timeout callback.
+struct IntervalInner {
+    /// How many ticks are waiting to be output.
+    pending: usize,
+
+    /// When the next tick is due, used to count ticks missed while the main loop was busy.
+    next_due: Instant,
+
+    waker_opt: Option<std::task::Waker>,
+
+    /// The timer for the next tick, once started.
+    timer_opt: Option<Timer>,
+}
+
+/// An infinite [`Stream`] which outputs `()` every period, made by [`interval`] or
+/// [`interval_with`].
+///
+/// The underlying timer is only added the first time the stream is polled, and is removed when
+/// the stream is dropped.
+pub struct Interval {
+    period: Duration,
+    missed: MissedTicks,
+    driver: Rc<dyn TimeDriver>,
+    inner: Rc<RefCell<IntervalInner>>,
+    started: bool,
+}
+
+/// An [`Interval`] can be unpinned as its state is a reference counted pointer.
+impl Unpin for Interval {}
+
+/// Make a [`Stream`] which outputs `()` every `period` using a glib timeout on the thread default
+/// main context, outputting only once for any number of missed ticks. See [`interval_with`] to
+/// output every missed tick instead.
+///
+/// ```
+///    # use futures::stream::StreamExt as _;
+///    # use springsteel::glib_run_future;
+///    # use springsteel::time::interval;
+///    # use std::future::ready;
+///    # use std::time::Duration;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    glib_run_future(interval(Duration::from_secs(1)).for_each(|()| {
+///        println!("tick");
+///        ready(())
+///    }));
+/// ```
+pub fn interval(period: Duration) -> Interval {
+    interval_with(period, MissedTicks::Skip)
+}
+
+/// Make a [`Stream`] which outputs `()` every `period` using a glib timeout on the thread default
+/// main context, handling missed ticks as given.
+pub fn interval_with(period: Duration, missed: MissedTicks) -> Interval {
+    let driver = time_driver();
+    let next_due = driver.now();
+    Interval {
+        period,
+        missed,
+        driver,
+        inner: Rc::new(RefCell::new(IntervalInner {
+            pending: 0,
+            next_due,
+            waker_opt: None,
+            timer_opt: None,
+        })),
+        started: false,
+    }
+}
+
+impl Interval {
+    /// Add the timer, with the first tick due one period from now.
+    fn start(&mut self) {
+        self.started = true;
+        self.inner.borrow_mut().next_due = self.driver.now() + self.period;
+        arm_interval(&self.driver, &self.inner, self.period, self.period);
+    }
+}
+
+/// Add the timer for the next tick of an [`Interval`], due after `delay`, which counts the ticks
+/// since the last and adds the timer for the one after.
+fn arm_interval(
+    driver: &Rc<dyn TimeDriver>,
+    inner: &Rc<RefCell<IntervalInner>>,
+    period: Duration,
+    delay: Duration,
+) {
+    let driver_for_tick = driver.clone();
+    let inner_for_tick = Rc::downgrade(inner);
+    let timer = driver.add_timer(
+        delay,
+        Box::new(move || {
+            let inner = match inner_for_tick.upgrade() {
+                Some(inner) => inner,
+                None => return,
+            };
+            let now = driver_for_tick.now();
+            let (waker_opt, next_delay) = {
+                let mut inner = inner.borrow_mut();
+                let mut ticks = 1;
+                if now > inner.next_due && !period.is_zero() {
+                    ticks += ((now - inner.next_due).as_nanos() / period.as_nanos()) as usize;
+                }
+                inner.next_due = now.max(inner.next_due) + period;
+                inner.pending += ticks;
+                (
+                    inner.waker_opt.take(),
+                    inner.next_due.saturating_duration_since(now),
+                )
+            };
+            arm_interval(&driver_for_tick, &inner, period, next_delay);
+            if let Some(w) = waker_opt {
+                w.wake();
+            }
+        }),
+    );
+
+    // Replacing the timer which just fired, if any, does nothing to it.
+    let old_timer_opt = inner.borrow_mut().timer_opt.replace(timer);
+    drop(old_timer_opt);
+}
+
+impl Stream for Interval {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<()>> {
+        if !self.started {
+            self.start();
+        }
+
+        let mut inner = self.inner.borrow_mut();
+        if inner.pending > 0 {
+            match self.missed {
+                MissedTicks::Skip => inner.pending = 0,
+                MissedTicks::Burst => inner.pending -= 1,
+            }
+            Poll::Ready(Some(()))
+        } else {
+            inner.waker_opt = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Interval {
+    fn drop(&mut self) {
+        let timer_opt = self.inner.borrow_mut().timer_opt.take();
+        drop(timer_opt);
+    }
+}
+
+/// State of a [`Sleep`] shared with its timeout callback.
+#[derive(Default)]
+struct SleepInner {
+    /// Whether the timeout has fired.
+    elapsed: bool,
+
+    waker_opt: Option<std::task::Waker>,
+}
+
+/// A [`Future`] which completes after some duration, made by [`sleep`].
+///
+/// The underlying timer is only added the first time the future is polled, and is removed if the
+/// future is dropped before it fires.
+pub struct Sleep {
+    duration: Duration,
+    driver: Rc<dyn TimeDriver>,
+    inner: Rc<RefCell<SleepInner>>,
+    timer_opt: Option<Timer>,
+}
+
+/// A [`Sleep`] can be unpinned as its state is a reference counted pointer.
+impl Unpin for Sleep {}
+
+/// Make a [`Future`] which completes after `duration`, using a glib timeout on the thread default
+/// main context. It doesn't need to be [`Send`], so can be awaited in any future run with
+/// [`glib_run_future`](crate::glib_run_future), e.g. to flash a button's label after each click:
+///
+/// ```
+///    # use springsteel::glib_future::glib_run_stream_async;
+///    # use springsteel::time::sleep;
+///    # use springsteel::ImpulseStream;
+///    # use gtk::prelude::ButtonExt as _;
+///    # use std::time::Duration;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let clicks = ImpulseStream::new();
+///    let button = gtk::Button::with_label("Save");
+///    button.connect_clicked(clicks.triggerer());
+///
+///    glib_run_stream_async(clicks, move |()| {
+///        let button = button.clone();
+///        async move {
+///            button.set_label("Saved!");
+///            sleep(Duration::from_millis(500)).await;
+///            button.set_label("Save");
+///        }
+///    });
+/// ```
+///
+/// The timeout goes on the thread default main context, so this works as well in a future run on
+/// a worker thread which has pushed a main context of its own:
+///
+/// ```
+///    # use springsteel::glib_run_future;
+///    # use springsteel::time::sleep;
+///    # use std::time::Duration;
+///    let worker = std::thread::spawn(|| {
+///        let context = glib::MainContext::new();
+///        context
+///            .with_thread_default(|| {
+///                let task = glib_run_future(Box::pin(sleep(Duration::from_millis(5))));
+///                while !task.is_finished() {
+///                    context.iteration(true);
+///                }
+///            })
+///            .expect("with_thread_default");
+///    });
+///    worker.join().expect("worker");
+/// ```
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        duration,
+        driver: time_driver(),
+        inner: Rc::new(RefCell::new(SleepInner::default())),
+        timer_opt: None,
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.inner.borrow().elapsed {
+            self.timer_opt = None;
+            return Poll::Ready(());
+        }
+
+        if self.timer_opt.is_none() {
+            let inner = self.inner.clone();
+            let timer = self.driver.add_timer(
+                self.duration,
+                Box::new(move || {
+                    let waker_opt = {
+                        let mut inner = inner.borrow_mut();
+                        inner.elapsed = true;
+                        inner.waker_opt.take()
+                    };
+                    if let Some(w) = waker_opt {
+                        w.wake();
+                    }
+                }),
+            );
+            self.timer_opt = Some(timer);
+        }
+
+        self.inner.borrow_mut().waker_opt = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Error output by [`with_timeout`] when the future didn't complete in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "timed out")
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+/// A [`Future`] racing another future against a [`Sleep`], made by [`with_timeout`].
+pub struct Timeout<F: Future> {
+    /// The future, until it completes or the timeout fires.
+    fut_opt: Option<Pin<Box<F>>>,
+
+    /// The timeout, until it fires or the future completes.
+    sleep_opt: Option<Sleep>,
+}
+
+/// A [`Timeout`] can be unpinned as the future it races is boxed.
+impl<F: Future> Unpin for Timeout<F> {}
+
+/// Race a future against a glib timeout on the thread default main context, outputting the
+/// future's output if it completes within `duration` or [`Elapsed`] if not.
+///
+/// Whichever loses is dropped as soon as the other wins: the timeout source is removed if the
+/// future completes first, and the future is dropped if the timeout fires first. This suits
+/// operations whose results update widgets, which should show an error rather than wait forever:
+///
+/// ```
+///    # use springsteel::glib_run_future;
+///    # use springsteel::time::{sleep, with_timeout, Elapsed};
+///    # use std::cell::Cell;
+///    # use std::rc::Rc;
+///    # use std::time::Duration;
+///    let outcome = Rc::new(Cell::new(None));
+///    let outcome_for_task = outcome.clone();
+///    glib_run_future(Box::pin(async move {
+///        let slow = sleep(Duration::from_secs(60));
+///        outcome_for_task.set(Some(with_timeout(slow, Duration::from_millis(10)).await));
+///    }));
+///
+///    # let context = glib::MainContext::default();
+///    # while outcome.get().is_none() {
+///    #     context.iteration(true);
+///    # }
+///    assert_eq!(outcome.get(), Some(Err(Elapsed)));
+/// ```
+pub fn with_timeout<F: Future>(fut: F, duration: Duration) -> Timeout<F> {
+    Timeout {
+        fut_opt: Some(Box::pin(fut)),
+        sleep_opt: Some(sleep(duration)),
+    }
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        if let Some(fut) = self.fut_opt.as_mut() {
+            if let Poll::Ready(output) = fut.as_mut().poll(cx) {
+                self.fut_opt = None;
+                self.sleep_opt = None;
+                return Poll::Ready(Ok(output));
+            }
+        }
+
+        match self.sleep_opt.as_mut() {
+            Some(sleep) => {
+                if Pin::new(sleep).poll(cx).is_ready() {
+                    self.fut_opt = None;
+                    self.sleep_opt = None;
+                    Poll::Ready(Err(Elapsed))
+                } else {
+                    Poll::Pending
+                }
+            }
+            None => Poll::Ready(Err(Elapsed)),
+        }
+    }
+}
+
+/// Options for [`retry_with_backoff`].
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// How many times to make and await the future in total, including the first attempt. `0` is
+    /// treated as `1`. `3` by default.
+    pub max_attempts: u32,
+
+    /// How long to wait after the first failed attempt. `100ms` by default.
+    pub initial_delay: Duration,
+
+    /// What each delay is multiplied by to give the next, which must be positive and finite. `2.0`
+    /// by default.
+    pub multiplier: f64,
+
+    /// Upper bound on any one delay. `30s` by default.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(100),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait after the given failed attempt, counting from `1`.
+    fn delay_after(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        if secs.is_finite() {
+            Duration::from_secs_f64(secs.max(0.0)).min(self.max_delay)
+        } else {
+            self.max_delay
+        }
+    }
+}
+
+/// Make and await futures with `make_fut` until one outputs `Ok` or `policy.max_attempts` have
+/// output `Err`, outputting the last output. Between attempts it waits with [`sleep`], starting at
+/// `policy.initial_delay` and growing by `policy.multiplier` up to `policy.max_delay`, so it never
+/// leaves the main loop. `on_error` is called with the number of the attempt, counting from `1`,
+/// and its error whenever another attempt follows, e.g. to log it or show progress; the error of
+/// the last attempt is output instead.
+///
+/// The result can be awaited or run with [`glib_run_future`](crate::glib_run_future) like any other
+/// future, and aborting its task cancels whichever attempt or delay is in progress. This suits
+/// loading flaky resources such as remote thumbnails whose results land in widgets:
+///
+/// ```
+///    # use springsteel::glib_future::glib_block_on;
+///    # use springsteel::time::{retry_with_backoff, RetryPolicy};
+///    # use std::cell::Cell;
+///    # use std::future::ready;
+///    # use std::rc::Rc;
+///    # use std::time::Duration;
+///    let attempts = Rc::new(Cell::new(0));
+///    let attempts_for_load = attempts.clone();
+///    let policy = RetryPolicy {
+///        initial_delay: Duration::from_millis(1),
+///        ..RetryPolicy::default()
+///    };
+///    let mut failures = Vec::new();
+///    let loaded = glib_block_on(retry_with_backoff(
+///        move || {
+///            let attempt = attempts_for_load.get() + 1;
+///            attempts_for_load.set(attempt);
+///            ready(if attempt < 3 { Err("unreachable") } else { Ok("thumbnail") })
+///        },
+///        policy,
+///        |attempt, err| failures.push((attempt, *err)),
+///    ));
+///
+///    assert_eq!(loaded, Ok("thumbnail"));
+///    assert_eq!(attempts.get(), 3);
+///    assert_eq!(failures, [(1, "unreachable"), (2, "unreachable")]);
+/// ```
+pub fn retry_with_backoff<M, Fut, T, E, C>(
+    mut make_fut: M,
+    policy: RetryPolicy,
+    mut on_error: C,
+) -> impl Future<Output = Result<T, E>>
+where
+    M: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    C: FnMut(u32, &E),
+{
+    async move {
+        let max_attempts = policy.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            match make_fut().await {
+                Ok(output) => return Ok(output),
+                Err(err) if attempt >= max_attempts => return Err(err),
+                Err(err) => {
+                    on_error(attempt, &err);
+                    sleep(policy.delay_after(attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Provides [`ValueStream`], a [`Stream`] which outputs values whenever they're pushed into it, for
+//! example by a signal handler which carries some payload such as a selected index.
+
+use futures::stream::Stream;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll};
+
+/// Inner state of a [`ValueStream`].
+struct ValueStreamInner<T> {
+    /// Values waiting to be dequeued, oldest first.
+    pending: VecDeque<T>,
+
+    /// How to fold a newly pushed value into the last pending one, rather than queueing every
+    /// value, if this stream coalesces.
+    merge_opt: Option<Box<dyn Fn(&mut T, T)>>,
+
+    /// Guards kept for as long as the stream, e.g. to disconnect the signal handlers pushing into
+    /// it once nothing can poll it any more.
+    held: Vec<Box<dyn Any>>,
+
+    /// Waker of the task last polling the stream while it had nothing pending, woken on push.
+    waker_opt: Option<std::task::Waker>,
+}
+
+/// An infinite [`Stream`] implementation which generates values whenever they're
+/// [`push`ed](Self::push), such as in response to a signal carrying a payload.
+///
+/// This is the payload-carrying counterpart of [`ImpulseStream`](crate::ImpulseStream). A
+/// `ValueStream` pends until [`push`](Self::push) is called, either directly or indirectly via
+/// [`pusher`](Self::pusher), at which point the stream outputs the value and pends again.
+///
+/// By default every pushed value is queued and output in order. A stream created with
+/// [`coalescing`](Self::coalescing) instead keeps only the latest value pushed since the last
+/// poll, which suits high-frequency events like pointer motion where only the current state
+/// matters, and one created with [`accumulating`](Self::accumulating) folds values pushed between
+/// polls together, e.g. summing scroll deltas.
+///
+/// `ValueStream`s are infinite. That is, they never yield `Ready(None)` from
+/// [`poll_next`](Self::poll_next).
+pub struct ValueStream<T>(Rc<RefCell<ValueStreamInner<T>>>);
+
+/// A weak reference to a [`ValueStream`], for pushing into it only while something else still
+/// holds the stream.
+pub(crate) struct WeakValueStream<T>(Weak<RefCell<ValueStreamInner<T>>>);
+
+impl<T> Clone for WeakValueStream<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> WeakValueStream<T> {
+    /// Return the stream, if it's still held anywhere.
+    pub(crate) fn upgrade(&self) -> Option<ValueStream<T>> {
+        self.0.upgrade().map(ValueStream)
+    }
+
+    /// [`Push`](ValueStream::push) a value into the stream if it's still held, otherwise drop it.
+    pub(crate) fn push(&self, value: T) {
+        if let Some(stream) = self.upgrade() {
+            stream.push(value);
+        }
+    }
+}
+
+impl<T> Clone for ValueStream<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// A [`ValueStream`] can be unpinned as its state is a reference counted pointer.
+impl<T> Unpin for ValueStream<T> {}
+
+impl<T> ValueStream<T> {
+    /// Create a new queueing `ValueStream`. Any poll will pend until [`push`](Self::push) is
+    /// invoked, either directly or indirectly via [`pusher`](Self::pusher).
+    pub fn new() -> Self {
+        Self::with_merge(None)
+    }
+
+    /// Create a new coalescing `ValueStream`, which only retains the latest value pushed between
+    /// polls.
+    pub fn coalescing() -> Self
+    where
+        T: 'static,
+    {
+        Self::accumulating(|pending, value| *pending = value)
+    }
+
+    /// Create a new accumulating `ValueStream`, which folds each value pushed between polls into
+    /// the pending one using `merge`, so that at most one value is ever pending.
+    pub fn accumulating(merge: impl Fn(&mut T, T) + 'static) -> Self {
+        Self::with_merge(Some(Box::new(merge)))
+    }
+
+    fn with_merge(merge_opt: Option<Box<dyn Fn(&mut T, T)>>) -> Self {
+        Self(Rc::new(RefCell::new(ValueStreamInner {
+            pending: VecDeque::new(),
+            merge_opt,
+            held: Vec::new(),
+            waker_opt: None,
+        })))
+    }
+
+    /// Push a value into the `ValueStream`, making it become ready with that value.
+    pub fn push(&self, value: T) {
+        let mut inner = self.0.borrow_mut();
+        let inner = &mut *inner;
+        let unmerged_opt = match (&inner.merge_opt, inner.pending.back_mut()) {
+            (Some(merge), Some(pending)) => {
+                merge(pending, value);
+                None
+            }
+            _ => Some(value),
+        };
+        if let Some(value) = unmerged_opt {
+            inner.pending.push_back(value);
+        }
+        if let Some(w) = inner.waker_opt.take() {
+            w.wake();
+        }
+    }
+
+    /// Keep the given guard for as long as the stream, dropping it along with the last clone.
+    pub(crate) fn hold(&self, guard: impl Any) {
+        self.0.borrow_mut().held.push(Box::new(guard));
+    }
+
+    /// Make a weak reference to this stream, which doesn't keep it alive.
+    pub(crate) fn downgrade(&self) -> WeakValueStream<T> {
+        WeakValueStream(Rc::downgrade(&self.0))
+    }
+
+    /// Make a closure which [`push`es](Self::push) whatever value it's called with.
+    pub fn pusher(&self) -> impl Fn(T)
+    where
+        T: 'static,
+    {
+        let inst = self.clone();
+        move |value| inst.push(value)
+    }
+}
+
+impl<T> Default for ValueStream<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Stream for ValueStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        let mut inner = self.0.borrow_mut();
+        if let Some(value) = inner.pending.pop_front() {
+            Poll::Ready(Some(value))
+        } else {
+            inner.waker_opt = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Provides [`WidgetSpawner`], which runs futures on the glib main loop for as long as some widget
+//! is alive, aborting them all when it goes away.
+
+use crate::glib_future::{glib_run_future, TaskHandle};
+use glib::object::IsA;
+use gtk::prelude::WidgetExt as _;
+use std::cell::RefCell;
+use std::future::Future;
+use std::rc::Rc;
+
+/// When a [`WidgetSpawner`] aborts its futures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpawnerScope {
+    /// When the widget is destroyed, i.e. disposed. This is the default.
+    Destroy,
+
+    /// When the widget is unrealized, e.g. because it's removed from its window. Futures spawned
+    /// afterwards, e.g. when it's realized again, run until it's next unrealized.
+    Unrealize,
+}
+
+impl Default for SpawnerScope {
+    fn default() -> Self {
+        Self::Destroy
+    }
+}
+
+/// State of a [`WidgetSpawner`].
+#[derive(Default)]
+struct SpawnerInner {
+    tasks: Vec<TaskHandle>,
+
+    /// Whether the widget was destroyed, after which futures aren't spawned at all.
+    closed: bool,
+}
+
+/// Spawner of futures on the glib main loop which belong to some widget, all of which are
+/// [aborted](TaskHandle::abort) when the widget is destroyed (or unrealized, see
+/// [`SpawnerScope`]), dropping whatever they captured.
+///
+/// Clones share the same set of futures.
+///
+/// ```
+///    # use springsteel::widget_spawner::WidgetSpawner;
+///    # use springsteel::ImpulseStream;
+///    # use futures::stream::StreamExt as _;
+///    # use std::cell::Cell;
+///    # use std::future::ready;
+///    # use std::rc::Rc;
+///    # use gtk::prelude::GtkWindowExt as _;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    struct SetOnDrop(Rc<Cell<bool>>);
+///    impl Drop for SetOnDrop {
+///        fn drop(&mut self) {
+///            self.0.set(true);
+///        }
+///    }
+///
+///    let dropped = Rc::new(Cell::new(false));
+///    let handled = Rc::new(Cell::new(0));
+///    let impulses = ImpulseStream::new();
+///
+///    let window = gtk::Window::new();
+///    let spawner = WidgetSpawner::for_widget(&window);
+///    let guard = SetOnDrop(dropped.clone());
+///    let handled_for_task = handled.clone();
+///    spawner.spawn(impulses.clone().for_each(move |()| {
+///        let _ = &guard;
+///        handled_for_task.set(handled_for_task.get() + 1);
+///        ready(())
+///    }));
+///
+///    window.destroy();
+///    assert!(dropped.get());
+///
+///    impulses.trigger();
+///    while glib::MainContext::default().iteration(false) {}
+///    assert_eq!(handled.get(), 0);
+/// ```
+#[derive(Clone)]
+pub struct WidgetSpawner(Rc<RefCell<SpawnerInner>>);
+
+impl WidgetSpawner {
+    /// Make a spawner whose futures are aborted when the given widget is destroyed.
+    pub fn for_widget(widget: &impl IsA<gtk::Widget>) -> Self {
+        Self::for_widget_until(widget, SpawnerScope::Destroy)
+    }
+
+    /// Make a spawner whose futures are aborted when the given widget is destroyed or unrealized,
+    /// depending on `scope`.
+    pub fn for_widget_until(widget: &impl IsA<gtk::Widget>, scope: SpawnerScope) -> Self {
+        let spawner = Self(Rc::new(RefCell::new(SpawnerInner::default())));
+
+        let spawner_for_destroy = spawner.clone();
+        widget.connect_destroy(move |_| {
+            spawner_for_destroy.0.borrow_mut().closed = true;
+            spawner_for_destroy.abort_all();
+        });
+
+        if scope == SpawnerScope::Unrealize {
+            let spawner_for_unrealize = spawner.clone();
+            widget.connect_unrealize(move |_| spawner_for_unrealize.abort_all());
+        }
+
+        spawner
+    }
+
+    /// Run a future on the glib main loop until it completes or is aborted along with the rest of
+    /// this spawner's futures. If the widget has already been destroyed, the future is dropped
+    /// without being run.
+    #[track_caller]
+    pub fn spawn<F>(&self, fut: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let mut inner = self.0.borrow_mut();
+        if inner.closed {
+            return;
+        }
+
+        inner.tasks.retain(|task| !task.is_finished());
+        inner.tasks.push(glib_run_future(Box::pin(fut)));
+    }
+
+    /// Abort every future spawned so far.
+    pub fn abort_all(&self) {
+        // Take the tasks first, as aborting drops futures which might spawn or abort in turn.
+        let tasks = std::mem::take(&mut self.0.borrow_mut().tasks);
+        for task in tasks {
+            task.abort();
+        }
+    }
+}
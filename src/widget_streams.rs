@@ -0,0 +1,587 @@
+//! Adapters turning widget signals and property notifications into [`ValueStream`]s, so that
+//! widget state changes can drive stream pipelines run with
+//! [`glib_run_future`](crate::glib_run_future) rather than nested closures.
+//!
+//! The signal handlers only hold the streams they push into weakly, and are disconnected when the
+//! stream (and every clone of it) is dropped, so an adapter never outlives whatever consumes it.
+
+use crate::controller_streams::ControllerGuard;
+use crate::impulse_stream::ImpulseStream;
+use crate::signal_future::SignalGuard;
+use crate::time::{time_driver, Timer};
+use crate::value_stream::ValueStream;
+use futures::stream::{Stream, StreamExt as _};
+use gio::prelude::ListModelExt as _;
+use glib::object::IsA;
+use glib::prelude::{Cast as _, ObjectExt as _};
+use gtk::prelude::{
+    AdjustmentExt as _, EditableExt as _, ListBoxRowExt as _, SelectionModelExt as _,
+    WidgetExt as _,
+};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Make a [`ValueStream`] of the `selected` position of a [`gtk::DropDown`], or of anything else
+/// with a `selected` `u32` property such as an `adw::ComboRow`.
+///
+/// A value is output every time the selection changes, whether by the user or programmatically.
+/// Like [`gtk::DropDown::selected`], [`gtk::INVALID_LIST_POSITION`] means nothing is selected. If
+/// `emit_initial` is true the current selection is output first, otherwise only subsequent changes
+/// are.
+///
+/// ```
+///    # use springsteel::widget_streams::selected_changes;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let layouts = gtk::DropDown::from_strings(&["Compact", "Spacious"]);
+///    let layout_indices = selected_changes(&layouts, true);
+/// ```
+pub fn selected_changes<O>(obj: &O, emit_initial: bool) -> ValueStream<u32>
+where
+    O: IsA<glib::Object>,
+{
+    notify_values(obj, "selected", emit_initial, |o| {
+        o.property::<u32>("selected")
+    })
+}
+
+/// Make a [`ValueStream`] of the `selected-item` of a [`gtk::DropDown`] (or `adw::ComboRow`),
+/// mapped through the given closure to the application's item type.
+///
+/// As with [`selected_changes`], a value is output for every selection change and optionally for
+/// the initial selection. The closure receives `None` if nothing is selected.
+pub fn selected_item_changes<O, T, F>(obj: &O, emit_initial: bool, f: F) -> ValueStream<T>
+where
+    O: IsA<glib::Object>,
+    T: 'static,
+    F: Fn(Option<glib::Object>) -> T + 'static,
+{
+    notify_values(obj, "selected-item", emit_initial, move |o| {
+        f(o.property::<Option<glib::Object>>("selected-item"))
+    })
+}
+
+/// Make a [`ValueStream`] of the selected item of a [`gtk::SingleSelection`], e.g. the model of a
+/// [`gtk::ListView`] or [`gtk::GridView`], mapped through the given closure to the application's
+/// item type.
+///
+/// A value is output every time the `selected-item` changes, and initially if `emit_initial` is
+/// true. The closure receives `None` if nothing is selected.
+pub fn single_selection_changes<T, F>(
+    selection: &gtk::SingleSelection,
+    emit_initial: bool,
+    f: F,
+) -> ValueStream<T>
+where
+    T: 'static,
+    F: Fn(Option<glib::Object>) -> T + 'static,
+{
+    notify_values(selection, "selected-item", emit_initial, move |s| {
+        f(s.selected_item())
+    })
+}
+
+/// Make a [`ValueStream`] of the whole set of selected items of a [`gtk::MultiSelection`], each
+/// mapped through the given closure to the application's item type, in model order.
+///
+/// The whole selection is output rather than just the range which changed, every time the
+/// selection changes or items are added to or removed from the model, and initially if
+/// `emit_initial` is true.
+pub fn multi_selection_changes<T, F>(
+    selection: &gtk::MultiSelection,
+    emit_initial: bool,
+    f: F,
+) -> ValueStream<Vec<T>>
+where
+    T: 'static,
+    F: Fn(glib::Object) -> T + 'static,
+{
+    let values = ValueStream::new();
+    let read = Rc::new(move |selection: &gtk::MultiSelection| {
+        let selected = selection.selection();
+        (0..selected.size() as u32)
+            .filter_map(|i| selection.item(selected.nth(i)))
+            .map(&f)
+            .collect::<Vec<_>>()
+    });
+    if emit_initial {
+        values.push(read(selection));
+    }
+
+    let values_for_selection = values.downgrade();
+    let read_for_selection = read.clone();
+    let selection_changed = selection.connect_selection_changed(move |selection, _, _| {
+        values_for_selection.push(read_for_selection(selection));
+    });
+
+    let values_for_items = values.downgrade();
+    let items_changed = selection.connect_items_changed(move |selection, _, _, _| {
+        values_for_items.push(read(selection));
+    });
+    values.hold(SignalGuard::new(
+        selection,
+        vec![selection_changed, items_changed],
+    ));
+
+    values
+}
+
+/// Make a [`ValueStream`] of whether the given widget is mapped, from its `map` and `unmap`
+/// signals, e.g. to only run an animation while the page showing it is visible. The current state
+/// is output first, so consumers never start in an unknown state.
+///
+/// ```
+///    # use futures::stream::{self, StreamExt as _};
+///    # use springsteel::glib_run_future;
+///    # use springsteel::time::interval;
+///    # use springsteel::widget_streams::mapped_changes;
+///    # use std::future::ready;
+///    # use std::time::Duration;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let spinner = gtk::Label::new(Some("|"));
+///    let ticks = interval(Duration::from_millis(100)).map(|()| None);
+///    let frames = stream::select(mapped_changes(&spinner).map(Some), ticks)
+///        .scan(false, |mapped, event| {
+///            let tick = match event {
+///                Some(now_mapped) => {
+///                    *mapped = now_mapped;
+///                    false
+///                }
+///                None => true,
+///            };
+///            ready(Some(tick && *mapped))
+///        })
+///        .filter(|animate| ready(*animate));
+///    glib_run_future(frames.for_each(move |_| {
+///        let next = if spinner.text().as_str() == "|" { "-" } else { "|" };
+///        spinner.set_text(next);
+///        ready(())
+///    }));
+/// ```
+pub fn mapped_changes(widget: &impl IsA<gtk::Widget>) -> ValueStream<bool> {
+    let values = ValueStream::new();
+    let widget = widget.upcast_ref::<gtk::Widget>();
+    values.push(widget.is_mapped());
+
+    let values_for_map = values.downgrade();
+    let map = widget.connect_map(move |_| values_for_map.push(true));
+
+    let values_for_unmap = values.downgrade();
+    let unmap = widget.connect_unmap(move |_| values_for_unmap.push(false));
+    values.hold(SignalGuard::new(widget, vec![map, unmap]));
+
+    values
+}
+
+/// Make a [`ValueStream`] of the text direction of the given widget, output first with the
+/// current direction and then from the `direction-changed` signal, e.g. to rebuild constraints
+/// which use `left` and `right` rather than `start` and `end` when the direction flips.
+///
+/// As with every adapter here, the handler is disconnected when the stream is dropped:
+///
+/// ```
+///    # use springsteel::widget_streams::direction_changes;
+///    # use glib::StaticType as _;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let label = gtk::Label::new(Some("Ltr"));
+///    let direction_changed =
+///        glib::subclass::SignalId::lookup("direction-changed", gtk::Widget::static_type())
+///            .expect("direction-changed signal");
+///    let connected =
+///        || glib::signal::signal_has_handler_pending(&label, direction_changed, None, true);
+///
+///    let directions = direction_changes(&label);
+///    assert!(connected());
+///    drop(directions);
+///    assert!(!connected());
+/// ```
+pub fn direction_changes(widget: &impl IsA<gtk::Widget>) -> ValueStream<gtk::TextDirection> {
+    let values = ValueStream::new();
+    let widget = widget.upcast_ref::<gtk::Widget>();
+    values.push(widget.direction());
+
+    let values_for_changed = values.downgrade();
+    let changed = widget
+        .connect_direction_changed(move |widget, _| values_for_changed.push(widget.direction()));
+    values.hold(SignalGuard::new(widget, vec![changed]));
+
+    values
+}
+
+/// Make a [`ValueStream`] which outputs `read(obj)` every time the named property of `obj` is
+/// notified, and once initially if `emit_initial` is true.
+fn notify_values<O, T, F>(obj: &O, property: &str, emit_initial: bool, read: F) -> ValueStream<T>
+where
+    O: IsA<glib::Object>,
+    T: 'static,
+    F: Fn(&O) -> T + 'static,
+{
+    let values = ValueStream::new();
+    if emit_initial {
+        values.push(read(obj));
+    }
+
+    let values_for_notify = values.downgrade();
+    let notify =
+        obj.connect_notify_local(Some(property), move |o, _| values_for_notify.push(read(o)));
+    values.hold(SignalGuard::new(obj, vec![notify]));
+
+    values
+}
+
+/// Make a [`ValueStream`] of the name of the visible child of a [`gtk::Stack`], output every time
+/// the visible child changes.
+///
+/// Adding the first page, removing the visible page, or removing the last page all change the
+/// visible child and so output its new name (`None` if the stack is now empty or the child is
+/// unnamed), but notifications which leave the visible child name the same aren't repeated. If
+/// `emit_initial` is true the current visible child name is output first.
+///
+/// ```
+///    # use springsteel::widget_streams::visible_child_name_changes;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let pages = gtk::Stack::new();
+///    pages.add_named(&gtk::Label::new(Some("Overview")), Some("overview"));
+///    let page_names = visible_child_name_changes(&pages, true);
+/// ```
+pub fn visible_child_name_changes(
+    stack: &gtk::Stack,
+    emit_initial: bool,
+) -> ValueStream<Option<glib::GString>> {
+    let values = ValueStream::new();
+    let last = Rc::new(RefCell::new(stack.visible_child_name()));
+    if emit_initial {
+        values.push(last.borrow().clone());
+    }
+
+    let values_for_notify = values.downgrade();
+    let notify = stack.connect_notify_local(Some("visible-child"), move |stack, _| {
+        let name = stack.visible_child_name();
+        if *last.borrow() != name {
+            *last.borrow_mut() = name.clone();
+            values_for_notify.push(name);
+        }
+    });
+    values.hold(SignalGuard::new(stack, vec![notify]));
+
+    values
+}
+
+/// Make a [`ValueStream`] of the index of the current page of a [`gtk::Notebook`], output from the
+/// `switch-page` signal every time the page changes. If `emit_initial` is true the current page
+/// index is output first, if the notebook has any pages.
+pub fn page_changes(notebook: &gtk::Notebook, emit_initial: bool) -> ValueStream<u32> {
+    let values = ValueStream::new();
+    if emit_initial {
+        if let Some(page) = notebook.current_page() {
+            values.push(page);
+        }
+    }
+
+    let values_for_switch = values.downgrade();
+    let switch = notebook.connect_switch_page(move |_, _, page| values_for_switch.push(page));
+    values.hold(SignalGuard::new(notebook, vec![switch]));
+
+    values
+}
+
+/// Make a [`ValueStream`] of the index of the selected row of a [`gtk::ListBox`], output from the
+/// `row-selected` signal every time the selection changes. `None` is output when the selection is
+/// cleared, e.g. by [`unselect_all`](gtk::ListBox::unselect_all) or removing the selected row.
+pub fn row_selections(list_box: &gtk::ListBox) -> ValueStream<Option<i32>> {
+    let values = ValueStream::new();
+    let values_for_selected = values.downgrade();
+    let selected = list_box.connect_row_selected(move |_, row_opt| {
+        values_for_selected.push(row_opt.map(|row| row.index()));
+    });
+    values.hold(SignalGuard::new(list_box, vec![selected]));
+    values
+}
+
+/// Like [`row_selections`] but also mapping the selected row through the given closure, e.g. to
+/// look up the application item it shows.
+pub fn row_selections_mapped<T, F>(list_box: &gtk::ListBox, f: F) -> ValueStream<Option<(i32, T)>>
+where
+    T: 'static,
+    F: Fn(&gtk::ListBoxRow) -> T + 'static,
+{
+    let values = ValueStream::new();
+    let values_for_selected = values.downgrade();
+    let selected = list_box.connect_row_selected(move |_, row_opt| {
+        values_for_selected.push(row_opt.map(|row| (row.index(), f(row))));
+    });
+    values.hold(SignalGuard::new(list_box, vec![selected]));
+    values
+}
+
+/// Make a [`ValueStream`] of the index of each row of a [`gtk::ListBox`] which is activated, output
+/// from the `row-activated` signal.
+pub fn row_activations(list_box: &gtk::ListBox) -> ValueStream<i32> {
+    let values = ValueStream::new();
+    let values_for_activated = values.downgrade();
+    let activated =
+        list_box.connect_row_activated(move |_, row| values_for_activated.push(row.index()));
+    values.hold(SignalGuard::new(list_box, vec![activated]));
+    values
+}
+
+/// Like [`row_activations`] but also mapping the activated row through the given closure.
+pub fn row_activations_mapped<T, F>(list_box: &gtk::ListBox, f: F) -> ValueStream<(i32, T)>
+where
+    T: 'static,
+    F: Fn(&gtk::ListBoxRow) -> T + 'static,
+{
+    let values = ValueStream::new();
+    let values_for_activated = values.downgrade();
+    let activated = list_box.connect_row_activated(move |_, row| {
+        values_for_activated.push((row.index(), f(row)));
+    });
+    values.hold(SignalGuard::new(list_box, vec![activated]));
+    values
+}
+
+/// A value of a [`gtk::SpinButton`] output by [`spin_values`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpinValue {
+    /// The new value.
+    pub value: f64,
+
+    /// Whether the change came from the user rather than from [`SpinValues::set_value`] (or the
+    /// initial value, with [`SpinOptions::emit_initial`]).
+    pub by_user: bool,
+}
+
+impl SpinValue {
+    /// Return the value rounded to the nearest integer, for spin buttons with no digits.
+    pub fn as_i64(&self) -> i64 {
+        self.value.round() as i64
+    }
+}
+
+/// Options for [`spin_values`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SpinOptions {
+    /// Output the current value first.
+    pub emit_initial: bool,
+
+    /// Hold back changes made by the user until there have been none for this long, or until the
+    /// user activates the spin button or moves focus away from it, so that intermediate states
+    /// while typing a number aren't output. `None` outputs every change immediately.
+    pub quiet_period: Option<Duration>,
+}
+
+/// The values of a [`gtk::SpinButton`], made by [`spin_values`].
+pub struct SpinValues {
+    /// Every value change, subject to [`SpinOptions::quiet_period`].
+    pub values: ValueStream<SpinValue>,
+
+    spin: gtk::SpinButton,
+    programmatic: Rc<Cell<bool>>,
+}
+
+impl SpinValues {
+    /// Set the value of the spin button, which is output immediately with
+    /// [`by_user`](SpinValue::by_user) false.
+    ///
+    /// Changes made any other way, e.g. with [`gtk::SpinButton::set_value`] directly, can't be
+    /// told apart from those the user made and are output as by the user.
+    pub fn set_value(&self, value: f64) {
+        self.programmatic.set(true);
+        self.spin.set_value(value);
+        self.programmatic.set(false);
+    }
+
+    /// Convert into a stream of just the values rounded to integers, for spin buttons with no
+    /// digits.
+    pub fn integers(self) -> impl Stream<Item = i64> + Unpin {
+        self.values.map(|v| v.as_i64())
+    }
+}
+
+/// A user change held back by [`SpinOptions::quiet_period`], and the timer which will output it.
+struct HeldSpinValue {
+    value: SpinValue,
+    _timer: Timer,
+}
+
+/// Make a [`ValueStream`] of the values of a [`gtk::SpinButton`] from its `value-changed` signal,
+/// distinguishing changes the user made from those made with [`SpinValues::set_value`] and
+/// optionally debouncing what the user types:
+///
+/// ```
+///    # use springsteel::widget_streams::{spin_values, SpinOptions};
+///    # use std::time::Duration;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let quantity = gtk::SpinButton::with_range(1.0, 99.0, 1.0);
+///    let quantities = spin_values(
+///        &quantity,
+///        SpinOptions {
+///            emit_initial: true,
+///            quiet_period: Some(Duration::from_millis(500)),
+///        },
+///    )
+///    .integers();
+/// ```
+pub fn spin_values(spin: &gtk::SpinButton, options: SpinOptions) -> SpinValues {
+    let spin_values = SpinValues {
+        values: ValueStream::new(),
+        spin: spin.clone(),
+        programmatic: Rc::new(Cell::new(false)),
+    };
+    if options.emit_initial {
+        spin_values.values.push(SpinValue {
+            value: spin.value(),
+            by_user: false,
+        });
+    }
+
+    let held: Rc<RefCell<Option<HeldSpinValue>>> = Rc::new(RefCell::new(None));
+
+    let flush = {
+        let values = spin_values.values.downgrade();
+        let held = held.clone();
+        move || {
+            // Dropping the held value cancels its timer.
+            let held_opt = held.borrow_mut().take();
+            if let Some(held) = held_opt {
+                values.push(held.value);
+            }
+        }
+    };
+    let flush = Rc::new(flush);
+
+    let flush_for_changed = flush.clone();
+    let values_for_changed = spin_values.values.downgrade();
+    let programmatic = spin_values.programmatic.clone();
+    let value_changed = spin.connect_value_changed(move |spin| {
+        let value = SpinValue {
+            value: spin.value(),
+            by_user: !programmatic.get(),
+        };
+        let quiet_period = match options.quiet_period {
+            Some(quiet_period) if value.by_user => quiet_period,
+            _ => {
+                flush_for_changed();
+                values_for_changed.push(value);
+                return;
+            }
+        };
+
+        // Dropping the previously held value cancels its timer.
+        let previous_opt = held.borrow_mut().take();
+        drop(previous_opt);
+
+        let values_for_timer = values_for_changed.clone();
+        let held_for_timer = held.clone();
+        let timer = time_driver().add_timer(
+            quiet_period,
+            Box::new(move || {
+                let held_opt = held_for_timer.borrow_mut().take();
+                if let Some(held) = held_opt {
+                    values_for_timer.push(held.value);
+                }
+            }),
+        );
+        *held.borrow_mut() = Some(HeldSpinValue {
+            value,
+            _timer: timer,
+        });
+    });
+    let values = &spin_values.values;
+    values.hold(SignalGuard::new(spin, vec![value_changed]));
+
+    if options.quiet_period.is_some() {
+        // The spin button itself has no activate signal, but the text it delegates to does.
+        if let Some(text) = spin.delegate() {
+            let flush_for_activate = flush.clone();
+            let activate = text.connect_local("activate", false, move |_| {
+                flush_for_activate();
+                None
+            });
+            values.hold(SignalGuard::new(&text, vec![activate]));
+        }
+
+        let focus = gtk::EventControllerFocus::new();
+        focus.connect_leave(move |_| flush());
+        spin.add_controller(&focus);
+        values.hold(ControllerGuard::new(spin, &focus));
+    }
+
+    spin_values
+}
+
+/// Make a [`ValueStream`] of the edges a [`gtk::ScrolledWindow`] is scrolled to, output from the
+/// `edge-reached` signal.
+pub fn edge_reached(scrolled: &gtk::ScrolledWindow) -> ValueStream<gtk::PositionType> {
+    let values = ValueStream::new();
+    let values_for_edge = values.downgrade();
+    let edge = scrolled.connect_edge_reached(move |_, position| values_for_edge.push(position));
+    values.hold(SignalGuard::new(scrolled, vec![edge]));
+    values
+}
+
+/// Make an [`ImpulseStream`] which is triggered when a [`gtk::ScrolledWindow`] is scrolled to
+/// within `threshold_px` of the bottom of its content, e.g. to load more items for infinite
+/// scrolling.
+///
+/// It's triggered once per approach rather than on every scroll within the threshold, and is
+/// re-armed when the content grows (e.g. because more items were loaded) or the user scrolls
+/// back out of the threshold. Content which doesn't fill the window counts as near the bottom, so
+/// a loader driven by this keeps loading until the window is filled. It's computed from the
+/// vertical [`gtk::Adjustment`], so is triggered before the edge is actually reached, unlike
+/// [`edge_reached`].
+///
+/// ```
+///    # use futures::stream::StreamExt as _;
+///    # use springsteel::glib_run_future;
+///    # use springsteel::widget_streams::near_bottom;
+///    # use std::future::ready;
+///    # gtk::init().expect("gtk::init");
+///    #
+///    let items = gtk::ListBox::new();
+///    let scrolled = gtk::ScrolledWindow::builder().child(&items).build();
+///    glib_run_future(near_bottom(&scrolled, 200.0).for_each(move |()| {
+///        items.append(&gtk::Label::new(Some("more")));
+///        ready(())
+///    }));
+/// ```
+pub fn near_bottom(scrolled: &gtk::ScrolledWindow, threshold_px: f64) -> ImpulseStream {
+    let impulses = ImpulseStream::new();
+    let adjustment = scrolled.vadjustment();
+
+    // The upper bound of the adjustment when last triggered, or `None` if armed.
+    let fired_at_opt: Rc<Cell<Option<f64>>> = Rc::new(Cell::new(None));
+
+    let check = {
+        let impulses = impulses.downgrade();
+        move |adjustment: &gtk::Adjustment| {
+            let upper = adjustment.upper();
+            let remaining = upper - adjustment.page_size() - adjustment.value();
+            let near = remaining <= threshold_px;
+
+            match fired_at_opt.get() {
+                Some(fired_at) if !near || upper > fired_at => fired_at_opt.set(None),
+                _ => (),
+            }
+
+            if near && fired_at_opt.get().is_none() {
+                fired_at_opt.set(Some(upper));
+                impulses.trigger();
+            }
+        }
+    };
+    let check = Rc::new(check);
+
+    let check_for_value = check.clone();
+    let value_changed =
+        adjustment.connect_value_changed(move |adjustment| check_for_value(adjustment));
+    let changed = adjustment.connect_changed(move |adjustment| check(adjustment));
+    impulses.hold(SignalGuard::new(&adjustment, vec![value_changed, changed]));
+
+    impulses
+}